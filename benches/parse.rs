@@ -0,0 +1,93 @@
+//! Benchmarks of the signature parser
+//!
+//! Run with `cargo bench --bench parse`.
+use chumsky::Parser;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use meerkat_ls::rule::{Rule, AST};
+use ropey::Rope;
+
+/// Create a rule file with the given number of signatures
+fn rule_file(signatures: usize) -> Rope {
+    let text = (1..=signatures)
+        .map(|sid| {
+            format!(
+                "alert http $HOME_NET any -> $EXTERNAL_NET any (msg:\"rule {}\"; \
+                 flow:established,to_server; http.uri; content:\"/index.php?id={}\"; \
+                 classtype:trojan-activity; sid:{}; rev:1;)\n",
+                sid, sid, sid
+            )
+        })
+        .collect::<String>();
+    Rope::from_str(&text)
+}
+
+/// Parsing the lines of a rope: borrowed from the rope or copied into a String
+///
+/// Borrowing saves an allocation per line, which is small next to the parsing
+/// itself, so both take about the same time. Both give the same signatures
+/// (see the tests of the parser).
+fn borrowed_lines(c: &mut Criterion) {
+    let rope = rule_file(30_000);
+    let mut group = c.benchmark_group("lines");
+    group.sample_size(10);
+    group.bench_function("string", |b| {
+        b.iter(|| {
+            rope.lines()
+                .for_each(|line| drop(black_box(Rule::parse_line(&line.to_string()))))
+        })
+    });
+    group.bench_function("slice", |b| {
+        b.iter(|| {
+            rope.lines()
+                .for_each(|line| drop(black_box(Rule::parse_slice(line))))
+        })
+    });
+    group.finish();
+}
+
+/// Parsing every line with a parser built for the line or with a single parser
+///
+/// Building the parser costs little next to the parsing, so both take about
+/// the same time and [Rule::parse_line] does not cache it.
+fn rebuilt_parser(c: &mut Criterion) {
+    let lines = rule_file(10_000)
+        .lines()
+        .map(|line| line.to_string())
+        .collect::<Vec<_>>();
+    let mut group = c.benchmark_group("parser");
+    group.sample_size(10);
+    group.bench_function("rebuilt", |b| {
+        b.iter(|| {
+            lines
+                .iter()
+                .for_each(|line| drop(black_box(Rule::parse_line(line))))
+        })
+    });
+    group.bench_function("reused", |b| {
+        let parser = Rule::parser().boxed();
+        b.iter(|| {
+            lines
+                .iter()
+                .for_each(|line| drop(black_box(parser.parse_recovery(line.as_str()))))
+        })
+    });
+    group.finish();
+}
+
+/// Parsing a large file on every available thread or on a single one
+///
+/// The speedup grows with the number of CPUs, with a single CPU the file is
+/// parsed on the calling thread either way.
+fn parallel_parsing(c: &mut Criterion) {
+    let rope = rule_file(50_000);
+    let mut group = c.benchmark_group("file");
+    group.sample_size(10);
+    group.bench_function("sequential", |b| {
+        b.iter(|| black_box(AST::parse_lines(&rope, 0..rope.len_lines())))
+    });
+    group.bench_function("parallel", |b| b.iter(|| black_box(AST::parse_rope(&rope))));
+    group.finish();
+}
+
+criterion_group!(benches, borrowed_lines, rebuilt_parser, parallel_parsing);
+criterion_main!(benches);
@@ -0,0 +1,30 @@
+//! Generates the long version of the binary, which names the Suricata version
+//! of the bundled keywords and the source of the events
+use std::env;
+use std::fs;
+use std::path::Path;
+
+fn main() {
+    let keywords = "resources/keywords.csv";
+    println!("cargo:rerun-if-changed={}", keywords);
+    let snapshot = fs::read_to_string(keywords).expect("the keywords should be bundled");
+    // The snapshot starts with the output of `suricata -V`
+    let suricata_version = snapshot
+        .lines()
+        .find_map(|line| line.strip_prefix("This is Suricata version "))
+        .and_then(|version| version.split_whitespace().next())
+        .expect("the snapshot should name the version of Suricata");
+
+    let long_version = format!(
+        "{}\nkeywords: Suricata {} (bundled), unless Suricata is installed\n\
+         events: bundled, extended with the event rules of the installed Suricata",
+        env::var("CARGO_PKG_VERSION").unwrap(),
+        suricata_version
+    );
+    let out_dir = env::var("OUT_DIR").unwrap();
+    fs::write(
+        Path::new(&out_dir).join("long_version.rs"),
+        format!("const LONG_VERSION: &str = {:?};\n", long_version),
+    )
+    .unwrap();
+}
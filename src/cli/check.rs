@@ -0,0 +1,222 @@
+//! Provides the `check` subcommand
+//!
+//! Verifies rule files with Suricata (the same way the language server does)
+//! and renders the findings with the offending line excerpted.
+use std::fs;
+use std::io::{self, ErrorKind, IsTerminal};
+use std::process::ExitCode;
+
+use clap::Args;
+use serde_json::json;
+use tower_lsp::lsp_types::{Diagnostic, NumberOrString};
+
+use crate::server_settings::{
+    ServerSettings, SuricataSettings, VerificationProfile, VerificationSettings,
+};
+use crate::suricata::{verify_text, VerificationMode};
+
+use super::{collect_files, io_errors, EXIT_IO_ERROR, EXIT_SURICATA_NOT_FOUND};
+
+#[derive(Args, Debug)]
+pub struct CheckArgs {
+    /// Rule files, directories or glob patterns to check
+    files: Vec<String>,
+    /// Path to the Suricata binary
+    #[arg(long)]
+    suricata: Option<String>,
+    /// Path to the Suricata config file
+    #[arg(short = 'c', long)]
+    suricata_config: Option<String>,
+    /// Override a setting of the Suricata config (e.g. "app-layer.protocols.sip.enabled=no")
+    #[arg(long, value_name = "NAME=VALUE")]
+    set: Vec<String>,
+    /// How Suricata verifies the signatures
+    #[arg(long, value_enum, default_value_t = VerificationMode::EngineAnalysis)]
+    mode: VerificationMode,
+    /// Print the findings as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+/// A finding of Suricata, mapped back to the file it originates from
+struct Finding<'a> {
+    file: &'a str,
+    line: u32,
+    line_text: &'a str,
+    diagnostic: Diagnostic,
+}
+
+/// Run the check subcommand
+pub async fn run(args: CheckArgs) -> ExitCode {
+    let files = match collect_files(&args.files) {
+        Ok(files) => files,
+        Err(errors) => return io_errors(errors),
+    };
+    if files.is_empty() {
+        eprintln!("error: no rule files found");
+        return ExitCode::from(EXIT_IO_ERROR);
+    }
+    let mut contents: Vec<(String, String)> = vec![];
+    for file in files {
+        match fs::read_to_string(&file) {
+            Ok(text) => contents.push((file.display().to_string(), text)),
+            Err(err) => {
+                eprintln!("error: could not read {}: {}", file.display(), err);
+                return ExitCode::from(EXIT_IO_ERROR);
+            }
+        }
+    }
+
+    // Merge all files into one, remembering where every line comes from
+    let mut merged = String::new();
+    let mut origins: Vec<(usize, u32)> = vec![];
+    contents
+        .iter()
+        .enumerate()
+        .for_each(|(file_nr, (_, text))| {
+            text.lines().enumerate().for_each(|(line_nr, line)| {
+                merged.push_str(line);
+                merged.push('\n');
+                origins.push((file_nr, line_nr as u32));
+            });
+        });
+
+    let settings = ServerSettings {
+        suricata: SuricataSettings {
+            path: args.suricata.clone(),
+        },
+        verification: VerificationSettings {
+            profile: VerificationProfile {
+                config_file: args.suricata_config,
+                set: args.set,
+                mode: args.mode,
+            },
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let diagnostics = match verify_text(&merged, &settings).await {
+        Ok(diagnostics) => diagnostics,
+        Err(err) => {
+            let not_found = err
+                .downcast_ref::<io::Error>()
+                .is_some_and(|err| err.kind() == ErrorKind::NotFound);
+            if not_found {
+                eprintln!(
+                    "error: Suricata was not found ({}), install it or pass its path with --suricata",
+                    args.suricata.as_deref().unwrap_or("suricata")
+                );
+                return ExitCode::from(EXIT_SURICATA_NOT_FOUND);
+            }
+            eprintln!("error: could not run Suricata: {}", err);
+            return ExitCode::from(EXIT_IO_ERROR);
+        }
+    };
+
+    // Map the diagnostics back to the original files
+    let mut findings = diagnostics
+        .into_iter()
+        .filter_map(|diagnostic| {
+            let (file_nr, line) = *origins.get(diagnostic.range.start.line as usize)?;
+            let (file, text) = &contents[file_nr];
+            Some(Finding {
+                file,
+                line,
+                line_text: text.lines().nth(line as usize).unwrap_or_default(),
+                diagnostic,
+            })
+        })
+        .collect::<Vec<_>>();
+    findings.sort_by_key(|finding| (finding.file, finding.line));
+
+    if args.json {
+        print_json(&findings);
+    } else {
+        print_human(&findings);
+    }
+
+    if findings.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+/// Get the code of the diagnostic as a string
+fn diagnostic_code(diagnostic: &Diagnostic) -> String {
+    match &diagnostic.code {
+        Some(NumberOrString::Number(code)) => code.to_string(),
+        Some(NumberOrString::String(code)) => code.clone(),
+        None => String::new(),
+    }
+}
+
+/// Print the findings with the line excerpted and the span underlined
+fn print_human(findings: &[Finding]) {
+    let colored = io::stdout().is_terminal();
+    let paint = |text: String, color: &str| {
+        if colored {
+            format!("\x1b[1;{}m{}\x1b[0m", color, text)
+        } else {
+            text
+        }
+    };
+    findings.iter().for_each(|finding| {
+        let range = finding.diagnostic.range;
+        let start = range.start.character as usize;
+        let length = (range.end.character as usize).saturating_sub(start).max(1);
+        let line_number = (finding.line + 1).to_string();
+        let gutter = " ".repeat(line_number.len());
+
+        println!(
+            "{}: {}",
+            paint(
+                format!("error[{}]", diagnostic_code(&finding.diagnostic)),
+                "31"
+            ),
+            finding.diagnostic.message
+        );
+        println!(
+            "{}{} {}:{}:{}",
+            gutter,
+            paint("-->".to_string(), "34"),
+            finding.file,
+            finding.line + 1,
+            start + 1
+        );
+        println!("{} {}", gutter, paint("|".to_string(), "34"));
+        println!(
+            "{} {} {}",
+            paint(line_number.clone(), "34"),
+            paint("|".to_string(), "34"),
+            finding.line_text
+        );
+        println!(
+            "{} {} {}{}",
+            gutter,
+            paint("|".to_string(), "34"),
+            " ".repeat(start),
+            paint("^".repeat(length), "31")
+        );
+        println!();
+    });
+}
+
+/// Print the findings as a JSON array
+fn print_json(findings: &[Finding]) {
+    let findings = findings
+        .iter()
+        .map(|finding| {
+            json!({
+                "file": finding.file,
+                "line": finding.line + 1,
+                "column": finding.diagnostic.range.start.character + 1,
+                "endColumn": finding.diagnostic.range.end.character + 1,
+                "severity": "error",
+                "code": diagnostic_code(&finding.diagnostic),
+                "message": finding.diagnostic.message,
+            })
+        })
+        .collect::<Vec<_>>();
+    println!("{}", serde_json::Value::Array(findings));
+}
@@ -0,0 +1,59 @@
+//! Provides the `convert` subcommand
+//!
+//! Converts rule files written for other engines (e.g. Snort 2) into Suricata
+//! rule files. Constructs which could not be translated are reported as
+//! warnings.
+use std::fs;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::Args;
+
+use crate::convert::{convert_text, SourceFormat};
+
+use super::EXIT_IO_ERROR;
+
+#[derive(Args, Debug)]
+pub struct ConvertArgs {
+    /// The rule file to convert
+    input: PathBuf,
+    /// The engine the rules were written for
+    #[arg(long, value_enum)]
+    from: SourceFormat,
+    /// Write the converted rules to a file instead of stdout
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+/// Run the convert subcommand
+pub fn run(args: ConvertArgs) -> ExitCode {
+    let text = match fs::read_to_string(&args.input) {
+        Ok(text) => text,
+        Err(err) => {
+            eprintln!("error: could not read {}: {}", args.input.display(), err);
+            return ExitCode::from(EXIT_IO_ERROR);
+        }
+    };
+    let conversion = convert_text(&text, args.from);
+
+    conversion.warnings.iter().for_each(|(line, warning)| {
+        eprintln!(
+            "{}:{}:{}: warning: {}",
+            args.input.display(),
+            line + 1,
+            warning.span.start + 1,
+            warning.message
+        );
+    });
+
+    match &args.output {
+        Some(output) => {
+            if let Err(err) = fs::write(output, conversion.text) {
+                eprintln!("error: could not write {}: {}", output.display(), err);
+                return ExitCode::from(EXIT_IO_ERROR);
+            }
+        }
+        None => print!("{}", conversion.text),
+    }
+    ExitCode::SUCCESS
+}
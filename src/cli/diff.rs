@@ -0,0 +1,334 @@
+//! Provides the `diff` subcommand
+//!
+//! Compares two versions of a rule pack and reports the added, removed and
+//! modified signatures. Signatures are identified by their gid and sid, the
+//! ones without a sid are identified by the hash of their formatted text.
+//! Since the signatures are compared in their formatted form, changes in the
+//! whitespace are not reported.
+//!
+//! The hash is the 64 bit FNV-1a hash of the formatted text, so the hashes
+//! printed by different builds can be compared.
+use std::collections::btree_map::Entry as MapEntry;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::process::ExitCode;
+
+use clap::Args;
+use serde::Serialize;
+use serde_json::json;
+
+use crate::format::FormatStyle;
+use crate::rule::{Rule, AST};
+
+use super::{collect_files, EXIT_IO_ERROR};
+
+#[derive(Args, Debug)]
+pub struct DiffArgs {
+    /// The old version of the rule pack (file, directory or glob pattern)
+    old: String,
+    /// The new version of the rule pack (file, directory or glob pattern)
+    new: String,
+    /// Show the changes of every modified signature
+    #[arg(long)]
+    detail: bool,
+    /// Print the differences as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+/// The identity of a signature inside a rule pack
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Identity {
+    /// The gid (1 if not set) and the sid of the signature
+    Sid(u32, u32),
+    /// The hash of the formatted signature, used if the signature has no sid
+    Hash(u64),
+}
+
+impl fmt::Display for Identity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Identity::Sid(gid, sid) => write!(f, "{}:{}", gid, sid),
+            Identity::Hash(hash) => write!(f, "hash {:016x}", hash),
+        }
+    }
+}
+
+/// A signature inside a rule pack
+#[derive(Debug, Clone, Serialize)]
+struct Entry {
+    file: String,
+    /// The line of the signature (starting at 1)
+    line: u32,
+    /// The formatted signature
+    rule: String,
+    /// The hash of the formatted signature
+    hash: String,
+    /// The signature split into the header and its options, used for the detailed diff
+    #[serde(skip)]
+    detail: Vec<String>,
+}
+
+/// All signatures of a rule pack, keyed by their identity
+#[derive(Debug, Default)]
+struct Pack {
+    rules: BTreeMap<Identity, Entry>,
+    /// Signatures, which have the same identity as an earlier signature
+    duplicates: Vec<(Identity, Entry)>,
+}
+
+/// Run the diff subcommand
+pub fn run(args: DiffArgs) -> ExitCode {
+    let style = FormatStyle::default();
+    let (old, new) = match (load_pack(&args.old, &style), load_pack(&args.new, &style)) {
+        (Ok(old), Ok(new)) => (old, new),
+        (Err(err), _) | (_, Err(err)) => {
+            eprintln!("error: {}", err);
+            return ExitCode::from(EXIT_IO_ERROR);
+        }
+    };
+
+    let added = new
+        .rules
+        .iter()
+        .filter(|(identity, _)| !old.rules.contains_key(identity))
+        .collect::<Vec<_>>();
+    let removed = old
+        .rules
+        .iter()
+        .filter(|(identity, _)| !new.rules.contains_key(identity))
+        .collect::<Vec<_>>();
+    let modified = old
+        .rules
+        .iter()
+        .filter_map(|(identity, old_entry)| {
+            let new_entry = new.rules.get(identity)?;
+            (old_entry.hash != new_entry.hash).then_some((identity, old_entry, new_entry))
+        })
+        .collect::<Vec<_>>();
+    let duplicates = old.duplicates.iter().chain(new.duplicates.iter());
+
+    if args.json {
+        let entry_json = |identity: &Identity, entry: &Entry| {
+            let mut value = serde_json::to_value(entry).unwrap_or_default();
+            value["id"] = json!(identity.to_string());
+            value
+        };
+        let output = json!({
+            "added": added.iter().map(|(identity, entry)| entry_json(identity, entry)).collect::<Vec<_>>(),
+            "removed": removed.iter().map(|(identity, entry)| entry_json(identity, entry)).collect::<Vec<_>>(),
+            "modified": modified.iter().map(|(identity, old_entry, new_entry)| {
+                let mut value = json!({
+                    "id": identity.to_string(),
+                    "old": old_entry,
+                    "new": new_entry,
+                });
+                if args.detail {
+                    value["diff"] = json!(diff_lines(old_entry, new_entry));
+                }
+                value
+            }).collect::<Vec<_>>(),
+            "duplicates": duplicates.map(|(identity, entry)| entry_json(identity, entry)).collect::<Vec<_>>(),
+        });
+        println!("{}", output);
+    } else {
+        duplicates.for_each(|(identity, entry)| {
+            eprintln!(
+                "warning: {} is defined more than once ({}:{}), only the first signature is compared",
+                identity,
+                entry.file,
+                entry.line
+            );
+        });
+        added.iter().for_each(|(identity, entry)| {
+            println!("added {} ({}:{})", identity, entry.file, entry.line);
+        });
+        removed.iter().for_each(|(identity, entry)| {
+            println!("removed {} ({}:{})", identity, entry.file, entry.line);
+        });
+        modified
+            .iter()
+            .for_each(|(identity, old_entry, new_entry)| {
+                println!(
+                    "modified {} ({}:{} -> {}:{})",
+                    identity, old_entry.file, old_entry.line, new_entry.file, new_entry.line
+                );
+                if args.detail {
+                    println!("  --- {}:{}", old_entry.file, old_entry.line);
+                    println!("  +++ {}:{}", new_entry.file, new_entry.line);
+                    diff_lines(old_entry, new_entry)
+                        .iter()
+                        .for_each(|line| println!("  {}", line));
+                }
+            });
+        println!(
+            "{} added, {} removed, {} modified",
+            added.len(),
+            removed.len(),
+            modified.len()
+        );
+    }
+
+    if added.is_empty() && removed.is_empty() && modified.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+/// Load all signatures of a rule pack
+fn load_pack(path: &str, style: &FormatStyle) -> Result<Pack, String> {
+    let files = collect_files(&[path.to_string()]).map_err(|errors| errors.join(", "))?;
+    if files.is_empty() {
+        return Err(format!("no rule files found in {}", path));
+    }
+    let mut pack = Pack::default();
+    for file in files {
+        let text = fs::read_to_string(&file)
+            .map_err(|err| format!("could not read {}: {}", file.display(), err))?;
+        pack.add_file(&file.display().to_string(), &text, style);
+    }
+    Ok(pack)
+}
+
+impl Pack {
+    /// Add the signatures of a file, the first signature of an identity is kept
+    fn add_file(&mut self, file: &str, text: &str, style: &FormatStyle) {
+        let (ast, _) = AST::parse_str(text);
+        let mut lines = ast.rules.iter().collect::<Vec<_>>();
+        lines.sort_by_key(|(line, _)| **line);
+        lines.into_iter().for_each(|(line, (rule, _))| {
+            let (identity, entry) = create_entry(file, *line, rule, style);
+            match self.rules.entry(identity) {
+                MapEntry::Occupied(_) => self.duplicates.push((identity, entry)),
+                MapEntry::Vacant(vacant) => {
+                    vacant.insert(entry);
+                }
+            }
+        });
+    }
+}
+
+/// Hash a text with the 64 bit FNV-1a hash, which does not change between builds
+fn fnv1a(text: &str) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+    text.bytes().fold(OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+    })
+}
+
+/// Create the entry for a signature, along with its identity
+fn create_entry(file: &str, line: u32, rule: &Rule, style: &FormatStyle) -> (Identity, Entry) {
+    let formatted = style.format_rule(rule);
+    let hash = fnv1a(&formatted);
+
+    let identity = match rule.sid() {
+        Some((sid, _)) => Identity::Sid(rule.gid().map(|(gid, _)| gid).unwrap_or(1), sid),
+        None => Identity::Hash(hash),
+    };
+
+    // Put the header and every option on its own line
+    let mut detail = vec![];
+    let mut header = rule.header.0.to_string();
+    if let Some((action, _)) = &rule.action {
+        header = format!("{} {}", action, header);
+    }
+    detail.push(header.trim_end().to_string());
+    rule.options.iter().flatten().for_each(|(option, _)| {
+        detail.push(format!("    {};", option));
+    });
+
+    let entry = Entry {
+        file: file.to_string(),
+        line: line + 1,
+        rule: formatted,
+        hash: format!("{:016x}", hash),
+        detail,
+    };
+    (identity, entry)
+}
+
+/// Get the unified diff of two signatures (every line starts with " ", "-" or "+")
+fn diff_lines(old: &Entry, new: &Entry) -> Vec<String> {
+    diff::slice(&old.detail, &new.detail)
+        .into_iter()
+        .map(|result| match result {
+            diff::Result::Left(line) => format!("-{}", line),
+            diff::Result::Right(line) => format!("+{}", line),
+            diff::Result::Both(line, _) => format!(" {}", line),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pack(text: &str) -> Pack {
+        let mut pack = Pack::default();
+        pack.add_file("a.rules", text, &FormatStyle::default());
+        pack
+    }
+
+    #[test]
+    fn missing_packs_are_an_error() {
+        let error = load_pack("missing.rules", &FormatStyle::default()).err();
+        assert_eq!(error.as_deref(), Some("missing.rules does not exist"));
+    }
+
+    #[test]
+    fn hashes_are_stable() {
+        assert_eq!(fnv1a(""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a("a"), 0xaf63dc4c8601ec8c);
+        let pack = pack("alert tcp any any -> any any (msg:\"x\";)");
+        let (identity, entry) = pack.rules.iter().next().unwrap();
+        assert_eq!(*identity, Identity::Hash(fnv1a(&entry.rule)));
+        assert_eq!(entry.hash, format!("{:016x}", fnv1a(&entry.rule)));
+    }
+
+    #[test]
+    fn duplicate_sids_are_reported() {
+        let pack = pack(concat!(
+            "alert tcp any any -> any any (msg:\"first\"; sid:1;)\n",
+            "alert tcp any any -> any any (msg:\"second\"; sid:1;)\n",
+        ));
+        assert_eq!(pack.rules[&Identity::Sid(1, 1)].line, 1);
+        let duplicates = pack
+            .duplicates
+            .iter()
+            .map(|(identity, entry)| (*identity, entry.line))
+            .collect::<Vec<_>>();
+        assert_eq!(duplicates, vec![(Identity::Sid(1, 1), 2)]);
+    }
+
+    #[test]
+    fn signatures_without_sid_are_matched_by_their_content() {
+        let old = pack("alert tcp any any -> any any (msg:\"x\"; content:\"a\";)");
+        let new = pack(concat!(
+            "\n",
+            "alert  tcp any any -> any any (msg: \"x\";content:\"a\";)\n",
+            "alert tcp any any -> any any (msg:\"x\"; content:\"b\";)\n",
+        ));
+        let identity = old.rules.keys().next().unwrap();
+        assert!(matches!(identity, Identity::Hash(_)));
+        assert_eq!(new.rules[identity].line, 2);
+        assert_eq!(new.rules.len(), 2);
+    }
+
+    #[test]
+    fn gids_are_part_of_the_identity() {
+        let pack = pack(concat!(
+            "alert tcp any any -> any any (msg:\"x\"; sid:1;)\n",
+            "alert tcp any any -> any any (msg:\"x\"; gid:3; sid:1;)\n",
+        ));
+        let identities = pack
+            .rules
+            .keys()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        assert_eq!(identities, vec!["1:1", "3:1"]);
+        assert!(pack.duplicates.is_empty());
+    }
+}
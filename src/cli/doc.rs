@@ -0,0 +1,74 @@
+//! Provides the `doc` subcommand
+//!
+//! Generates Markdown documentation pages for rule files, e.g. to publish an
+//! internal catalog of the signatures.
+use std::fs;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::Args;
+
+use crate::doc::{generate, DocGrouping};
+use crate::rule::AST;
+
+use super::{collect_files, io_errors, load_settings, load_variables, EXIT_IO_ERROR};
+
+#[derive(Args, Debug)]
+pub struct DocArgs {
+    /// Rule files, directories or glob patterns to document
+    files: Vec<String>,
+    /// The directory the pages are written to
+    #[arg(long)]
+    out: PathBuf,
+    /// Write one page per signature or one page per classtype
+    #[arg(long, value_enum, default_value_t = DocGrouping::Rule)]
+    group_by: DocGrouping,
+    /// Path to the settings file used by the editor, which defines the variables
+    #[arg(long)]
+    config: Option<PathBuf>,
+}
+
+/// Run the doc subcommand
+pub fn run(args: DocArgs) -> ExitCode {
+    let settings = match load_settings(args.config.as_deref()) {
+        Ok(settings) => settings,
+        Err(code) => return code,
+    };
+    let files = match collect_files(&args.files) {
+        Ok(files) => files,
+        Err(errors) => return io_errors(errors),
+    };
+    if files.is_empty() {
+        eprintln!("error: no rule files found");
+        return ExitCode::from(EXIT_IO_ERROR);
+    }
+    let mut parsed = vec![];
+    for file in files {
+        match fs::read_to_string(&file) {
+            Ok(text) => parsed.push((file.display().to_string(), AST::parse_str(&text).0)),
+            Err(err) => {
+                eprintln!("error: could not read {}: {}", file.display(), err);
+                return ExitCode::from(EXIT_IO_ERROR);
+            }
+        }
+    }
+    let asts = parsed
+        .iter()
+        .map(|(file, ast)| (file.clone(), ast))
+        .collect::<Vec<_>>();
+    let pages = generate(&asts, args.group_by, &load_variables(&settings));
+
+    if let Err(err) = fs::create_dir_all(&args.out) {
+        eprintln!("error: could not create {}: {}", args.out.display(), err);
+        return ExitCode::from(EXIT_IO_ERROR);
+    }
+    for page in &pages {
+        let path = args.out.join(&page.name);
+        if let Err(err) = fs::write(&path, &page.content) {
+            eprintln!("error: could not write {}: {}", path.display(), err);
+            return ExitCode::from(EXIT_IO_ERROR);
+        }
+    }
+    println!("wrote {} pages to {}", pages.len(), args.out.display());
+    ExitCode::SUCCESS
+}
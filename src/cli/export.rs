@@ -0,0 +1,257 @@
+//! Provides the `export` subcommand
+//!
+//! Exports an inventory of the signatures (one record per signature) as JSON
+//! or CSV, e.g. for coverage spreadsheets. The files are processed line by
+//! line and every record is written as soon as it is parsed, so large rule
+//! packs never have to be held in memory.
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::process::ExitCode;
+
+use clap::{Args, ValueEnum};
+use serde::Serialize;
+
+use crate::rule::Rule;
+
+use super::{collect_files, io_errors, EXIT_IO_ERROR};
+
+#[derive(Args, Debug)]
+pub struct ExportArgs {
+    /// Rule files, directories or glob patterns to export
+    files: Vec<String>,
+    /// Output format of the inventory
+    #[arg(long, value_enum, default_value_t = ExportFormat::Json)]
+    format: ExportFormat,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    Json,
+    Csv,
+}
+
+/// The columns of the CSV output, in the same order as the fields of [InventoryRecord]
+const CSV_HEADER: [&str; 15] = [
+    "file",
+    "line",
+    "sid",
+    "gid",
+    "rev",
+    "action",
+    "protocol",
+    "source",
+    "direction",
+    "destination",
+    "msg",
+    "classtype",
+    "references",
+    "flowbits",
+    "contents",
+];
+
+/// A single signature inside the inventory
+#[derive(Debug, Serialize)]
+pub struct InventoryRecord {
+    pub file: String,
+    pub line: u32,
+    pub sid: Option<u32>,
+    pub gid: Option<u32>,
+    pub rev: Option<u32>,
+    pub action: Option<String>,
+    pub protocol: Option<String>,
+    /// The source address and port (e.g. "$HOME_NET any")
+    pub source: String,
+    pub direction: Option<String>,
+    /// The destination address and port (e.g. "$EXTERNAL_NET 80")
+    pub destination: String,
+    pub msg: Option<String>,
+    pub classtype: Option<String>,
+    pub references: Vec<String>,
+    /// The flowbit operations (e.g. "set,login")
+    pub flowbits: Vec<String>,
+    pub contents: Vec<String>,
+}
+
+impl InventoryRecord {
+    /// Create the record for a signature
+    pub fn new(file: &str, line: u32, rule: &Rule) -> Self {
+        let summary =
+            |parts: [Option<String>; 2]| parts.into_iter().flatten().collect::<Vec<_>>().join(" ");
+        let value = |spanned: Option<(String, _)>| spanned.map(|(value, _)| value);
+        // The values of every option with the keyword (e.g. "set,login" for flowbits)
+        let joined_values = |keyword: &str| {
+            rule.option_values(keyword)
+                .into_iter()
+                .map(|values| {
+                    values
+                        .into_iter()
+                        .map(|(value, _)| value)
+                        .collect::<Vec<_>>()
+                        .join(",")
+                })
+                .collect()
+        };
+        InventoryRecord {
+            file: file.to_string(),
+            line: line + 1,
+            sid: rule.sid().map(|(sid, _)| sid),
+            gid: rule.gid().map(|(gid, _)| gid),
+            rev: rule.rev().map(|(rev, _)| rev),
+            action: rule.action.as_ref().map(|(action, _)| action.to_string()),
+            protocol: rule
+                .protocol()
+                .as_ref()
+                .map(|(protocol, _)| protocol.to_string()),
+            source: summary([
+                rule.source()
+                    .as_ref()
+                    .map(|(address, _)| address.to_string()),
+                rule.source_port()
+                    .as_ref()
+                    .map(|(port, _)| port.to_string()),
+            ]),
+            direction: rule
+                .direction()
+                .as_ref()
+                .map(|(direction, _)| direction.to_string()),
+            destination: summary([
+                rule.destination()
+                    .as_ref()
+                    .map(|(address, _)| address.to_string()),
+                rule.destination_port()
+                    .as_ref()
+                    .map(|(port, _)| port.to_string()),
+            ]),
+            msg: value(rule.msg()),
+            classtype: value(rule.classtype()),
+            references: rule
+                .references()
+                .iter()
+                .map(|reference| reference.to_string())
+                .collect(),
+            flowbits: joined_values("flowbits"),
+            contents: rule
+                .contents()
+                .into_iter()
+                .map(|(content, _)| content)
+                .collect(),
+        }
+    }
+
+    /// Get the record as CSV fields, lists are separated by new lines
+    fn csv_fields(&self) -> [String; 15] {
+        let optional =
+            |value: &Option<u32>| value.map(|value| value.to_string()).unwrap_or_default();
+        [
+            self.file.clone(),
+            self.line.to_string(),
+            optional(&self.sid),
+            optional(&self.gid),
+            optional(&self.rev),
+            self.action.clone().unwrap_or_default(),
+            self.protocol.clone().unwrap_or_default(),
+            self.source.clone(),
+            self.direction.clone().unwrap_or_default(),
+            self.destination.clone(),
+            self.msg.clone().unwrap_or_default(),
+            self.classtype.clone().unwrap_or_default(),
+            self.references.join("\n"),
+            self.flowbits.join("\n"),
+            self.contents.join("\n"),
+        ]
+    }
+}
+
+/// Writes the records as soon as they are created
+enum RecordWriter<W: Write> {
+    Json { writer: W, first: bool },
+    Csv(Box<csv::Writer<W>>),
+}
+
+impl<W: Write> RecordWriter<W> {
+    fn new(format: ExportFormat, mut writer: W) -> io::Result<Self> {
+        match format {
+            ExportFormat::Json => {
+                write!(writer, "[")?;
+                Ok(RecordWriter::Json {
+                    writer,
+                    first: true,
+                })
+            }
+            ExportFormat::Csv => {
+                let mut writer = csv::Writer::from_writer(writer);
+                writer.write_record(CSV_HEADER)?;
+                Ok(RecordWriter::Csv(Box::new(writer)))
+            }
+        }
+    }
+
+    fn write(&mut self, record: &InventoryRecord) -> io::Result<()> {
+        match self {
+            RecordWriter::Json { writer, first } => {
+                if !*first {
+                    write!(writer, ",")?;
+                }
+                *first = false;
+                writeln!(writer)?;
+                serde_json::to_writer(&mut *writer, record)?;
+                Ok(())
+            }
+            RecordWriter::Csv(writer) => Ok(writer.write_record(record.csv_fields())?),
+        }
+    }
+
+    fn finish(self) -> io::Result<()> {
+        match self {
+            RecordWriter::Json { mut writer, .. } => {
+                writeln!(writer, "\n]")?;
+                writer.flush()
+            }
+            RecordWriter::Csv(mut writer) => writer.flush(),
+        }
+    }
+}
+
+/// Run the export subcommand
+pub fn run(args: ExportArgs) -> ExitCode {
+    let files = match collect_files(&args.files) {
+        Ok(files) => files,
+        Err(errors) => return io_errors(errors),
+    };
+    if files.is_empty() {
+        eprintln!("error: no rule files found");
+        return ExitCode::from(EXIT_IO_ERROR);
+    }
+
+    let stdout = BufWriter::new(io::stdout().lock());
+    let result = RecordWriter::new(args.format, stdout).and_then(|mut writer| {
+        for file in files {
+            let name = file.display().to_string();
+            let reader = File::open(&file).map(BufReader::new).map_err(|err| {
+                io::Error::new(err.kind(), format!("could not read {}: {}", name, err))
+            })?;
+            for (line_nr, line) in reader.lines().enumerate() {
+                let line = line?;
+                // Skip empty lines and comments
+                if line.trim().is_empty() || line.trim().starts_with('#') {
+                    continue;
+                }
+                if let (Some((rule, _)), errors) = Rule::parse_line(&line) {
+                    // Signatures with syntax errors are skipped
+                    if errors.is_empty() {
+                        writer.write(&InventoryRecord::new(&name, line_nr as u32, &rule))?;
+                    }
+                }
+            }
+        }
+        writer.finish()
+    });
+
+    match result {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            ExitCode::from(EXIT_IO_ERROR)
+        }
+    }
+}
@@ -0,0 +1,114 @@
+//! Provides the `fmt` subcommand
+//!
+//! Formats rule files from the command line, using the same formatter as the
+//! language server.
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::Args;
+
+use crate::format::{format_text, FormatStyle};
+use crate::server_settings::ServerSettings;
+
+use super::{collect_files, io_errors, EXIT_IO_ERROR};
+
+#[derive(Args, Debug)]
+pub struct FmtArgs {
+    /// Rule files, directories or glob patterns to format
+    files: Vec<String>,
+    /// Do not write the files, exit with a non-zero code if a file is not formatted
+    #[arg(long)]
+    check: bool,
+    /// Read the rules from stdin and write the formatted rules to stdout
+    #[arg(long)]
+    stdin: bool,
+    /// Write the formatted files to stdout instead of rewriting them in place
+    #[arg(long)]
+    stdout: bool,
+    /// Path to the settings file, its formatting section defines the style
+    #[arg(long)]
+    config: Option<PathBuf>,
+}
+
+/// Run the fmt subcommand
+pub fn run(args: FmtArgs) -> ExitCode {
+    let style = match &args.config {
+        Some(config) => match ServerSettings::from_file(config) {
+            Ok(parsed) => {
+                parsed
+                    .errors
+                    .iter()
+                    .for_each(|error| eprintln!("warning: {}: {}", config.display(), error));
+                FormatStyle::from(&parsed.settings.formatting)
+            }
+            Err(err) => {
+                eprintln!("error: could not load {}: {}", config.display(), err);
+                return ExitCode::from(EXIT_IO_ERROR);
+            }
+        },
+        None => FormatStyle::default(),
+    };
+
+    // Format the rules provided trough stdin
+    if args.stdin {
+        let mut text = String::new();
+        if let Err(err) = io::stdin().read_to_string(&mut text) {
+            eprintln!("error: could not read stdin: {}", err);
+            return ExitCode::from(EXIT_IO_ERROR);
+        }
+        let formatted = format_text(&text, &style);
+        if args.check {
+            return check_result(formatted == text);
+        }
+        return match io::stdout().write_all(formatted.as_bytes()) {
+            Ok(_) => ExitCode::SUCCESS,
+            Err(_) => ExitCode::from(EXIT_IO_ERROR),
+        };
+    }
+
+    let files = match collect_files(&args.files) {
+        Ok(files) => files,
+        Err(errors) => return io_errors(errors),
+    };
+    if files.is_empty() {
+        eprintln!("error: no rule files found");
+        return ExitCode::from(EXIT_IO_ERROR);
+    }
+
+    let mut unformatted = 0;
+    for file in files {
+        let text = match fs::read_to_string(&file) {
+            Ok(text) => text,
+            Err(err) => {
+                eprintln!("error: could not read {}: {}", file.display(), err);
+                return ExitCode::from(EXIT_IO_ERROR);
+            }
+        };
+        let formatted = format_text(&text, &style);
+        if args.check {
+            if formatted != text {
+                println!("{} is not formatted", file.display());
+                unformatted += 1;
+            }
+        } else if args.stdout {
+            print!("{}", formatted);
+        } else if formatted != text {
+            if let Err(err) = fs::write(&file, formatted) {
+                eprintln!("error: could not write {}: {}", file.display(), err);
+                return ExitCode::from(EXIT_IO_ERROR);
+            }
+        }
+    }
+    check_result(unformatted == 0)
+}
+
+/// Convert the result of a check into an exit code
+fn check_result(formatted: bool) -> ExitCode {
+    if formatted {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
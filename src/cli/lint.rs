@@ -0,0 +1,324 @@
+//! Provides the `lint` subcommand
+//!
+//! Runs the lints over rule files and reports the violations, so the quality
+//! of the rules can be checked inside a CI pipeline.
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::error::ErrorKind;
+use clap::{Args, ValueEnum};
+use ropey::Rope;
+use serde_json::json;
+
+use crate::lint::{
+    validate_document, validate_lua_scripts, validate_sid_range, validate_workspace, LintCode,
+    LintLevel, RelatedLocation, Severity, ValidationContext, Violation,
+};
+use crate::rule::{Span, AST};
+use crate::syntax::{syntax_errors, SyntaxError};
+use crate::threshold::ThresholdConfig;
+
+use super::{collect_files, io_errors, load_settings, load_variables, EXIT_IO_ERROR};
+
+#[derive(Args, Debug)]
+pub struct LintArgs {
+    /// Rule files, directories or glob patterns to lint
+    files: Vec<String>,
+    /// Output format of the violations
+    #[arg(long, value_enum, default_value_t = OutputFormat::Human)]
+    format: OutputFormat,
+    /// Treat the violations of a lint as errors
+    #[arg(long, value_name = "CODE", value_parser = parse_lint_code)]
+    deny: Vec<LintCode>,
+    /// Turn off a lint
+    #[arg(long, value_name = "CODE", value_parser = parse_lint_code)]
+    allow: Vec<LintCode>,
+    /// Path to the settings file used by the editor
+    #[arg(long)]
+    config: Option<PathBuf>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    Human,
+    Json,
+}
+
+/// Parse the code of a lint
+fn parse_lint_code(code: &str) -> Result<LintCode, String> {
+    LintCode::from_code(code).ok_or_else(|| "unknown lint".to_string())
+}
+
+/// A problem found inside a file: a syntax error or the violation of a lint
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Finding {
+    file: String,
+    line: u32,
+    span: Span,
+    severity: Severity,
+    code: &'static str,
+    message: String,
+    related: Vec<RelatedLocation>,
+}
+
+impl Finding {
+    fn from_violation(file: String, line: u32, violation: Violation) -> Finding {
+        Finding {
+            file,
+            line,
+            span: violation.span,
+            severity: violation.severity,
+            code: violation.code.code(),
+            message: violation.message,
+            related: violation.related,
+        }
+    }
+
+    /// Signatures which could not be parsed are errors, which can not be turned off
+    fn from_syntax_error(file: String, error: SyntaxError) -> Finding {
+        Finding {
+            file,
+            line: error.line,
+            span: error.span,
+            severity: Severity::Error,
+            code: "syntax",
+            message: error.message,
+            related: vec![],
+        }
+    }
+}
+
+/// Run the lint subcommand
+pub fn run(args: LintArgs) -> ExitCode {
+    if let Some(code) = args.deny.iter().find(|code| args.allow.contains(code)) {
+        let message = format!("the lint {} is both denied and allowed\n", code.code());
+        let error = clap::Error::raw(ErrorKind::ArgumentConflict, message);
+        let _ = error.print();
+        return ExitCode::from(error.exit_code() as u8);
+    }
+    // Load the lint levels from the settings, the flags take precedence
+    let settings = match load_settings(args.config.as_deref()) {
+        Ok(settings) => settings,
+        Err(code) => return code,
+    };
+    let mut lints = settings.lints.clone();
+    args.deny.iter().for_each(|code| {
+        lints
+            .levels
+            .insert(code.code().to_string(), LintLevel::Error);
+    });
+    args.allow.iter().for_each(|code| {
+        lints.levels.insert(code.code().to_string(), LintLevel::Off);
+    });
+    let variables = load_variables(&settings);
+    let mut context = ValidationContext::new(&lints).with_variables(variables);
+    if let Some(path) = &lints.threshold_config {
+        match ThresholdConfig::from_file(Path::new(path)) {
+            Ok((threshold_config, errors)) => {
+                errors.iter().for_each(|(line, error)| {
+                    eprintln!("warning: {}:{}: {}", path, line + 1, error)
+                });
+                context = context.with_threshold_config(threshold_config);
+            }
+            Err(err) => {
+                eprintln!("error: could not load {}: {}", path, err);
+                return ExitCode::from(EXIT_IO_ERROR);
+            }
+        }
+    }
+
+    // Parse all files
+    let files = match collect_files(&args.files) {
+        Ok(files) => files,
+        Err(errors) => return io_errors(errors),
+    };
+    if files.is_empty() {
+        eprintln!("error: no rule files found");
+        return ExitCode::from(EXIT_IO_ERROR);
+    }
+    let mut texts = vec![];
+    for file in files {
+        match fs::read_to_string(&file) {
+            Ok(text) => texts.push((file.display().to_string(), text)),
+            Err(err) => {
+                eprintln!("error: could not read {}: {}", file.display(), err);
+                return ExitCode::from(EXIT_IO_ERROR);
+            }
+        }
+    }
+    let findings = lint_files(&texts, &context);
+
+    match args.format {
+        OutputFormat::Human => print_human(&findings),
+        OutputFormat::Json => print_json(&findings),
+    }
+
+    let has_errors = findings
+        .iter()
+        .any(|finding| finding.severity == Severity::Error);
+    if has_errors {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// Parse the files and run the rule and the workspace lints over them
+///
+/// Returns the syntax errors along with the violations, sorted by their position.
+fn lint_files(texts: &[(String, String)], context: &ValidationContext) -> Vec<Finding> {
+    let mut findings = vec![];
+    let parsed = texts
+        .iter()
+        .map(|(file, text)| {
+            let rope = Rope::from_str(text);
+            let (ast, _) = AST::parse_rope(&rope);
+            findings.extend(
+                syntax_errors(&rope, &ast)
+                    .into_iter()
+                    .map(|error| Finding::from_syntax_error(file.clone(), error)),
+            );
+            (file.clone(), ast)
+        })
+        .collect::<Vec<_>>();
+
+    let asts = parsed
+        .iter()
+        .map(|(file, ast)| (file.clone(), ast))
+        .collect::<Vec<_>>();
+    let violations = parsed
+        .iter()
+        .flat_map(|(file, ast)| {
+            // The globs of the sid ranges are matched against the path as given
+            validate_document(ast, context)
+                .into_iter()
+                .chain(validate_sid_range(Path::new(file), ast, context))
+                .chain(validate_lua_scripts(Path::new(file), ast, context))
+                .map(|(line, violation)| (file.clone(), line, violation.in_file(file)))
+        })
+        .chain(validate_workspace(&asts, context));
+    findings.extend(
+        violations.map(|(file, line, violation)| Finding::from_violation(file, line, violation)),
+    );
+    findings.sort_by(|a, b| (&a.file, a.line, a.span.start).cmp(&(&b.file, b.line, b.span.start)));
+    findings
+}
+
+/// Print the findings in a human readable format (file:line:col)
+fn print_human(findings: &[Finding]) {
+    let colored = io::stdout().is_terminal();
+    findings.iter().for_each(|finding| {
+        let severity = finding.severity.as_str();
+        let severity = if colored {
+            let color = match finding.severity {
+                Severity::Error => "31",
+                Severity::Warning => "33",
+                Severity::Information => "34",
+                Severity::Hint => "36",
+            };
+            format!("\x1b[1;{}m{}\x1b[0m", color, severity)
+        } else {
+            severity.to_string()
+        };
+        println!(
+            "{}:{}:{}: {}[{}]: {}",
+            finding.file,
+            finding.line + 1,
+            finding.span.start + 1,
+            severity,
+            finding.code,
+            finding.message
+        );
+        finding.related.iter().for_each(|related| {
+            println!(
+                "  note: {}:{}:{}: {}",
+                related.file,
+                related.line + 1,
+                related.span.start + 1,
+                related.message
+            );
+        });
+    });
+}
+
+/// Print the findings as a JSON array
+fn print_json(findings: &[Finding]) {
+    let findings = findings
+        .iter()
+        .map(|finding| {
+            json!({
+                "file": finding.file,
+                "line": finding.line + 1,
+                "column": finding.span.start + 1,
+                "endColumn": finding.span.end + 1,
+                "severity": finding.severity,
+                "code": finding.code,
+                "message": finding.message,
+                "related": finding.related.iter().map(|related| json!({
+                    "file": related.file,
+                    "line": related.line + 1,
+                    "column": related.span.start + 1,
+                    "message": related.message,
+                })).collect::<Vec<_>>(),
+            })
+        })
+        .collect::<Vec<_>>();
+    println!("{}", serde_json::Value::Array(findings));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::server_settings::LintsSettings;
+
+    #[test]
+    fn signatures_which_do_not_parse_are_errors() {
+        let text = [
+            "# a comment",
+            "alert tcp any any -> any any (msg:\"a\"; sid:1; rev:1;)",
+            "alert tcp any any -> any any msg:\"b\"; sid:2; rev:1;)",
+            "",
+            "alert tcp any any -> any any (msg:\"c\"; sid:3; rev:1;",
+        ]
+        .join("\n");
+        let findings = lint_files(
+            &[("a.rules".to_string(), text)],
+            &ValidationContext::default(),
+        );
+        let syntax_errors = findings
+            .iter()
+            .filter(|finding| finding.code == "syntax")
+            .map(|finding| (finding.line, finding.severity))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            syntax_errors,
+            vec![(2, Severity::Error), (4, Severity::Error)]
+        );
+    }
+
+    #[test]
+    fn syntax_errors_can_not_be_turned_off() {
+        let text = "alert tcp any any -> any any msg:\"b\"; sid:2; rev:1;)".to_string();
+        let lints = LintsSettings {
+            levels: HashMap::from([("syntax".to_string(), LintLevel::Off)]),
+            ..Default::default()
+        };
+        let context = ValidationContext::new(&lints);
+        let findings = lint_files(&[("a.rules".to_string(), text)], &context);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].severity, Severity::Error);
+    }
+
+    #[test]
+    fn lint_codes_are_validated() {
+        assert_eq!(parse_lint_code("duplicate-sid"), Ok(LintCode::DuplicateSid));
+        assert_eq!(
+            parse_lint_code("no-such-lint"),
+            Err("unknown lint".to_string())
+        );
+    }
+}
@@ -0,0 +1,148 @@
+//! Provides the command line interface
+//!
+//! Besides running the language server, the binary can be used directly from
+//! the command line (for example inside a CI pipeline). Every subcommand reuses
+//! the same code paths as the language server, so the editor and the command
+//! line always agree.
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::Subcommand;
+
+use crate::server_settings::ServerSettings;
+use crate::variables::{parse_config_variables, Variables};
+use crate::workspace::find_rule_files;
+
+pub mod check;
+pub mod convert;
+pub mod diff;
+pub mod doc;
+pub mod export;
+pub mod fmt;
+pub mod lint;
+
+/// Exit code used when the input files could not be read or written
+pub const EXIT_IO_ERROR: u8 = 2;
+/// Exit code used when Suricata is not installed
+pub const EXIT_SURICATA_NOT_FOUND: u8 = 3;
+
+/// All available subcommands
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Format rule files
+    Fmt(fmt::FmtArgs),
+    /// Lint rule files
+    Lint(lint::LintArgs),
+    /// Verify rule files with Suricata
+    Check(check::CheckArgs),
+    /// Convert rule files written for other engines
+    Convert(convert::ConvertArgs),
+    /// Export an inventory of the signatures as JSON or CSV
+    Export(export::ExportArgs),
+    /// Compare two versions of a rule pack
+    Diff(diff::DiffArgs),
+    /// Generate documentation pages for rule files
+    Doc(doc::DocArgs),
+}
+
+impl Command {
+    /// Run the subcommand and return the exit code of the process
+    pub async fn run(self) -> ExitCode {
+        match self {
+            Command::Fmt(args) => fmt::run(args),
+            Command::Lint(args) => lint::run(args),
+            Command::Check(args) => check::run(args).await,
+            Command::Convert(args) => convert::run(args),
+            Command::Export(args) => export::run(args),
+            Command::Diff(args) => diff::run(args),
+            Command::Doc(args) => doc::run(args),
+        }
+    }
+}
+
+/// Collect the rule files from the arguments
+///
+/// An argument can be a file, a directory (searched recursively for *.rules
+/// files) or a glob pattern (e.g. "rules/**/*.rules"). Arguments, which match
+/// nothing, are returned as errors.
+pub fn collect_files(paths: &[String]) -> Result<Vec<PathBuf>, Vec<String>> {
+    let mut files = vec![];
+    let mut errors = vec![];
+    paths.iter().for_each(|path| {
+        let path_buf = PathBuf::from(path);
+        if path_buf.is_dir() {
+            find_rule_files(&path_buf, &mut files);
+        } else if path_buf.exists() {
+            files.push(path_buf);
+        } else {
+            let entries = glob::glob(path)
+                .map(|entries| entries.flatten().collect::<Vec<_>>())
+                .unwrap_or_default();
+            if entries.is_empty() {
+                errors.push(format!("{} does not exist", path));
+            }
+            entries.into_iter().for_each(|entry| {
+                if entry.is_dir() {
+                    find_rule_files(&entry, &mut files);
+                } else {
+                    files.push(entry);
+                }
+            });
+        }
+    });
+    if errors.is_empty() {
+        Ok(files)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Print the errors of the arguments, which could not be collected
+pub fn io_errors(errors: Vec<String>) -> ExitCode {
+    errors
+        .iter()
+        .for_each(|error| eprintln!("error: {}", error));
+    ExitCode::from(EXIT_IO_ERROR)
+}
+
+/// Load the settings file given with `--config`, or the defaults without one
+///
+/// The settings, which could not be read, are reported as warnings.
+pub fn load_settings(config: Option<&Path>) -> Result<ServerSettings, ExitCode> {
+    let Some(config) = config else {
+        return Ok(ServerSettings::default());
+    };
+    match ServerSettings::from_file(config) {
+        Ok(parsed) => {
+            parsed
+                .errors
+                .iter()
+                .for_each(|error| eprintln!("warning: {}: {}", config.display(), error));
+            Ok(parsed.settings)
+        }
+        Err(err) => {
+            eprintln!("error: could not load {}: {}", config.display(), err);
+            Err(ExitCode::from(EXIT_IO_ERROR))
+        }
+    }
+}
+
+/// Get the variables as the lints see them
+///
+/// The variables of the settings override the ones of the suricata.yaml of the
+/// active verification profile, which override the defaults.
+pub fn load_variables(settings: &ServerSettings) -> Variables {
+    let variables = Variables::new(&settings.variables);
+    let (_, profile) = settings.verification.active();
+    let Some(config_file) = &profile.config_file else {
+        return variables;
+    };
+    match fs::read_to_string(config_file) {
+        Ok(text) => variables.with_config_variables(parse_config_variables(&text)),
+        Err(err) => {
+            eprintln!("warning: could not read {}: {}", config_file, err);
+            variables
+        }
+    }
+}
@@ -2,65 +2,188 @@
 //!
 //! The completion logic analyzes which part of the rule is needed to be
 //! completed and then provides the nessassary options
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 
 use ropey::RopeSlice;
-use tower_lsp::lsp_types::{CompletionItem, CompletionItemKind, InsertTextFormat};
+use lsp_types::{
+    CompletionItem, CompletionItemKind, CompletionTextEdit, InsertTextFormat, Position, Range,
+    TextEdit,
+};
 
 use crate::{
     rule::{
-        header::{NetworkAddress, NetworkPort},
+        action::Action,
+        bits::BitsKind,
+        datasets::{DATASET_FILE_ARGUMENTS, DATASET_KEYWORDS},
+        flow::FlowValue,
+        header::{NetworkAddress, NetworkDirection, NetworkPort, Protocol},
         Completions, Rule, AST,
     },
     suricata::Keyword,
 };
 
+/// The metadata keys used by the community rulesets, along with what they
+/// describe and their usual values
+#[rustfmt::skip]
+const METADATA_KEYS: [(&str, &str, &[&str]); 16] = [
+    ("affected_product", "The product targeted by the attack", &[]),
+    ("attack_target", "The kind of host targeted by the attack", &["Client_Endpoint", "Server", "Web_Server", "Mobile_Client", "IoT", "Networking_Equipment"]),
+    ("confidence", "How likely the alert is a true positive", &["Low", "Medium", "High"]),
+    ("created_at", "The date the signature was created (YYYY_MM_DD)", &[]),
+    ("deployment", "Where the sensor should be placed", &["Perimeter", "Internal", "Datacenter", "SSLDecrypt"]),
+    ("former_category", "The category of the signature before it was moved", &[]),
+    ("malware_family", "The malware family detected by the signature", &[]),
+    ("mitre_tactic_id", "The MITRE ATT&CK tactic (e.g. TA0011)", &[]),
+    ("mitre_tactic_name", "The name of the MITRE ATT&CK tactic", &[]),
+    ("mitre_technique_id", "The MITRE ATT&CK technique (e.g. T1071)", &[]),
+    ("mitre_technique_name", "The name of the MITRE ATT&CK technique", &[]),
+    ("performance_impact", "The impact of the signature on the performance", &["Low", "Moderate", "Significant"]),
+    ("policy", "The policy the signature belongs to", &[]),
+    ("reviewed_at", "The date the signature was reviewed (YYYY_MM_DD)", &[]),
+    ("signature_severity", "The severity of the alert", &["Informational", "Minor", "Major", "Critical"]),
+    ("updated_at", "The date the signature was updated (YYYY_MM_DD)", &[]),
+];
+
+/// The values used inside the workspace, offered by the completion
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CompletionIndex {
+    /// The values used for every metadata key
+    pub metadata: HashMap<String, BTreeSet<String>>,
+    /// Where every flowbit, xbit and hostbit is used
+    pub bits: BTreeMap<(BitsKind, String), BitsUsages>,
+    /// The signatures using every dataset: (file, line)
+    pub datasets: BTreeMap<String, Vec<(String, u32)>>,
+}
+
+/// The signatures setting and checking a bit: (file, line)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BitsUsages {
+    pub sets: Vec<(String, u32)>,
+    pub checks: Vec<(String, u32)>,
+}
+
+impl CompletionIndex {
+    /// Collect the values used by the signatures of the workspace
+    pub fn new(files: &[(String, &AST)]) -> Self {
+        let mut index = CompletionIndex::default();
+        files.iter().for_each(|(file, ast)| {
+            ast.rules.iter().for_each(|(line, (rule, _))| {
+                rule.metadata()
+                    .into_iter()
+                    .for_each(|((key, _), (value, _))| {
+                        index.metadata.entry(key).or_default().insert(value);
+                    });
+                rule.bits_operations().into_iter().for_each(|operation| {
+                    // Incomplete operations (e.g. "isset,") have an empty name
+                    let names = operation.names.iter().filter(|(name, _)| !name.is_empty());
+                    names.for_each(|(name, _)| {
+                        let usages = index
+                            .bits
+                            .entry((operation.kind, name.clone()))
+                            .or_default();
+                        if operation.sets() {
+                            usages.sets.push((file.clone(), *line));
+                        } else if operation.checks() {
+                            usages.checks.push((file.clone(), *line));
+                        }
+                    });
+                });
+                rule.datasets().into_iter().for_each(|dataset| {
+                    let (name, _) = dataset.name;
+                    if !name.is_empty() {
+                        index
+                            .datasets
+                            .entry(name)
+                            .or_default()
+                            .push((file.clone(), *line));
+                    }
+                });
+            });
+        });
+        index.bits.values_mut().for_each(|usages| {
+            usages.sets.sort();
+            usages.checks.sort();
+        });
+        index.datasets.values_mut().for_each(|usages| usages.sort());
+        index
+    }
+}
+
 /// Fetches the completion options for the signature
 pub fn get_completion(
     ast: &AST,
     line_text: &RopeSlice,
-    _line: usize,
     col: usize,
-    _address_variables: &HashSet<String>,
-    _port_variables: &HashSet<String>,
+    known_address_variables: &HashSet<String>,
+    known_port_variables: &HashSet<String>,
     keywords: &HashMap<String, Keyword>,
+    index: &CompletionIndex,
 ) -> Option<Vec<CompletionItem>> {
+    // Complete the values of the option under the cursor
+    let line = line_text.chars().take(col).collect::<String>();
+    if let Some((keyword, values)) = option_at(&line) {
+        return match keyword.as_str() {
+            "metadata" => Some(get_completion_for_metadata(&values, index)),
+            "dataset" => get_completion_for_datasets(&values, index),
+            "flow" => Some(get_completion_for_flow(&values)),
+            keyword => {
+                let kind = BitsKind::ALL
+                    .into_iter()
+                    .find(|kind| kind.keyword() == keyword)?;
+                get_completion_for_bits(kind, &values, index)
+            }
+        };
+    }
     let mut completion_tokens = vec![];
-    let mut address_variables = HashSet::new();
-    let mut port_variables = HashSet::new();
+    let mut address_variables = known_address_variables.clone();
+    let mut port_variables = known_port_variables.clone();
 
-    // Get all variables
+    // Add the variables used inside the file
     get_variables_from_ast(ast, &mut address_variables, &mut port_variables);
-    // match get_next_uncompleted(rule) {
-    //     Uncompleted::Action => {
-    //         Action::get_completion(&address_variables, &port_variables, &mut completion_tokens)
-    //     }
-    //     Uncompleted::Protocol => {}
-    //     Uncompleted::Direction => NetworkDirection::get_completion(
-    //         &address_variables,
-    //         &port_variables,
-    //         &mut completion_tokens,
-    //     ),
-    //     Uncompleted::Address => NetworkAddress::get_completion(
-    //         &address_variables,
-    //         &port_variables,
-    //         &mut completion_tokens,
-    //     ),
-    //     Uncompleted::Port => {
-    //         NetworkPort::get_completion(&address_variables, &port_variables, &mut completion_tokens)
-    //     }
-    //     Uncompleted::OptionKeyword => {
-    //         get_completion_for_option_keywords(keywords, &mut completion_tokens)
-    //     }
-    //     Uncompleted::Other => {}
-    // }
+    // Complete the next part of a header, which is being typed
+    let typed = line.trim_start();
+    if !typed.contains('(') && (typed.is_empty() || typed.ends_with(char::is_whitespace)) {
+        // The header is parsed up to the cursor, even if the signature is incomplete
+        if let (Some((rule, _)), _) = Rule::parse_line(typed) {
+            let (address_variables, port_variables) = (&address_variables, &port_variables);
+            match get_next_uncompleted(&rule) {
+                Uncompleted::Action => Action::get_completion(
+                    address_variables,
+                    port_variables,
+                    &mut completion_tokens,
+                ),
+                Uncompleted::Protocol => Protocol::get_completion(
+                    address_variables,
+                    port_variables,
+                    &mut completion_tokens,
+                ),
+                Uncompleted::Direction => NetworkDirection::get_completion(
+                    address_variables,
+                    port_variables,
+                    &mut completion_tokens,
+                ),
+                Uncompleted::Address => NetworkAddress::get_completion(
+                    address_variables,
+                    port_variables,
+                    &mut completion_tokens,
+                ),
+                Uncompleted::Port => NetworkPort::get_completion(
+                    address_variables,
+                    port_variables,
+                    &mut completion_tokens,
+                ),
+                // The options are completed once their parenthesis is opened
+                Uncompleted::OptionKeyword | Uncompleted::Other => {}
+            }
+            return Some(completion_tokens);
+        }
+    }
     // Generate completion tokens (old way)
     if col > 0 && line_text.get_char(col - 1)? == '$' {
         NetworkAddress::get_completion(&address_variables, &port_variables, &mut completion_tokens);
         NetworkPort::get_completion(&address_variables, &port_variables, &mut completion_tokens);
     } else if col > 1 && line_text.get_char(col - 2)? == ';' || line_text.get_char(col - 1)? == '(' {
         get_completion_for_option_keywords(keywords, &mut completion_tokens);
-    } else {
     }
     Some(completion_tokens)
 }
@@ -75,7 +198,11 @@ fn get_next_uncompleted(rule: &Rule) -> Uncompleted {
         Uncompleted::Address
     } else if rule.source_port().is_none() {
         Uncompleted::Port
-    } else if rule.direction().is_none() {
+    } else if rule.direction().is_none()
+        // The parser accepts an empty direction
+        || matches!(rule.direction(), Some((NetworkDirection::Unrecognized(direction), _))
+            if direction.is_empty())
+    {
         Uncompleted::Direction
     } else if rule.destination().is_none() {
         Uncompleted::Address
@@ -125,28 +252,269 @@ pub fn get_completion_for_option_keywords(
     })
 }
 
+/// Find the option the text ends in, if the cursor is past its colon
+///
+/// Returns the keyword of the option along with its values up to the cursor.
+fn option_at(line: &str) -> Option<(String, String)> {
+    // The options start after the header
+    let options_start = line.find('(')? + 1;
+    let mut option_start = options_start;
+    let mut quoted = false;
+    let mut escaped = false;
+    for (index, char) in line[options_start..].char_indices() {
+        match char {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => quoted = !quoted,
+            ';' if !quoted => option_start = options_start + index + 1,
+            _ => (),
+        }
+    }
+    let (keyword, values) = line[option_start..].split_once(':')?;
+    Some((keyword.trim().to_string(), values.to_string()))
+}
+
+/// Complete the keys of the metadata, or the values of the key before the cursor
+///
+/// The values are the usual ones of the key, along with the ones used inside
+/// the workspace.
+fn get_completion_for_metadata(values: &str, index: &CompletionIndex) -> Vec<CompletionItem> {
+    let entry = values.rsplit(',').next().unwrap_or_default();
+    let values_of = |key: &str| {
+        let mut values = METADATA_KEYS
+            .iter()
+            .filter(|(standard_key, _, _)| *standard_key == key)
+            .flat_map(|(_, _, values)| values.iter().map(|value| value.to_string()))
+            .collect::<BTreeSet<_>>();
+        values.extend(index.metadata.get(key).into_iter().flatten().cloned());
+        values
+    };
+    // Complete the value, once the key is followed by whitespace
+    if let Some((key, _)) = entry.trim_start().split_once(char::is_whitespace) {
+        return values_of(key)
+            .into_iter()
+            .map(|value| CompletionItem {
+                label: value,
+                kind: Some(CompletionItemKind::VALUE),
+                detail: Some(format!("value of {}", key)),
+                ..Default::default()
+            })
+            .collect();
+    }
+    // Entries are separated by a comma and a space
+    let separator = if entry.is_empty() && !values.is_empty() {
+        " "
+    } else {
+        ""
+    };
+    let standard_keys = METADATA_KEYS
+        .iter()
+        .map(|(key, description, _)| (key.to_string(), description.to_string()));
+    let used_keys = index
+        .metadata
+        .keys()
+        .filter(|key| {
+            METADATA_KEYS
+                .iter()
+                .all(|(standard_key, _, _)| standard_key != key)
+        })
+        .map(|key| (key.clone(), "used inside the workspace".to_string()));
+    standard_keys
+        .chain(used_keys)
+        .map(|(key, detail)| {
+            let values = values_of(&key);
+            // Offer the known values as choices
+            let placeholder = match values.is_empty() {
+                true => "$1".to_string(),
+                false => format!(
+                    "${{1|{}|}}",
+                    values
+                        .iter()
+                        .map(|value| escape_choice(value))
+                        .collect::<Vec<_>>()
+                        .join(",")
+                ),
+            };
+            CompletionItem {
+                insert_text: Some(format!("{}{} {}", separator, key, placeholder)),
+                insert_text_format: Some(InsertTextFormat::SNIPPET),
+                label: key,
+                kind: Some(CompletionItemKind::PROPERTY),
+                detail: Some(detail),
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+/// Complete the values of the flow option, which are not used yet
+fn get_completion_for_flow(values: &str) -> Vec<CompletionItem> {
+    let used = values.split(',').map(str::trim).collect::<Vec<_>>();
+    FlowValue::ALL
+        .into_iter()
+        .filter(|value| !used.contains(&value.as_str()))
+        .map(|value| CompletionItem {
+            label: value.to_string(),
+            kind: Some(CompletionItemKind::ENUM_MEMBER),
+            detail: value.description().map(str::to_string),
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// Complete the name of a bit, once the operation is followed by a comma
+///
+/// Checked bits are completed with the bits set inside the workspace, set bits
+/// with the checked ones first (to pair them), followed by the other bits.
+fn get_completion_for_bits(
+    kind: BitsKind,
+    values: &str,
+    index: &CompletionIndex,
+) -> Option<Vec<CompletionItem>> {
+    let [operation, _] = values.split(',').collect::<Vec<_>>()[..] else {
+        return None;
+    };
+    let checks = matches!(operation.trim(), "isset" | "isnotset");
+    // Point to the first signature of the counterpart operation
+    let location = |usages: &[(String, u32)]| {
+        let (file, line) = usages.first()?;
+        let name = file.rsplit('/').next().unwrap_or(file);
+        Some(match usages.len() {
+            1 => format!("{}:{}", name, line + 1),
+            count => format!("{}:{} and {} more", name, line + 1, count - 1),
+        })
+    };
+    let items = index
+        .bits
+        .iter()
+        .filter(|((bits_kind, _), _)| *bits_kind == kind)
+        .filter_map(|((_, name), usages)| {
+            let (sets, checked) = (location(&usages.sets), location(&usages.checks));
+            let (detail, sort_text) = match (checks, sets, checked) {
+                (true, Some(sets), _) => (format!("set in {}", sets), "0"),
+                (true, None, _) => return None,
+                (false, _, Some(checks)) => (format!("checked in {}", checks), "0"),
+                (false, Some(sets), None) => (format!("set in {}", sets), "1"),
+                (false, None, None) => return None,
+            };
+            Some(CompletionItem {
+                label: name.clone(),
+                kind: Some(CompletionItemKind::VARIABLE),
+                detail: Some(detail),
+                sort_text: Some(format!("{}{}", sort_text, name)),
+                ..Default::default()
+            })
+        })
+        .collect();
+    Some(items)
+}
+
+/// Complete the name of a dataset, once the operation is followed by a comma
+fn get_completion_for_datasets(
+    values: &str,
+    index: &CompletionIndex,
+) -> Option<Vec<CompletionItem>> {
+    let [_, _] = values.split(',').collect::<Vec<_>>()[..] else {
+        return None;
+    };
+    let items = index
+        .datasets
+        .iter()
+        .map(|(name, usages)| {
+            let (file, line) = &usages[0];
+            let file = file.rsplit('/').next().unwrap_or(file);
+            let detail = match usages.len() {
+                1 => format!("used in {}:{}", file, line + 1),
+                count => format!("used in {}:{} and {} more", file, line + 1, count - 1),
+            };
+            CompletionItem {
+                label: name.clone(),
+                kind: Some(CompletionItemKind::VARIABLE),
+                detail: Some(detail),
+                ..Default::default()
+            }
+        })
+        .collect();
+    Some(items)
+}
+
+/// Find the file of a dataset argument (load, save or state) under the cursor
+///
+/// Returns the column the file starts at, along with the text of the file up
+/// to the cursor.
+pub fn dataset_file_at(line_text: &RopeSlice, col: usize) -> Option<(usize, String)> {
+    let line = line_text.chars().take(col).collect::<String>();
+    let (keyword, values) = option_at(&line)?;
+    if !DATASET_KEYWORDS.contains(&keyword.as_str()) {
+        return None;
+    }
+    let argument = values.rsplit(',').next()?.trim_start();
+    let (key, file) = argument.split_once(char::is_whitespace)?;
+    if !DATASET_FILE_ARGUMENTS.contains(&key) {
+        return None;
+    }
+    let file = file.trim_start().to_string();
+    Some((col - file.chars().count(), file))
+}
+
+/// Complete the file of a dataset argument with the files of the dataset directories
+///
+/// The files are relative to their directory, the typed part of the file is
+/// replaced by the completion.
+pub fn get_completion_for_dataset_files(
+    line: u32,
+    (start, typed): (usize, &str),
+    col: usize,
+    files: &[String],
+) -> Vec<CompletionItem> {
+    let range = Range::new(
+        Position::new(line, start as u32),
+        Position::new(line, col as u32),
+    );
+    files
+        .iter()
+        .filter(|file| file.starts_with(typed))
+        .map(|file| CompletionItem {
+            label: file.clone(),
+            kind: Some(CompletionItemKind::FILE),
+            text_edit: Some(CompletionTextEdit::Edit(TextEdit::new(range, file.clone()))),
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// Escape the characters with a meaning inside a snippet choice
+fn escape_choice(value: &str) -> String {
+    value
+        .chars()
+        .flat_map(|char| match char {
+            '$' | '}' | '\\' | ',' | '|' => vec!['\\', char],
+            char => vec![char],
+        })
+        .collect()
+}
+
 /// generic function to fetch the port of a certain protocol.
-fn get_port_by_protocol(protocol: String) -> Vec<u16> {
-    match protocol.as_str() {
-        "HTTP" => vec![80, 443],
-        "HTTP/2" => vec![80, 443],
-        "SSL" => vec![443],
-        "TLS" => vec![443],
-        "SMB" => vec![139, 445],
-        "DCERPC" => vec![135],
-        "SMTP" => vec![25],
-        "FTP" => vec![21],
-        "SSH" => vec![22],
-        "DNS" => vec![53],
-        "Modbus" => vec![502],
-        "NFS" => vec![111],
-        "NTP" => vec![123],
-        "DHCP" => vec![67],
-        "TFTP" => vec![69],
-        "KRB5" => vec![88],
-        "SIP" => vec![5060, 5061],
-        "SNMP" => vec![161, 162],
-        "RDP" => vec![3389],
+#[allow(dead_code)]
+fn get_port_by_protocol(protocol: &Protocol) -> Vec<u16> {
+    match protocol {
+        Protocol::Http | Protocol::Http1 | Protocol::Http2 => vec![80, 443],
+        Protocol::Tls => vec![443],
+        Protocol::Smb => vec![139, 445],
+        Protocol::Dcerpc => vec![135],
+        Protocol::Smtp => vec![25],
+        Protocol::Ftp => vec![21],
+        Protocol::Ssh => vec![22],
+        Protocol::Dns => vec![53],
+        Protocol::Modbus => vec![502],
+        Protocol::Nfs => vec![111],
+        Protocol::Ntp => vec![123],
+        Protocol::Dhcp => vec![67],
+        Protocol::Tftp => vec![69],
+        Protocol::Krb5 => vec![88],
+        Protocol::Sip => vec![5060, 5061],
+        Protocol::Snmp => vec![161, 162],
+        Protocol::Rdp => vec![3389],
         _ => vec![],
     }
 }
@@ -171,7 +539,7 @@ pub fn get_variables_from_ast(
 
 fn search_for_port_variables(port: &NetworkPort, port_variables: &mut HashSet<String>) {
     match port {
-        NetworkPort::PortGroup(group) => group.into_iter().for_each(|port| {
+        NetworkPort::PortGroup(group) => group.iter().for_each(|port| {
             search_for_port_variables(&port.0, port_variables);
         }),
         NetworkPort::NegPort(port) => search_for_port_variables(&port.as_ref().0, port_variables),
@@ -184,7 +552,7 @@ fn search_for_port_variables(port: &NetworkPort, port_variables: &mut HashSet<St
 
 fn search_for_address_variables(address: &NetworkAddress, address_variables: &mut HashSet<String>) {
     match address {
-        NetworkAddress::IPGroup(group) => group.into_iter().for_each(|address| {
+        NetworkAddress::IPGroup(group) => group.iter().for_each(|address| {
             search_for_address_variables(&address.0, address_variables);
         }),
         NetworkAddress::NegIP(ip) => {
@@ -196,3 +564,238 @@ fn search_for_address_variables(address: &NetworkAddress, address_variables: &mu
         _ => (),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use ropey::Rope;
+
+    use super::*;
+
+    fn metadata_index() -> CompletionIndex {
+        let (ast, _) = AST::parse_str(
+            "\
+alert tcp any any -> any any (msg:\"a\"; metadata:deployment Lab, team  blue; sid:1;)
+alert tcp any any -> any any (msg:\"b\"; metadata:team red; metadata:confidence High; sid:2;)
+",
+        );
+        CompletionIndex::new(&[("a.rules".to_string(), &ast)])
+    }
+
+    /// Complete the line at its end, as (label, insert text)
+    fn complete_metadata(line: &str) -> Vec<(String, Option<String>)> {
+        let rope = Rope::from_str(line);
+        let (ast, _) = AST::parse_str(line);
+        get_completion(
+            &ast,
+            &rope.slice(..),
+            line.chars().count(),
+            &HashSet::new(),
+            &HashSet::new(),
+            &HashMap::new(),
+            &metadata_index(),
+        )
+        .unwrap_or_default()
+        .into_iter()
+        .map(|item| (item.label, item.insert_text))
+        .collect()
+    }
+
+    #[test]
+    fn metadata_values_of_the_workspace_are_indexed() {
+        let index = metadata_index();
+        let values = |key: &str| index.metadata[key].iter().cloned().collect::<Vec<_>>();
+        assert_eq!(values("deployment"), ["Lab"]);
+        assert_eq!(values("team"), ["blue", "red"]);
+        assert_eq!(values("confidence"), ["High"]);
+    }
+
+    #[test]
+    fn metadata_keys_offer_their_values() {
+        let items = complete_metadata("alert tcp any any -> any any (msg:\"a\"; metadata:");
+        assert_eq!(items.len(), METADATA_KEYS.len() + 1);
+        let item = |label: &str| items.iter().find(|(key, _)| key == label).cloned();
+        assert_eq!(
+            item("confidence").unwrap().1.unwrap(),
+            "confidence ${1|High,Low,Medium|}"
+        );
+        assert_eq!(item("policy").unwrap().1.unwrap(), "policy $1");
+        assert_eq!(item("team").unwrap().1.unwrap(), "team ${1|blue,red|}");
+
+        // The next entry follows the comma after a space
+        let items = complete_metadata("alert tcp any any -> any any (metadata:team red,");
+        assert_eq!(items[0].1.as_deref(), Some(" affected_product $1"));
+    }
+
+    #[test]
+    fn metadata_values_are_completed_after_their_key() {
+        let items =
+            complete_metadata("alert tcp any any -> any any (metadata:policy x, deployment ");
+        let labels = items
+            .into_iter()
+            .map(|(label, _)| label)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            labels,
+            ["Datacenter", "Internal", "Lab", "Perimeter", "SSLDecrypt"]
+        );
+        // Other options are not completed yet
+        assert!(complete_metadata("alert tcp any any -> any any (msg:").is_empty());
+    }
+
+    #[test]
+    fn choices_are_escaped() {
+        assert_eq!(escape_choice("a,b|c$d"), "a\\,b\\|c\\$d");
+    }
+
+    const WORKSPACE: &str = "\
+alert http any any -> any any (msg:\"a\"; flowbits:set,login; flowbits:noalert; sid:1;)
+alert http any any -> any any (msg:\"b\"; flowbits:isset,login; flowbits:isset,token; sid:2;)
+alert http any any -> any any (msg:\"c\"; flowbits:set,seen; sid:3;)
+alert http any any -> any any (msg:\"d\"; xbits:set,bad,track ip_src; sid:4;)
+alert dns any any -> any any (msg:\"e\"; dns.query; dataset:isset,domains; sid:5;)
+alert dns any any -> any any (msg:\"f\"; dns.query; dataset:set,domains; sid:6;)
+";
+
+    fn index() -> CompletionIndex {
+        let (ast, _) = AST::parse_str(WORKSPACE);
+        CompletionIndex::new(&[("/rules/a.rules".to_string(), &ast)])
+    }
+
+    /// Complete at the end of the line, returning (label, detail, sort text)
+    fn complete(line: &str, index: &CompletionIndex) -> Vec<(String, String, Option<String>)> {
+        let (ast, _) = AST::parse_str(line);
+        let rope = Rope::from_str(line);
+        let col = rope.len_chars();
+        let (addresses, ports) = (HashSet::new(), HashSet::new());
+        let mut items = get_completion(
+            &ast,
+            &rope.line(0),
+            col,
+            &addresses,
+            &ports,
+            &HashMap::new(),
+            index,
+        )
+        .unwrap();
+        items.sort_by(|a, b| a.sort_text.cmp(&b.sort_text).then(a.label.cmp(&b.label)));
+        items
+            .into_iter()
+            .map(|item| {
+                // The name is inserted as it is, after the comma which is already typed
+                assert!(item.insert_text.is_none() && item.text_edit.is_none());
+                (item.label, item.detail.unwrap_or_default(), item.sort_text)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn checked_bits_are_completed_with_the_set_bits() {
+        let items = complete("alert tcp any any -> any any (flowbits:isset,", &index());
+        let labels = items
+            .iter()
+            .map(|(label, detail, _)| (label.as_str(), detail.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            labels,
+            vec![("login", "set in a.rules:1"), ("seen", "set in a.rules:3")]
+        );
+        // Other kinds of bits are not mixed in
+        let items = complete("alert tcp any any -> any any (xbits:isnotset,", &index());
+        assert_eq!(items[0].0, "bad");
+        assert_eq!(items.len(), 1);
+    }
+
+    #[test]
+    fn set_bits_are_completed_with_the_checked_bits_first() {
+        let items = complete(
+            "alert tcp any any -> any any (msg:\"x\"; flowbits:set,",
+            &index(),
+        );
+        let labels = items
+            .iter()
+            .map(|(label, detail, _)| (label.as_str(), detail.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            labels,
+            vec![
+                ("login", "checked in a.rules:2"),
+                ("token", "checked in a.rules:2"),
+                ("seen", "set in a.rules:3"),
+            ]
+        );
+    }
+
+    #[test]
+    fn bits_are_completed_after_the_operation() {
+        // No name is offered before the comma, or once the name is followed by a comma
+        let (ast, _) = AST::parse_str("");
+        let index = index();
+        [
+            "alert tcp any any -> any any (flowbits:isset",
+            "alert tcp any any -> any any (flowbits:set,a,",
+        ]
+        .into_iter()
+        .for_each(|line| {
+            let rope = Rope::from_str(line);
+            let completion = get_completion(
+                &ast,
+                &rope.line(0),
+                rope.len_chars(),
+                &HashSet::new(),
+                &HashSet::new(),
+                &HashMap::new(),
+                &index,
+            );
+            assert!(completion.is_none(), "{}", line);
+        });
+    }
+
+    #[test]
+    fn empty_index_completes_nothing() {
+        let index = CompletionIndex::default();
+        assert!(complete("alert tcp any any -> any any (flowbits:isset,", &index).is_empty());
+        assert!(complete("alert tcp any any -> any any (flowbits:set,", &index).is_empty());
+        assert!(complete("alert dns any any -> any any (dataset:isset,", &index).is_empty());
+    }
+
+    #[test]
+    fn dataset_names_are_completed() {
+        let items = complete(
+            "alert dns any any -> any any (dns.query; dataset:set,",
+            &index(),
+        );
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].0, "domains");
+        assert_eq!(items[0].1, "used in a.rules:5 and 1 more");
+    }
+
+    #[test]
+    fn dataset_files_are_completed() {
+        let line = "alert dns any any -> any any (dataset:set,domains,type string,load lists/b";
+        let rope = Rope::from_str(line);
+        let col = rope.len_chars();
+        let (start, typed) = dataset_file_at(&rope.line(0), col).unwrap();
+        assert_eq!((start, typed.as_str()), (col - 7, "lists/b"));
+
+        let files = [
+            "lists/bad.lst".to_string(),
+            "lists/nested/bad.lst".to_string(),
+            "lists/good.lst".to_string(),
+        ];
+        let items = get_completion_for_dataset_files(0, (start, &typed), col, &files);
+        let labels = items
+            .iter()
+            .map(|item| item.label.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(labels, vec!["lists/bad.lst"]);
+        assert_eq!(items[0].kind, Some(CompletionItemKind::FILE));
+        let Some(CompletionTextEdit::Edit(edit)) = &items[0].text_edit else {
+            panic!("the typed part of the file is not replaced");
+        };
+        assert_eq!(edit.range.start.character as usize, start);
+        // Other arguments are not completed with files
+        let line = "alert dns any any -> any any (dataset:set,domains,type ";
+        let rope = Rope::from_str(line);
+        assert!(dataset_file_at(&rope.line(0), rope.len_chars()).is_none());
+    }
+}
@@ -0,0 +1,107 @@
+//! Provides the conversion of signatures written for other engines
+//!
+//! The converters work on the parsed signatures, so every construct which could
+//! not be translated can be reported with its span.
+use ropey::Rope;
+
+use crate::format::line_ending;
+use crate::rule::{Span, AST};
+
+pub mod snort2;
+
+/// The engine the signatures were written for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "server", derive(clap::ValueEnum))]
+pub enum SourceFormat {
+    Snort2,
+}
+
+/// A construct, which could not be translated
+///
+/// The span is relative to the start of the line, like all spans of the rule
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConversionWarning {
+    pub span: Span,
+    pub message: String,
+}
+
+/// The result of converting a whole rule file
+#[derive(Debug, Clone, Default)]
+pub struct FileConversion {
+    pub text: String,
+    /// The warnings, along with the line of the signature
+    pub warnings: Vec<(u32, ConversionWarning)>,
+}
+
+/// Convert every signature inside a rule file
+///
+/// Signatures which do not need to be changed, comments and lines which could
+/// not be parsed are left as they are.
+pub fn convert_text(text: &str, from: SourceFormat) -> FileConversion {
+    let rope = Rope::from_str(text);
+    let (ast, _) = AST::parse_rope(&rope);
+    let mut conversion = FileConversion::default();
+    rope.lines().enumerate().for_each(|(line_nr, line)| {
+        let converted = ast
+            .rules
+            .get(&(line_nr as u32))
+            .map(|(rule, _)| match from {
+                SourceFormat::Snort2 => snort2::convert_rule(rule),
+            });
+        match converted {
+            Some(converted) => {
+                if converted.changed {
+                    conversion.text.push_str(&converted.rule.to_string());
+                    conversion.text.push_str(line_ending(line));
+                } else {
+                    conversion.text.push_str(&line.to_string());
+                }
+                conversion.warnings.extend(
+                    converted
+                        .warnings
+                        .into_iter()
+                        .map(|warning| (line_nr as u32, warning)),
+                );
+            }
+            None => conversion.text.push_str(&line.to_string()),
+        }
+    });
+    conversion
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SNORT2_INPUT: &str = include_str!("../../tests/fixtures/snort2/input.rules");
+    const SNORT2_EXPECTED: &str = include_str!("../../tests/fixtures/snort2/expected.rules");
+
+    #[test]
+    fn snort2_fixtures_are_converted() {
+        let conversion = convert_text(SNORT2_INPUT, SourceFormat::Snort2);
+        assert_eq!(conversion.text, SNORT2_EXPECTED);
+        let warnings = conversion
+            .warnings
+            .iter()
+            .map(|(line, warning)| (*line + 1, warning.message.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            warnings,
+            vec![
+                (9, "urilen >=0 always matches"),
+                (10, "urilen <=4294967295 can not be rewritten"),
+                (
+                    16,
+                    "the signature applies to multiple services, the protocol was not changed"
+                ),
+                (18, "rawbytes is not supported by Suricata and was removed"),
+            ]
+        );
+    }
+
+    #[test]
+    fn converted_fixtures_are_not_changed_again() {
+        let conversion = convert_text(SNORT2_EXPECTED, SourceFormat::Snort2);
+        assert_eq!(conversion.text, SNORT2_EXPECTED);
+    }
+}
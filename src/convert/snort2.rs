@@ -0,0 +1,517 @@
+//! Converts Snort 2 signatures into Suricata signatures
+//!
+//! Snort 2 and Suricata share most of the signature language, so only the
+//! mechanical differences are rewritten:
+//! - Content modifiers (`content:"x"; http_uri;`) and `uricontent` become
+//!   sticky buffers (`http.uri; content:"x";`)
+//! - `urilen` comparisons with `<=`/`>=` are rewritten into `<`/`>`
+//! - The fields of `threshold` are reordered into the order Suricata expects
+//! - `metadata: service http` on a TCP/UDP signature becomes the application
+//!   layer protocol of the header
+//! - `from_client`/`from_server` inside `flow` become `to_server`/`to_client`
+//! - The `sdrop` action becomes `drop` with `noalert`
+//!
+//! Everything which cannot be translated is reported as a warning, attached to
+//! the span of the original construct.
+use crate::rule::action::Action;
+use crate::rule::header::Protocol;
+use crate::rule::options::{is_sticky_buffer, OptionsVariable, RuleOption};
+use crate::rule::{Rule, Span, Spanned};
+
+use super::ConversionWarning;
+
+/// Content modifiers of Snort 2 and the sticky buffer, which replaces them
+const HTTP_MODIFIERS: [(&str, &str); 10] = [
+    ("http_uri", "http.uri"),
+    ("http_raw_uri", "http.uri.raw"),
+    ("http_header", "http.header"),
+    ("http_raw_header", "http.header.raw"),
+    ("http_cookie", "http.cookie"),
+    ("http_raw_cookie", "http.cookie"),
+    ("http_method", "http.method"),
+    ("http_client_body", "http.request_body"),
+    ("http_stat_code", "http.stat_code"),
+    ("http_stat_msg", "http.stat_msg"),
+];
+
+/// Modifiers, which apply to the previous content and are understood by Suricata
+const CONTENT_MODIFIERS: [&str; 6] = [
+    "nocase",
+    "depth",
+    "offset",
+    "distance",
+    "within",
+    "fast_pattern",
+];
+
+/// Snort 2 keywords, which do not have a Suricata equivalent
+const UNSUPPORTED_KEYWORDS: [&str; 12] = [
+    "rawbytes",
+    "http_encode",
+    "resp",
+    "react",
+    "logto",
+    "session",
+    "activates",
+    "activated_by",
+    "count",
+    "sd_pattern",
+    "protected_content",
+    "cvs",
+];
+
+/// Services of Snort 2 and the matching application layer protocol of Suricata
+const SERVICES: [(&str, &str); 21] = [
+    ("http", "http"),
+    ("ftp", "ftp"),
+    ("ftp-data", "ftp-data"),
+    ("smtp", "smtp"),
+    ("imap", "imap"),
+    ("dns", "dns"),
+    ("ssl", "tls"),
+    ("tls", "tls"),
+    ("ssh", "ssh"),
+    ("smb", "smb"),
+    ("netbios-ssn", "smb"),
+    ("dcerpc", "dcerpc"),
+    ("krb5", "krb5"),
+    ("kerberos", "krb5"),
+    ("dhcp", "dhcp"),
+    ("sip", "sip"),
+    ("snmp", "snmp"),
+    ("tftp", "tftp"),
+    ("nfs", "nfs"),
+    ("ntp", "ntp"),
+    ("rdp", "rdp"),
+];
+
+/// The result of converting a single signature
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conversion {
+    pub rule: Rule,
+    /// Whether the signature was modified
+    pub changed: bool,
+    pub warnings: Vec<ConversionWarning>,
+}
+
+/// Find all content modifiers, which are written in the Snort 2 style
+///
+/// Returns the name of the sticky buffer, which should be used instead, along
+/// with the span of the modifier.
+pub fn legacy_modifiers(rule: &Rule) -> Vec<Spanned<String>> {
+    rule.options
+        .iter()
+        .flatten()
+        .filter_map(|(option, span)| {
+            let (keyword, _) = option.keyword();
+            let buffer = match option {
+                RuleOption::KeywordPair(_, _) if keyword == "uricontent" => "http.uri",
+                RuleOption::Buffer(_) => sticky_buffer(keyword)?,
+                _ => return None,
+            };
+            Some((buffer.to_string(), span.clone()))
+        })
+        .collect()
+}
+
+/// Convert a Snort 2 signature into a Suricata signature
+pub fn convert_rule(rule: &Rule) -> Conversion {
+    let mut converter = Converter {
+        protocol: rule.protocol().clone(),
+        changed: false,
+        warnings: vec![],
+        // The buffer selected by the conversion (None if the rule selected it)
+        generated_buffer: None,
+    };
+    let mut options = vec![];
+
+    // Convert the action
+    let mut action = rule.action.clone();
+    if let Some((Action::Other(name), span)) = &rule.action {
+        match name.as_str() {
+            "sdrop" => {
+                action = Some((Action::Drop, span.clone()));
+                options.push((
+                    RuleOption::Buffer(("noalert".to_string(), span.clone())),
+                    span.clone(),
+                ));
+                converter.changed = true;
+            }
+            "activate" | "dynamic" => converter.warn(
+                span,
+                format!("the {} action is not supported by Suricata", name),
+            ),
+            _ => {}
+        }
+    }
+
+    // Convert the options
+    let rule_options = rule.options.clone().unwrap_or_default();
+    let mut index = 0;
+    while index < rule_options.len() {
+        let (option, span) = &rule_options[index];
+        let (keyword, _) = option.keyword();
+        if keyword == "content" || keyword == "uricontent" {
+            // A content together with all of its modifiers
+            let group_length = rule_options[index + 1..]
+                .iter()
+                .take_while(|(option, _)| is_content_modifier(&option.keyword().0))
+                .count();
+            converter.convert_content(&rule_options[index..=index + group_length], &mut options);
+            index += group_length + 1;
+            continue;
+        }
+        // Sticky buffers of the rule
+        if matches!(option, RuleOption::Buffer(_)) && is_sticky_buffer(keyword) {
+            converter.generated_buffer = None;
+        }
+        if let Some(option) = converter.convert_option(option, span) {
+            options.push((option, span.clone()));
+        }
+        index += 1;
+    }
+
+    let (header, header_span) = &rule.header;
+    let mut header = header.clone();
+    header.protocol = converter.protocol.clone();
+    Conversion {
+        rule: Rule {
+            action,
+            header: (header, header_span.clone()),
+            options: rule.options.as_ref().map(|_| options),
+            options_span: rule.options_span.clone(),
+            comment: rule.comment.clone(),
+        },
+        changed: converter.changed,
+        warnings: converter.warnings,
+    }
+}
+
+/// Keeps the state while converting the options of a signature
+struct Converter {
+    protocol: Option<Spanned<Protocol>>,
+    changed: bool,
+    warnings: Vec<ConversionWarning>,
+    generated_buffer: Option<&'static str>,
+}
+
+impl Converter {
+    fn warn(&mut self, span: &Span, message: String) {
+        self.warnings.push(ConversionWarning {
+            span: span.clone(),
+            message,
+        });
+    }
+
+    /// Convert a content with its modifiers, selecting the sticky buffer before it
+    fn convert_content(
+        &mut self,
+        group: &[Spanned<RuleOption>],
+        options: &mut Vec<Spanned<RuleOption>>,
+    ) {
+        let (content, content_span) = &group[0];
+        let mut buffer = None;
+        let content = match content {
+            RuleOption::KeywordPair((keyword, keyword_span), values) if keyword == "uricontent" => {
+                buffer = Some("http.uri");
+                self.changed = true;
+                RuleOption::KeywordPair(
+                    ("content".to_string(), keyword_span.clone()),
+                    values.clone(),
+                )
+            }
+            content => content.clone(),
+        };
+
+        let mut modifiers = vec![];
+        group[1..].iter().for_each(|(modifier, span)| {
+            let (keyword, _) = modifier.keyword();
+            if let Some(sticky_buffer) = sticky_buffer(keyword) {
+                if keyword == "http_raw_cookie" {
+                    self.warn(
+                        span,
+                        "Suricata does not have a raw cookie buffer, http.cookie is used instead"
+                            .to_string(),
+                    );
+                }
+                if buffer.is_some_and(|buffer| buffer != sticky_buffer) {
+                    self.warn(
+                        span,
+                        format!(
+                            "the content is already inside {}, {} is ignored",
+                            buffer.unwrap_or_default(),
+                            keyword
+                        ),
+                    );
+                } else {
+                    buffer = Some(sticky_buffer);
+                }
+                self.changed = true;
+            } else if let Some(modifier) = self.convert_option(modifier, span) {
+                modifiers.push((modifier, span.clone()));
+            }
+        });
+
+        match buffer {
+            // Select the buffer, unless it is already selected
+            Some(buffer) if self.generated_buffer != Some(buffer) => {
+                options.push((
+                    RuleOption::Buffer((buffer.to_string(), content_span.clone())),
+                    content_span.clone(),
+                ));
+                self.generated_buffer = Some(buffer);
+            }
+            Some(_) => {}
+            // Switch back to the payload, if a buffer was selected by the conversion
+            None if self.generated_buffer.is_some() => {
+                options.push((
+                    RuleOption::Buffer(("pkt_data".to_string(), content_span.clone())),
+                    content_span.clone(),
+                ));
+                self.generated_buffer = None;
+            }
+            None => {}
+        }
+        options.push((content, content_span.clone()));
+        options.extend(modifiers);
+    }
+
+    /// Convert a single option, returns None if the option should be removed
+    fn convert_option(&mut self, option: &RuleOption, span: &Span) -> Option<RuleOption> {
+        let (keyword, keyword_span) = option.keyword();
+        if UNSUPPORTED_KEYWORDS.contains(&keyword.as_str()) {
+            self.warn(
+                span,
+                format!("{} is not supported by Suricata and was removed", keyword),
+            );
+            self.changed = true;
+            return None;
+        }
+        let RuleOption::KeywordPair(_, _) = option else {
+            return Some(option.clone());
+        };
+        let values = option.values();
+        let converted = match keyword.as_str() {
+            "urilen" => self.convert_urilen(&values),
+            "threshold" => self.convert_threshold(&values, span),
+            "flow" => self.convert_flow(&values),
+            "metadata" => {
+                let values = self.convert_metadata(&values);
+                if values.is_empty() {
+                    self.changed = true;
+                    return None;
+                }
+                values
+            }
+            _ => return Some(option.clone()),
+        };
+        // Only replace the option if its values changed
+        let original = values
+            .iter()
+            .map(|(value, _)| value.clone())
+            .collect::<Vec<_>>();
+        if converted.iter().map(|(value, _)| value).eq(original.iter()) {
+            return Some(option.clone());
+        }
+        self.changed = true;
+        Some(RuleOption::KeywordPair(
+            (keyword.clone(), keyword_span.clone()),
+            converted
+                .into_iter()
+                .map(|(value, span)| (OptionsVariable::Other((value, span.clone())), span))
+                .collect(),
+        ))
+    }
+
+    /// Rewrite `<=`/`>=` comparisons (not supported by Suricata) and remove the whitespace
+    fn convert_urilen(&mut self, values: &[Spanned<String>]) -> Vec<Spanned<String>> {
+        let mut values = values.to_vec();
+        let Some((length, span)) = values.first_mut() else {
+            return values;
+        };
+        let compact = length.split_whitespace().collect::<String>();
+        let bound = |number: &str| number.parse::<u32>().ok();
+        *length = if let Some(number) = compact.strip_prefix("<=").and_then(bound) {
+            match number.checked_add(1) {
+                Some(number) => format!("<{}", number),
+                None => {
+                    let span = span.clone();
+                    self.warn(&span, format!("urilen <={} can not be rewritten", number));
+                    compact
+                }
+            }
+        } else if let Some(number) = compact.strip_prefix(">=").and_then(bound) {
+            match number {
+                0 => {
+                    let span = span.clone();
+                    self.warn(&span, "urilen >=0 always matches".to_string());
+                    compact
+                }
+                number => format!(">{}", number - 1),
+            }
+        } else {
+            compact
+        };
+        values
+    }
+
+    /// Reorder the fields of threshold into "type, track, count, seconds"
+    fn convert_threshold(
+        &mut self,
+        values: &[Spanned<String>],
+        span: &Span,
+    ) -> Vec<Spanned<String>> {
+        let fields = ["type", "track", "count", "seconds"];
+        let mut ordered = vec![];
+        for field in fields {
+            let value = values
+                .iter()
+                .find(|(value, _)| value.split_whitespace().next() == Some(field));
+            match value {
+                Some((value, span)) => {
+                    let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+                    ordered.push((value, span.clone()));
+                }
+                None => {
+                    self.warn(span, format!("threshold is missing the {} field", field));
+                    return values.to_vec();
+                }
+            }
+        }
+        values
+            .iter()
+            .filter(|(value, _)| {
+                !fields.contains(&value.split_whitespace().next().unwrap_or_default())
+            })
+            .for_each(|(value, span)| {
+                self.warn(span, format!("unknown threshold field \"{}\"", value))
+            });
+        ordered
+    }
+
+    /// Use the names of the flow options, which are documented by Suricata
+    fn convert_flow(&mut self, values: &[Spanned<String>]) -> Vec<Spanned<String>> {
+        values
+            .iter()
+            .filter_map(|(value, span)| match value.as_str() {
+                "from_client" => Some(("to_server".to_string(), span.clone())),
+                "from_server" => Some(("to_client".to_string(), span.clone())),
+                "no_frag" | "only_frag" => {
+                    self.warn(
+                        span,
+                        format!(
+                            "flow option {} is not supported by Suricata and was removed",
+                            value
+                        ),
+                    );
+                    None
+                }
+                _ => Some((value.clone(), span.clone())),
+            })
+            .collect()
+    }
+
+    /// Move a single service into the protocol of the header
+    fn convert_metadata(&mut self, values: &[Spanned<String>]) -> Vec<Spanned<String>> {
+        let services = values
+            .iter()
+            .filter_map(|(value, span)| {
+                let mut parts = value.split_whitespace();
+                match (parts.next(), parts.next(), parts.next()) {
+                    (Some("service"), Some(service), None) => Some((service, span)),
+                    _ => None,
+                }
+            })
+            .collect::<Vec<_>>();
+        let is_transport = self
+            .protocol
+            .as_ref()
+            .is_some_and(|(protocol, _)| matches!(protocol, Protocol::Tcp | Protocol::Udp));
+        let [(service, span)] = services.as_slice() else {
+            if let (true, Some((_, span))) = (is_transport, services.first()) {
+                self.warn(
+                    span,
+                    "the signature applies to multiple services, the protocol was not changed"
+                        .to_string(),
+                );
+            }
+            return values.to_vec();
+        };
+        if !is_transport {
+            return values.to_vec();
+        }
+        let Some((_, protocol)) = SERVICES.iter().find(|(name, _)| name == service) else {
+            self.warn(
+                span,
+                format!(
+                    "service {} is not known to Suricata, the protocol was not changed",
+                    service
+                ),
+            );
+            return values.to_vec();
+        };
+        if let Some((name, _)) = &mut self.protocol {
+            *name = Protocol::parse(protocol);
+        }
+        values
+            .iter()
+            .filter(|(_, value_span)| value_span != *span)
+            .cloned()
+            .collect()
+    }
+}
+
+/// Get the sticky buffer, which replaces a Snort 2 content modifier
+fn sticky_buffer(modifier: &str) -> Option<&'static str> {
+    HTTP_MODIFIERS
+        .iter()
+        .find(|(name, _)| *name == modifier)
+        .map(|(_, buffer)| *buffer)
+}
+
+/// Check if the option modifies the previous content
+fn is_content_modifier(keyword: &str) -> bool {
+    CONTENT_MODIFIERS.contains(&keyword)
+        || keyword == "rawbytes"
+        || keyword == "http_encode"
+        || sticky_buffer(keyword).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use chumsky::Parser;
+
+    use super::*;
+
+    fn convert(line: &str) -> Conversion {
+        let (rule, _) = Rule::parser()
+            .parse(line)
+            .expect("the signature should parse");
+        convert_rule(&rule)
+    }
+
+    fn urilen(conversion: &Conversion) -> String {
+        let values = conversion.rule.option_values("urilen");
+        let (urilen, _) = values[0][0].clone();
+        urilen
+    }
+
+    #[test]
+    fn urilen_bounds_are_rewritten() {
+        let conversion = convert("alert tcp any any -> any 80 (urilen:<= 5; sid:1;)");
+        assert_eq!(urilen(&conversion), "<6");
+        let conversion = convert("alert tcp any any -> any 80 (urilen:>=100; sid:1;)");
+        assert_eq!(urilen(&conversion), ">99");
+        assert!(conversion.warnings.is_empty());
+    }
+
+    #[test]
+    fn largest_urilen_bound_is_kept() {
+        let line = "alert tcp any any -> any 80 (urilen:<=4294967295; sid:1;)";
+        let conversion = convert(line);
+        assert_eq!(urilen(&conversion), "<=4294967295");
+        let warning = &conversion.warnings[0];
+        assert_eq!(warning.message, "urilen <=4294967295 can not be rewritten");
+        assert_eq!(&line[warning.span.clone()], "<=4294967295");
+    }
+}
@@ -0,0 +1,259 @@
+//! Provides the store of the diagnostics published by the language server
+//!
+//! The diagnostics of a document come from several sources, which finish at
+//! different times: the syntax errors and the lints right after a change,
+//! Suricata once the verification is done. Publishing replaces every
+//! diagnostic of the document, so the store keeps the latest diagnostics of
+//! every source and publishes them together. Publications of a document are
+//! rate-limited, so sources finishing at almost the same time are published
+//! once.
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use lsp_types::Diagnostic;
+
+use crate::server_settings::DiagnosticsSettings;
+
+/// The shortest time between two publications of the same document
+pub const MIN_PUBLISH_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Where diagnostics come from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DiagnosticSource {
+    /// Lines, which could not be parsed
+    Syntax,
+    /// The lints of the signatures and of the workspace
+    Lints,
+    /// The verification with Suricata
+    Suricata,
+}
+
+impl DiagnosticSource {
+    /// Check if the diagnostics are kept until the source reports again
+    ///
+    /// Suricata is slow and debounced, dropping its diagnostics on every
+    /// change would make them disappear while typing.
+    fn outlives_version(&self) -> bool {
+        matches!(self, DiagnosticSource::Suricata)
+    }
+}
+
+/// When the diagnostics of a document should be published
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Publication {
+    Now,
+    /// After the delay, to respect the rate limit
+    Later(Duration),
+    /// A publication is already scheduled, it will include the diagnostics
+    Scheduled,
+}
+
+#[derive(Debug, Default)]
+struct DocumentDiagnostics {
+    /// The latest version of the document, a source reported for
+    version: i32,
+    /// The diagnostics of every source, along with the version they were computed for
+    sources: HashMap<DiagnosticSource, (i32, Vec<Diagnostic>)>,
+    settings: DiagnosticsSettings,
+    last_published: Option<Instant>,
+    scheduled: bool,
+}
+
+/// The diagnostics of every document, keyed by the URI and the source
+#[derive(Debug, Default)]
+pub struct DiagnosticsStore {
+    documents: Mutex<HashMap<String, DocumentDiagnostics>>,
+}
+
+impl DiagnosticsStore {
+    /// Store the diagnostics of a source, computed for a version of the document
+    ///
+    /// Returns false if the diagnostics are stale (a source already reported
+    /// for a newer version), they are dropped. Once the version advances, the
+    /// diagnostics of the older versions are dropped, except for the sources
+    /// which outlive a version.
+    pub fn update(
+        &self,
+        uri: &str,
+        version: i32,
+        source: DiagnosticSource,
+        diagnostics: Vec<Diagnostic>,
+        settings: &DiagnosticsSettings,
+    ) -> bool {
+        let Ok(mut documents) = self.documents.lock() else {
+            return false;
+        };
+        let document = documents.entry(uri.to_string()).or_default();
+        if version < document.version {
+            return false;
+        }
+        if version > document.version {
+            document.version = version;
+            document.sources.retain(|source, (source_version, _)| {
+                *source_version == version || source.outlives_version()
+            });
+        }
+        document.sources.insert(source, (version, diagnostics));
+        document.settings = settings.clone();
+        true
+    }
+
+    /// Get all diagnostics of a document, along with the latest version
+    ///
+    /// The diagnostics are filtered by the settings of the diagnostics.
+    pub fn diagnostics(&self, uri: &str) -> Option<(i32, Vec<Diagnostic>)> {
+        let documents = self.documents.lock().ok()?;
+        let document = documents.get(uri)?;
+        let diagnostics = document
+            .sources
+            .values()
+            .flat_map(|(_, diagnostics)| diagnostics.iter().cloned())
+            .collect();
+        Some((document.version, document.settings.filter(diagnostics)))
+    }
+
+    /// Decide when the diagnostics of a document are published
+    ///
+    /// Remembers the publication, so the caller has to publish (now or after
+    /// the delay) and call [DiagnosticsStore::published] afterwards.
+    pub fn schedule(&self, uri: &str, now: Instant) -> Publication {
+        let Ok(mut documents) = self.documents.lock() else {
+            return Publication::Scheduled;
+        };
+        let document = documents.entry(uri.to_string()).or_default();
+        if document.scheduled {
+            return Publication::Scheduled;
+        }
+        let elapsed = document
+            .last_published
+            .map_or(MIN_PUBLISH_INTERVAL, |last_published| now - last_published);
+        if elapsed >= MIN_PUBLISH_INTERVAL {
+            document.last_published = Some(now);
+            return Publication::Now;
+        }
+        document.scheduled = true;
+        Publication::Later(MIN_PUBLISH_INTERVAL - elapsed)
+    }
+
+    /// Remember that the diagnostics of a scheduled publication were published
+    pub fn published(&self, uri: &str, now: Instant) {
+        if let Ok(mut documents) = self.documents.lock() {
+            if let Some(document) = documents.get_mut(uri) {
+                document.scheduled = false;
+                document.last_published = Some(now);
+            }
+        }
+    }
+
+    /// Forget the diagnostics of a document
+    pub fn remove(&self, uri: &str) {
+        if let Ok(mut documents) = self.documents.lock() {
+            documents.remove(uri);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const URI: &str = "file:///a.rules";
+
+    fn diagnostic(message: &str) -> Diagnostic {
+        Diagnostic {
+            message: message.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Store the diagnostics of a source, with one diagnostic named after the source and version
+    fn update(store: &DiagnosticsStore, version: i32, source: DiagnosticSource) -> bool {
+        let message = format!("{:?} {}", source, version);
+        let settings = DiagnosticsSettings::default();
+        store.update(URI, version, source, vec![diagnostic(&message)], &settings)
+    }
+
+    /// Get the version and the sorted messages of the published diagnostics
+    fn published(store: &DiagnosticsStore) -> (i32, Vec<String>) {
+        let (version, diagnostics) = store.diagnostics(URI).unwrap();
+        let mut messages = diagnostics
+            .into_iter()
+            .map(|diagnostic| diagnostic.message)
+            .collect::<Vec<_>>();
+        messages.sort();
+        (version, messages)
+    }
+
+    #[test]
+    fn sources_are_published_together() {
+        let store = DiagnosticsStore::default();
+        assert!(update(&store, 1, DiagnosticSource::Syntax));
+        assert!(update(&store, 1, DiagnosticSource::Lints));
+        assert_eq!(
+            published(&store),
+            (1, vec!["Lints 1".into(), "Syntax 1".into()])
+        );
+        // Suricata finishes later, the other sources are kept
+        assert!(update(&store, 1, DiagnosticSource::Suricata));
+        let expected = vec!["Lints 1".into(), "Suricata 1".into(), "Syntax 1".into()];
+        assert_eq!(published(&store), (1, expected));
+        // A source reporting again replaces only its own diagnostics
+        store.update(URI, 1, DiagnosticSource::Lints, vec![], &Default::default());
+        let expected = vec!["Suricata 1".into(), "Syntax 1".into()];
+        assert_eq!(published(&store), (1, expected));
+    }
+
+    #[test]
+    fn older_versions_are_dropped() {
+        let store = DiagnosticsStore::default();
+        update(&store, 1, DiagnosticSource::Syntax);
+        update(&store, 1, DiagnosticSource::Lints);
+        update(&store, 1, DiagnosticSource::Suricata);
+        // The document changed, Suricata is kept until it reports again
+        assert!(update(&store, 2, DiagnosticSource::Lints));
+        let expected = vec!["Lints 2".into(), "Suricata 1".into()];
+        assert_eq!(published(&store), (2, expected));
+        // The lints of the old version finish after the ones of the new version
+        assert!(!update(&store, 1, DiagnosticSource::Lints));
+        assert!(!update(&store, 1, DiagnosticSource::Syntax));
+        let expected = vec!["Lints 2".into(), "Suricata 1".into()];
+        assert_eq!(published(&store), (2, expected));
+        assert!(update(&store, 2, DiagnosticSource::Suricata));
+        let expected = vec!["Lints 2".into(), "Suricata 2".into()];
+        assert_eq!(published(&store), (2, expected));
+    }
+
+    #[test]
+    fn publications_are_rate_limited() {
+        let store = DiagnosticsStore::default();
+        let start = Instant::now();
+        assert_eq!(store.schedule(URI, start), Publication::Now);
+        let now = start + Duration::from_millis(30);
+        assert_eq!(
+            store.schedule(URI, now),
+            Publication::Later(MIN_PUBLISH_INTERVAL - Duration::from_millis(30))
+        );
+        // Sources finishing before the scheduled publication are included in it
+        assert_eq!(store.schedule(URI, now), Publication::Scheduled);
+        let now = start + MIN_PUBLISH_INTERVAL;
+        store.published(URI, now);
+        assert!(matches!(store.schedule(URI, now), Publication::Later(_)));
+        store.published(URI, now);
+        let now = now + MIN_PUBLISH_INTERVAL;
+        assert_eq!(store.schedule(URI, now), Publication::Now);
+        // Other documents have their own rate limit
+        assert_eq!(store.schedule("file:///b.rules", now), Publication::Now);
+    }
+
+    #[test]
+    fn closed_documents_are_forgotten() {
+        let store = DiagnosticsStore::default();
+        update(&store, 3, DiagnosticSource::Lints);
+        store.remove(URI);
+        assert_eq!(store.diagnostics(URI), None);
+        // A reopened document starts again at its first version
+        assert!(update(&store, 1, DiagnosticSource::Lints));
+        assert_eq!(published(&store), (1, vec!["Lints 1".into()]));
+    }
+}
@@ -0,0 +1,576 @@
+//! Provides the generation of documentation pages for signatures
+//!
+//! Every signature (or every classtype, with all of its signatures) gets its own
+//! Markdown page, which contains the formatted signature, its message, the
+//! decoded content patterns, the references as links and the signatures which
+//! set the flowbits it checks. An index page links to all other pages.
+//!
+//! The variables of the header are shown with their values, as the lints see
+//! them (see [Variables]).
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use crate::format::FormatStyle;
+use crate::rule::references::Reference;
+use crate::rule::{Rule, Spanned, AST};
+use crate::variables::{VariableKind, Variables};
+
+/// Template of the section of a single signature
+const RULE_TEMPLATE: &str = "## {{title}}
+
+```
+{{rule}}
+```
+
+| Field | Value |
+| --- | --- |
+| File | {{location}} |
+| Message | {{msg}} |
+| Action | {{action}} |
+| Protocol | {{protocol}} |
+| sid | {{sid}} |
+| rev | {{rev}} |
+| classtype | {{classtype}} |
+
+### Content patterns
+{{contents}}
+
+### References
+{{references}}
+
+### Flowbits
+{{flowbits}}
+
+### Variables
+{{variables}}
+";
+
+/// Template of a page
+const PAGE_TEMPLATE: &str = "# {{title}}
+
+{{sections}}";
+
+/// How the signatures are split into pages
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "server", derive(clap::ValueEnum))]
+pub enum DocGrouping {
+    /// One page per signature
+    #[default]
+    Rule,
+    /// One page per classtype
+    Classtype,
+}
+
+/// A generated documentation page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocPage {
+    /// The file name of the page (e.g. "sid-1000.md")
+    pub name: String,
+    pub content: String,
+}
+
+/// A signature, along with its location
+struct Entry<'a> {
+    file: &'a str,
+    line: u32,
+    rule: &'a Rule,
+}
+
+/// Generate the documentation pages for all signatures
+///
+/// Every file is provided as a pair of its name and its AST. The last page is
+/// the index, which links to all other pages.
+pub fn generate(
+    files: &[(String, &AST)],
+    grouping: DocGrouping,
+    variables: &Variables,
+) -> Vec<DocPage> {
+    let mut entries = files
+        .iter()
+        .flat_map(|(file, ast)| {
+            ast.rules.iter().map(|(line, (rule, _))| Entry {
+                file,
+                line: *line,
+                rule,
+            })
+        })
+        .collect::<Vec<_>>();
+    entries.sort_by_key(|entry| (entry.file, entry.line));
+
+    // Index the signatures, which set a flowbit
+    let mut setters: HashMap<String, Vec<&Entry>> = HashMap::new();
+    entries.iter().for_each(|entry| {
+        flowbits(entry.rule)
+            .into_iter()
+            .filter(|(command, _)| command == "set" || command == "toggle")
+            .for_each(|(_, name)| setters.entry(name).or_default().push(entry));
+    });
+
+    let mut pages = vec![];
+    let mut names = HashSet::new();
+    match grouping {
+        DocGrouping::Rule => entries.iter().for_each(|entry| {
+            let name = unique_name(&mut names, page_name(entry));
+            let title = entry_title(entry);
+            let sections = render_rule(entry, &setters, variables);
+            pages.push((name, title, sections));
+        }),
+        DocGrouping::Classtype => {
+            let mut classtypes: Vec<(String, Vec<&Entry>)> = vec![];
+            entries.iter().for_each(|entry| {
+                let classtype = entry
+                    .rule
+                    .classtype()
+                    .map(|(classtype, _)| classtype)
+                    .unwrap_or_else(|| "unclassified".to_string());
+                match classtypes.iter_mut().find(|(name, _)| *name == classtype) {
+                    Some((_, group)) => group.push(entry),
+                    None => classtypes.push((classtype, vec![entry])),
+                }
+            });
+            classtypes.sort_by(|(a, _), (b, _)| a.cmp(b));
+            classtypes.into_iter().for_each(|(classtype, group)| {
+                let name = unique_name(&mut names, format!("{}.md", slug(&classtype)));
+                let sections = group
+                    .iter()
+                    .map(|entry| render_rule(entry, &setters, variables))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                pages.push((name, classtype, sections));
+            });
+        }
+    }
+
+    // Link all pages from the index
+    let index = pages
+        .iter()
+        .map(|(name, title, _)| format!("- [{}]({})", link_text(title), name))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let mut pages = pages
+        .into_iter()
+        .map(|(name, title, sections)| DocPage {
+            name,
+            content: render(PAGE_TEMPLATE, &[("title", title), ("sections", sections)]),
+        })
+        .collect::<Vec<_>>();
+    pages.push(DocPage {
+        name: "index.md".to_string(),
+        content: render(
+            PAGE_TEMPLATE,
+            &[
+                ("title", "Signatures".to_string()),
+                ("sections", index + "\n"),
+            ],
+        ),
+    });
+    pages
+}
+
+/// Replace every `{{name}}` inside the template with its value
+///
+/// The template is rendered in a single pass, so placeholders inside the
+/// values (e.g. a msg containing "{{rule}}") are kept as they are. Unknown
+/// placeholders are kept as well.
+fn render(template: &str, values: &[(&str, String)]) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        rendered.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let value = rest.find("}}").and_then(|end| {
+            values
+                .iter()
+                .find(|(name, _)| *name == &rest[2..end])
+                .map(|(_, value)| (value, end + 2))
+        });
+        match value {
+            Some((value, end)) => {
+                rendered.push_str(value);
+                rest = &rest[end..];
+            }
+            None => {
+                rendered.push_str("{{");
+                rest = &rest[2..];
+            }
+        }
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+/// Render the section of a single signature
+fn render_rule(
+    entry: &Entry,
+    setters: &HashMap<String, Vec<&Entry>>,
+    variables: &Variables,
+) -> String {
+    let rule = entry.rule;
+    let value = |spanned: Option<Spanned<String>>| {
+        spanned
+            .map(|(value, _)| table_cell(&value))
+            .unwrap_or_else(|| "-".to_string())
+    };
+    let number =
+        |spanned: Option<Spanned<u32>>| value(spanned.map(|(n, span)| (n.to_string(), span)));
+    let list = |items: Vec<String>| {
+        if items.is_empty() {
+            "None".to_string()
+        } else {
+            items
+                .iter()
+                .map(|item| format!("- {}", item))
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+    };
+
+    let contents = rule
+        .contents()
+        .into_iter()
+        .map(|(content, _)| code_span(&decode_content(&content)))
+        .collect();
+    let references = rule.references().iter().map(reference_link).collect();
+    let flowbit_lines = flowbits(rule)
+        .into_iter()
+        .map(|(command, name)| {
+            if command != "isset" && command != "isnotset" {
+                return format!("{} {}", command, code_span(&name));
+            }
+            let set_by = setters
+                .get(&name)
+                .map(|setters| {
+                    setters
+                        .iter()
+                        .map(|setter| entry_title(setter))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                })
+                .unwrap_or_else(|| "no signature".to_string());
+            format!("{} {} (set by {})", command, code_span(&name), set_by)
+        })
+        .collect();
+
+    render(
+        RULE_TEMPLATE,
+        &[
+            ("title", entry_title(entry)),
+            ("rule", FormatStyle::default().format_rule(rule)),
+            (
+                "location",
+                table_cell(&format!("{}:{}", entry.file, entry.line + 1)),
+            ),
+            ("msg", value(rule.msg())),
+            (
+                "action",
+                rule.action
+                    .as_ref()
+                    .map(|(action, _)| action.to_string())
+                    .unwrap_or_else(|| "-".to_string()),
+            ),
+            (
+                "protocol",
+                rule.protocol()
+                    .as_ref()
+                    .map(|(protocol, _)| protocol.to_string())
+                    .unwrap_or_else(|| "-".to_string()),
+            ),
+            ("sid", number(rule.sid())),
+            ("rev", number(rule.rev())),
+            ("classtype", value(rule.classtype())),
+            ("contents", list(contents)),
+            ("references", list(references)),
+            ("flowbits", list(flowbit_lines)),
+            ("variables", list(expand_variables(rule, variables))),
+        ],
+    )
+}
+
+/// Get the title of a signature (its message and sid)
+fn entry_title(entry: &Entry) -> String {
+    let msg = entry.rule.msg().map(|(msg, _)| msg);
+    match (msg, entry.rule.sid()) {
+        (Some(msg), Some((sid, _))) => format!("{} (sid {})", msg, sid),
+        (Some(msg), None) => msg,
+        (None, Some((sid, _))) => format!("sid {}", sid),
+        (None, None) => format!("{}:{}", entry.file, entry.line + 1),
+    }
+}
+
+/// Get the name of the page of a signature
+fn page_name(entry: &Entry) -> String {
+    match entry.rule.sid() {
+        Some((sid, _)) => format!("sid-{}.md", sid),
+        None => {
+            let stem = Path::new(entry.file)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default();
+            format!("{}-line-{}.md", slug(&stem), entry.line + 1)
+        }
+    }
+}
+
+/// Make sure every page has its own name (e.g. for duplicate sids)
+fn unique_name(names: &mut HashSet<String>, name: String) -> String {
+    let stem = name.trim_end_matches(".md").to_string();
+    let mut unique = name;
+    let mut counter = 2;
+    while !names.insert(unique.clone()) {
+        unique = format!("{}-{}.md", stem, counter);
+        counter += 1;
+    }
+    unique
+}
+
+/// Keep only the characters which are safe inside a file name
+fn slug(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+/// Get the flowbit operations of a signature as (command, name)
+fn flowbits(rule: &Rule) -> Vec<(String, String)> {
+    rule.option_values("flowbits")
+        .into_iter()
+        .filter_map(|values| {
+            let mut values = values.into_iter();
+            let (command, _) = values.next()?;
+            let names = values.next().map(|(name, _)| name);
+            Some(match names {
+                Some(names) => names
+                    .split(['|', '&'])
+                    .map(|name| (command.clone(), name.trim().to_string()))
+                    .collect(),
+                None => vec![(command, String::new())],
+            })
+        })
+        .flatten()
+        .collect()
+}
+
+/// Get the variables used inside the header of a signature, along with their values
+fn expand_variables(rule: &Rule, definitions: &Variables) -> Vec<String> {
+    let addresses = rule
+        .addresses()
+        .into_iter()
+        .flat_map(|(address, _)| address.find_variables(&None).unwrap_or_default())
+        .map(|(name, _)| (VariableKind::Address, name));
+    let ports = rule
+        .ports()
+        .into_iter()
+        .flat_map(|(port, _)| port.find_variables(&None).unwrap_or_default())
+        .map(|(name, _)| (VariableKind::Port, name));
+    let mut variables = addresses
+        .chain(ports)
+        .map(|(kind, name)| match definitions.get(kind, &name) {
+            Some(definition) => format!(
+                "{} = {} ({} defined in {})",
+                code_span(&format!("${}", name)),
+                code_span(&definition.value),
+                kind,
+                definition.source
+            ),
+            None => format!("{} (undefined {})", code_span(&format!("${}", name)), kind),
+        })
+        .collect::<Vec<_>>();
+    variables.sort();
+    variables.dedup();
+    variables
+}
+
+/// Put the text into a code span, whose backticks outnumber the ones inside the text
+fn code_span(text: &str) -> String {
+    let backticks = text.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(backticks + 1);
+    if text.starts_with('`') || text.ends_with('`') {
+        format!("{} {} {}", fence, text, fence)
+    } else {
+        format!("{}{}{}", fence, text, fence)
+    }
+}
+
+/// Escape the pipes, which would end the cell of a table
+fn table_cell(text: &str) -> String {
+    text.replace('|', "\\|")
+}
+
+/// Escape the brackets (and backslashes), which would end the text of a link
+fn link_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('[', "\\[")
+        .replace(']', "\\]")
+}
+
+/// Decode a content pattern into readable text
+///
+/// Hex bytes (e.g. `|0d 0a|`) are shown as printable characters where possible
+/// and as `\xNN` otherwise, the negation and the quotes are kept out.
+pub fn decode_content(content: &str) -> String {
+    let (negated, content) = match content.trim().strip_prefix('!') {
+        Some(content) => (true, content.trim()),
+        None => (false, content.trim()),
+    };
+    let content = content.trim_matches('"');
+    let mut decoded = String::new();
+    content.split('|').enumerate().for_each(|(index, part)| {
+        // Every second part is inside pipes (hex bytes)
+        if index % 2 == 0 {
+            decoded.push_str(part);
+            return;
+        }
+        part.split_whitespace()
+            .flat_map(|bytes| {
+                bytes
+                    .as_bytes()
+                    .chunks(2)
+                    .map(|byte| String::from_utf8_lossy(byte).to_string())
+                    .collect::<Vec<_>>()
+            })
+            .for_each(|byte| match u8::from_str_radix(&byte, 16) {
+                Ok(byte) if byte.is_ascii_graphic() || byte == b' ' => decoded.push(byte as char),
+                Ok(byte) => decoded.push_str(&format!("\\x{:02x}", byte)),
+                Err(_) => decoded.push_str(&byte),
+            });
+    });
+    if negated {
+        format!("not {}", decoded)
+    } else {
+        decoded
+    }
+}
+
+/// Get the link of a reference (e.g. "cve,2021-44228")
+///
+/// Unknown systems are shown without a link.
+pub fn reference_link(reference: &Reference) -> String {
+    let (system, _) = &reference.scheme;
+    let Some((id, _)) = &reference.value else {
+        return system.to_string();
+    };
+    match reference_url(system, id) {
+        Some(url) => format!("[{}]({})", link_text(&format!("{}: {}", system, id)), url),
+        None => format!("{}: {}", system, id),
+    }
+}
+
+/// Get the URL of a reference (e.g. system "cve" and id "2021-44228")
+///
+/// The prefixes are the ones of the reference.config shipped with Suricata,
+/// except for the CVEs, which link to the NVD (with or without the "CVE-" prefix).
+pub fn reference_url(system: &str, id: &str) -> Option<String> {
+    Some(match system {
+        "url" if id.starts_with("http://") || id.starts_with("https://") => id.to_string(),
+        "url" => format!("http://{}", id),
+        "cve" => format!(
+            "https://nvd.nist.gov/vuln/detail/CVE-{}",
+            id.trim_start_matches("CVE-").trim_start_matches("cve-")
+        ),
+        "bugtraq" => format!("http://www.securityfocus.com/bid/{}", id),
+        "nessus" => format!("http://cgi.nessus.org/plugins/dump.php3?id={}", id),
+        "arachnids" => format!("http://www.whitehats.com/info/IDS{}", id),
+        "mcafee" => format!("http://vil.nai.com/vil/dispVirus.asp?virus_k={}", id),
+        "exploitdb" => format!("http://www.exploit-db.com/exploits/{}", id),
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::server_settings::VariablesSettings;
+
+    const INPUT: &str = include_str!("../tests/fixtures/doc/input.rules");
+
+    /// The defaults, with HOME_NET defined by the settings
+    fn definitions() -> Variables {
+        let mut settings = VariablesSettings::default();
+        settings
+            .address_groups
+            .insert("HOME_NET".to_string(), "[10.0.0.0/8]".to_string());
+        Variables::new(&settings)
+    }
+
+    /// Compare the generated pages with the ones in tests/fixtures/doc/<directory>
+    fn assert_golden(grouping: DocGrouping, directory: &str) {
+        let (ast, _) = AST::parse_str(INPUT);
+        let pages = generate(
+            &[("input.rules".to_string(), &ast)],
+            grouping,
+            &definitions(),
+        );
+        let directory = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/doc")
+            .join(directory);
+        let mut expected_names = fs::read_dir(&directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        expected_names.sort();
+        let mut names = pages
+            .iter()
+            .map(|page| page.name.clone())
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, expected_names);
+        pages.iter().for_each(|page| {
+            let expected = fs::read_to_string(directory.join(&page.name)).unwrap();
+            assert_eq!(page.content, expected, "{} differs", page.name);
+        });
+    }
+
+    #[test]
+    fn pages_per_rule_match_the_golden_files() {
+        assert_golden(DocGrouping::Rule, "rule");
+    }
+
+    #[test]
+    fn pages_per_classtype_match_the_golden_files() {
+        assert_golden(DocGrouping::Classtype, "classtype");
+    }
+
+    #[test]
+    fn placeholders_inside_values_are_not_rendered() {
+        let values = [
+            ("title", "{{rule}}".to_string()),
+            ("rule", "alert".to_string()),
+        ];
+        assert_eq!(
+            render("{{title}} {{rule}} {{unknown}} {{", &values),
+            "{{rule}} alert {{unknown}} {{"
+        );
+    }
+
+    #[test]
+    fn variables_are_listed_once() {
+        let (ast, _) =
+            AST::parse_str("alert tcp $HOME_NET $P -> [$EXTERNAL_NET,$HOME_NET] $P (sid:1;)");
+        let (rule, _) = ast.rules.values().next().unwrap();
+        assert_eq!(
+            expand_variables(rule, &definitions()),
+            vec![
+                "`$EXTERNAL_NET` = `!$HOME_NET` (address group defined in the default suricata.yaml)",
+                "`$HOME_NET` = `[10.0.0.0/8]` (address group defined in the settings)",
+                "`$P` (undefined port group)",
+            ]
+        );
+    }
+
+    #[test]
+    fn markdown_is_escaped() {
+        assert_eq!(code_span("a`b"), "``a`b``");
+        assert_eq!(code_span("`a``"), "``` `a`` ```");
+        assert_eq!(table_cell("a | b"), "a \\| b");
+        assert_eq!(link_text("[a] \\ b"), "\\[a\\] \\\\ b");
+    }
+}
@@ -0,0 +1,165 @@
+//! Provides the document links for the language server
+//!
+//! The targets of the references (e.g. `reference:url,example.com;`) become
+//! clickable, as do the keywords, which link to their documentation. The
+//! documentation of a keyword is only looked up, once the link is resolved.
+use std::collections::HashMap;
+
+use lsp_types::{DocumentLink, Position, Range, Url};
+use serde_json::Value;
+
+use crate::doc::reference_url;
+use crate::rule::{Span, AST};
+use crate::suricata::Keyword;
+
+/// Get the links of the references and the keywords inside a file
+///
+/// Only the keywords known to Suricata are linked. Their links have no target
+/// yet, it is set by [resolve_document_link].
+pub fn get_document_links(ast: &AST, keywords: &HashMap<String, Keyword>) -> Vec<DocumentLink> {
+    let mut links = vec![];
+    ast.rules.iter().for_each(|(line, (rule, _))| {
+        rule.references().into_iter().for_each(|reference| {
+            let ((system, _), Some((id, span))) = (reference.scheme, reference.value) else {
+                return;
+            };
+            let target = reference_url(&system, &id).and_then(|target| Url::parse(&target).ok());
+            if let Some(target) = target {
+                links.push(document_link(*line, span, Some(target), None));
+            }
+        });
+        rule.options.iter().flatten().for_each(|(option, _)| {
+            let (keyword, span) = option.keyword();
+            if !keywords.contains_key(keyword) {
+                return;
+            }
+            let data = Value::String(keyword.clone());
+            links.push(document_link(*line, span.clone(), None, Some(data)));
+        });
+    });
+    links.sort_by_key(|link| (link.range.start.line, link.range.start.character));
+    links
+}
+
+/// Set the target of a keyword link to the documentation of the keyword
+///
+/// Returns None if the keyword has no documentation.
+pub fn resolve_document_link(
+    mut link: DocumentLink,
+    keywords: &HashMap<String, Keyword>,
+) -> Option<DocumentLink> {
+    let keyword = link.data.as_ref()?.as_str()?;
+    let (Keyword::NoOption(record) | Keyword::Other(record)) = keywords.get(keyword)?;
+    link.target = Some(Url::parse(&record.documentation).ok()?);
+    link.tooltip = Some(format!("Documentation of {}", keyword));
+    Some(link)
+}
+
+fn document_link(line: u32, span: Span, target: Option<Url>, data: Option<Value>) -> DocumentLink {
+    DocumentLink {
+        range: Range::new(
+            Position::new(line, span.start as u32),
+            Position::new(line, span.end as u32),
+        ),
+        target,
+        tooltip: None,
+        data,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::suricata::KeywordRecord;
+
+    fn keywords() -> HashMap<String, Keyword> {
+        let record = |name: &str, documentation: &str| {
+            KeywordRecord::to_keyword(KeywordRecord {
+                name: name.to_string(),
+                description: String::new(),
+                app_layer: String::new(),
+                features: String::new(),
+                documentation: documentation.to_string(),
+            })
+        };
+        HashMap::from([
+            record(
+                "content",
+                "https://docs.suricata.io/en/latest/rules/payload-keywords.html#content",
+            ),
+            record("msg", ""),
+        ])
+    }
+
+    /// Get the links as (line, span, target, data)
+    fn links(text: &str) -> Vec<(u32, Span, Option<String>, Option<Value>)> {
+        let (ast, _) = AST::parse_str(text);
+        get_document_links(&ast, &keywords())
+            .into_iter()
+            .map(|link| {
+                let range = link.range;
+                (
+                    range.start.line,
+                    range.start.character as usize..range.end.character as usize,
+                    link.target.map(String::from),
+                    link.data,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn references_link_to_their_target() {
+        let text = "\
+alert tcp any any -> any any (reference:url,example.com/a,b; reference:cve,2021-44228; \
+reference:cve,CVE-2014-0160; reference:unknown,1; sid:1;)";
+        let targets = links(text)
+            .into_iter()
+            .filter_map(|(_, span, target, _)| Some((span, target?)))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            targets,
+            [
+                (44..59, "http://example.com/a,b".to_string()),
+                (
+                    75..85,
+                    "https://nvd.nist.gov/vuln/detail/CVE-2021-44228".to_string()
+                ),
+                (
+                    101..114,
+                    "https://nvd.nist.gov/vuln/detail/CVE-2014-0160".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn known_keywords_are_resolved_to_their_documentation() {
+        let text = "alert tcp any any -> any any (msg:\"a\"; content:\"x\"; nocase; sid:1;)";
+        let keyword_links = links(text)
+            .into_iter()
+            .filter_map(|(line, span, _, data)| Some((line, span, data?)))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            keyword_links,
+            [
+                (0, 30..33, Value::from("msg")),
+                (0, 39..46, Value::from("content")),
+            ]
+        );
+
+        let (ast, _) = AST::parse_str(text);
+        let mut links = get_document_links(&ast, &keywords()).into_iter();
+        // Keywords without documentation have no target
+        assert_eq!(
+            resolve_document_link(links.next().unwrap(), &keywords()),
+            None
+        );
+        let content = resolve_document_link(links.next().unwrap(), &keywords()).unwrap();
+        assert_eq!(
+            content.target.unwrap().as_str(),
+            "https://docs.suricata.io/en/latest/rules/payload-keywords.html#content"
+        );
+        assert_eq!(content.tooltip.unwrap(), "Documentation of content");
+    }
+}
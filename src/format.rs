@@ -0,0 +1,486 @@
+//! Provides the formatting logic for rule files
+//!
+//! The language server and the command line share the same code path, so the
+//! editor and the CLI never disagree on how a formatted file looks.
+//! Only lines which contain a parsed signature are touched, comments and lines
+//! which could not be parsed are left as they are. Every signature is kept on
+//! a single line, since the signatures are parsed line by line.
+use std::collections::HashMap;
+
+use ropey::{Rope, RopeSlice};
+
+use crate::rule::header::Protocol;
+use crate::rule::options::{OptionsVariable, RuleOption};
+use crate::rule::threshold::Threshold;
+use crate::rule::{Rule, Span, Spanned, AST};
+use crate::server_settings::FormattingSettings;
+
+/// Describes how the signatures should be formatted
+///
+/// The default style produces the same text as the [std::fmt::Display]
+/// implementation of [Rule].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatStyle {
+    /// Write `msg: "x"` instead of `msg:"x"`
+    pub space_after_option_colon: bool,
+    /// Write `msg: "x"; sid: 1;` instead of `msg: "x";sid: 1;`
+    pub space_after_semicolon: bool,
+    /// Put the options into the canonical order (see [canonical_option_order])
+    pub sort_options: bool,
+    /// Lowercase the action, the protocol and the keywords and trim the values
+    pub sanitize: bool,
+    /// Make sure a formatted file ends with a line ending
+    pub insert_final_newline: bool,
+}
+
+impl Default for FormatStyle {
+    fn default() -> Self {
+        FormatStyle {
+            space_after_option_colon: true,
+            space_after_semicolon: true,
+            sort_options: false,
+            sanitize: false,
+            insert_final_newline: false,
+        }
+    }
+}
+
+impl From<&FormattingSettings> for FormatStyle {
+    fn from(settings: &FormattingSettings) -> Self {
+        FormatStyle {
+            space_after_option_colon: settings.space_after_option_colon,
+            space_after_semicolon: settings.space_after_semicolon,
+            sort_options: settings.sort_options,
+            sanitize: settings.sanitize_on_format,
+            insert_final_newline: settings.insert_final_newline,
+        }
+    }
+}
+
+impl FormatStyle {
+    /// Format a single signature, along with its comment
+    pub fn format_rule(&self, rule: &Rule) -> String {
+        let formatted = self.format_signature(rule);
+        match &rule.comment {
+            Some((comment, _)) => format!("{} {}", formatted, comment),
+            None => formatted,
+        }
+    }
+
+    /// Format a single signature, without its comment
+    fn format_signature(&self, rule: &Rule) -> String {
+        let mut header = String::new();
+        if let Some((action, _)) = &rule.action {
+            header.push_str(&self.sanitized(action.to_string()));
+            header.push(' ');
+        }
+        let (rule_header, _) = &rule.header;
+        match &rule_header.protocol {
+            Some((protocol, _)) if self.sanitize => {
+                let mut sanitized_header = rule_header.clone();
+                let protocol = Protocol::parse(&protocol.as_str().to_lowercase());
+                sanitized_header.protocol = Some((protocol, 0..0));
+                header.push_str(&sanitized_header.to_string());
+            }
+            _ => header.push_str(&rule_header.to_string()),
+        }
+        let Some(options) = &rule.options else {
+            return header;
+        };
+        if options.is_empty() {
+            return format!("{} ()", header);
+        }
+
+        let mut order = (0..options.len()).collect::<Vec<_>>();
+        if self.sort_options {
+            order = canonical_option_order(options);
+        }
+        let options = order
+            .into_iter()
+            .map(|index| self.format_option(&options[index].0))
+            .collect::<Vec<_>>();
+
+        match self.space_after_semicolon {
+            true => format!("{} ({};)", header, options.join("; ")),
+            false => format!("{} ({};)", header, options.join(";")),
+        }
+    }
+
+    /// Format a single option
+    fn format_option(&self, option: &RuleOption) -> String {
+        match option {
+            RuleOption::KeywordPair((keyword, _), values) => {
+                let separator = if self.space_after_option_colon {
+                    ": "
+                } else {
+                    ":"
+                };
+                // The fields of a threshold are written in the canonical order
+                if let Some(threshold) = Threshold::parse(option, &(0..0)) {
+                    let keyword = self.sanitized(keyword.clone());
+                    return format!("{}{}{}", keyword, separator, threshold);
+                }
+                let values = values
+                    .iter()
+                    .map(|(value, _)| match value {
+                        OptionsVariable::Other((other, _)) if self.sanitize => {
+                            other.trim().to_string()
+                        }
+                        value => value.to_string(),
+                    })
+                    .collect::<Vec<_>>();
+                format!(
+                    "{}{}{}",
+                    self.sanitized(keyword.clone()),
+                    separator,
+                    values.join(", ")
+                )
+            }
+            RuleOption::Buffer((keyword, _)) => self.sanitized(keyword.clone()),
+        }
+    }
+
+    /// Lowercase the text, if the style sanitizes the signatures
+    fn sanitized(&self, text: String) -> String {
+        if self.sanitize {
+            text.to_lowercase()
+        } else {
+            text
+        }
+    }
+}
+
+/// Get the canonical order of the options, as indices into the options
+///
+/// The message comes first, followed by the detection options (in their
+/// original order, since modifiers and sticky buffers depend on it), the
+/// references, the classtype, the metadata and finally gid, sid and rev.
+pub fn canonical_option_order(options: &[Spanned<RuleOption>]) -> Vec<usize> {
+    let rank = |option: &RuleOption| {
+        let keyword = match option {
+            RuleOption::KeywordPair((keyword, _), _) => keyword,
+            RuleOption::Buffer((keyword, _)) => keyword,
+        };
+        match keyword.to_lowercase().as_str() {
+            "msg" => 0,
+            "reference" => 2,
+            "classtype" => 3,
+            "metadata" => 4,
+            "gid" => 5,
+            "sid" => 6,
+            "rev" => 7,
+            _ => 1,
+        }
+    };
+    let mut order = (0..options.len()).collect::<Vec<_>>();
+    // The sort is stable, so options with the same rank keep their order
+    order.sort_by_key(|index| rank(&options[*index].0));
+    order
+}
+
+/// Reorder the options of a signature into the canonical order, as they are written
+///
+/// Returns the span of the options inside the line along with their reordered
+/// text, or None if the options are already in the canonical order. Unlike
+/// the formatter, the options themselves are not rewritten.
+pub fn reorder_options(rule: &Rule, line: &str) -> Option<(Span, String)> {
+    let options = rule.options.as_ref()?;
+    let order = canonical_option_order(options);
+    if order
+        .iter()
+        .enumerate()
+        .all(|(position, index)| position == *index)
+    {
+        return None;
+    }
+    let chars = line.chars().collect::<Vec<_>>();
+    // The text of every option, up to (and including) its semicolon
+    let texts = options
+        .iter()
+        .map(|(_, span)| {
+            let start = span.start.min(chars.len());
+            // Quoted values may contain semicolons, so search after the option
+            let option_end = span.end.clamp(start, chars.len());
+            let end = chars[option_end..]
+                .iter()
+                .position(|char| *char == ';')
+                .map_or(chars.len(), |semicolon| option_end + semicolon + 1);
+            let text = chars[start..end].iter().collect::<String>();
+            let leading = text.chars().count() - text.trim_start().chars().count();
+            (start + leading..end, text.trim().to_string())
+        })
+        .collect::<Vec<_>>();
+    let (first, _) = texts.first()?;
+    let (last, _) = texts.last()?;
+    // Keep the whitespace between the options as it is written (e.g. " ", "\t" or none)
+    let separator = texts
+        .get(1)
+        .and_then(|(second, _)| chars.get(first.end..second.start))
+        .map_or(" ".to_string(), |separator| separator.iter().collect());
+    let reordered = order
+        .into_iter()
+        .map(|index| texts[index].1.as_str())
+        .collect::<Vec<_>>()
+        .join(&separator);
+    Some((first.start..last.end, reordered))
+}
+
+/// A formatted line, which should replace the line with the same number
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineEdit {
+    pub line: u32,
+    pub new_text: String,
+}
+
+/// Get the edits needed to format every signature inside the file
+///
+/// The edits are sorted by line number, lines which are already formatted
+/// do not produce an edit.
+pub fn format_lines(rope: &Rope, ast: &AST, style: &FormatStyle) -> Vec<LineEdit> {
+    let mut edits = ast
+        .rules
+        .iter()
+        .filter_map(|(line_nr, (rule, _))| {
+            let line = rope.get_line(*line_nr as usize)?;
+            let formatted_rule = style.format_rule(rule);
+            if line_content(line) == formatted_rule {
+                return None;
+            }
+            Some(LineEdit {
+                line: *line_nr,
+                new_text: formatted_rule,
+            })
+        })
+        .collect::<Vec<_>>();
+    edits.sort_by_key(|edit| edit.line);
+    edits
+}
+
+/// Format a whole rule file
+pub fn format_text(text: &str, style: &FormatStyle) -> String {
+    let rope = Rope::from_str(text);
+    let (ast, _) = AST::parse_rope(&rope);
+    let edits = format_lines(&rope, &ast, style)
+        .into_iter()
+        .map(|edit| (edit.line as usize, edit.new_text))
+        .collect::<HashMap<_, _>>();
+    let mut formatted = rope
+        .lines()
+        .enumerate()
+        .map(|(line_nr, line)| match edits.get(&line_nr) {
+            // Keep the original line ending
+            Some(new_text) => format!("{}{}", new_text, line_ending(line)),
+            None => line.to_string(),
+        })
+        .collect::<String>();
+    if let Some(final_newline) = final_newline(&rope, style) {
+        formatted.push_str(final_newline);
+    }
+    formatted
+}
+
+/// Get the line ending, which has to be appended to the file (if any)
+///
+/// The line ending of the first line is reused, so "\r\n" files stay that way.
+pub fn final_newline(rope: &Rope, style: &FormatStyle) -> Option<&'static str> {
+    if !style.insert_final_newline || rope.len_chars() == 0 {
+        return None;
+    }
+    let last_line = rope.line(rope.len_lines() - 1);
+    if last_line.len_chars() == 0 {
+        return None;
+    }
+    match line_ending(rope.line(0)) {
+        "" => Some("\n"),
+        line_ending => Some(line_ending),
+    }
+}
+
+/// Get the content of the line without the line ending
+pub fn line_content(line: RopeSlice) -> String {
+    let line = line.to_string();
+    line.trim_end_matches(['\n', '\r']).to_string()
+}
+
+/// Get the line ending of the line ("\r\n", "\n" or nothing)
+pub fn line_ending(line: RopeSlice) -> &'static str {
+    let line = line.to_string();
+    if line.ends_with("\r\n") {
+        "\r\n"
+    } else if line.ends_with('\n') {
+        "\n"
+    } else {
+        ""
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chumsky::Parser;
+
+    use super::*;
+
+    const FIXTURE: &str = "ALERT TCP $HOME_NET any -> any 80 (sid:1; Content:\"GET \"; msg:\"x\"; \
+                           reference:url,a.b; http.uri; rev:2;)";
+
+    #[test]
+    fn styles_are_applied() {
+        // (space after colon, space after semicolon, sort options, sanitize)
+        let styles = [
+            (
+                (true, true, false, false),
+                "ALERT tcp $HOME_NET any -> any 80 (sid: 1; Content: \"GET \"; msg: \"x\"; \
+                 reference: url, a.b; http.uri; rev: 2;)",
+            ),
+            (
+                (false, true, false, false),
+                "ALERT tcp $HOME_NET any -> any 80 (sid:1; Content:\"GET \"; msg:\"x\"; \
+                 reference:url, a.b; http.uri; rev:2;)",
+            ),
+            (
+                (true, false, false, false),
+                "ALERT tcp $HOME_NET any -> any 80 (sid: 1;Content: \"GET \";msg: \"x\";\
+                 reference: url, a.b;http.uri;rev: 2;)",
+            ),
+            (
+                (false, false, true, false),
+                "ALERT tcp $HOME_NET any -> any 80 (msg:\"x\";Content:\"GET \";http.uri;\
+                 reference:url, a.b;sid:1;rev:2;)",
+            ),
+            (
+                (true, true, true, false),
+                "ALERT tcp $HOME_NET any -> any 80 (msg: \"x\"; Content: \"GET \"; http.uri; \
+                 reference: url, a.b; sid: 1; rev: 2;)",
+            ),
+            (
+                (true, true, false, true),
+                "alert tcp $HOME_NET any -> any 80 (sid: 1; content: \"GET \"; msg: \"x\"; \
+                 reference: url, a.b; http.uri; rev: 2;)",
+            ),
+            (
+                (false, false, true, true),
+                "alert tcp $HOME_NET any -> any 80 (msg:\"x\";content:\"GET \";http.uri;\
+                 reference:url, a.b;sid:1;rev:2;)",
+            ),
+        ];
+        let (rule, _) = Rule::parser().parse(FIXTURE).unwrap();
+        styles
+            .into_iter()
+            .for_each(|((colon, semicolon, sort, sanitize), expected)| {
+                let style = FormatStyle {
+                    space_after_option_colon: colon,
+                    space_after_semicolon: semicolon,
+                    sort_options: sort,
+                    sanitize,
+                    ..FormatStyle::default()
+                };
+                let formatted = style.format_rule(&rule);
+                assert_eq!(formatted, expected, "{:?}", style);
+                // The formatted signature reads back as the same signature
+                let (reparsed, _) = Rule::parser().parse(formatted.as_str()).unwrap();
+                assert_eq!(style.format_rule(&reparsed), formatted, "{:?}", style);
+            });
+    }
+
+    #[test]
+    fn default_style_matches_display() {
+        let (rule, _) = Rule::parser().parse(FIXTURE).unwrap();
+        assert_eq!(FormatStyle::default().format_rule(&rule), rule.to_string());
+    }
+
+    #[test]
+    fn canonical_order_keeps_the_detection_options_in_place() {
+        let (rule, _) = Rule::parser().parse(FIXTURE).unwrap();
+        let options = rule.options.as_ref().unwrap();
+        // sid, content, msg, reference, http.uri, rev
+        assert_eq!(canonical_option_order(options), vec![2, 1, 4, 3, 0, 5]);
+
+        let (rule, _) = Rule::parser()
+            .parse(
+                "alert tcp any any -> any any (REV:1; Metadata:a b; sid:1; gid:1; \
+                 classtype:trojan-activity; MSG:\"x\";)",
+            )
+            .unwrap();
+        let options = rule.options.as_ref().unwrap();
+        assert_eq!(canonical_option_order(options), vec![5, 4, 1, 3, 2, 0]);
+    }
+
+    #[test]
+    fn options_are_reordered_as_they_are_written() {
+        let line =
+            "alert tcp any any -> any any ( sid:1;  content:\"a;b\"; msg:\"x\";nocase; rev:2;)";
+        let (rule, _) = Rule::parser().parse(line).unwrap();
+        let (span, reordered) = reorder_options(&rule, line).unwrap();
+        assert_eq!(span, 31..76);
+        // The whitespace after the first option separates all of them
+        assert_eq!(
+            reordered,
+            "msg:\"x\";  content:\"a;b\";  nocase;  sid:1;  rev:2;"
+        );
+
+        let line = "alert tcp any any -> any any (rev:2;\tmsg:\"x\";)";
+        let (rule, _) = Rule::parser().parse(line).unwrap();
+        assert_eq!(
+            reorder_options(&rule, line),
+            Some((30..45, "msg:\"x\";\trev:2;".to_string()))
+        );
+
+        // Options without a space between them stay that way
+        let line = "alert tcp any any -> any any (rev:2;msg:\"x\";)";
+        let (rule, _) = Rule::parser().parse(line).unwrap();
+        assert_eq!(
+            reorder_options(&rule, line),
+            Some((30..44, "msg:\"x\";rev:2;".to_string()))
+        );
+    }
+
+    #[test]
+    fn ordered_options_are_left_alone() {
+        let line = "alert tcp any any -> any any (msg:\"x\"; content:\"a\"; sid:1; rev:2;)";
+        let (rule, _) = Rule::parser().parse(line).unwrap();
+        assert_eq!(reorder_options(&rule, line), None);
+    }
+
+    #[test]
+    fn comments_follow_the_formatted_signature() {
+        let style = FormatStyle {
+            space_after_option_colon: false,
+            ..FormatStyle::default()
+        };
+        let format = |line: &str| style.format_rule(&Rule::parser().parse(line).unwrap().0);
+        assert_eq!(
+            format("alert tcp any any -> any any (sid: 1;)\t# reviewed"),
+            "alert tcp any any -> any any (sid:1;) # reviewed"
+        );
+        assert_eq!(
+            format("alert tcp any any -> any any #  no options"),
+            "alert tcp any any -> any any #  no options"
+        );
+    }
+
+    #[test]
+    fn formatted_signatures_are_parsed_again() {
+        let text = include_str!("../tests/fixtures/fmt/unformatted.rules");
+        let (ast, _) = AST::parse_rope(&Rope::from_str(text));
+        let style = FormatStyle {
+            sort_options: true,
+            sanitize: true,
+            ..FormatStyle::default()
+        };
+        let formatted = format_text(text, &style);
+        // Every signature stays on its line, so it is still parsed (and linted)
+        let (reparsed, _) = AST::parse_rope(&Rope::from_str(&formatted));
+        let sids = |ast: &AST| {
+            let mut sids = ast
+                .rules
+                .iter()
+                .map(|(line, (rule, _))| (*line, rule.sid().map(|(sid, _)| sid)))
+                .collect::<Vec<_>>();
+            sids.sort();
+            sids
+        };
+        assert!(!ast.rules.is_empty());
+        assert_eq!(sids(&reparsed), sids(&ast));
+        assert_eq!(formatted.lines().count(), text.lines().count());
+    }
+}
@@ -3,23 +3,133 @@
 //! The hover logic provides additional information:
 //! - IP start and end on IP ranges
 //! - Description and Documentation for keywords
+//! - Values of the variables, along with where they are used
+//! - What triggers the events of app-layer-event and decode-event
+//! - The bytes of the hex parts of a content
+//! - The path of the lua scripts
 use std::collections::HashMap;
+use std::path::Path;
 
 use crate::rule::Hover;
-use tower_lsp::lsp_types::HoverContents;
+use lsp_types::{HoverContents, MarkupContent, MarkupKind};
 
 use crate::{
-    rule::{Spanned, AST},
-    suricata::Keyword,
+    rule::{content::ContentSegment, Spanned, AST},
+    suricata::{EventTable, Keyword},
+    variables::{variable_at, VariableUsages, Variables},
 };
 
+/// How many usages of a variable are listed
+const USAGE_EXAMPLES: usize = 3;
+
 /// Provides hover information
+///
+/// The usages of the variable under the cursor are listed, if they are known.
 pub fn get_hover(
     ast: &AST,
     line: &u32,
     col: &usize,
     keywords: &HashMap<String, Keyword>,
+    variables: &Variables,
+    events: &EventTable,
+    usages: Option<&VariableUsages>,
 ) -> Option<Spanned<HoverContents>> {
-    let (rule, _) = ast.rules.get(line)?;
+    let (rule, _) = ast.rule_at(line)?;
+    if let Some((kind, (name, span))) = variable_at(rule, *col) {
+        let definition = variables.get(kind, &name)?;
+        let mut value = format!(
+            "`${}` = `{}`\n\n{} defined in {}",
+            name, definition.value, kind, definition.source
+        );
+        if let Some(usages) = usages {
+            value.push_str(&format!("\n\n{}", usages.summary(USAGE_EXAMPLES)));
+        }
+        let contents = HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value,
+        });
+        return Some((contents, span));
+    }
+    let event = rule
+        .events()
+        .into_iter()
+        .find(|(_, (_, span))| span.contains(col));
+    if let Some((keyword, (name, span))) = event {
+        let description = events.get(keyword, &name)?;
+        let contents = HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value: format!("`{}` ({})\n\n{}", name, keyword, description),
+        });
+        return Some((contents, span));
+    }
+    let flow = rule.flow().into_iter().find(|(_, span)| span.contains(col));
+    if let Some((value, span)) = flow {
+        let description = value.description()?;
+        let contents = HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value: format!("`{}` (flow)\n\n{}", value, description),
+        });
+        return Some((contents, span));
+    }
+    let bytes = rule
+        .content_matches()
+        .iter()
+        .flat_map(|content| content.segments())
+        .find(|segment| {
+            matches!(segment, ContentSegment::Bytes { .. }) && segment.span().contains(col)
+        });
+    if let Some(segment) = bytes {
+        let value = match segment.bytes() {
+            Ok(bytes) => format!(
+                "`{}` ({} bytes)\n\n`{}`",
+                segment,
+                bytes.len(),
+                decode_bytes(&bytes)
+            ),
+            Err((message, _)) => format!("`{}`\n\n{}", segment, message),
+        };
+        let contents = HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value,
+        });
+        return Some((contents, segment.span().clone()));
+    }
     rule.get_hover(col, keywords)
+}
+
+/// Provides the path of the lua script under the cursor
+///
+/// Relative scripts are resolved against the directory of the rule file.
+pub fn get_lua_hover(
+    ast: &AST,
+    line: &u32,
+    col: &usize,
+    directory: &Path,
+) -> Option<Spanned<HoverContents>> {
+    let (rule, _) = ast.rule_at(line)?;
+    let script = rule
+        .lua_scripts()
+        .into_iter()
+        .find(|script| script.file.1.contains(col))?;
+    let path = script.path(directory);
+    let mut value = format!("`{}` ({} script)", path.display(), script.keyword.0);
+    if !path.is_file() {
+        value.push_str("\n\nThe script does not exist");
+    }
+    let contents = HoverContents::Markup(MarkupContent {
+        kind: MarkupKind::Markdown,
+        value,
+    });
+    Some((contents, script.file.1))
+}
+
+/// Show printable bytes as characters and the others as \xNN
+fn decode_bytes(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|byte| match byte {
+            0x20..=0x7e => (*byte as char).to_string(),
+            byte => format!("\\x{:02x}", byte),
+        })
+        .collect()
 }
\ No newline at end of file
@@ -2,7 +2,28 @@ pub mod rule;
 pub mod parser;
 pub mod completion;
 pub mod semantic_token;
+pub mod position;
 pub mod reference;
 pub mod hover;
+pub mod document_link;
 pub mod suricata;
-pub mod server_settings;
\ No newline at end of file
+pub mod server_settings;
+#[cfg(feature = "server")]
+pub mod workspace;
+#[cfg(feature = "server")]
+pub mod diagnostics;
+pub mod format;
+pub mod renumber;
+pub mod skeleton;
+pub mod syntax;
+#[cfg(feature = "server")]
+pub mod cli;
+#[cfg(feature = "server")]
+pub mod logging;
+pub mod lint;
+pub mod convert;
+pub mod doc;
+pub mod variables;
+pub mod threshold;
+#[cfg(feature = "wasm")]
+pub mod wasm;
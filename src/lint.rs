@@ -0,0 +1,3872 @@
+//! Provides the lints for the signatures
+//!
+//! Lints are pure Rust checks, which do not require Suricata to be installed.
+//! They are divided into two categories:
+//! - Rule lints, which only need a single signature ([Rule::validate])
+//! - Workspace lints, which need every signature of the workspace (duplicate
+//!   sids, flowbits which are checked but never set, etc.)
+//!
+//! Every lint has a code (e.g. "duplicate-sid") and a default severity, which can
+//! be overridden (or turned off) trough the settings.
+use std::collections::{HashMap, HashSet};
+use std::ops::RangeInclusive;
+use std::path::Path;
+
+use glob::Pattern;
+use ipnet::IpNet;
+use regex::Regex;
+use serde::{Deserialize, Serialize, Serializer};
+use lsp_types::{CodeDescription, Diagnostic, DiagnosticSeverity, NumberOrString, Position, Range, Url};
+
+use crate::convert::snort2::legacy_modifiers;
+use crate::rule::bits::{BitsKind, BitsOperation};
+use crate::rule::action::{Action, ACTIONS};
+use crate::rule::header::{NetworkAddress, NetworkDirection, NetworkPort};
+use crate::rule::options::{
+    is_sticky_buffer, quoted_content, ContentMatch, OptionsVariable, RuleOption,
+};
+use crate::rule::{Rule, Span, Spanned, AST};
+use crate::server_settings::LintsSettings;
+use crate::suricata::{edit_distance, EventTable, Keyword, SuricataVersion};
+use crate::threshold::ThresholdConfig;
+use crate::variables::{variable_usages, VariableKind, Variables};
+
+/// How deep variables are resolved, when they reference other variables
+const MAX_VARIABLE_DEPTH: usize = 8;
+
+/// How many signatures sharing a msg are reported, rulesets may share msgs on purpose
+const MAX_DUPLICATE_MSGS: usize = 20;
+
+/// How many of the searched files are named by the unset-flowbit lint
+const MAX_SEARCHED_FILES: usize = 5;
+
+/// A keyword, which was replaced by another one
+pub struct DeprecatedKeyword {
+    pub keyword: &'static str,
+    pub replacement: &'static str,
+    /// The first version with the replacement
+    pub deprecated_in: SuricataVersion,
+    /// The first version without the keyword
+    pub removed_in: Option<SuricataVersion>,
+    /// The value of the keyword becomes a content of the replacement (e.g.
+    /// ssh.protoversion:2.0; is ssh.proto; content:"2.0";)
+    pub value_as_content: bool,
+}
+
+/// The keywords, which were renamed to the dotted sticky buffer names
+///
+/// Content modifiers (content:"x"; http_uri;) are covered by the
+/// legacy-modifier lint instead.
+pub const DEPRECATED_KEYWORDS: [DeprecatedKeyword; 18] = {
+    const fn renamed(
+        keyword: &'static str,
+        replacement: &'static str,
+        removed_in: Option<SuricataVersion>,
+    ) -> DeprecatedKeyword {
+        DeprecatedKeyword {
+            keyword,
+            replacement,
+            deprecated_in: SuricataVersion::new(5, 0, 0),
+            removed_in,
+            value_as_content: false,
+        }
+    }
+    // The keywords, which matched their value, were replaced by sticky buffers
+    const fn buffered(keyword: &'static str, replacement: &'static str) -> DeprecatedKeyword {
+        DeprecatedKeyword {
+            keyword,
+            replacement,
+            deprecated_in: SuricataVersion::new(7, 0, 0),
+            removed_in: Some(SuricataVersion::new(8, 0, 0)),
+            value_as_content: true,
+        }
+    }
+    [
+        renamed("tls_sni", "tls.sni", None),
+        renamed("tls_cert_subject", "tls.cert_subject", None),
+        renamed("tls_cert_issuer", "tls.cert_issuer", None),
+        renamed("tls_cert_serial", "tls.cert_serial", None),
+        renamed("tls_cert_fingerprint", "tls.cert_fingerprint", None),
+        renamed("ja3_hash", "ja3.hash", None),
+        renamed("ja3_string", "ja3.string", None),
+        renamed("ja3s_hash", "ja3s.hash", None),
+        renamed("ja3s_string", "ja3s.string", None),
+        renamed("dns_query", "dns.query", None),
+        renamed("ssh_proto", "ssh.proto", None),
+        renamed("ssh_software", "ssh.software", None),
+        renamed("krb5_cname", "krb5.cname", None),
+        renamed("krb5_sname", "krb5.sname", None),
+        renamed("smb_named_pipe", "smb.named_pipe", None),
+        renamed("smb_share", "smb.share", None),
+        buffered("ssh.protoversion", "ssh.proto"),
+        buffered("ssh.softwareversion", "ssh.software"),
+    ]
+};
+
+/// Get the entry of a deprecated keyword
+pub fn deprecated_keyword(keyword: &str) -> Option<&'static DeprecatedKeyword> {
+    DEPRECATED_KEYWORDS
+        .iter()
+        .find(|deprecated| deprecated.keyword == keyword)
+}
+
+/// Protocols, which run over TCP (and therefore have a flow direction)
+const TCP_PROTOCOLS: [&str; 21] = [
+    "tcp",
+    "tcp-pkt",
+    "tcp-stream",
+    "http",
+    "http1",
+    "http2",
+    "tls",
+    "ssh",
+    "ftp",
+    "ftp-data",
+    "smtp",
+    "imap",
+    "pop3",
+    "smb",
+    "dcerpc",
+    "rdp",
+    "rfb",
+    "mqtt",
+    "modbus",
+    "pgsql",
+    "telnet",
+];
+
+/// Keywords, which Suricata only accepts once per signature
+const SINGLE_KEYWORDS: [&str; 8] = [
+    "sid",
+    "rev",
+    "gid",
+    "msg",
+    "classtype",
+    "priority",
+    "threshold",
+    "target",
+];
+
+/// The severity of a violation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+    Information,
+    Hint,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Information => "information",
+            Severity::Hint => "hint",
+        }
+    }
+}
+
+impl From<Severity> for DiagnosticSeverity {
+    fn from(severity: Severity) -> Self {
+        match severity {
+            Severity::Error => DiagnosticSeverity::ERROR,
+            Severity::Warning => DiagnosticSeverity::WARNING,
+            Severity::Information => DiagnosticSeverity::INFORMATION,
+            Severity::Hint => DiagnosticSeverity::HINT,
+        }
+    }
+}
+
+/// The configured level of a lint, which overrides the default severity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LintLevel {
+    Off,
+    Hint,
+    #[serde(alias = "info")]
+    Information,
+    Warning,
+    Error,
+}
+
+impl LintLevel {
+    /// Get the severity for the level (None if the lint is turned off)
+    pub fn severity(&self) -> Option<Severity> {
+        match self {
+            LintLevel::Off => None,
+            LintLevel::Hint => Some(Severity::Hint),
+            LintLevel::Information => Some(Severity::Information),
+            LintLevel::Warning => Some(Severity::Warning),
+            LintLevel::Error => Some(Severity::Error),
+        }
+    }
+}
+
+/// How strictly the any-to-any lint treats signatures, which match any traffic
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnyToAnyStrictness {
+    /// Only report signatures without a content, a pcre or a sticky buffer
+    Relaxed,
+    /// Report anchored signatures as information
+    #[default]
+    Normal,
+    /// Report every signature with the severity of the lint, even anchored ones
+    Strict,
+}
+
+/// All lints known to the language server
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LintCode {
+    /// The sid (or the gid, the rev) is not a valid number
+    InvalidSid,
+    /// The same sid is used by more than one signature
+    DuplicateSid,
+    /// The signature has no sid option
+    MissingSid,
+    /// The signature has no rev option
+    MissingRev,
+    /// The signature has no msg option
+    MissingMsg,
+    /// A flowbit (or xbit, hostbit) is checked or unset, but never set
+    UnsetFlowbit,
+    /// The operation, the track or the expiry of a flowbits, xbits or hostbits
+    /// option is not valid
+    InvalidBits,
+    /// The classtype is empty, has several values or is set more than once
+    InvalidClasstype,
+    /// The priority is not a number between 1 and 255
+    InvalidPriority,
+    /// A content modifier is written in the Snort 2 style (content:"x"; http_uri;)
+    LegacyModifier,
+    /// The signature matches any traffic (any any -> any any)
+    AnyToAny,
+    /// The sid is outside of the range reserved for the file (local or vendor)
+    SidRange,
+    /// The lua script of a signature does not exist
+    MissingLuaScript,
+    /// The modifiers of a content contradict each other, so it can never match
+    ImpossibleContent,
+    /// A content modifier does not follow a content, or is repeated for the same content
+    InvalidModifier,
+    /// The expression or the flags of a pcre are not valid
+    InvalidPcre,
+    /// The arguments of a byte_test, byte_jump or byte_extract are not valid
+    InvalidByteOption,
+    /// A TCP signature inspects the payload, without a flow option
+    MissingFlow,
+    /// A signature only sets bits, but still alerts
+    MissingNoalert,
+    /// A signature never alerts, but does not set any bits either
+    OrphanNoalert,
+    /// A signature has more than one threshold option
+    DuplicateThreshold,
+    /// A signature combines a threshold with a detection_filter
+    ThresholdWithDetectionFilter,
+    /// The threshold of a signature is replaced by an entry of threshold.config
+    ShadowedThreshold,
+    /// A keyword is deprecated or removed in the installed Suricata
+    DeprecatedKeyword,
+    /// The msg does not match the configured pattern
+    MsgPattern,
+    /// The msg starts or ends with whitespace
+    MsgWhitespace,
+    /// The msg is not (completely) enclosed in quotes
+    UnquotedMsg,
+    /// The msg is longer than the configured maximum
+    MsgLength,
+    /// The addresses or ports of the header match nothing
+    EmptyHeader,
+    /// Signatures with different sids share the same msg
+    DuplicateMsg,
+    /// An app-layer-event or decode-event is not raised by Suricata
+    UnknownEvent,
+    /// The fast pattern of the signature is too short to be selective
+    ShortFastPattern,
+    /// The keyword of an option is not known to Suricata
+    UnknownKeyword,
+    /// An address or port group is used, but not defined
+    UndefinedVariable,
+    /// The signature uses the reversed direction (<-)
+    ReversedDirection,
+    /// The direction is missing, or is not one of ->, <> and <-
+    InvalidDirection,
+    /// The action is not known to Suricata
+    UnknownAction,
+    /// A port range is inverted or covers a single port, a port is repeated
+    /// inside a group, or any is negated
+    SuspiciousPort,
+    /// The address of a CIDR is not the network address of its prefix
+    CidrHostBits,
+    /// A keyword, which Suricata only accepts once, is repeated
+    DuplicateOption,
+    /// A relative modifier has no previous match, or is combined with depth or offset
+    RelativeModifier,
+    /// The hex bytes of a content are not closed, or are not valid hex
+    InvalidHex,
+}
+
+impl LintCode {
+    /// All lints, in the order they are documented
+    pub const ALL: [LintCode; 42] = [
+        LintCode::InvalidSid,
+        LintCode::DuplicateSid,
+        LintCode::MissingSid,
+        LintCode::MissingRev,
+        LintCode::MissingMsg,
+        LintCode::UnsetFlowbit,
+        LintCode::InvalidBits,
+        LintCode::InvalidClasstype,
+        LintCode::InvalidPriority,
+        LintCode::LegacyModifier,
+        LintCode::AnyToAny,
+        LintCode::SidRange,
+        LintCode::MissingLuaScript,
+        LintCode::ImpossibleContent,
+        LintCode::InvalidModifier,
+        LintCode::InvalidPcre,
+        LintCode::InvalidByteOption,
+        LintCode::MissingFlow,
+        LintCode::MissingNoalert,
+        LintCode::OrphanNoalert,
+        LintCode::DuplicateThreshold,
+        LintCode::ThresholdWithDetectionFilter,
+        LintCode::ShadowedThreshold,
+        LintCode::DeprecatedKeyword,
+        LintCode::MsgPattern,
+        LintCode::MsgWhitespace,
+        LintCode::UnquotedMsg,
+        LintCode::MsgLength,
+        LintCode::EmptyHeader,
+        LintCode::DuplicateMsg,
+        LintCode::UnknownEvent,
+        LintCode::ShortFastPattern,
+        LintCode::UnknownKeyword,
+        LintCode::UndefinedVariable,
+        LintCode::ReversedDirection,
+        LintCode::InvalidDirection,
+        LintCode::UnknownAction,
+        LintCode::SuspiciousPort,
+        LintCode::CidrHostBits,
+        LintCode::DuplicateOption,
+        LintCode::RelativeModifier,
+        LintCode::InvalidHex,
+    ];
+
+    /// Find the lint with the given code
+    pub fn from_code(code: &str) -> Option<LintCode> {
+        LintCode::ALL.into_iter().find(|lint| lint.code() == code)
+    }
+
+    /// Get the code of the lint, used inside diagnostics and settings
+    pub fn code(&self) -> &'static str {
+        match self {
+            LintCode::InvalidSid => "invalid-sid",
+            LintCode::DuplicateSid => "duplicate-sid",
+            LintCode::MissingSid => "missing-sid",
+            LintCode::MissingRev => "missing-rev",
+            LintCode::MissingMsg => "missing-msg",
+            LintCode::UnsetFlowbit => "unset-flowbit",
+            LintCode::InvalidBits => "invalid-bits",
+            LintCode::InvalidClasstype => "invalid-classtype",
+            LintCode::InvalidPriority => "invalid-priority",
+            LintCode::LegacyModifier => "legacy-modifier",
+            LintCode::AnyToAny => "any-to-any",
+            LintCode::SidRange => "sid-range",
+            LintCode::MissingLuaScript => "missing-lua-script",
+            LintCode::ImpossibleContent => "impossible-content",
+            LintCode::InvalidModifier => "invalid-modifier",
+            LintCode::InvalidPcre => "invalid-pcre",
+            LintCode::InvalidByteOption => "invalid-byte-option",
+            LintCode::MissingFlow => "missing-flow",
+            LintCode::MissingNoalert => "missing-noalert",
+            LintCode::OrphanNoalert => "orphan-noalert",
+            LintCode::DuplicateThreshold => "duplicate-threshold",
+            LintCode::ThresholdWithDetectionFilter => "threshold-detection-filter",
+            LintCode::ShadowedThreshold => "shadowed-threshold",
+            LintCode::DeprecatedKeyword => "deprecated-keyword",
+            LintCode::MsgPattern => "msg-pattern",
+            LintCode::MsgWhitespace => "msg-whitespace",
+            LintCode::UnquotedMsg => "unquoted-msg",
+            LintCode::MsgLength => "msg-length",
+            LintCode::EmptyHeader => "empty-header",
+            LintCode::DuplicateMsg => "duplicate-msg",
+            LintCode::UnknownEvent => "unknown-event",
+            LintCode::ShortFastPattern => "short-fast-pattern",
+            LintCode::UnknownKeyword => "unknown-keyword",
+            LintCode::UndefinedVariable => "undefined-variable",
+            LintCode::ReversedDirection => "reversed-direction",
+            LintCode::InvalidDirection => "invalid-direction",
+            LintCode::UnknownAction => "unknown-action",
+            LintCode::SuspiciousPort => "suspicious-port",
+            LintCode::CidrHostBits => "cidr-host-bits",
+            LintCode::DuplicateOption => "duplicate-option",
+            LintCode::RelativeModifier => "relative-modifier",
+            LintCode::InvalidHex => "invalid-hex",
+        }
+    }
+    /// Get the severity of the lint, when it is not configured
+    pub fn default_severity(&self) -> Severity {
+        match self {
+            LintCode::InvalidSid => Severity::Error,
+            LintCode::DuplicateSid => Severity::Warning,
+            LintCode::MissingSid => Severity::Warning,
+            LintCode::MissingRev => Severity::Warning,
+            LintCode::MissingMsg => Severity::Warning,
+            LintCode::UnsetFlowbit => Severity::Warning,
+            LintCode::InvalidBits => Severity::Error,
+            LintCode::InvalidClasstype => Severity::Error,
+            LintCode::InvalidPriority => Severity::Error,
+            LintCode::LegacyModifier => Severity::Warning,
+            LintCode::AnyToAny => Severity::Warning,
+            LintCode::SidRange => Severity::Warning,
+            LintCode::MissingLuaScript => Severity::Warning,
+            LintCode::ImpossibleContent => Severity::Error,
+            LintCode::InvalidModifier => Severity::Error,
+            LintCode::InvalidPcre => Severity::Error,
+            LintCode::InvalidByteOption => Severity::Error,
+            LintCode::MissingFlow => Severity::Information,
+            LintCode::MissingNoalert => Severity::Information,
+            LintCode::OrphanNoalert => Severity::Information,
+            LintCode::DuplicateThreshold => Severity::Error,
+            LintCode::ThresholdWithDetectionFilter => Severity::Error,
+            LintCode::ShadowedThreshold => Severity::Information,
+            LintCode::DeprecatedKeyword => Severity::Warning,
+            LintCode::MsgPattern => Severity::Information,
+            LintCode::MsgWhitespace => Severity::Warning,
+            LintCode::UnquotedMsg => Severity::Warning,
+            LintCode::MsgLength => Severity::Warning,
+            LintCode::EmptyHeader => Severity::Error,
+            LintCode::DuplicateMsg => Severity::Information,
+            LintCode::UnknownEvent => Severity::Warning,
+            LintCode::ShortFastPattern => Severity::Warning,
+            LintCode::UnknownKeyword => Severity::Warning,
+            LintCode::UndefinedVariable => Severity::Warning,
+            LintCode::ReversedDirection => Severity::Hint,
+            LintCode::InvalidDirection => Severity::Error,
+            LintCode::UnknownAction => Severity::Error,
+            LintCode::SuspiciousPort => Severity::Warning,
+            LintCode::CidrHostBits => Severity::Warning,
+            LintCode::DuplicateOption => Severity::Error,
+            LintCode::RelativeModifier => Severity::Error,
+            LintCode::InvalidHex => Severity::Error,
+        }
+    }
+
+    /// Get the documentation, which explains the lint in more detail
+    pub fn documentation(&self) -> Option<&'static str> {
+        match self {
+            LintCode::ShortFastPattern => {
+                Some("https://docs.suricata.io/en/latest/rules/fast-pattern.html")
+            }
+            LintCode::InvalidModifier
+            | LintCode::InvalidByteOption
+            | LintCode::RelativeModifier => {
+                Some("https://docs.suricata.io/en/latest/rules/payload-keywords.html")
+            }
+            LintCode::InvalidPcre => Some("https://docs.suricata.io/en/latest/rules/pcre.html"),
+            LintCode::InvalidClasstype => {
+                Some("https://docs.suricata.io/en/latest/rules/meta.html#classtype")
+            }
+            LintCode::InvalidPriority => {
+                Some("https://docs.suricata.io/en/latest/rules/meta.html#priority")
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Lints are serialized as their code (e.g. "duplicate-sid")
+impl Serialize for LintCode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.code())
+    }
+}
+
+/// A location related to a violation (e.g. the other signature with the same sid)
+///
+/// Locations without a file are inside the signature of the violation, their
+/// line is set by [validate_document].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RelatedLocation {
+    pub file: String,
+    pub line: u32,
+    pub span: Span,
+    pub message: String,
+}
+
+/// A single problem found by a lint
+///
+/// The span is relative to the start of the line, like all spans of the rule
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Violation {
+    pub code: LintCode,
+    pub severity: Severity,
+    pub message: String,
+    pub span: Span,
+    pub related: Vec<RelatedLocation>,
+}
+
+impl Violation {
+    /// Create a violation with the default severity of the lint
+    pub fn new(code: LintCode, message: String, span: Span) -> Self {
+        Violation {
+            code,
+            severity: code.default_severity(),
+            message,
+            span,
+            related: vec![],
+        }
+    }
+
+    /// Point the related locations without a file to the given file
+    pub fn in_file(mut self, file: &str) -> Self {
+        self.related
+            .iter_mut()
+            .filter(|related| related.file.is_empty())
+            .for_each(|related| related.file = file.to_string());
+        self
+    }
+
+    /// Convert the violation on the given line into a diagnostic
+    pub fn to_diagnostic(&self, line: u32) -> Diagnostic {
+        Diagnostic {
+            range: Range::new(
+                Position::new(line, self.span.start as u32),
+                Position::new(line, self.span.end as u32),
+            ),
+            severity: Some(self.severity.into()),
+            code: Some(NumberOrString::String(self.code.code().to_string())),
+            code_description: self
+                .code
+                .documentation()
+                .and_then(|documentation| Url::parse(documentation).ok())
+                .map(|href| CodeDescription { href }),
+            source: Some("meerkat".to_string()),
+            message: self.message.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+/// A violation inside a file of the workspace: (file, line, violation)
+pub type FileViolation = (String, u32, Violation);
+
+/// The configuration of the lints, shared by all validations
+///
+/// Holds the configured levels and the variables, which are used to resolve
+/// the address and port groups inside the headers. Lints which are turned off
+/// do not run at all.
+#[derive(Debug, Clone)]
+pub struct ValidationContext {
+    /// The severities of the configured lints (None if it is turned off)
+    severities: HashMap<LintCode, Option<Severity>>,
+    variables: Variables,
+    /// The sids reserved for local signatures
+    local_sids: RangeInclusive<u32>,
+    local_files: Vec<Pattern>,
+    vendor_files: Vec<Pattern>,
+    threshold_config: ThresholdConfig,
+    /// The installed Suricata (the newest version is assumed if unknown)
+    suricata_version: Option<SuricataVersion>,
+    msg_pattern: Option<Regex>,
+    msg_max_length: usize,
+    /// Normalized prefixes of the msgs, which are shared on purpose
+    duplicate_msg_ignore: Vec<String>,
+    /// The events raised by Suricata (the bundled ones if unknown)
+    events: EventTable,
+    /// The shortest content (in bytes), which makes a good fast pattern
+    fast_pattern_min_length: usize,
+    any_to_any_strictness: AnyToAnyStrictness,
+    /// The keywords known to the installed Suricata (empty if unknown)
+    keywords: HashSet<String>,
+}
+
+impl Default for ValidationContext {
+    fn default() -> Self {
+        ValidationContext::new(&LintsSettings::default())
+    }
+}
+
+impl ValidationContext {
+    /// Resolve the configured levels (keyed by the lint code)
+    ///
+    /// Levels of unknown lints and invalid globs are ignored.
+    pub fn new(settings: &LintsSettings) -> Self {
+        let severities = LintCode::ALL
+            .into_iter()
+            .filter_map(|code| Some((code, settings.levels.get(code.code())?.severity())))
+            .collect();
+        let patterns = |globs: &[String]| {
+            globs
+                .iter()
+                .filter_map(|glob| Pattern::new(glob).ok())
+                .collect()
+        };
+        let [first_sid, last_sid] = settings.sid_range.local;
+        ValidationContext {
+            severities,
+            variables: Variables::default(),
+            local_sids: first_sid..=last_sid,
+            local_files: patterns(&settings.sid_range.local_files),
+            vendor_files: patterns(&settings.sid_range.vendor_files),
+            threshold_config: ThresholdConfig::default(),
+            suricata_version: None,
+            msg_pattern: settings
+                .msg_pattern
+                .as_ref()
+                .and_then(|msg_pattern| Regex::new(msg_pattern).ok()),
+            msg_max_length: settings.msg_max_length,
+            duplicate_msg_ignore: settings
+                .duplicate_msg_ignore
+                .iter()
+                .map(|prefix| normalize_msg(prefix))
+                .collect(),
+            events: EventTable::default(),
+            fast_pattern_min_length: settings.fast_pattern_min_length,
+            any_to_any_strictness: settings.any_to_any_strictness,
+            keywords: HashSet::new(),
+        }
+    }
+
+    /// Use the variables to resolve the address and port groups
+    pub fn with_variables(mut self, variables: Variables) -> Self {
+        self.variables = variables;
+        self
+    }
+
+    /// Use the entries of threshold.config, to find the shadowed thresholds
+    pub fn with_threshold_config(mut self, threshold_config: ThresholdConfig) -> Self {
+        self.threshold_config = threshold_config;
+        self
+    }
+
+    /// Check the keywords against the installed Suricata
+    pub fn with_suricata_version(mut self, suricata_version: Option<SuricataVersion>) -> Self {
+        self.suricata_version = suricata_version;
+        self
+    }
+
+    /// Check the events against the ones raised by the installed Suricata
+    pub fn with_events(mut self, events: EventTable) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// Check the keywords of the options against the ones of the installed Suricata
+    pub fn with_keywords(mut self, keywords: &HashMap<String, Keyword>) -> Self {
+        self.keywords = keywords.keys().cloned().collect();
+        self
+    }
+
+    /// Get the severity of a lint (None if it is turned off)
+    pub fn severity(&self, code: LintCode) -> Option<Severity> {
+        self.severities
+            .get(&code)
+            .copied()
+            .unwrap_or(Some(code.default_severity()))
+    }
+
+    /// Check if a lint should run
+    pub fn is_enabled(&self, code: LintCode) -> bool {
+        self.severity(code).is_some()
+    }
+
+    /// Check if the level of a lint was set trough the settings
+    pub fn is_configured(&self, code: LintCode) -> bool {
+        self.severities.contains_key(&code)
+    }
+
+    /// Create a violation with the configured severity
+    ///
+    /// Returns None if the lint is turned off.
+    fn violation(&self, code: LintCode, message: String, span: Span) -> Option<Violation> {
+        let mut violation = Violation::new(code, message, span);
+        violation.severity = self.severity(code)?;
+        Some(violation)
+    }
+
+    /// Check if the address matches any host, after resolving the variables
+    ///
+    /// Unknown variables are not considered to be `any`, as they might be narrow.
+    fn address_is_any(&self, address: &NetworkAddress, depth: usize) -> bool {
+        match address {
+            NetworkAddress::IPVariable((name, _)) if depth < MAX_VARIABLE_DEPTH => self
+                .variables
+                .get(VariableKind::Address, name)
+                .and_then(|definition| NetworkAddress::parse_str(&definition.value).ok())
+                .is_some_and(|address| self.address_is_any(&address, depth + 1)),
+            address => address.is_any(),
+        }
+    }
+
+    /// Check if the port matches any port, after resolving the variables
+    fn port_is_any(&self, port: &NetworkPort, depth: usize) -> bool {
+        match port {
+            NetworkPort::PortVar((name, _)) if depth < MAX_VARIABLE_DEPTH => self
+                .variables
+                .get(VariableKind::Port, name)
+                .and_then(|definition| NetworkPort::parse_str(&definition.value).ok())
+                .is_some_and(|port| self.port_is_any(&port, depth + 1)),
+            port => port.is_any(),
+        }
+    }
+}
+
+impl Rule {
+    /// Run all enabled lints, which only need this signature
+    pub fn validate(&self, context: &ValidationContext) -> Vec<Violation> {
+        let mut violations = vec![];
+        // Check if the sid, the gid and the rev are numbers (Suricata rejects sid:0)
+        ["sid", "gid", "rev"].into_iter().for_each(|keyword| {
+            let invalid = match self.number_value(keyword) {
+                Some(Ok((0, span))) if keyword == "sid" => Some(("0".to_string(), span)),
+                Some(Err(invalid)) => Some(invalid),
+                _ => None,
+            };
+            if let Some((value, span)) = invalid {
+                violations.extend(context.violation(
+                    LintCode::InvalidSid,
+                    format!(
+                        "{} should be a positive number, found \"{}\"",
+                        keyword, value
+                    ),
+                    span,
+                ));
+            }
+        });
+        violations.extend(self.validate_required_options(context));
+        if context.is_enabled(LintCode::AnyToAny) {
+            violations.extend(self.validate_any_to_any(context));
+        }
+        if context.is_enabled(LintCode::EmptyHeader) {
+            violations.extend(self.validate_empty_header(context));
+        }
+        if context.is_enabled(LintCode::SuspiciousPort) {
+            let header = &self.header.0;
+            header
+                .source_port
+                .iter()
+                .chain(header.destination_port.iter())
+                .for_each(|port| validate_port(port, context, &mut violations));
+        }
+        if context.is_enabled(LintCode::CidrHostBits) {
+            let header = &self.header.0;
+            header
+                .source
+                .iter()
+                .chain(header.destination.iter())
+                .for_each(|address| validate_cidr(address, context, &mut violations));
+        }
+        violations.extend(self.validate_direction(context));
+        if context.is_enabled(LintCode::UnknownAction) {
+            violations.extend(self.validate_action(context));
+        }
+        if context.is_enabled(LintCode::InvalidBits) {
+            self.bits_operations().iter().for_each(|operation| {
+                violations.extend(validate_bits(operation, context));
+            });
+        }
+        if context.is_enabled(LintCode::InvalidClasstype) {
+            violations.extend(self.validate_classtype(context));
+        }
+        violations.extend(self.validate_duplicate_options(context));
+        if context.is_enabled(LintCode::InvalidPriority) {
+            violations.extend(self.validate_priority(context));
+        }
+        violations.extend(self.validate_noalert(context));
+        violations.extend(self.validate_thresholds(context));
+        if context.is_enabled(LintCode::DeprecatedKeyword) {
+            violations.extend(self.validate_deprecated_keywords(context));
+        }
+        violations.extend(self.validate_msg(context));
+        if context.is_enabled(LintCode::UnknownEvent) {
+            violations.extend(self.validate_events(context));
+        }
+        if context.is_enabled(LintCode::UnknownKeyword) {
+            violations.extend(self.validate_keywords(context));
+        }
+        if context.is_enabled(LintCode::MissingFlow) {
+            violations.extend(self.validate_flow(context));
+        }
+        if context.is_enabled(LintCode::ShortFastPattern) {
+            violations.extend(self.validate_fast_pattern(context));
+        }
+        if context.is_enabled(LintCode::InvalidModifier) {
+            violations.extend(self.validate_modifier_placement(context));
+        }
+        if context.is_enabled(LintCode::RelativeModifier) {
+            violations.extend(self.validate_relative_modifiers(context));
+        }
+        if context.is_enabled(LintCode::InvalidPcre) {
+            self.pcres().into_iter().for_each(|pcre| {
+                let errors = match pcre {
+                    Ok(pcre) => pcre.errors(),
+                    Err(error) => vec![error],
+                };
+                errors.into_iter().for_each(|(message, span)| {
+                    violations.extend(context.violation(LintCode::InvalidPcre, message, span));
+                });
+            });
+        }
+        if context.is_enabled(LintCode::InvalidByteOption) {
+            self.byte_operations().iter().for_each(|operation| {
+                operation.errors().into_iter().for_each(|(message, span)| {
+                    let violation = context.violation(LintCode::InvalidByteOption, message, span);
+                    violations.extend(violation);
+                });
+            });
+        }
+        if context.is_enabled(LintCode::InvalidHex) {
+            self.content_matches()
+                .iter()
+                .flat_map(|content| content.segments())
+                .flat_map(|segment| segment.errors())
+                .for_each(|(message, span)| {
+                    violations.extend(context.violation(LintCode::InvalidHex, message, span));
+                });
+        }
+        if context.is_enabled(LintCode::ImpossibleContent) {
+            self.content_matches().iter().for_each(|content| {
+                violations.extend(validate_content_modifiers(content, context));
+            });
+        }
+        // Check for content modifiers, which were replaced by sticky buffers
+        if !context.is_enabled(LintCode::LegacyModifier) {
+            return violations;
+        }
+        legacy_modifiers(self)
+            .into_iter()
+            .for_each(|(buffer, span)| {
+                violations.extend(context.violation(
+                    LintCode::LegacyModifier,
+                    format!(
+                        "Snort 2 style content modifier, use the {} sticky buffer instead",
+                        buffer
+                    ),
+                    span,
+                ));
+            });
+        violations
+    }
+
+    /// Check that every content modifier follows a content, at most once per content,
+    /// and that the values of fast_pattern are valid
+    ///
+    /// Suricata rejects all of them, the modifiers only apply to the preceding content.
+    /// Orphaned within and distance are reported by the relative-modifier lint.
+    fn validate_modifier_placement(&self, context: &ValidationContext) -> Vec<Violation> {
+        let orphans = self
+            .orphan_modifiers()
+            .into_iter()
+            .filter(|(option, _)| !matches!(option.keyword().0.as_str(), "within" | "distance"))
+            .map(|(option, span)| {
+                let message = format!("{} does not follow a content", option.keyword().0);
+                (message, span.clone())
+            });
+        let contents = self.content_matches();
+        let duplicates = contents.iter().flat_map(|content| {
+            content
+                .duplicate_modifiers()
+                .into_iter()
+                .map(|(option, span)| {
+                    let (keyword, _) = option.keyword();
+                    let message = format!("{} is set more than once for the content", keyword);
+                    (message, span.clone())
+                })
+        });
+        let fast_patterns = self.fast_patterns().into_iter().filter_map(Result::err);
+        orphans
+            .chain(duplicates)
+            .chain(fast_patterns)
+            .filter_map(|(message, span)| {
+                context.violation(LintCode::InvalidModifier, message, span)
+            })
+            .collect()
+    }
+
+    /// Check that the relative modifiers (within, distance and the R flag of
+    /// pcre) have a previous match, and that within and distance are not
+    /// combined with depth or offset
+    ///
+    /// A sticky buffer starts a new chain of matches. Suricata accepts within
+    /// and distance on the first content (relative to the start of the buffer),
+    /// but not without a content to modify.
+    fn validate_relative_modifiers(&self, context: &ValidationContext) -> Vec<Violation> {
+        let pcres = self.pcres().into_iter().flatten().collect::<Vec<_>>();
+        let mut violations = vec![];
+        let mut report = |message: String, span: Span| {
+            violations.extend(context.violation(LintCode::RelativeModifier, message, span));
+        };
+        let mut buffer: Option<&String> = None;
+        // A content or a pcre was matched inside the buffer
+        let mut matched = false;
+        // The modifiers of the content, which the next modifiers apply to
+        let mut content: Option<Vec<&String>> = None;
+        self.options.iter().flatten().for_each(|(option, span)| {
+            let (keyword, keyword_span) = option.keyword();
+            let chain = match buffer {
+                Some(buffer) => format!("the {} buffer", buffer),
+                None => "the signature".to_string(),
+            };
+            match (option, keyword.as_str()) {
+                (RuleOption::Buffer(_), _) if is_sticky_buffer(keyword) => {
+                    buffer = Some(keyword);
+                    matched = false;
+                    content = None;
+                }
+                (_, "content") => {
+                    content = Some(vec![]);
+                    matched = true;
+                }
+                (_, "pcre") => {
+                    let pcre = pcres.iter().find(|pcre| pcre.span == *span);
+                    if let Some(flag) = pcre.and_then(|pcre| pcre.relative_flag()) {
+                        if !matched {
+                            let message = format!(
+                                "the R flag of pcre is relative to the previous match, but it is the first match of {}",
+                                chain
+                            );
+                            report(message, flag);
+                        }
+                    }
+                    matched = true;
+                    content = None;
+                }
+                (_, "within" | "distance" | "depth" | "offset") => {
+                    let relative = matches!(keyword.as_str(), "within" | "distance");
+                    let Some(modifiers) = &mut content else {
+                        if relative {
+                            let message = format!(
+                                "{} has no previous match, it should follow a content of {}",
+                                keyword, chain
+                            );
+                            report(message, keyword_span.clone());
+                        }
+                        return;
+                    };
+                    let conflicting = modifiers.iter().find(|modifier| {
+                        matches!(modifier.as_str(), "within" | "distance") != relative
+                    });
+                    if let Some(conflicting) = conflicting {
+                        let message = format!(
+                            "{} can not be combined with {} on the same content",
+                            keyword, conflicting
+                        );
+                        report(message, keyword_span.clone());
+                    }
+                    modifiers.push(keyword);
+                }
+                _ => (),
+            }
+        });
+        violations
+    }
+
+    /// Check that the classtype has a single value
+    ///
+    /// A classtype set more than once is reported with the other duplicate options.
+    fn validate_classtype(&self, context: &ValidationContext) -> Vec<Violation> {
+        self.options_with_keyword("classtype")
+            .into_iter()
+            .filter_map(|(option, span)| {
+                let values = option.values();
+                let message = match values.as_slice() {
+                    [] => "classtype needs a value",
+                    [(value, _)] if value.is_empty() => "classtype is empty",
+                    [_] => return None,
+                    _ => "classtype takes a single value",
+                };
+                context.violation(
+                    LintCode::InvalidClasstype,
+                    message.to_string(),
+                    span.clone(),
+                )
+            })
+            .collect()
+    }
+
+    /// Check that the priority is a number between 1 and 255
+    fn validate_priority(&self, context: &ValidationContext) -> Option<Violation> {
+        let (message, span) = match self.priority()? {
+            Ok((priority, _)) if (1..=255).contains(&priority) => return None,
+            Ok((priority, span)) => (
+                format!("priority should be between 1 and 255, found {}", priority),
+                span,
+            ),
+            Err((value, span)) if value.is_empty() => ("priority needs a value".to_string(), span),
+            Err((value, span)) => (
+                format!(
+                    "priority should be a number between 1 and 255, found \"{}\"",
+                    value
+                ),
+                span,
+            ),
+        };
+        context.violation(LintCode::InvalidPriority, message, span)
+    }
+
+    /// Check that the keywords, which Suricata only accepts once, are not repeated
+    ///
+    /// Every occurrence after the first one is reported, pointing to the first
+    /// one. Repeated classtypes and thresholds keep their own lints.
+    fn validate_duplicate_options(&self, context: &ValidationContext) -> Vec<Violation> {
+        SINGLE_KEYWORDS
+            .into_iter()
+            .flat_map(|keyword| {
+                let code = match keyword {
+                    "classtype" => LintCode::InvalidClasstype,
+                    "threshold" => LintCode::DuplicateThreshold,
+                    _ => LintCode::DuplicateOption,
+                };
+                let options = self.options_with_keyword(keyword);
+                let Some((first, _)) = options.first() else {
+                    return vec![];
+                };
+                options
+                    .iter()
+                    .skip(1)
+                    .filter_map(|(option, _)| {
+                        let message = format!("{} is set more than once", keyword);
+                        let span = option.keyword().1.clone();
+                        let mut violation = context.violation(code, message, span)?;
+                        violation.related = vec![RelatedLocation {
+                            file: String::new(),
+                            line: 0,
+                            span: first.keyword().1.clone(),
+                            message: format!("{} is first set here", keyword),
+                        }];
+                        Some(violation)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Check if the action is known to Suricata, suggesting the closest action
+    ///
+    /// Signatures without a complete header are still being typed, their
+    /// action is not checked.
+    fn validate_action(&self, context: &ValidationContext) -> Option<Violation> {
+        let (Action::Other(action), span) = self.action.as_ref()? else {
+            return None;
+        };
+        let header = &self.header.0;
+        if header.protocol.is_none() || header.direction.is_none() || header.destination.is_none() {
+            return None;
+        }
+        let lowercase = action.to_lowercase();
+        if ACTIONS.contains(&lowercase.as_str()) {
+            return None;
+        }
+        let max_distance = (action.chars().count() / 4).max(2);
+        let closest = ACTIONS
+            .into_iter()
+            .map(|known| (edit_distance(&lowercase, known), known))
+            .filter(|(distance, _)| *distance <= max_distance)
+            .min();
+        let message = match closest {
+            Some((_, closest)) => format!(
+                "unknown action \"{}\", did you mean \"{}\"? (expected one of {})",
+                action,
+                closest,
+                ACTIONS.join(", ")
+            ),
+            None => format!(
+                "unknown action \"{}\" (expected one of {})",
+                action,
+                ACTIONS.join(", ")
+            ),
+        };
+        context.violation(LintCode::UnknownAction, message, span.clone())
+    }
+
+    /// Check if the direction is missing, unrecognized (e.g. "=>") or reversed (<-)
+    fn validate_direction(&self, context: &ValidationContext) -> Option<Violation> {
+        let header = &self.header.0;
+        match (&header.direction, &header.destination) {
+            (Some((NetworkDirection::DstToSrc, span)), _) => context.violation(
+                LintCode::ReversedDirection,
+                "\"<-\" is deprecated since Suricata 6, swap the source and the destination \
+                    and use \"->\""
+                    .to_string(),
+                span.clone(),
+            ),
+            // The parser accepts any combination of the direction characters
+            (Some((NetworkDirection::Unrecognized(direction), span)), _)
+                if !direction.is_empty() =>
+            {
+                context.violation(
+                    LintCode::InvalidDirection,
+                    format!(
+                        "expected a direction (->, <> or <-) here, found \"{}\"",
+                        direction
+                    ),
+                    span.clone(),
+                )
+            }
+            // The direction is missing, the destination follows the source
+            (Some((NetworkDirection::Unrecognized(_), _)) | None, Some((_, span))) => context
+                .violation(
+                    LintCode::InvalidDirection,
+                    "expected a direction (->, <> or <-) before the destination".to_string(),
+                    span.start..span.start + 1,
+                ),
+            _ => None,
+        }
+    }
+
+    /// Fix the reversed-direction lint, by swapping the source and the
+    /// destination (along with their ports)
+    pub fn swap_direction(&self, line: &str) -> Option<String> {
+        let header = &self.header.0;
+        let (NetworkDirection::DstToSrc, direction) = header.direction.as_ref()? else {
+            return None;
+        };
+        let source = header.source.as_ref()?.1.start..header.source_port.as_ref()?.1.end;
+        let destination =
+            header.destination.as_ref()?.1.start..header.destination_port.as_ref()?.1.end;
+        let chars = line.chars().collect::<Vec<_>>();
+        if destination.end > chars.len() {
+            return None;
+        }
+        let text = |span: Span| chars[span].iter().collect::<String>();
+        Some(format!(
+            "{}{}{}->{}{}{}",
+            text(0..source.start),
+            text(destination.clone()),
+            text(source.end..direction.start),
+            text(direction.end..destination.start),
+            text(source),
+            text(destination.end..chars.len()),
+        ))
+    }
+
+    /// Check if the signature matches any traffic (any any -> any any)
+    ///
+    /// Such a signature is evaluated against every packet. Signatures anchored
+    /// by a content, a pcre or a sticky buffer are cheaper, so they are only
+    /// reported as information (unless the level of the lint is configured),
+    /// or not at all with the relaxed strictness.
+    fn validate_any_to_any(&self, context: &ValidationContext) -> Option<Violation> {
+        let (header, span) = &self.header;
+        if !header.has_forward_direction() {
+            return None;
+        }
+        let addresses_any = header
+            .source
+            .iter()
+            .chain(header.destination.iter())
+            .all(|(address, _)| context.address_is_any(address, 0));
+        let ports_any = header
+            .source_port
+            .iter()
+            .chain(header.destination_port.iter())
+            .all(|(port, _)| context.port_is_any(port, 0));
+        let complete = header.source.is_some()
+            && header.source_port.is_some()
+            && header.destination.is_some()
+            && header.destination_port.is_some();
+        if !complete || !addresses_any || !ports_any {
+            return None;
+        }
+
+        let anchored = !self.contents().is_empty()
+            || !self.options_with_keyword("pcre").is_empty()
+            || self.options.iter().flatten().any(|(option, _)| {
+                matches!(option, RuleOption::Buffer((keyword, _)) if is_sticky_buffer(keyword))
+            });
+        let strictness = context.any_to_any_strictness;
+        if anchored && strictness == AnyToAnyStrictness::Relaxed {
+            return None;
+        }
+        let message = if anchored {
+            "signature matches any traffic (any any -> any any), narrow down the addresses or ports"
+        } else {
+            "signature matches any traffic (any any -> any any) without a content, a pcre or a \
+                sticky buffer, so it is evaluated against every packet, narrow down the addresses \
+                or ports"
+        };
+        let mut violation =
+            context.violation(LintCode::AnyToAny, message.to_string(), span.clone())?;
+        if anchored
+            && strictness == AnyToAnyStrictness::Normal
+            && !context.is_configured(LintCode::AnyToAny)
+        {
+            violation.severity = Severity::Information;
+        }
+        Some(violation)
+    }
+
+    /// Check if the addresses or the ports of the header match nothing
+    ///
+    /// Only literal values are evaluated, headers with variables are skipped.
+    fn validate_empty_header(&self, context: &ValidationContext) -> Option<Violation> {
+        let (header, span) = &self.header;
+        let empty_address = |address: &Option<Spanned<NetworkAddress>>| {
+            address
+                .as_ref()
+                .and_then(|(address, _)| address.address_set())
+                .is_some_and(|set| set.is_empty())
+        };
+        let empty_port = |port: &Option<Spanned<NetworkPort>>| {
+            port.as_ref()
+                .and_then(|(port, _)| port.port_set())
+                .is_some_and(|set| set.is_empty())
+        };
+        let part = if empty_address(&header.source) {
+            "source addresses"
+        } else if empty_port(&header.source_port) {
+            "source ports"
+        } else if empty_address(&header.destination) {
+            "destination addresses"
+        } else if empty_port(&header.destination_port) {
+            "destination ports"
+        } else {
+            return None;
+        };
+        context.violation(
+            LintCode::EmptyHeader,
+            format!(
+                "the {} match nothing, so the signature can never match",
+                part
+            ),
+            span.clone(),
+        )
+    }
+
+    /// Check if the fast pattern is long enough to be selective
+    ///
+    /// A short fast pattern matches a lot of traffic, so the signature is
+    /// evaluated far more often. Without fast_pattern, the engine picks one of
+    /// the contents, which is only reported when all of them are short.
+    fn validate_fast_pattern(&self, context: &ValidationContext) -> Vec<Violation> {
+        let min_length = context.fast_pattern_min_length;
+        let contents = self
+            .content_matches()
+            .into_iter()
+            .filter(|content| !content.negated)
+            .filter_map(|content| Some((content.byte_length()?, content)))
+            .collect::<Vec<_>>();
+        let is_fast_pattern = |content: &ContentMatch| content.fast_pattern().is_some();
+        if contents.iter().any(|(_, content)| is_fast_pattern(content)) {
+            return contents
+                .iter()
+                .filter(|(length, content)| *length < min_length && is_fast_pattern(content))
+                .filter_map(|(length, content)| {
+                    context.violation(
+                        LintCode::ShortFastPattern,
+                        format!(
+                            "fast_pattern on a {} byte content matches too much traffic, use a content of at least {} bytes",
+                            length, min_length
+                        ),
+                        content.pattern.1.clone(),
+                    )
+                })
+                .collect();
+        }
+        let Some((_, first)) = contents.first() else {
+            return vec![];
+        };
+        if contents.iter().any(|(length, _)| *length >= min_length) {
+            return vec![];
+        }
+        context
+            .violation(
+                LintCode::ShortFastPattern,
+                format!(
+                    "every content is shorter than {} bytes, so the fast pattern will be poor, add a longer content",
+                    min_length
+                ),
+                first.pattern.1.clone(),
+            )
+            .into_iter()
+            .collect()
+    }
+
+    /// Check if the signature has a msg, a sid and a rev
+    ///
+    /// Suricata accepts signatures without them, however they are required by
+    /// every style guide. The violations point to the parentheses of the options.
+    fn validate_required_options(&self, context: &ValidationContext) -> Vec<Violation> {
+        let Some(span) = &self.options_span else {
+            return vec![];
+        };
+        [
+            ("msg", LintCode::MissingMsg),
+            ("sid", LintCode::MissingSid),
+            ("rev", LintCode::MissingRev),
+        ]
+        .into_iter()
+        .filter(|(keyword, _)| self.options_with_keyword(keyword).is_empty())
+        .filter_map(|(keyword, code)| {
+            let message = format!("signature has no {} option", keyword);
+            context.violation(code, message, span.clone())
+        })
+        .collect()
+    }
+
+    /// Check if a TCP signature, which inspects the payload, has a flow option
+    fn validate_flow(&self, context: &ValidationContext) -> Option<Violation> {
+        let (protocol, span) = self.protocol().as_ref()?;
+        if !TCP_PROTOCOLS.contains(&protocol.as_str().to_lowercase().as_str()) {
+            return None;
+        }
+        let inspects_payload =
+            !self.contents().is_empty() || !self.options_with_keyword("pcre").is_empty();
+        if !inspects_payload || !self.options_with_keyword("flow").is_empty() {
+            return None;
+        }
+        context.violation(
+            LintCode::MissingFlow,
+            format!(
+                "signature inspects the payload without a flow option, add \"flow:{};\"",
+                self.suggested_flow()
+            ),
+            span.clone(),
+        )
+    }
+
+    /// Guess the flow of the signature from its header
+    ///
+    /// A specific destination port (e.g. 80) suggests the signature inspects
+    /// the traffic to the server, a specific source port the traffic to the
+    /// client. Signatures for both directions (<>) only require an established
+    /// flow.
+    pub fn suggested_flow(&self) -> &'static str {
+        let is_any = |port: &Option<Spanned<NetworkPort>>| {
+            port.as_ref().is_none_or(|(port, _)| port.is_any())
+        };
+        if matches!(self.direction(), Some((NetworkDirection::Both, _))) {
+            "established"
+        } else if is_any(self.destination_port()) && !is_any(self.source_port()) {
+            "established,to_client"
+        } else {
+            "established,to_server"
+        }
+    }
+
+    /// Fix the missing-flow lint, by inserting a flow option
+    pub fn insert_flow(&self, line: &str) -> Option<String> {
+        self.insert_option(line, &format!("flow:{};", self.suggested_flow()))
+    }
+
+    /// Fix the missing-noalert lint, by inserting a flowbits:noalert option
+    pub fn insert_noalert(&self, line: &str) -> Option<String> {
+        self.insert_option(line, "flowbits:noalert;")
+    }
+
+    /// Fix the orphan-noalert lint, by removing every noalert option
+    pub fn remove_noalert(&self, line: &str) -> Option<String> {
+        let spans = self
+            .noalert()
+            .into_iter()
+            .map(|(_, span)| span.clone())
+            .collect();
+        remove_options(line, spans)
+    }
+
+    /// Get the msg, if it is not (completely) enclosed in quotes
+    ///
+    /// Returns the content of the msg, without a stray quote at either end,
+    /// along with the span of its value. Commas of an unquoted msg split it
+    /// into several values, which are joined again.
+    pub fn unquoted_msg(&self) -> Option<Spanned<String>> {
+        let (RuleOption::KeywordPair(_, values), _) = self.options_with_keyword("msg").first()?
+        else {
+            return None;
+        };
+        if let [(OptionsVariable::String(_), _)] = values.as_slice() {
+            return None;
+        }
+        // The whitespace around the commas is not part of the values
+        let mut content = String::new();
+        let mut previous_end = None;
+        values.iter().for_each(|(value, span)| {
+            if let Some(previous_end) = previous_end {
+                content.push(',');
+                let spaces = span.start.saturating_sub(previous_end + 1);
+                content.extend(std::iter::repeat_n(' ', spaces));
+            }
+            match value {
+                OptionsVariable::String((value, _)) | OptionsVariable::Other((value, _)) => {
+                    content.push_str(value)
+                }
+                OptionsVariable::NegatedString(..) => content.push_str(&value.to_string()),
+            }
+            previous_end = Some(span.end);
+        });
+        let mut content = content.trim();
+        // Only strip a quote at either end, if it has no counterpart
+        if content.matches('"').count() % 2 == 1 {
+            content = match content.strip_prefix('"') {
+                Some(content) => content,
+                None => content.strip_suffix('"').unwrap_or(content),
+            };
+        }
+        let (_, first) = values.first()?.0.trimmed();
+        let (_, last) = values.last()?.0.trimmed();
+        Some((
+            quoted_content(content),
+            first.start..last.end.max(first.start),
+        ))
+    }
+
+    /// Fix the unquoted-msg lint, by enclosing the msg in quotes
+    ///
+    /// The characters with a meaning inside quotes are escaped. An empty msg
+    /// is removed instead.
+    pub fn quote_msg(&self, line: &str) -> Option<String> {
+        let (content, span) = self.unquoted_msg()?;
+        if content.trim().is_empty() {
+            let (_, option_span) = self.options_with_keyword("msg").first()?;
+            return remove_options(line, vec![option_span.clone()]);
+        }
+        let quoted = OptionsVariable::String((content, span.clone())).to_string();
+        let mut chars = line.chars().collect::<Vec<_>>();
+        let span = span.start.min(chars.len())..span.end.min(chars.len());
+        chars.splice(span, quoted.chars());
+        Some(chars.into_iter().collect())
+    }
+
+    /// Insert an option (including its semicolon) after the msg option
+    ///
+    /// Returns the fixed line, the option is inserted first if there is no msg.
+    fn insert_option(&self, line: &str, option: &str) -> Option<String> {
+        let options = self.options.as_ref()?;
+        let chars = line.chars().collect::<Vec<_>>();
+        let (column, text) = match self.options_with_keyword("msg").first() {
+            Some((_, span)) => {
+                let semicolon = chars.iter().skip(span.end).position(|char| *char == ';')?;
+                (span.end + semicolon + 1, format!(" {}", option))
+            }
+            None => (options.first()?.1.start, format!("{} ", option)),
+        };
+        let (before, after) = chars.split_at(column.min(chars.len()));
+        Some(format!(
+            "{}{}{}",
+            before.iter().collect::<String>(),
+            text,
+            after.iter().collect::<String>()
+        ))
+    }
+
+    /// Check if a signature setting bits does not alert, and the other way around
+    ///
+    /// Signatures, which also check bits, are part of a chain and may alert.
+    fn validate_noalert(&self, context: &ValidationContext) -> Vec<Violation> {
+        let operations = self.bits_operations();
+        let noalert = self.noalert().into_iter().next();
+        let setting = operations.iter().find(|operation| operation.sets());
+        let changes_bits = operations
+            .iter()
+            .any(|operation| operation.sets() || operation.operation.0 == "unset");
+        let checks_bits = operations.iter().any(|operation| operation.checks());
+        match (noalert, setting) {
+            (None, Some(operation)) if !checks_bits => context
+                .violation(
+                    LintCode::MissingNoalert,
+                    format!(
+                        "signature only sets the {} \"{}\", add \"flowbits:noalert;\" so it does not alert",
+                        operation.kind,
+                        operation
+                            .names
+                            .iter()
+                            .map(|(name, _)| name.as_str())
+                            .collect::<Vec<_>>()
+                            .join("|")
+                    ),
+                    operation.span.clone(),
+                )
+                .into_iter()
+                .collect(),
+            (Some((option, _)), _) if !changes_bits => context
+                .violation(
+                    LintCode::OrphanNoalert,
+                    "signature never alerts and does not set any flowbits or xbits, so it has no effect"
+                        .to_string(),
+                    option.keyword().1.clone(),
+                )
+                .into_iter()
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    /// Check the msg against the configured pattern, its length and whitespace
+    fn validate_msg(&self, context: &ValidationContext) -> Vec<Violation> {
+        let mut violations = vec![];
+        if let Some((msg, span)) = self.unquoted_msg() {
+            let message = match msg.trim().is_empty() {
+                true => "msg is empty, remove it or add a message in quotes",
+                false => "msg should be enclosed in quotes",
+            };
+            // An empty value has an empty span, point to the keyword instead
+            let span = match span.is_empty() {
+                true => self.options_with_keyword("msg")[0].0.keyword().1.clone(),
+                false => span,
+            };
+            violations.extend(context.violation(LintCode::UnquotedMsg, message.to_string(), span));
+            return violations;
+        }
+        let Some((msg, span)) = self.msg() else {
+            return violations;
+        };
+        if let Some(msg_pattern) = &context.msg_pattern {
+            if !msg_pattern.is_match(&msg) {
+                violations.extend(context.violation(
+                    LintCode::MsgPattern,
+                    format!("msg does not match the pattern \"{}\"", msg_pattern),
+                    span.clone(),
+                ));
+            }
+        }
+        if msg.trim() != msg {
+            violations.extend(context.violation(
+                LintCode::MsgWhitespace,
+                "msg starts or ends with whitespace".to_string(),
+                span.clone(),
+            ));
+        }
+        let length = msg.chars().count();
+        if length > context.msg_max_length {
+            violations.extend(context.violation(
+                LintCode::MsgLength,
+                format!(
+                    "msg is {} characters long, the maximum is {}",
+                    length, context.msg_max_length
+                ),
+                span,
+            ));
+        }
+        violations
+    }
+
+    /// Check the names of the events, suggesting the closest known event
+    ///
+    /// Events of protocols without known events are only reported, when they
+    /// are close to a known event (e.g. "htpp.invalid_request").
+    fn validate_events(&self, context: &ValidationContext) -> Vec<Violation> {
+        self.events()
+            .into_iter()
+            .filter(|(keyword, (name, _))| context.events.get(*keyword, name).is_none())
+            .filter_map(|(keyword, (name, span))| {
+                let closest = context.events.closest(keyword, &name);
+                if closest.is_none() && !context.events.knows_protocol(keyword, &name) {
+                    return None;
+                }
+                let message = match closest {
+                    Some(closest) => format!(
+                        "unknown {} \"{}\", did you mean \"{}\"?",
+                        keyword, name, closest
+                    ),
+                    None => format!("unknown {} \"{}\"", keyword, name),
+                };
+                context.violation(LintCode::UnknownEvent, message, span)
+            })
+            .collect()
+    }
+
+    /// Check if the keywords of the options are known to Suricata, suggesting
+    /// the closest known keyword
+    ///
+    /// Nothing is reported, when the keywords of Suricata are not known.
+    /// Deprecated keywords are reported by the deprecated-keyword lint.
+    fn validate_keywords(&self, context: &ValidationContext) -> Vec<Violation> {
+        if context.keywords.is_empty() {
+            return vec![];
+        }
+        self.options
+            .iter()
+            .flatten()
+            .filter_map(|(option, _)| {
+                let (keyword, span) = option.keyword();
+                if context.keywords.contains(keyword) || deprecated_keyword(keyword).is_some() {
+                    return None;
+                }
+                let max_distance = (keyword.chars().count() / 4).max(2);
+                let closest = context
+                    .keywords
+                    .iter()
+                    .map(|known| (edit_distance(keyword, known), known))
+                    .filter(|(distance, _)| *distance <= max_distance)
+                    .min();
+                let message = match closest {
+                    Some((_, closest)) => {
+                        format!(
+                            "unknown keyword \"{}\", did you mean \"{}\"?",
+                            keyword, closest
+                        )
+                    }
+                    None => format!("unknown keyword \"{}\"", keyword),
+                };
+                context.violation(LintCode::UnknownKeyword, message, span.clone())
+            })
+            .collect()
+    }
+
+    /// Check for keywords, which are deprecated or removed in the installed Suricata
+    ///
+    /// Keywords are not reported before their replacement exists. Removed
+    /// keywords are errors, unless the level of the lint is configured.
+    fn validate_deprecated_keywords(&self, context: &ValidationContext) -> Vec<Violation> {
+        self.options
+            .iter()
+            .flatten()
+            .filter_map(|(option, _)| {
+                let (keyword, span) = option.keyword();
+                let deprecated = deprecated_keyword(keyword)?;
+                let removed = match context.suricata_version {
+                    Some(version) if version < deprecated.deprecated_in => return None,
+                    Some(version) => deprecated
+                        .removed_in
+                        .is_some_and(|removed| version >= removed),
+                    None => deprecated.removed_in.is_some(),
+                };
+                let replacement = if deprecated.value_as_content {
+                    format!("{} with a content", deprecated.replacement)
+                } else {
+                    deprecated.replacement.to_string()
+                };
+                let message = match deprecated.removed_in {
+                    Some(removed_in) if removed => format!(
+                        "{} was removed in Suricata {}, use {} instead",
+                        keyword, removed_in, replacement
+                    ),
+                    _ => format!(
+                        "{} is deprecated since Suricata {}, use {} instead",
+                        keyword, deprecated.deprecated_in, replacement
+                    ),
+                };
+                let mut violation =
+                    context.violation(LintCode::DeprecatedKeyword, message, span.clone())?;
+                if removed && !context.is_configured(LintCode::DeprecatedKeyword) {
+                    violation.severity = Severity::Error;
+                }
+                Some(violation)
+            })
+            .collect()
+    }
+
+    /// Fix the deprecated-keyword lint, by renaming the keywords
+    ///
+    /// The value of a keyword, which was replaced by a sticky buffer, becomes
+    /// the content of the buffer. Values without an equivalent content (e.g.
+    /// ssh.protoversion:2_compat;) are left alone.
+    pub fn replace_deprecated_keywords(&self, line: &str) -> Option<String> {
+        let mut spans = self
+            .options
+            .iter()
+            .flatten()
+            .filter_map(|(option, option_span)| {
+                let (keyword, span) = option.keyword();
+                let deprecated = deprecated_keyword(keyword)?;
+                let value = match option {
+                    RuleOption::KeywordPair(_, values) if deprecated.value_as_content => {
+                        values.first()
+                    }
+                    _ => None,
+                };
+                let Some((value, _)) = value else {
+                    return Some((span.clone(), deprecated.replacement.to_string()));
+                };
+                let content = match value {
+                    OptionsVariable::Other((value, _)) if value.trim().ends_with("_compat") => {
+                        return None;
+                    }
+                    OptionsVariable::Other((value, _)) => format!("\"{}\"", value.trim()),
+                    value => value.to_string(),
+                };
+                let replacement = format!("{}; content:{}", deprecated.replacement, content);
+                Some((span.start..option_span.end, replacement))
+            })
+            .collect::<Vec<_>>();
+        if spans.is_empty() {
+            return None;
+        }
+        // Replace the keywords from the back, so the spans stay valid
+        spans.sort_by_key(|(span, _)| span.start);
+        let mut chars = line.chars().collect::<Vec<_>>();
+        for (span, replacement) in spans.into_iter().rev() {
+            let span = span.start.min(chars.len())..span.end.min(chars.len());
+            chars.splice(span, replacement.chars());
+        }
+        Some(chars.into_iter().collect())
+    }
+
+    /// Check the threshold options against each other and against threshold.config
+    fn validate_thresholds(&self, context: &ValidationContext) -> Vec<Violation> {
+        let thresholds = self.options_with_keyword("threshold");
+        let mut violations = vec![];
+        let Some((threshold, _)) = thresholds.first() else {
+            return violations;
+        };
+        if let Some((detection_filter, _)) = self.options_with_keyword("detection_filter").first() {
+            violations.extend(context.violation(
+                LintCode::ThresholdWithDetectionFilter,
+                "threshold can not be combined with detection_filter".to_string(),
+                detection_filter.keyword().1.clone(),
+            ));
+        }
+        let Some((sid, _)) = self.sid() else {
+            return violations;
+        };
+        let gid = self.gid().map_or(1, |(gid, _)| gid);
+        let config = &context.threshold_config;
+        let related = config
+            .get(gid, sid)
+            .iter()
+            .filter(|entry| entry.overrides_threshold())
+            .map(|entry| RelatedLocation {
+                file: config.path.clone(),
+                line: entry.line,
+                span: entry.span.clone(),
+                message: format!("{} of sid {} inside threshold.config", entry.kind, sid),
+            })
+            .collect::<Vec<_>>();
+        if !related.is_empty() {
+            let message = "threshold is replaced by the entry of threshold.config".to_string();
+            if let Some(mut violation) = context.violation(
+                LintCode::ShadowedThreshold,
+                message,
+                threshold.keyword().1.clone(),
+            ) {
+                violation.related = related;
+                violations.push(violation);
+            }
+        }
+        violations
+    }
+}
+
+/// Remove options (including their semicolon) from a line
+fn remove_options(line: &str, spans: Vec<Span>) -> Option<String> {
+    if spans.is_empty() {
+        return None;
+    }
+    // Remove the options from the back, so the spans stay valid
+    let mut chars = line.chars().collect::<Vec<_>>();
+    for span in spans.into_iter().rev() {
+        let start = span.start.min(chars.len());
+        let semicolon = chars
+            .iter()
+            .skip(span.end)
+            .position(|char| *char == ';')
+            .map_or(span.end, |semicolon| span.end + semicolon + 1);
+        chars.drain(start..semicolon.min(chars.len()));
+    }
+    Some(chars.into_iter().collect())
+}
+
+/// Check the ports of the header for mistakes, which Suricata does not report
+///
+/// Every range, group and negation is checked, including the nested ones, and
+/// the violations point to the offending element.
+fn validate_port(
+    (port, span): &Spanned<NetworkPort>,
+    context: &ValidationContext,
+    violations: &mut Vec<Violation>,
+) {
+    let (message, span) = match port {
+        NetworkPort::PortRange((from, from_span), (to, to_span)) if from > to => (
+            format!(
+                "port range {}:{} is inverted and matches nothing, use {}:{}",
+                from, to, to, from
+            ),
+            from_span.start..to_span.end,
+        ),
+        NetworkPort::PortRange((from, from_span), (to, to_span)) if from == to => (
+            format!("port range {}:{} only matches port {}", from, to, from),
+            from_span.start..to_span.end,
+        ),
+        NetworkPort::NegPort(inner) if inner.0.is_any() => {
+            ("!any matches no port".to_string(), span.clone())
+        }
+        NetworkPort::NegPort(inner) => return validate_port(inner, context, violations),
+        NetworkPort::PortGroup(members) => {
+            let mut seen = HashSet::new();
+            members.iter().for_each(|member| {
+                let (port, span) = member;
+                if !seen.insert(port.to_string()) {
+                    violations.extend(context.violation(
+                        LintCode::SuspiciousPort,
+                        format!("{} is repeated inside the port group", port),
+                        span.clone(),
+                    ));
+                }
+                validate_port(member, context, violations);
+            });
+            return;
+        }
+        _ => return,
+    };
+    violations.extend(context.violation(LintCode::SuspiciousPort, message, span));
+}
+
+/// Check if the CIDRs of the header are written with their network address
+///
+/// Suricata ignores the host bits (192.168.1.5/24 matches 192.168.1.0/24),
+/// however they usually mean the wrong network or prefix was written.
+fn validate_cidr(
+    (address, _): &Spanned<NetworkAddress>,
+    context: &ValidationContext,
+    violations: &mut Vec<Violation>,
+) {
+    match address {
+        NetworkAddress::CIDR((ip, ip_span), (mask, mask_span)) => {
+            let Ok(network) = IpNet::new(*ip, *mask) else {
+                return;
+            };
+            if network.addr() == network.network() {
+                return;
+            }
+            violations.extend(context.violation(
+                LintCode::CidrHostBits,
+                format!(
+                    "{} has host bits set, the network is {}",
+                    network,
+                    network.trunc()
+                ),
+                ip_span.start..mask_span.end,
+            ));
+        }
+        NetworkAddress::IPGroup(members) => members
+            .iter()
+            .for_each(|member| validate_cidr(member, context, violations)),
+        NetworkAddress::NegIP(inner) => validate_cidr(inner, context, violations),
+        _ => (),
+    }
+}
+
+/// Check the arguments of a flowbits, xbits or hostbits option
+fn validate_bits(operation: &BitsOperation, context: &ValidationContext) -> Vec<Violation> {
+    let kind = operation.kind;
+    let mut violations = vec![];
+    let (name, span) = &operation.operation;
+    if !kind.operations().contains(&name.as_str()) {
+        violations.extend(context.violation(
+            LintCode::InvalidBits,
+            format!(
+                "unknown {} operation \"{}\", expected one of {}",
+                kind.keyword(),
+                name,
+                kind.operations().join(", ")
+            ),
+            span.clone(),
+        ));
+    }
+    // Every operation names the bits, except for noalert
+    let has_names = operation.names.iter().any(|(name, _)| !name.is_empty());
+    match name.as_str() {
+        "noalert" if has_names => violations.extend(context.violation(
+            LintCode::InvalidBits,
+            "noalert does not take the name of a flowbit".to_string(),
+            operation.span.clone(),
+        )),
+        "noalert" => (),
+        name if !has_names => violations.extend(context.violation(
+            LintCode::InvalidBits,
+            format!("{} needs the name of the {}", name, kind),
+            operation.span.clone(),
+        )),
+        _ => (),
+    }
+    match (operation.unknown_track(), &operation.track) {
+        (Some((track, span)), _) => {
+            let message = if kind.tracks().is_empty() {
+                format!("{} do not accept a track", kind.keyword())
+            } else {
+                format!(
+                    "unknown {} track \"{}\", expected one of {}",
+                    kind.keyword(),
+                    track,
+                    kind.tracks().join(", ")
+                )
+            };
+            violations.extend(context.violation(LintCode::InvalidBits, message, span.clone()));
+        }
+        (None, None) if kind == BitsKind::Xbits => {
+            violations.extend(context.violation(
+                LintCode::InvalidBits,
+                "xbits require a track (e.g. \"track ip_src\")".to_string(),
+                operation.span.clone(),
+            ));
+        }
+        _ => (),
+    }
+    if let Some((expire, span)) = &operation.expire {
+        if !expire.parse::<u32>().is_ok_and(|expire| expire > 0) {
+            violations.extend(context.violation(
+                LintCode::InvalidBits,
+                format!(
+                    "expire should be a positive number of seconds, found \"{}\"",
+                    expire
+                ),
+                span.clone(),
+            ));
+        }
+    }
+    violations
+}
+
+/// Check if the modifiers of a content contradict each other
+///
+/// Depth counts from the offset and within from the end of the previous match
+/// (after the distance), so the pattern only has to fit into them. Modifiers,
+/// which are not numbers (e.g. extracted with byte_extract), and negated
+/// contents are skipped.
+fn validate_content_modifiers(
+    content: &ContentMatch,
+    context: &ValidationContext,
+) -> Vec<Violation> {
+    let Some(length) = content.byte_length().filter(|_| !content.negated) else {
+        return vec![];
+    };
+    let mut violations = vec![];
+    if let Some((depth, span)) = content.numeric_modifier("depth") {
+        if depth < length as i64 {
+            violations.extend(context.violation(
+                LintCode::ImpossibleContent,
+                format!(
+                    "content is {} bytes long, but depth only inspects {} bytes, so it can never match",
+                    length, depth
+                ),
+                span,
+            ));
+        }
+    }
+    if let Some((within, span)) = content.numeric_modifier("within") {
+        if within < length as i64 {
+            violations.extend(context.violation(
+                LintCode::ImpossibleContent,
+                format!(
+                    "content is {} bytes long, but within only inspects {} bytes, so it can never match",
+                    length, within
+                ),
+                span,
+            ));
+        }
+    }
+    let starts_with = content
+        .modifiers
+        .iter()
+        .any(|(option, _)| option.keyword().0 == "startswith");
+    if let Some((offset, span)) = content.numeric_modifier("offset") {
+        if starts_with && offset > 0 {
+            violations.extend(context.violation(
+                LintCode::ImpossibleContent,
+                format!(
+                    "startswith matches at the start of the buffer, but offset skips {} bytes, so it can never match",
+                    offset
+                ),
+                span,
+            ));
+        }
+    }
+    violations
+}
+
+/// Run the rule lints for every signature inside the file
+pub fn validate_document(ast: &AST, context: &ValidationContext) -> Vec<(u32, Violation)> {
+    let mut violations = ast
+        .rules
+        .iter()
+        .flat_map(|(line, (rule, _))| {
+            rule.validate(context).into_iter().map(|mut violation| {
+                violation
+                    .related
+                    .iter_mut()
+                    .filter(|related| related.file.is_empty())
+                    .for_each(|related| related.line = *line);
+                (*line, violation)
+            })
+        })
+        .collect::<Vec<_>>();
+    violations.extend(validate_variables(ast, context));
+    violations.sort_by_key(|(line, violation)| (*line, violation.span.start));
+    violations
+}
+
+/// Check that the address and port groups used by the signatures are defined,
+/// suggesting the closest defined group
+///
+/// A group used by several signatures of the file is likely provided at
+/// runtime, so it is only reported as a hint (unless the level of the lint is
+/// configured).
+fn validate_variables(ast: &AST, context: &ValidationContext) -> Vec<(u32, Violation)> {
+    if !context.is_enabled(LintCode::UndefinedVariable) {
+        return vec![];
+    }
+    let undefined = ast
+        .rules
+        .iter()
+        .flat_map(|(line, (rule, _))| {
+            variable_usages(rule)
+                .into_iter()
+                .map(move |(kind, variable)| (*line, kind, variable))
+        })
+        .filter(|(_, kind, (name, _))| context.variables.get(*kind, name).is_none())
+        .collect::<Vec<_>>();
+    let mut usages: HashMap<(VariableKind, &str), HashSet<u32>> = HashMap::new();
+    undefined.iter().for_each(|(line, kind, (name, _))| {
+        usages.entry((*kind, name)).or_default().insert(*line);
+    });
+    undefined
+        .iter()
+        .filter_map(|(line, kind, (name, span))| {
+            let defined = match kind {
+                VariableKind::Address => &context.variables.addresses,
+                VariableKind::Port => &context.variables.ports,
+            };
+            let max_distance = (name.chars().count() / 4).max(2);
+            let closest = defined
+                .keys()
+                .map(|defined| (edit_distance(name, defined), defined))
+                .filter(|(distance, _)| *distance <= max_distance)
+                .min();
+            let message = match closest {
+                Some((_, closest)) => format!(
+                    "{} ${} is not defined, did you mean ${}?",
+                    kind, name, closest
+                ),
+                None => format!("{} ${} is not defined", kind, name),
+            };
+            let mut violation =
+                context.violation(LintCode::UndefinedVariable, message, span.clone())?;
+            let used_consistently = usages[&(*kind, name.as_str())].len() > 1;
+            if used_consistently && !context.is_configured(LintCode::UndefinedVariable) {
+                violation.severity = Severity::Hint;
+            }
+            Some((*line, violation))
+        })
+        .collect()
+}
+
+/// Check if the sids of the file are inside the range reserved for it
+///
+/// Files with local signatures should only use the local range, while vendor
+/// rulesets should not use it. The path should be relative to the workspace
+/// folder, files matching neither glob are not checked.
+pub fn validate_sid_range(
+    path: &Path,
+    ast: &AST,
+    context: &ValidationContext,
+) -> Vec<(u32, Violation)> {
+    let matches = |patterns: &[Pattern]| patterns.iter().any(|pattern| pattern.matches_path(path));
+    let local = matches(&context.local_files);
+    if !context.is_enabled(LintCode::SidRange) || !local && !matches(&context.vendor_files) {
+        return vec![];
+    }
+    let (first_sid, last_sid) = (context.local_sids.start(), context.local_sids.end());
+    let mut violations = ast
+        .rules
+        .iter()
+        .filter_map(|(line, (rule, _))| {
+            let (sid, span) = rule.sid()?;
+            let message = match (local, context.local_sids.contains(&sid)) {
+                (true, false) => format!(
+                    "sid {} is outside of the local range {}-{}",
+                    sid, first_sid, last_sid
+                ),
+                (false, true) => format!(
+                    "sid {} is reserved for local signatures ({}-{})",
+                    sid, first_sid, last_sid
+                ),
+                _ => return None,
+            };
+            Some((*line, context.violation(LintCode::SidRange, message, span)?))
+        })
+        .collect::<Vec<_>>();
+    violations.sort_by_key(|(line, _)| *line);
+    violations
+}
+
+/// Check that the lua scripts of the signatures exist
+///
+/// Relative scripts are looked up in the directory of the rule file.
+pub fn validate_lua_scripts(
+    path: &Path,
+    ast: &AST,
+    context: &ValidationContext,
+) -> Vec<(u32, Violation)> {
+    if !context.is_enabled(LintCode::MissingLuaScript) {
+        return vec![];
+    }
+    let directory = path.parent().unwrap_or(Path::new(""));
+    let mut violations = ast
+        .rules
+        .iter()
+        .flat_map(|(line, (rule, _))| {
+            rule.lua_scripts().into_iter().filter_map(|script| {
+                let script_path = script.path(directory);
+                if script_path.is_file() {
+                    return None;
+                }
+                let message = format!("lua script {} does not exist", script_path.display());
+                let (_, span) = script.file;
+                Some((
+                    *line,
+                    context.violation(LintCode::MissingLuaScript, message, span)?,
+                ))
+            })
+        })
+        .collect::<Vec<_>>();
+    violations.sort_by_key(|(line, _)| *line);
+    violations
+}
+
+/// Run the workspace lints over all files
+///
+/// Every file is provided as a pair of its name and its AST
+pub fn validate_workspace(
+    files: &[(String, &AST)],
+    context: &ValidationContext,
+) -> Vec<FileViolation> {
+    let mut violations = validate_duplicate_sids(files, context);
+    violations.extend(validate_unset_flowbits(files, context));
+    violations.extend(validate_duplicate_msgs(files, context));
+    violations
+}
+
+/// Find all signatures, which share their sid with another signature
+///
+/// Every signature points to the others using its sid. Signatures without a
+/// sid, or with a sid which is not a number, are skipped.
+pub fn validate_duplicate_sids(
+    files: &[(String, &AST)],
+    context: &ValidationContext,
+) -> Vec<FileViolation> {
+    let Some(severity) = context.severity(LintCode::DuplicateSid) else {
+        return vec![];
+    };
+    // Group the signatures by their sid
+    let mut sids: HashMap<u32, Vec<(&String, u32, Span)>> = HashMap::new();
+    files.iter().for_each(|(file, ast)| {
+        ast.rules.iter().for_each(|(line, (rule, _))| {
+            if let Some((sid, span)) = rule.sid() {
+                sids.entry(sid).or_default().push((file, *line, span));
+            }
+        });
+    });
+
+    sids.values_mut()
+        .for_each(|locations| locations.sort_by_key(|(file, line, _)| (*file, *line)));
+    let mut violations = vec![];
+    sids.iter()
+        .filter(|(_, locations)| locations.len() > 1)
+        .for_each(|(sid, locations)| {
+            locations.iter().for_each(|(file, line, span)| {
+                // Point to all other signatures with the same sid
+                let related = locations
+                    .iter()
+                    .filter(|(other_file, other_line, _)| other_file != file || other_line != line)
+                    .map(|(other_file, other_line, other_span)| RelatedLocation {
+                        file: other_file.to_string(),
+                        line: *other_line,
+                        span: other_span.clone(),
+                        message: format!("sid {} is also used here", sid),
+                    })
+                    .collect();
+                let mut violation = Violation::new(
+                    LintCode::DuplicateSid,
+                    format!("sid {} is used by {} signatures", sid, locations.len()),
+                    span.clone(),
+                );
+                violation.severity = severity;
+                violation.related = related;
+                violations.push((file.to_string(), *line, violation));
+            });
+        });
+    violations
+}
+
+/// Find the signatures, which share their msg with a signature of another sid
+///
+/// The msgs are compared ignoring the case and the whitespace, every duplicate
+/// points to the first signature using the msg. Only the first
+/// MAX_DUPLICATE_MSGS duplicates of a msg are reported.
+pub fn validate_duplicate_msgs(
+    files: &[(String, &AST)],
+    context: &ValidationContext,
+) -> Vec<FileViolation> {
+    let Some(severity) = context.severity(LintCode::DuplicateMsg) else {
+        return vec![];
+    };
+    // Group the signatures by their msg: (file, line, sid, msg)
+    let mut msgs: HashMap<String, Vec<_>> = HashMap::new();
+    files.iter().for_each(|(file, ast)| {
+        ast.rules.iter().for_each(|(line, (rule, _))| {
+            let Some(msg) = rule.msg() else {
+                return;
+            };
+            let normalized = normalize_msg(&msg.0);
+            let ignored = normalized.is_empty()
+                || context
+                    .duplicate_msg_ignore
+                    .iter()
+                    .any(|prefix| normalized.starts_with(prefix));
+            if !ignored {
+                let sid = rule.sid().map(|(sid, _)| sid);
+                msgs.entry(normalized)
+                    .or_default()
+                    .push((file, *line, sid, msg));
+            }
+        });
+    });
+
+    let mut violations = vec![];
+    msgs.values_mut().for_each(|locations| {
+        locations.sort_by_key(|(file, line, _, _)| (*file, *line));
+        let (first_file, first_line, first_sid, (_, first_span)) = &locations[0];
+        locations
+            .iter()
+            .skip(1)
+            // Revisions of the same signature are reported by duplicate-sid
+            .filter(|(_, _, sid, _)| sid.is_none() || sid != first_sid)
+            .take(MAX_DUPLICATE_MSGS)
+            .for_each(|(file, line, _, (msg, span))| {
+                let mut violation = Violation::new(
+                    LintCode::DuplicateMsg,
+                    format!("msg \"{}\" is already used by another signature", msg),
+                    span.clone(),
+                );
+                violation.severity = severity;
+                violation.related = vec![RelatedLocation {
+                    file: first_file.to_string(),
+                    line: *first_line,
+                    span: first_span.clone(),
+                    message: match first_sid {
+                        Some(sid) => format!("first used by sid {}", sid),
+                        None => "first used here".to_string(),
+                    },
+                }];
+                violations.push((file.to_string(), *line, violation));
+            });
+    });
+    violations
+}
+
+/// Normalize a msg for comparison: lowercase, with single spaces between words
+fn normalize_msg(msg: &str) -> String {
+    msg.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Find all flowbits (xbits, hostbits), which are checked or unset, but never set
+///
+/// The bits set by any of the files count, so a bit may be set by another
+/// document. The violations name the files, which were searched.
+pub fn validate_unset_flowbits(
+    files: &[(String, &AST)],
+    context: &ValidationContext,
+) -> Vec<FileViolation> {
+    if !context.is_enabled(LintCode::UnsetFlowbit) {
+        return vec![];
+    }
+    // Collect all operations: (file, line, operation)
+    let mut operations: Vec<(&String, u32, BitsOperation)> = vec![];
+    files.iter().for_each(|(file, ast)| {
+        ast.rules.iter().for_each(|(line, (rule, _))| {
+            rule.bits_operations()
+                .into_iter()
+                .for_each(|operation| operations.push((file, *line, operation)));
+        });
+    });
+
+    // A bit is only set by an operation of the same kind
+    let set_names = operations
+        .iter()
+        .filter(|(_, _, operation)| operation.sets())
+        .flat_map(|(_, _, operation)| {
+            operation
+                .names
+                .iter()
+                .map(|(name, _)| (operation.kind, name.clone()))
+        })
+        .collect::<HashSet<_>>();
+
+    let mut searched = files
+        .iter()
+        .map(|(file, _)| file.rsplit(['/', '\\']).next().unwrap_or(file))
+        .filter(|name| !name.is_empty())
+        .take(MAX_SEARCHED_FILES)
+        .collect::<Vec<_>>()
+        .join(", ");
+    if files.len() > MAX_SEARCHED_FILES {
+        searched = format!(
+            "{} and {} other files",
+            searched,
+            files.len() - MAX_SEARCHED_FILES
+        );
+    }
+    let mut violations = vec![];
+    operations
+        .iter()
+        .filter(|(_, _, operation)| operation.checks() || operation.unsets())
+        .for_each(|(file, line, operation)| {
+            operation
+                .names
+                .iter()
+                .filter(|(name, _)| !set_names.contains(&(operation.kind, name.clone())))
+                .for_each(|(name, span)| {
+                    let mut message = format!(
+                        "{} \"{}\" is used by {}, but never set",
+                        operation.kind, name, operation.operation.0
+                    );
+                    if !searched.is_empty() {
+                        message = format!("{} (searched {})", message, searched);
+                    }
+                    let code = LintCode::UnsetFlowbit;
+                    if let Some(violation) = context.violation(code, message, span.clone()) {
+                        violations.push((file.to_string(), *line, violation));
+                    }
+                });
+        });
+    violations
+}
+
+#[cfg(test)]
+mod tests {
+    use chumsky::Parser;
+
+    use super::*;
+    use crate::server_settings::{SidRangeSettings, VariablesSettings};
+    use crate::suricata::KeywordRecord;
+
+    /// Get the lints of a document, which have the code, as (line, severity)
+    fn lints(text: &str, code: LintCode, context: &ValidationContext) -> Vec<(u32, Severity)> {
+        let (ast, _) = AST::parse_str(text);
+        let mut lints = validate_document(&ast, context)
+            .into_iter()
+            .filter(|(_, violation)| violation.code == code)
+            .map(|(line, violation)| (line, violation.severity))
+            .collect::<Vec<_>>();
+        lints.sort_by_key(|(line, _)| *line);
+        lints
+    }
+
+    #[test]
+    fn signatures_matching_any_traffic_are_reported() {
+        let text = "\
+alert tcp any any -> any any (msg:\"a\"; sid:1;)
+alert tcp any any <> any any (msg:\"b\"; sid:2;)
+alert tcp any any -> any 80 (msg:\"c\"; sid:3;)
+alert tcp 10.0.0.0/8 any -> any any (msg:\"d\"; sid:4;)
+alert tcp any any -> any any (msg:\"e\"; content:\"x\"; sid:5;)
+alert http any any -> any any (msg:\"f\"; http.uri; sid:6;)
+";
+        let context = ValidationContext::default();
+        assert_eq!(
+            lints(text, LintCode::AnyToAny, &context),
+            vec![
+                (0, Severity::Warning),
+                (1, Severity::Warning),
+                (4, Severity::Information),
+                (5, Severity::Information),
+            ]
+        );
+    }
+
+    #[test]
+    fn configured_levels_apply_to_anchored_signatures() {
+        let text = "alert tcp any any -> any any (msg:\"e\"; content:\"x\"; sid:5;)";
+        let settings = LintsSettings {
+            levels: HashMap::from([("any-to-any".to_string(), LintLevel::Error)]),
+            ..Default::default()
+        };
+        let context = ValidationContext::new(&settings);
+        assert_eq!(
+            lints(text, LintCode::AnyToAny, &context),
+            vec![(0, Severity::Error)]
+        );
+        let settings = LintsSettings {
+            levels: HashMap::from([("any-to-any".to_string(), LintLevel::Off)]),
+            ..Default::default()
+        };
+        let context = ValidationContext::new(&settings);
+        assert!(lints(text, LintCode::AnyToAny, &context).is_empty());
+    }
+
+    #[test]
+    fn strictness_decides_about_anchored_signatures() {
+        let text = "\
+alert tcp any any -> any any (msg:\"a\"; sid:1;)
+alert tcp any any -> any any (msg:\"b\"; pcre:\"/x/\"; sid:2;)
+alert dns any any -> any any (msg:\"c\"; dns.query; sid:3;)
+";
+        let strictness = |any_to_any_strictness| {
+            let settings = LintsSettings {
+                any_to_any_strictness,
+                ..Default::default()
+            };
+            lints(text, LintCode::AnyToAny, &ValidationContext::new(&settings))
+        };
+        assert_eq!(
+            strictness(AnyToAnyStrictness::Relaxed),
+            vec![(0, Severity::Warning)]
+        );
+        assert_eq!(
+            strictness(AnyToAnyStrictness::Normal),
+            vec![
+                (0, Severity::Warning),
+                (1, Severity::Information),
+                (2, Severity::Information),
+            ]
+        );
+        assert_eq!(
+            strictness(AnyToAnyStrictness::Strict),
+            vec![
+                (0, Severity::Warning),
+                (1, Severity::Warning),
+                (2, Severity::Warning),
+            ]
+        );
+    }
+
+    #[test]
+    fn variables_are_resolved() {
+        let text = "\
+alert tcp $HOME_NET any -> $EXTERNAL_NET $ALL_PORTS (msg:\"a\"; sid:1;)
+alert tcp $HOME_NET any -> $UNKNOWN_NET any (msg:\"b\"; sid:2;)
+alert tcp $LOOP_NET any -> any any (msg:\"c\"; sid:3;)
+";
+        let mut settings = VariablesSettings::default();
+        settings
+            .address_groups
+            .insert("HOME_NET".to_string(), "any".to_string());
+        settings
+            .address_groups
+            .insert("EXTERNAL_NET".to_string(), "$HOME_NET".to_string());
+        settings
+            .address_groups
+            .insert("LOOP_NET".to_string(), "$LOOP_NET".to_string());
+        settings
+            .port_groups
+            .insert("ALL_PORTS".to_string(), "any".to_string());
+        let context = ValidationContext::default().with_variables(Variables::new(&settings));
+        // Unknown variables and cycles are not considered to be any
+        assert_eq!(
+            lints(text, LintCode::AnyToAny, &context),
+            vec![(0, Severity::Warning)]
+        );
+    }
+
+    #[test]
+    fn contents_which_can_never_match_are_reported() {
+        let text = "\
+alert tcp any any -> any any (content:\"GET /\"; depth:4; sid:1;)
+alert tcp any any -> any any (content:\"a\"; content:\"|41 42 43|\"; distance:0; within:2; sid:2;)
+alert tcp any any -> any any (content:\"GET\"; startswith; offset:1; sid:3;)
+alert tcp any any -> any any (content:\"GET /\"; depth:5; content:\"|41 42|\"; within:2; sid:4;)
+alert tcp any any -> any any (content:\"GET\"; startswith; offset:0; sid:5;)
+alert tcp any any -> any any (byte_extract:1,0,size; content:\"GET\"; depth:size; sid:6;)
+alert tcp any any -> any any (content:!\"GET /\"; depth:4; sid:7;)
+alert tcp any any -> any any (content:\"|4|\"; depth:0; sid:8;)
+";
+        let context = ValidationContext::default();
+        assert_eq!(
+            lints(text, LintCode::ImpossibleContent, &context),
+            vec![
+                (0, Severity::Error),
+                (1, Severity::Error),
+                (2, Severity::Error),
+            ]
+        );
+    }
+
+    #[test]
+    fn the_byte_length_counts_the_hex_digits_as_bytes() {
+        let length = |pattern: &str| {
+            let content = ContentMatch {
+                pattern: (pattern.to_string(), 0..0),
+                negated: false,
+                quoted: true,
+                modifiers: vec![],
+            };
+            content.byte_length()
+        };
+        assert_eq!(length("GET"), Some(3));
+        assert_eq!(length("|47 45 54|"), Some(3));
+        assert_eq!(length("a|0d0a|b"), Some(4));
+        assert_eq!(length("|0d 0|"), None);
+        assert_eq!(length("|zz|"), None);
+    }
+
+    #[test]
+    fn payload_signatures_without_flow_are_reported() {
+        let text = "\
+alert tcp any any -> any 80 (msg:\"a\"; content:\"GET\"; sid:1;)
+alert http any any -> any any (msg:\"b\"; pcre:\"/GET/\"; sid:2;)
+alert tcp any any -> any 80 (msg:\"c\"; flow:established,to_server; content:\"GET\"; sid:3;)
+alert udp any any -> any 53 (msg:\"d\"; content:\"GET\"; sid:4;)
+alert tcp any any -> any 80 (msg:\"e\"; flags:S; sid:5;)
+";
+        let context = ValidationContext::default();
+        assert_eq!(
+            lints(text, LintCode::MissingFlow, &context),
+            vec![(0, Severity::Information), (1, Severity::Information)]
+        );
+    }
+
+    #[test]
+    fn the_flow_is_suggested_from_the_header() {
+        let suggested_flow = |line: &str| {
+            let (rule, _) = Rule::parser().parse(line).unwrap();
+            rule.suggested_flow()
+        };
+        let options = "(content:\"x\"; sid:1;)";
+        let cases = [
+            ("alert tcp any any -> any 80", "established,to_server"),
+            ("alert tcp any 80 -> any any", "established,to_client"),
+            ("alert tcp any any -> any any", "established,to_server"),
+            ("alert tcp any 80 <> any any", "established"),
+        ];
+        cases.into_iter().for_each(|(header, expected)| {
+            let line = format!("{} {}", header, options);
+            assert_eq!(suggested_flow(&line), expected, "{}", line);
+        });
+    }
+
+    #[test]
+    fn the_flow_is_inserted_after_the_msg() {
+        let insert_flow = |line: &str| {
+            let (rule, _) = Rule::parser().parse(line).unwrap();
+            rule.insert_flow(line).unwrap()
+        };
+        assert_eq!(
+            insert_flow("alert tcp any any -> any 80 (msg:\"a\"; content:\"GET\"; sid:1;)"),
+            "alert tcp any any -> any 80 (msg:\"a\"; flow:established,to_server; content:\"GET\"; sid:1;)"
+        );
+        assert_eq!(
+            insert_flow("alert tcp any 80 -> any any (content:\"GET\"; sid:1;)"),
+            "alert tcp any 80 -> any any (flow:established,to_client; content:\"GET\"; sid:1;)"
+        );
+        // The fixed signature is not reported anymore
+        let fixed = insert_flow("alert tcp any any -> any 80 (msg:\"a\"; content:\"GET\"; sid:1;)");
+        let context = ValidationContext::default();
+        assert!(lints(&fixed, LintCode::MissingFlow, &context).is_empty());
+    }
+
+    #[test]
+    fn invalid_bits_are_reported() {
+        let text = "\
+alert tcp any any -> any any (flowbits:set,a; flowbits:isset,a; sid:1;)
+alert tcp any any -> any any (flowbits:check,a; sid:2;)
+alert tcp any any -> any any (flowbits:set,a,track ip_src; sid:3;)
+alert tcp any any -> any any (xbits:set,a; sid:4;)
+alert tcp any any -> any any (xbits:set,a,track ip_src,expire 0; sid:5;)
+alert tcp any any -> any any (hostbits:set,a,track both; sid:6;)
+alert tcp any any -> any any (hostbits:set,a,track ip_pair; sid:7;)
+";
+        let context = ValidationContext::default();
+        let lines = lints(text, LintCode::InvalidBits, &context)
+            .into_iter()
+            .map(|(line, _)| line)
+            .collect::<Vec<_>>();
+        assert_eq!(lines, vec![1, 2, 3, 4, 6]);
+    }
+
+    #[test]
+    fn bits_are_only_set_by_the_same_kind() {
+        let text = "\
+alert tcp any any -> any any (xbits:set,a,track ip_src; sid:1;)
+alert tcp any any -> any any (flowbits:isset,a; sid:2;)
+alert tcp any any -> any any (xbits:isset,a,track ip_src; sid:3;)
+alert tcp any any -> any any (hostbits:isnotset,b,track ip_src; sid:4;)
+";
+        let (ast, _) = AST::parse_str(text);
+        let files = [("a.rules".to_string(), &ast)];
+        let mut unset = validate_workspace(&files, &ValidationContext::default())
+            .into_iter()
+            .filter(|(_, _, violation)| violation.code == LintCode::UnsetFlowbit)
+            .map(|(_, line, _)| line)
+            .collect::<Vec<_>>();
+        unset.sort();
+        assert_eq!(unset, vec![1, 3]);
+    }
+
+    #[test]
+    fn bits_set_by_any_document_are_known() {
+        let rules = "\
+alert tcp any any -> any any (flowbits:set,login; flowbits:noalert; sid:1;)
+alert tcp any any -> any any (flowbits:isset,login; flowbits:unset,login; sid:2;)
+alert tcp any any -> any any (flowbits:isnotset,token; sid:3;)
+alert tcp any any -> any any (flowbits:isset,session; sid:4;)
+alert tcp any any -> any any (flowbits:unset,logout; sid:5;)
+";
+        let other = "alert tcp any any -> any any (flowbits:set,token; flowbits:noalert; sid:6;)";
+        let (rules, _) = AST::parse_str(rules);
+        let (other, _) = AST::parse_str(other);
+        let unset = |files: &[(String, &AST)]| {
+            let mut unset = validate_unset_flowbits(files, &ValidationContext::default())
+                .into_iter()
+                .map(|(_, line, violation)| (line, violation.message, violation.span))
+                .collect::<Vec<_>>();
+            unset.sort_by_key(|(line, _, _)| *line);
+            unset
+        };
+        assert_eq!(
+            unset(&[
+                ("file:///rules/local.rules".to_string(), &rules),
+                ("file:///rules/other.rules".to_string(), &other),
+            ]),
+            [
+                (
+                    3,
+                    "flowbit \"session\" is used by isset, but never set \
+                        (searched local.rules, other.rules)"
+                        .to_string(),
+                    45..52
+                ),
+                (
+                    4,
+                    "flowbit \"logout\" is used by unset, but never set \
+                        (searched local.rules, other.rules)"
+                        .to_string(),
+                    45..51
+                ),
+            ]
+        );
+        // Without the other document, the token is never set
+        let lines = unset(&[("local.rules".to_string(), &rules)])
+            .into_iter()
+            .map(|(line, _, _)| line)
+            .collect::<Vec<_>>();
+        assert_eq!(lines, [2, 3, 4]);
+    }
+
+    #[test]
+    fn duplicate_sids_point_to_each_other() {
+        let text = "\
+alert tcp any any -> any any (msg:\"a\"; sid:1;)
+alert tcp any any -> any any (msg:\"b\";)
+alert tcp any any -> any any (msg:\"c\"; sid:1;)
+alert tcp any any -> any any (msg:\"d\"; sid:x;)
+alert tcp any any -> any any (msg:\"e\"; sid:x;)
+";
+        let (ast, _) = AST::parse_str(text);
+        let files = [("a.rules".to_string(), &ast)];
+        let mut settings = LintsSettings::default();
+        settings
+            .levels
+            .insert("duplicate-sid".to_string(), LintLevel::Hint);
+        let context = ValidationContext::new(&settings);
+        let mut duplicates = validate_duplicate_sids(&files, &context)
+            .into_iter()
+            .map(|(_, line, violation)| {
+                let related = violation.related[0].line;
+                (line, related, violation.span, violation.severity)
+            })
+            .collect::<Vec<_>>();
+        duplicates.sort_by_key(|(line, ..)| *line);
+        assert_eq!(
+            duplicates,
+            [
+                (0, 2, 43..44, Severity::Hint),
+                (2, 0, 43..44, Severity::Hint)
+            ]
+        );
+        settings
+            .levels
+            .insert("duplicate-sid".to_string(), LintLevel::Off);
+        let context = ValidationContext::new(&settings);
+        assert!(validate_duplicate_sids(&files, &context).is_empty());
+    }
+
+    #[test]
+    fn missing_options_point_to_the_parentheses() {
+        let text = "\
+alert tcp any any -> any any (msg:\"a\"; sid:1; rev:1;)
+alert tcp any any -> any any (sid:2;)
+#alert tcp any any -> any any (msg:\"b\";)
+alert tcp any any -> any any (sid:3
+";
+        let (ast, _) = AST::parse_str(text);
+        let missing = |context: &ValidationContext| {
+            let mut missing = validate_document(&ast, context)
+                .into_iter()
+                .map(|(line, violation)| (line, violation.code, violation.span))
+                .filter(|(_, code, _)| matches!(code, LintCode::MissingMsg | LintCode::MissingRev))
+                .collect::<Vec<_>>();
+            missing.sort_by_key(|(_, code, _)| code.code());
+            missing
+        };
+        assert_eq!(
+            missing(&ValidationContext::default()),
+            [
+                (1, LintCode::MissingMsg, 29..37),
+                (1, LintCode::MissingRev, 29..37)
+            ]
+        );
+        let mut settings = LintsSettings::default();
+        settings
+            .levels
+            .insert("missing-rev".to_string(), LintLevel::Off);
+        let context = ValidationContext::new(&settings);
+        assert_eq!(missing(&context), [(1, LintCode::MissingMsg, 29..37)]);
+    }
+
+    #[test]
+    fn unknown_keywords_suggest_the_closest_keyword() {
+        let keywords = ["content", "msg", "sid"]
+            .into_iter()
+            .map(|name| {
+                KeywordRecord::to_keyword(KeywordRecord {
+                    name: name.to_string(),
+                    description: String::new(),
+                    app_layer: String::new(),
+                    features: String::new(),
+                    documentation: String::new(),
+                })
+            })
+            .collect::<HashMap<_, _>>();
+        let (rule, _) = Rule::parser()
+            .parse("alert tcp any any -> any any (msg:\"a\"; contnet:\"b\"; xyzzy; sid:1;)")
+            .unwrap();
+        let unknown = |context: &ValidationContext| {
+            rule.validate(context)
+                .into_iter()
+                .filter(|violation| violation.code == LintCode::UnknownKeyword)
+                .map(|violation| (violation.message, violation.span))
+                .collect::<Vec<_>>()
+        };
+        let context = ValidationContext::default().with_keywords(&keywords);
+        assert_eq!(
+            unknown(&context),
+            [
+                (
+                    "unknown keyword \"contnet\", did you mean \"content\"?".to_string(),
+                    39..46
+                ),
+                ("unknown keyword \"xyzzy\"".to_string(), 52..57),
+            ]
+        );
+        // Without the keywords of Suricata, every keyword is accepted
+        assert!(unknown(&ValidationContext::default()).is_empty());
+    }
+
+    #[test]
+    fn undefined_groups_suggest_the_closest_group() {
+        let text = "\
+alert tcp $HOME_NETT any -> any $HTTP_PORTS (msg:\"a\"; sid:1;)
+alert tcp $SENSOR_NET any -> any any (msg:\"b\"; sid:2;)
+alert tcp $SENSOR_NET any -> any $HTTP_PORTS (msg:\"c\"; sid:3;)
+";
+        let mut settings = VariablesSettings::default();
+        settings
+            .address_groups
+            .insert("HOME_NET".to_string(), "any".to_string());
+        settings
+            .port_groups
+            .insert("HTTP_PORTS".to_string(), "80".to_string());
+        let (ast, _) = AST::parse_str(text);
+        let undefined = |lints: &LintsSettings| {
+            let context = ValidationContext::new(lints).with_variables(Variables::new(&settings));
+            validate_document(&ast, &context)
+                .into_iter()
+                .filter(|(_, violation)| violation.code == LintCode::UndefinedVariable)
+                .map(|(line, violation)| (line, violation.message, violation.severity))
+                .collect::<Vec<_>>()
+        };
+        // A group used by several signatures is only a hint
+        assert_eq!(
+            undefined(&LintsSettings::default()),
+            [
+                (
+                    0,
+                    "address group $HOME_NETT is not defined, did you mean $HOME_NET?".to_string(),
+                    Severity::Warning
+                ),
+                (
+                    1,
+                    "address group $SENSOR_NET is not defined".to_string(),
+                    Severity::Hint
+                ),
+                (
+                    2,
+                    "address group $SENSOR_NET is not defined".to_string(),
+                    Severity::Hint
+                ),
+            ]
+        );
+        let mut lints = LintsSettings::default();
+        lints
+            .levels
+            .insert("undefined-variable".to_string(), LintLevel::Off);
+        assert!(undefined(&lints).is_empty());
+    }
+
+    #[test]
+    fn invalid_directions_are_reported() {
+        let text = "\
+alert tcp any any => any any (msg:\"a\"; sid:1;)
+alert tcp any any any any (msg:\"b\"; sid:2;)
+alert tcp any any <> any any (msg:\"c\"; sid:3;)
+";
+        let context = ValidationContext::default();
+        let (ast, _) = AST::parse_str(text);
+        let mut findings = validate_document(&ast, &context)
+            .into_iter()
+            .filter(|(_, violation)| violation.code == LintCode::InvalidDirection)
+            .map(|(line, violation)| (line, violation.message, violation.span))
+            .collect::<Vec<_>>();
+        findings.sort_by_key(|(line, _, _)| *line);
+        assert_eq!(
+            findings,
+            [
+                (
+                    0,
+                    "expected a direction (->, <> or <-) here, found \"=>\"".to_string(),
+                    18..20
+                ),
+                (
+                    1,
+                    "expected a direction (->, <> or <-) before the destination".to_string(),
+                    18..19
+                ),
+            ]
+        );
+        // The lint can be turned off like any other
+        let settings = LintsSettings {
+            levels: HashMap::from([("invalid-direction".to_string(), LintLevel::Off)]),
+            ..Default::default()
+        };
+        let context = ValidationContext::new(&settings);
+        assert!(lints(text, LintCode::InvalidDirection, &context).is_empty());
+    }
+
+    #[test]
+    fn reversed_directions_are_swapped() {
+        let text = "\
+alert tcp $HOME_NET 1024: <- $EXTERNAL_NET 80 (msg:\"a\"; sid:1;)
+alert tcp $HOME_NET any -> $EXTERNAL_NET 80 (msg:\"b\"; sid:2;)
+alert tcp any any <> any any (msg:\"c\"; sid:3;)
+";
+        assert_eq!(
+            lints(
+                text,
+                LintCode::ReversedDirection,
+                &ValidationContext::default()
+            ),
+            vec![(0, Severity::Hint)]
+        );
+        let swapped = text
+            .lines()
+            .map(|line| {
+                let (rule, _) = Rule::parser().parse(line).unwrap();
+                rule.swap_direction(line)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            swapped,
+            [
+                Some(
+                    "alert tcp $EXTERNAL_NET 80 -> $HOME_NET 1024: (msg:\"a\"; sid:1;)".to_string()
+                ),
+                None,
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn suspicious_ports_point_to_the_element() {
+        let text = "\
+alert tcp any 1024:80 -> any 80:80 (msg:\"a\"; sid:1;)
+alert tcp any [80,[443,8080:8000,443],80] -> any !any (msg:\"b\"; sid:2;)
+alert tcp any ![22,[21,21]] -> any [1:1024,!1:1024] (msg:\"c\"; sid:3;)
+alert tcp any 1:1024 -> any [80,443] (msg:\"d\"; sid:4;)
+";
+        let (ast, _) = AST::parse_str(text);
+        let suspicious = validate_document(&ast, &ValidationContext::default())
+            .into_iter()
+            .filter(|(_, violation)| violation.code == LintCode::SuspiciousPort)
+            .map(|(line, violation)| (line, violation.message, violation.span))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            suspicious,
+            [
+                (
+                    0,
+                    "port range 1024:80 is inverted and matches nothing, use 80:1024".to_string(),
+                    14..21
+                ),
+                (
+                    0,
+                    "port range 80:80 only matches port 80".to_string(),
+                    29..34
+                ),
+                (
+                    1,
+                    "port range 8080:8000 is inverted and matches nothing, use 8000:8080"
+                        .to_string(),
+                    23..32
+                ),
+                (
+                    1,
+                    "443 is repeated inside the port group".to_string(),
+                    33..36
+                ),
+                (
+                    1,
+                    "80 is repeated inside the port group".to_string(),
+                    38..40
+                ),
+                (1, "!any matches no port".to_string(), 49..53),
+                (
+                    2,
+                    "21 is repeated inside the port group".to_string(),
+                    23..25
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn cidrs_with_host_bits_show_the_network() {
+        let text = "\
+alert tcp 192.168.1.5/24 any -> [10.0.0.0/8,!10.1.2.3/16] any (msg:\"a\"; sid:1;)
+alert tcp 2001:db8::1/64 any -> 2001:db8::/32 any (msg:\"b\"; sid:2;)
+alert tcp 192.168.1.5/32 any -> 2001:db8::1/128 any (msg:\"c\"; sid:3;)
+";
+        let (ast, _) = AST::parse_str(text);
+        let host_bits = validate_document(&ast, &ValidationContext::default())
+            .into_iter()
+            .filter(|(_, violation)| violation.code == LintCode::CidrHostBits)
+            .map(|(line, violation)| (line, violation.message, violation.span))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            host_bits,
+            [
+                (
+                    0,
+                    "192.168.1.5/24 has host bits set, the network is 192.168.1.0/24".to_string(),
+                    10..24
+                ),
+                (
+                    0,
+                    "10.1.2.3/16 has host bits set, the network is 10.1.0.0/16".to_string(),
+                    45..56
+                ),
+                (
+                    1,
+                    "2001:db8::1/64 has host bits set, the network is 2001:db8::/64".to_string(),
+                    10..24
+                ),
+            ]
+        );
+    }
+
+    /// Run the sid-range lint for a file, with the local range 1000-1999
+    fn sid_range(path: &str, text: &str) -> Vec<(u32, String)> {
+        let settings = LintsSettings {
+            sid_range: SidRangeSettings {
+                local: [1000, 1999],
+                local_files: vec!["local/*.rules".to_string()],
+                vendor_files: vec!["vendor/**/*.rules".to_string()],
+            },
+            ..Default::default()
+        };
+        let context = ValidationContext::new(&settings);
+        let (ast, _) = AST::parse_str(text);
+        validate_sid_range(Path::new(path), &ast, &context)
+            .into_iter()
+            .map(|(line, violation)| (line, violation.message))
+            .collect()
+    }
+
+    const BOUNDARIES: &str = "\
+alert tcp any any -> any any (msg:\"a\"; sid:999;)
+alert tcp any any -> any any (msg:\"b\"; sid:1000;)
+alert tcp any any -> any any (msg:\"c\"; sid:1999;)
+alert tcp any any -> any any (msg:\"d\"; sid:2000;)
+alert tcp any any -> any any (msg:\"e\";)
+";
+
+    #[test]
+    fn local_files_use_the_local_range() {
+        assert_eq!(
+            sid_range("local/a.rules", BOUNDARIES),
+            vec![
+                (
+                    0,
+                    "sid 999 is outside of the local range 1000-1999".to_string()
+                ),
+                (
+                    3,
+                    "sid 2000 is outside of the local range 1000-1999".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn vendor_files_do_not_use_the_local_range() {
+        assert_eq!(
+            sid_range("vendor/et/open.rules", BOUNDARIES),
+            vec![
+                (
+                    1,
+                    "sid 1000 is reserved for local signatures (1000-1999)".to_string()
+                ),
+                (
+                    2,
+                    "sid 1999 is reserved for local signatures (1000-1999)".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn files_matching_neither_glob_are_not_checked() {
+        assert!(sid_range("other/a.rules", BOUNDARIES).is_empty());
+        // The globs are matched against the whole relative path
+        assert!(sid_range("rules/local/a.rules", BOUNDARIES).is_empty());
+    }
+
+    /// Get the noalert lints of a document, as (line, code)
+    fn noalert_lints(text: &str) -> Vec<(u32, &'static str)> {
+        let context = ValidationContext::new(&LintsSettings::default());
+        let (ast, _) = AST::parse_str(text);
+        let mut lints = validate_document(&ast, &context)
+            .into_iter()
+            .filter(|(_, violation)| NOALERT.contains(&violation.code))
+            .map(|(line, violation)| (line, violation.code.code()))
+            .collect::<Vec<_>>();
+        lints.sort();
+        lints
+    }
+
+    const NOALERT: [LintCode; 2] = [LintCode::MissingNoalert, LintCode::OrphanNoalert];
+
+    #[test]
+    fn signatures_setting_and_checking_bits_may_alert() {
+        let text = "\
+alert tcp any any -> any any (msg:\"a\"; flowbits:isset,a; flowbits:set,b; sid:1;)
+alert tcp any any -> any any (msg:\"b\"; xbits:isset,a,track ip_src; \
+xbits:set,b,track ip_src; sid:2;)
+";
+        assert!(noalert_lints(text).is_empty());
+    }
+
+    #[test]
+    fn chained_signatures_are_checked_one_by_one() {
+        let text = "\
+alert http any any -> any any (msg:\"login\"; flowbits:set,login; sid:1;)
+alert http any any -> any any (msg:\"token\"; flowbits:set,token; flowbits:noalert; sid:2;)
+alert http any any -> any any (msg:\"both\"; flowbits:isset,login; flowbits:isset,token; \
+flowbits:set,both; sid:3;)
+alert http any any -> any any (msg:\"done\"; flowbits:isset,both; noalert; sid:4;)
+alert http any any -> any any (msg:\"reset\"; flowbits:isset,both; flowbits:unset,both; \
+noalert; sid:5;)
+";
+        assert_eq!(
+            noalert_lints(text),
+            vec![(0, "missing-noalert"), (3, "orphan-noalert")]
+        );
+    }
+
+    #[test]
+    fn noalert_fixes_are_valid() {
+        let line = "alert tcp any any -> any any (msg:\"x\"; flowbits:set,a; sid:1;)";
+        let (rule, _) = Rule::parser().parse(line).unwrap();
+        let fixed = rule.insert_noalert(line).unwrap();
+        assert_eq!(
+            fixed,
+            "alert tcp any any -> any any (msg:\"x\"; flowbits:noalert; flowbits:set,a; sid:1;)"
+        );
+        assert!(noalert_lints(&fixed).is_empty());
+
+        let line = "alert tcp any any -> any any (msg:\"x\"; noalert; sid:1; flowbits:noalert;)";
+        let (rule, _) = Rule::parser().parse(line).unwrap();
+        let fixed = rule.remove_noalert(line).unwrap();
+        assert_eq!(fixed, "alert tcp any any -> any any (msg:\"x\"; sid:1;)");
+        assert!(noalert_lints(&fixed).is_empty());
+    }
+
+    #[test]
+    fn duplicate_thresholds_are_reported() {
+        let text = "\
+alert tcp any any -> any any (msg:\"a\"; threshold:type limit,track by_src,count 1,seconds 60; sid:1;)
+alert tcp any any -> any any (msg:\"b\"; threshold:type limit,track by_src,count 1,seconds 60; \
+threshold:type both,track by_dst,count 5,seconds 30; sid:2;)
+";
+        let context = ValidationContext::default();
+        assert_eq!(
+            lints(text, LintCode::DuplicateThreshold, &context),
+            [(1, Severity::Error)]
+        );
+    }
+
+    #[test]
+    fn repeated_single_keywords_point_to_the_first_one() {
+        let text = "\
+alert tcp any any -> any any (msg:\"a\"; content:\"x\"; content:\"y\"; sid:1; sid:2; rev:1;)
+alert tcp any any -> any any (msg:\"b\"; reference:url,a; reference:url,b; metadata:a b; \
+metadata:c d; flowbits:set,x; flowbits:noalert; sid:3; rev:1;)
+alert tcp any any -> any any (msg:\"c\"; msg:\"d\"; classtype:a; classtype:b; sid:4; rev:1; rev:2;)
+";
+        let (ast, _) = AST::parse_str(text);
+        let duplicates = validate_document(&ast, &ValidationContext::default())
+            .into_iter()
+            .filter(|(_, violation)| !violation.related.is_empty())
+            .map(|(line, violation)| {
+                let related = &violation.related[0];
+                (
+                    line,
+                    violation.code,
+                    violation.message,
+                    violation.span,
+                    related.line,
+                    related.span.clone(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            duplicates,
+            [
+                (
+                    0,
+                    LintCode::DuplicateOption,
+                    "sid is set more than once".to_string(),
+                    72..75,
+                    0,
+                    65..68
+                ),
+                (
+                    2,
+                    LintCode::DuplicateOption,
+                    "msg is set more than once".to_string(),
+                    39..42,
+                    2,
+                    30..33
+                ),
+                (
+                    2,
+                    LintCode::InvalidClasstype,
+                    "classtype is set more than once".to_string(),
+                    61..70,
+                    2,
+                    48..57
+                ),
+                (
+                    2,
+                    LintCode::DuplicateOption,
+                    "rev is set more than once".to_string(),
+                    88..91,
+                    2,
+                    81..84
+                ),
+            ]
+        );
+        let (_, violation) = validate_document(&ast, &ValidationContext::default())
+            .into_iter()
+            .find(|(_, violation)| violation.code == LintCode::DuplicateOption)
+            .unwrap();
+        assert_eq!(
+            violation.in_file("local.rules").related[0].file,
+            "local.rules"
+        );
+    }
+
+    #[test]
+    fn thresholds_with_detection_filters_are_reported() {
+        let text = "\
+alert tcp any any -> any any (msg:\"a\"; detection_filter:track by_src,count 5,seconds 60; sid:1;)
+alert tcp any any -> any any (msg:\"b\"; threshold:type limit,track by_src,count 1,seconds 60; \
+detection_filter:track by_src,count 5,seconds 60; sid:2;)
+";
+        let context = ValidationContext::default();
+        assert_eq!(
+            lints(text, LintCode::ThresholdWithDetectionFilter, &context),
+            [(1, Severity::Error)]
+        );
+    }
+
+    #[test]
+    fn thresholds_replaced_by_threshold_config_are_reported() {
+        let (mut config, _) = ThresholdConfig::parse(
+            "\
+threshold gen_id 1, sig_id 1, type limit, track by_src, count 1, seconds 60
+suppress gen_id 1, sig_id 2, track by_src, ip 10.0.0.1
+event_filter gen_id 3, sig_id 3, type both, track by_dst, count 5, seconds 30
+",
+        );
+        config.path = "/etc/suricata/threshold.config".to_string();
+        let context = ValidationContext::default().with_threshold_config(config);
+        let threshold = "threshold:type limit,track by_src,count 1,seconds 60;";
+        let text = format!(
+            "\
+alert tcp any any -> any any (msg:\"a\"; {threshold} sid:1;)
+alert tcp any any -> any any (msg:\"b\"; {threshold} sid:2;)
+alert tcp any any -> any any (msg:\"c\"; {threshold} sid:3;)
+alert tcp any any -> any any (msg:\"d\"; {threshold} gid:3; sid:3;)
+alert tcp any any -> any any (msg:\"e\"; sid:1;)
+"
+        );
+        assert_eq!(
+            lints(&text, LintCode::ShadowedThreshold, &context),
+            [(0, Severity::Information), (3, Severity::Information)]
+        );
+
+        let (rule, _) = Rule::parser().parse(text.lines().next().unwrap()).unwrap();
+        let violation = rule
+            .validate(&context)
+            .into_iter()
+            .find(|violation| violation.code == LintCode::ShadowedThreshold)
+            .unwrap();
+        assert_eq!(violation.span, 39..48);
+        assert_eq!(
+            violation.related,
+            [RelatedLocation {
+                file: "/etc/suricata/threshold.config".to_string(),
+                line: 0,
+                span: 0..75,
+                message: "threshold of sid 1 inside threshold.config".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn deprecated_keywords_depend_on_the_suricata_version() {
+        let text = "\
+alert tls any any -> any any (msg:\"a\"; tls_sni; content:\"x\"; sid:1;)
+alert ssh any any -> any any (msg:\"b\"; ssh.protoversion; content:\"2.0\"; sid:2;)
+alert tls any any -> any any (msg:\"c\"; tls.sni; content:\"x\"; sid:3;)
+";
+        let deprecated = |version| {
+            let context = ValidationContext::default().with_suricata_version(version);
+            lints(text, LintCode::DeprecatedKeyword, &context)
+        };
+        assert!(deprecated(Some(SuricataVersion::new(4, 1, 0))).is_empty());
+        assert_eq!(
+            deprecated(Some(SuricataVersion::new(7, 0, 2))),
+            [(0, Severity::Warning), (1, Severity::Warning)]
+        );
+        assert_eq!(
+            deprecated(Some(SuricataVersion::new(8, 0, 0))),
+            [(0, Severity::Warning), (1, Severity::Error)]
+        );
+        // The newest version is assumed without Suricata
+        assert_eq!(
+            deprecated(None),
+            [(0, Severity::Warning), (1, Severity::Error)]
+        );
+    }
+
+    #[test]
+    fn configured_levels_apply_to_removed_keywords() {
+        let text = "alert ssh any any -> any any (msg:\"b\"; ssh.protoversion; sid:2;)";
+        let mut settings = LintsSettings::default();
+        settings
+            .levels
+            .insert("deprecated-keyword".to_string(), LintLevel::Warning);
+        let context = ValidationContext::new(&settings);
+        assert_eq!(
+            lints(text, LintCode::DeprecatedKeyword, &context),
+            [(0, Severity::Warning)]
+        );
+    }
+
+    #[test]
+    fn deprecated_keywords_are_renamed() {
+        let line =
+            "alert tls any any -> any any (msg:\"a\"; tls_sni; content:\"x\"; ja3_hash; sid:1;)";
+        let (rule, _) = Rule::parser().parse(line).unwrap();
+        assert_eq!(
+            rule.replace_deprecated_keywords(line).unwrap(),
+            "alert tls any any -> any any (msg:\"a\"; tls.sni; content:\"x\"; ja3.hash; sid:1;)"
+        );
+        let line = "alert tls any any -> any any (msg:\"a\"; tls.sni; sid:1;)";
+        let (rule, _) = Rule::parser().parse(line).unwrap();
+        assert_eq!(rule.replace_deprecated_keywords(line), None);
+    }
+
+    #[test]
+    fn deprecated_keywords_with_a_value_become_a_content() {
+        let context = ValidationContext::default();
+        let cases = [
+            ("ssh.protoversion:2.0;", Some("ssh.proto; content:\"2.0\";")),
+            (
+                "ssh.softwareversion:\"OpenSSH_8.9\";",
+                Some("ssh.software; content:\"OpenSSH_8.9\";"),
+            ),
+            // 2_compat matches 2.0 and 1.99, which a single content can not do
+            ("ssh.protoversion:2_compat;", None),
+        ];
+        cases.into_iter().for_each(|(option, fixed)| {
+            let line = format!(
+                "alert ssh any any -> any any (msg:\"a\"; flow:established,to_server; {} sid:1; rev:1;)",
+                option
+            );
+            let (rule, _) = Rule::parser().parse(line.as_str()).unwrap();
+            let replaced = rule.replace_deprecated_keywords(&line);
+            let expected = fixed.map(|fixed| line.replace(option, fixed));
+            assert_eq!(replaced, expected, "{}", line);
+            // The fixed signature has no new findings (besides the version being
+            // a short fast pattern)
+            let Some(replaced) = replaced else {
+                return;
+            };
+            let findings = |line: &str| {
+                let (rule, _) = Rule::parser().parse(line).unwrap();
+                rule.validate(&context)
+                    .into_iter()
+                    .map(|violation| violation.code)
+                    .filter(|code| {
+                        !matches!(code, LintCode::DeprecatedKeyword | LintCode::ShortFastPattern)
+                    })
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(findings(&replaced), findings(&line), "{}", replaced);
+        });
+    }
+
+    #[test]
+    fn msgs_are_checked_against_the_settings() {
+        let text = "\
+alert tcp any any -> any any (msg:\"LOCAL TROJAN beacon\"; sid:1;)
+alert tcp any any -> any any (msg:\"local trojan beacon\"; sid:2;)
+alert tcp any any -> any any (msg:\" LOCAL TROJAN beacon\"; sid:3;)
+alert tcp any any -> any any (msg:\"LOCAL TROJAN beacon to a very distant host\"; sid:4;)
+";
+        let settings = LintsSettings {
+            msg_pattern: Some("^[A-Z]+ [A-Z]+ ".to_string()),
+            msg_max_length: 30,
+            ..Default::default()
+        };
+        let context = ValidationContext::new(&settings);
+        assert_eq!(
+            lints(text, LintCode::MsgPattern, &context),
+            [(1, Severity::Information), (2, Severity::Information)]
+        );
+        assert_eq!(
+            lints(text, LintCode::MsgWhitespace, &context),
+            [(2, Severity::Warning)]
+        );
+        assert_eq!(
+            lints(text, LintCode::MsgLength, &context),
+            [(3, Severity::Warning)]
+        );
+        // Without a pattern any msg is accepted
+        let context = ValidationContext::default();
+        assert!(lints(text, LintCode::MsgPattern, &context).is_empty());
+        assert!(lints(text, LintCode::MsgLength, &context).is_empty());
+    }
+
+    #[test]
+    fn headers_matching_nothing_are_reported() {
+        let text = "\
+alert tcp [10.0.0.0/8,![10.0.0.0/9,10.128.0.0/9]] any -> any 80 (msg:\"a\"; sid:1;)
+alert tcp any [80,![1:100]] -> any 80 (msg:\"b\"; sid:2;)
+alert tcp any any -> [10.0.0.0/8,![10.1.0.0/16,!10.1.2.3]] 80 (msg:\"c\"; sid:3;)
+alert tcp any any -> ![$HOME_NET,[!$HOME_NET]] 80 (msg:\"d\"; sid:4;)
+alert tcp any any -> any [1:100,![50:60,[!55]],!1:100] (msg:\"e\"; sid:5;)
+";
+        let context = ValidationContext::default();
+        assert_eq!(
+            lints(text, LintCode::EmptyHeader, &context),
+            [
+                (0, Severity::Error),
+                (1, Severity::Error),
+                (4, Severity::Error)
+            ]
+        );
+    }
+
+    /// Get the duplicate msgs of the files, as (file, line, related line)
+    fn duplicate_msgs(
+        files: &[(&str, &str)],
+        context: &ValidationContext,
+    ) -> Vec<(String, u32, u32)> {
+        let asts = files
+            .iter()
+            .map(|(file, text)| (file.to_string(), AST::parse_str(text).0))
+            .collect::<Vec<_>>();
+        let files = asts
+            .iter()
+            .map(|(file, ast)| (file.clone(), ast))
+            .collect::<Vec<_>>();
+        let mut duplicates = validate_duplicate_msgs(&files, context)
+            .into_iter()
+            .map(|(file, line, violation)| (file, line, violation.related[0].line))
+            .collect::<Vec<_>>();
+        duplicates.sort();
+        duplicates
+    }
+
+    #[test]
+    fn msgs_are_compared_across_files() {
+        let files = [
+            (
+                "a.rules",
+                "\
+alert tcp any any -> any any (msg:\"LOCAL Beacon\"; sid:1; rev:1;)
+alert tcp any any -> any any (msg:\"local  beacon\"; sid:2;)
+",
+            ),
+            (
+                "b.rules",
+                "\
+alert tcp any any -> any any (msg:\"Other\"; sid:3;)
+alert tcp any any -> any any (msg:\"LOCAL beacon\"; sid:4;)
+",
+            ),
+        ];
+        assert_eq!(
+            duplicate_msgs(&files, &ValidationContext::default()),
+            [("a.rules".to_string(), 1, 0), ("b.rules".to_string(), 1, 0),]
+        );
+    }
+
+    #[test]
+    fn revisions_and_ignored_msgs_are_not_duplicates() {
+        let text = "\
+alert tcp any any -> any any (msg:\"LOCAL beacon\"; sid:1; rev:1;)
+alert tcp any any -> any any (msg:\"LOCAL beacon\"; sid:1; rev:2;)
+alert tcp any any -> any any (msg:\"TODO Rewrite\"; sid:2;)
+alert tcp any any -> any any (msg:\"todo rewrite\"; sid:3;)
+";
+        let settings = LintsSettings {
+            duplicate_msg_ignore: vec!["TODO ".to_string()],
+            ..Default::default()
+        };
+        let context = ValidationContext::new(&settings);
+        assert!(duplicate_msgs(&[("a.rules", text)], &context).is_empty());
+        let mut settings = LintsSettings::default();
+        settings
+            .levels
+            .insert("duplicate-msg".to_string(), LintLevel::Off);
+        let context = ValidationContext::new(&settings);
+        let text = "\
+alert tcp any any -> any any (msg:\"a\"; sid:1;)
+alert tcp any any -> any any (msg:\"a\"; sid:2;)
+";
+        assert!(duplicate_msgs(&[("a.rules", text)], &context).is_empty());
+    }
+
+    #[test]
+    fn duplicate_msgs_are_capped() {
+        let text = (1..=MAX_DUPLICATE_MSGS + 5)
+            .map(|sid| format!("alert tcp any any -> any any (msg:\"a\"; sid:{};)\n", sid))
+            .collect::<String>();
+        let duplicates = duplicate_msgs(&[("a.rules", &text)], &ValidationContext::default());
+        assert_eq!(duplicates.len(), MAX_DUPLICATE_MSGS);
+        assert_eq!(duplicates[0], ("a.rules".to_string(), 1, 0));
+    }
+
+    #[test]
+    fn unknown_events_suggest_the_closest_event() {
+        let text = "\
+alert http any any -> any any (msg:\"a\"; app-layer-event:http.unknown_error; sid:1;)
+alert http any any -> any any (msg:\"b\"; app-layer-event:htpp.unknown_error; sid:2;)
+alert http any any -> any any (msg:\"c\"; app-layer-event:http.no_such_event_at_all; sid:3;)
+alert http any any -> any any (msg:\"d\"; app-layer-event:acme.custom_event; sid:4;)
+alert ip any any -> any any (msg:\"e\"; decode-event:ipv4.pkt_to_small; sid:5;)
+";
+        let (ast, _) = AST::parse_str(text);
+        let messages = validate_document(&ast, &ValidationContext::default())
+            .into_iter()
+            .filter(|(_, violation)| violation.code == LintCode::UnknownEvent)
+            .map(|(line, violation)| (line, violation.message))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            [
+                (
+                    1,
+                    "unknown app-layer-event \"htpp.unknown_error\", did you mean \
+                     \"http.unknown_error\"?"
+                        .to_string()
+                ),
+                (
+                    2,
+                    "unknown app-layer-event \"http.no_such_event_at_all\"".to_string()
+                ),
+                (
+                    4,
+                    "unknown decode-event \"ipv4.pkt_to_small\", did you mean \
+                     \"ipv4.pkt_too_small\"?"
+                        .to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn events_of_the_installed_suricata_are_known() {
+        let text = "alert http any any -> any any (msg:\"d\"; app-layer-event:acme.custom; sid:4;)";
+        let mut events = EventTable::default();
+        events.extend_from_rules(
+            "alert acme any any -> any any (msg:\"custom\"; app-layer-event:acme.custom; sid:1;)",
+        );
+        let context = ValidationContext::default().with_events(events);
+        assert!(lints(text, LintCode::UnknownEvent, &context).is_empty());
+        let text = "alert http any any -> any any (msg:\"d\"; app-layer-event:acme.custon; sid:4;)";
+        assert_eq!(
+            lints(text, LintCode::UnknownEvent, &context),
+            [(0, Severity::Warning)]
+        );
+    }
+
+    #[test]
+    fn short_fast_patterns_are_reported() {
+        let text = "\
+alert http any any -> any any (msg:\"a\"; content:\"GET\"; fast_pattern; content:\"/login.php\"; sid:1;)
+alert http any any -> any any (msg:\"b\"; content:\"GET\"; content:\"/login.php\"; fast_pattern; sid:2;)
+alert http any any -> any any (msg:\"c\"; content:\"|00 01|\"; content:\"ab\"; sid:3;)
+alert http any any -> any any (msg:\"d\"; content:\"|00 01 02 03|\"; content:\"ab\"; sid:4;)
+alert http any any -> any any (msg:\"e\"; content:!\"/login.php\"; content:\"ab\"; sid:5;)
+alert http any any -> any any (msg:\"f\"; sid:6;)
+";
+        let context = ValidationContext::default();
+        assert_eq!(
+            lints(text, LintCode::ShortFastPattern, &context),
+            [
+                (0, Severity::Warning),
+                (2, Severity::Warning),
+                (4, Severity::Warning)
+            ]
+        );
+        let settings = LintsSettings {
+            fast_pattern_min_length: 2,
+            ..Default::default()
+        };
+        let context = ValidationContext::new(&settings);
+        assert!(lints(text, LintCode::ShortFastPattern, &context).is_empty());
+    }
+
+    #[test]
+    fn short_fast_patterns_link_to_the_documentation() {
+        let line =
+            "alert http any any -> any any (msg:\"a\"; content:\"GET\"; fast_pattern; sid:1;)";
+        let (rule, _) = Rule::parser().parse(line).unwrap();
+        let violation = rule
+            .validate(&ValidationContext::default())
+            .into_iter()
+            .find(|violation| violation.code == LintCode::ShortFastPattern)
+            .unwrap();
+        assert_eq!(violation.span, 48..53);
+        let diagnostic = violation.to_diagnostic(0);
+        assert_eq!(
+            diagnostic.code_description.unwrap().href.as_str(),
+            "https://docs.suricata.io/en/latest/rules/fast-pattern.html"
+        );
+        assert_eq!(LintCode::DuplicateSid.documentation(), None);
+    }
+
+    #[test]
+    fn quoting_the_msg_clears_the_lint() {
+        let context = ValidationContext::default();
+        let header = "alert tcp any any -> any any";
+        let cases = [
+            (
+                "(msg:ET POLICY test; sid:1;)",
+                "(msg:\"ET POLICY test\"; sid:1;)",
+            ),
+            ("(msg: a,  b ; sid:1;)", "(msg: \"a,  b\" ; sid:1;)"),
+            (
+                "(msg:\"stray quote; sid:1;)",
+                "(msg:\"stray quote\"; sid:1;)",
+            ),
+            ("(msg:C:\\temp; sid:1;)", "(msg:\"C:\\temp\"; sid:1;)"),
+            (
+                "(msg:say \\\"hi\\\"; sid:1;)",
+                "(msg:\"say \\\"hi\\\"\"; sid:1;)",
+            ),
+            ("(msg:a\\;b; sid:1;)", "(msg:\"a\\;b\"; sid:1;)"),
+            ("(msg:  ; sid:1;)", "( sid:1;)"),
+        ];
+        cases.into_iter().for_each(|(options, expected)| {
+            let line = format!("{} {}", header, options);
+            assert_eq!(
+                lints(&line, LintCode::UnquotedMsg, &context).len(),
+                1,
+                "{}",
+                line
+            );
+            let (rule, _) = Rule::parser().parse(line.as_str()).unwrap();
+            let fixed = rule.quote_msg(&line).unwrap();
+            assert_eq!(fixed, format!("{} {}", header, expected));
+            assert!(
+                lints(&fixed, LintCode::UnquotedMsg, &context).is_empty(),
+                "{}",
+                fixed
+            );
+        });
+        // Quoted msgs are left alone
+        let line = format!("{} (msg:\"x\"; sid:1;)", header);
+        let (rule, _) = Rule::parser().parse(line.as_str()).unwrap();
+        assert!(rule.quote_msg(&line).is_none());
+    }
+
+    #[test]
+    fn malformed_sids_gids_and_revs_are_reported() {
+        let header = "alert tcp any any -> any any";
+        let cases = [
+            (
+                "(msg:\"x\"; gid:g; sid:1a; rev:-1;)",
+                vec![
+                    ("sid should be a positive number, found \"1a\"", 50..52),
+                    ("gid should be a positive number, found \"g\"", 43..44),
+                    ("rev should be a positive number, found \"-1\"", 58..60),
+                ],
+            ),
+            (
+                "(msg:\"x\"; sid:0;)",
+                vec![("sid should be a positive number, found \"0\"", 43..44)],
+            ),
+        ];
+        cases.into_iter().for_each(|(options, expected)| {
+            let line = format!("{} {}", header, options);
+            let (rule, _) = Rule::parser().parse(line.as_str()).unwrap();
+            let violations = rule
+                .validate(&ValidationContext::default())
+                .into_iter()
+                .filter(|violation| violation.code == LintCode::InvalidSid)
+                .map(|violation| (violation.message, violation.span))
+                .collect::<Vec<_>>();
+            let expected = expected
+                .into_iter()
+                .map(|(message, span)| (message.to_string(), span))
+                .collect::<Vec<_>>();
+            assert_eq!(violations, expected, "{}", line);
+        });
+        let valid = "alert tcp any any -> any any (msg:\"x\"; gid:1; sid:2; rev:3;)";
+        assert!(lints(valid, LintCode::InvalidSid, &ValidationContext::default()).is_empty());
+    }
+
+    #[test]
+    fn malformed_classtypes_are_reported() {
+        let header = "alert tcp any any -> any any";
+        let context = ValidationContext::default();
+        let classtype_lints = |options: &str| {
+            let (rule, _) = Rule::parser()
+                .parse(format!("{} {}", header, options))
+                .unwrap();
+            rule.validate(&context)
+                .into_iter()
+                .filter(|violation| violation.code == LintCode::InvalidClasstype)
+                .map(|violation| violation.message)
+                .collect::<Vec<_>>()
+        };
+        assert!(classtype_lints("(classtype:trojan-activity; sid:1;)").is_empty());
+        assert!(classtype_lints("(sid:1;)").is_empty());
+        assert_eq!(
+            classtype_lints("(classtype:a,b; sid:1;)"),
+            vec!["classtype takes a single value"]
+        );
+        assert_eq!(
+            classtype_lints("(classtype:a; classtype:a; sid:1;)"),
+            vec!["classtype is set more than once"]
+        );
+    }
+
+    #[test]
+    fn misplaced_modifiers_are_reported() {
+        let header = "alert http any any -> any any";
+        let context = ValidationContext::default();
+        let misplaced = |options: &str| {
+            let (rule, _) = Rule::parser()
+                .parse(format!("{} ({} sid:1;)", header, options))
+                .unwrap();
+            rule.validate(&context)
+                .into_iter()
+                .filter(|violation| violation.code == LintCode::InvalidModifier)
+                .map(|violation| (violation.message, violation.span))
+                .collect::<Vec<_>>()
+        };
+        assert!(misplaced("content:\"a\"; nocase; http.uri; content:\"b\"; nocase;").is_empty());
+        // Reported by the relative-modifier lint
+        assert!(misplaced("within:10; content:\"a\";").is_empty());
+        assert_eq!(
+            misplaced("nocase; content:\"a\";"),
+            [("nocase does not follow a content".to_string(), 31..37)]
+        );
+        assert_eq!(
+            misplaced("content:\"a\"; depth:4; nocase; depth:8;"),
+            [(
+                "depth is set more than once for the content".to_string(),
+                60..68
+            )]
+        );
+        // The sticky buffer and the pcre end the content
+        assert_eq!(
+            misplaced("content:\"a\"; http.uri; nocase;"),
+            [("nocase does not follow a content".to_string(), 53..60)]
+        );
+        assert_eq!(
+            misplaced("content:\"a\"; pcre:\"/b/\"; nocase;"),
+            [("nocase does not follow a content".to_string(), 55..62)]
+        );
+    }
+
+    #[test]
+    fn relative_modifiers_need_a_previous_match() {
+        let header = "alert http any any -> any any";
+        let context = ValidationContext::default();
+        let relative = |options: &str| {
+            let (rule, _) = Rule::parser()
+                .parse(format!("{} ({} sid:1;)", header, options))
+                .unwrap();
+            rule.validate(&context)
+                .into_iter()
+                .filter(|violation| violation.code == LintCode::RelativeModifier)
+                .map(|violation| (violation.message, violation.span))
+                .collect::<Vec<_>>()
+        };
+        assert!(relative("content:\"a\"; content:\"b\"; distance:0; within:10;").is_empty());
+        assert!(relative("content:\"a\"; pcre:\"/b/R\";").is_empty());
+        // The first content is relative to the start of the buffer
+        assert!(relative("content:\"a\"; within:10;").is_empty());
+        assert!(relative("content:\"a\"; http.uri; content:\"b\"; distance:0;").is_empty());
+        assert_eq!(
+            relative("within:10; content:\"a\";"),
+            [(
+                "within has no previous match, it should follow a content of the signature"
+                    .to_string(),
+                31..37
+            )]
+        );
+        // The sticky buffer restarts the chain, the pcre ends the content
+        assert_eq!(
+            relative("content:\"a\"; http.uri; within:5; content:\"b\";"),
+            [(
+                "within has no previous match, it should follow a content of the http.uri buffer"
+                    .to_string(),
+                54..60
+            )]
+        );
+        assert_eq!(
+            relative("content:\"a\"; pcre:\"/b/\"; distance:0;"),
+            [(
+                "distance has no previous match, it should follow a content of the signature"
+                    .to_string(),
+                56..64
+            )]
+        );
+        assert_eq!(
+            relative("http.uri; content:\"a\"; http.host; pcre:\"/b/iR\";"),
+            [(
+                "the R flag of pcre is relative to the previous match, but it is the first match of the http.host buffer"
+                    .to_string(),
+                75..76
+            )]
+        );
+        // Relative and absolute modifiers on the same content
+        assert_eq!(
+            relative("content:\"a\"; content:\"b\"; depth:5; within:10;"),
+            [(
+                "within can not be combined with depth on the same content".to_string(),
+                66..72
+            )]
+        );
+        assert_eq!(
+            relative("content:\"a\"; content:\"b\"; within:10; offset:2;"),
+            [(
+                "offset can not be combined with within on the same content".to_string(),
+                68..74
+            )]
+        );
+    }
+
+    #[test]
+    fn invalid_hex_bytes_are_reported() {
+        let signatures = concat!(
+            "alert tcp any any -> any any (content:\"|0a 0g|\"; sid:1;)\n",
+            "alert tcp any any -> any any (content:\"|0a 0|\"; sid:2;)\n",
+            "alert tcp any any -> any any (content:!\"|0a\"; sid:3;)\n",
+            "alert tcp any any -> any any (content:\"GET\\|x|20|HTTP\"; sid:4;)\n",
+        );
+        let context = ValidationContext::default();
+        assert_eq!(
+            lints(signatures, LintCode::InvalidHex, &context),
+            vec![
+                (0, Severity::Error),
+                (1, Severity::Error),
+                (2, Severity::Error)
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_pcres_are_reported() {
+        let signatures = concat!(
+            "alert http any any -> any any (pcre:\"/a/Ui\"; sid:1;)\n",
+            "alert http any any -> any any (pcre:\"/a/Rq\"; sid:2;)\n",
+            "alert http any any -> any any (content:\"a\"; pcre:!\"/b/R\"; sid:3;)\n",
+        );
+        let context = ValidationContext::default();
+        assert_eq!(
+            lints(signatures, LintCode::InvalidPcre, &context),
+            vec![(1, Severity::Error)]
+        );
+    }
+
+    #[test]
+    fn bits_operations_need_their_names() {
+        let text = "\
+alert tcp any any -> any any (flowbits:set; sid:1;)
+alert tcp any any -> any any (flowbits:isset,; sid:2;)
+alert tcp any any -> any any (flowbits:noalert; sid:3;)
+alert tcp any any -> any any (flowbits:noalert,a; sid:4;)
+alert tcp any any -> any any (xbits:unset,a,track ip_src; sid:5;)
+";
+        let (ast, _) = AST::parse_str(text);
+        let mut messages = validate_document(&ast, &ValidationContext::default())
+            .into_iter()
+            .filter(|(_, violation)| violation.code == LintCode::InvalidBits)
+            .map(|(line, violation)| (line, violation.message))
+            .collect::<Vec<_>>();
+        messages.sort();
+        assert_eq!(
+            messages,
+            vec![
+                (0, "set needs the name of the flowbit".to_string()),
+                (1, "isset needs the name of the flowbit".to_string()),
+                (3, "noalert does not take the name of a flowbit".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn priorities_outside_of_1_255_are_reported() {
+        let header = "alert tcp any any -> any any";
+        let context = ValidationContext::default();
+        let priority_lints = |options: &str| {
+            let (rule, _) = Rule::parser()
+                .parse(format!("{} {}", header, options))
+                .unwrap();
+            rule.validate(&context)
+                .into_iter()
+                .filter(|violation| violation.code == LintCode::InvalidPriority)
+                .map(|violation| (violation.message, violation.span))
+                .collect::<Vec<_>>()
+        };
+        assert!(priority_lints("(priority:1; sid:1;)").is_empty());
+        assert!(priority_lints("(priority:255; sid:1;)").is_empty());
+        assert!(priority_lints("(sid:1;)").is_empty());
+        assert_eq!(
+            priority_lints("(priority:0; sid:1;)"),
+            vec![(
+                "priority should be between 1 and 255, found 0".to_string(),
+                39..40
+            )]
+        );
+        assert_eq!(
+            priority_lints("(priority:high; sid:1;)"),
+            vec![(
+                "priority should be a number between 1 and 255, found \"high\"".to_string(),
+                39..43
+            )]
+        );
+        assert_eq!(
+            priority_lints("(priority; sid:1;)"),
+            vec![("priority needs a value".to_string(), 30..38)]
+        );
+    }
+}
@@ -0,0 +1,91 @@
+//! Provides the logging of the binary
+//!
+//! The language server communicates over stdio, so its logs are either written
+//! to a file or forwarded to the client (as `window/logMessage`), where they
+//! show up in the output panel of the editor. The command line tools log to
+//! stderr.
+use std::fs::File;
+use std::path::Path;
+
+use env_logger::{Builder, Target};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tower_lsp::lsp_types::MessageType;
+use tower_lsp::Client;
+
+/// Where the logs are written to
+pub enum LogTarget<'a> {
+    Stderr,
+    File(&'a Path),
+    /// Forward the logs to the client, they are queued until the client is available
+    Client,
+}
+
+/// A log message, which is waiting to be sent to the client
+pub type ClientLogMessage = (MessageType, String);
+
+/// Install the logger
+///
+/// Returns the receiver of the log messages, if they should be forwarded to the
+/// client (see [forward_to_client]).
+pub fn init(
+    level: LevelFilter,
+    target: LogTarget,
+) -> std::io::Result<Option<UnboundedReceiver<ClientLogMessage>>> {
+    match target {
+        LogTarget::Stderr => {
+            Builder::new().filter_level(level).init();
+            Ok(None)
+        }
+        LogTarget::File(path) => {
+            let file = File::options().create(true).append(true).open(path)?;
+            Builder::new()
+                .filter_level(level)
+                .target(Target::Pipe(Box::new(file)))
+                .init();
+            Ok(None)
+        }
+        LogTarget::Client => {
+            let (sender, receiver) = unbounded_channel();
+            // Only fails if a logger is already installed
+            let _ = log::set_boxed_logger(Box::new(ClientLogger { level, sender }));
+            log::set_max_level(level);
+            Ok(Some(receiver))
+        }
+    }
+}
+
+/// Send the queued log messages to the client, until the logger is dropped
+pub async fn forward_to_client(client: Client, mut receiver: UnboundedReceiver<ClientLogMessage>) {
+    while let Some((message_type, message)) = receiver.recv().await {
+        client.log_message(message_type, message).await;
+    }
+}
+
+/// A logger, which queues the messages for the client
+struct ClientLogger {
+    level: LevelFilter,
+    sender: UnboundedSender<ClientLogMessage>,
+}
+
+impl Log for ClientLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let message_type = match record.level() {
+            Level::Error => MessageType::ERROR,
+            Level::Warn => MessageType::WARNING,
+            Level::Info => MessageType::INFO,
+            Level::Debug | Level::Trace => MessageType::LOG,
+        };
+        let message = format!("[{}] {}", record.target(), record.args());
+        let _ = self.sender.send((message_type, message));
+    }
+
+    fn flush(&self) {}
+}
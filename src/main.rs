@@ -4,22 +4,57 @@
 //! by IWANABETHATGUY.
 //!
 //! [boilerplate code]: https://github.com/IWANABETHATGUY/tower-lsp-boilerplate
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::time::{Duration, Instant};
 
-use chumsky::Parser;
-use clap::{Parser as CP, Subcommand};
+use arc_swap::ArcSwap;
+use clap::Parser as CP;
 use dashmap::DashMap;
-use meerkat_ls::completion::get_completion;
-use meerkat_ls::hover::get_hover;
-use meerkat_ls::reference::get_reference;
-use meerkat_ls::rule::{Rule, AST};
-use meerkat_ls::semantic_token::{semantic_token_from_rule, ImCompleteSemanticToken, LEGEND_TYPE};
-use meerkat_ls::server_settings::LanguageServerSettings;
-use meerkat_ls::suricata::{verify_rule, Keyword, get_keywords};
-use ropey::{Rope, RopeSlice};
+use log::LevelFilter;
+use meerkat_ls::cli::{Command, EXIT_IO_ERROR};
+use meerkat_ls::completion::{
+    dataset_file_at, get_completion, get_completion_for_dataset_files, CompletionIndex,
+};
+use meerkat_ls::convert::snort2::convert_rule;
+use meerkat_ls::diagnostics::{DiagnosticSource, DiagnosticsStore, Publication};
+use meerkat_ls::document_link::{get_document_links, resolve_document_link};
+use meerkat_ls::format::{
+    final_newline, format_lines, line_content, line_ending, reorder_options, FormatStyle, LineEdit,
+};
+use meerkat_ls::hover::{get_hover, get_lua_hover};
+use meerkat_ls::lint::{
+    validate_document, validate_duplicate_msgs, validate_duplicate_sids, validate_lua_scripts,
+    validate_sid_range, validate_unset_flowbits, LintCode, ValidationContext, Violation,
+};
+use meerkat_ls::logging::{self, LogTarget};
+use meerkat_ls::reference::{get_bit_reference, get_reference};
+use meerkat_ls::renumber::{next_free_sid, renumber_sids, used_sids, RenumberOptions};
+use meerkat_ls::parser::comment_prefix;
+use meerkat_ls::position::PositionEncoding;
+use meerkat_ls::rule::{Rule, Span, AST};
+use meerkat_ls::semantic_token::{encode_semantic_tokens, ImCompleteSemanticToken, LEGEND_TYPE};
+use meerkat_ls::server_settings::{merge_json, ParsedSettings, ServerSettings, VerificationSettings};
+use meerkat_ls::skeleton::{rule_skeleton, SkeletonOptions};
+use meerkat_ls::suricata::{
+    bundled_keywords, get_events, get_keywords, get_suricata_version, verify_rule, EventTable,
+    Keyword, SuricataVersion,
+};
+use meerkat_ls::syntax::{syntax_errors, SyntaxError};
+use meerkat_ls::threshold::ThresholdConfig;
+use meerkat_ls::variables::{
+    find_definition, parse_config_variables, variable_at, variable_usages, VariableIndex,
+    VariableKind, VariableSource, VariableUsages, Variables,
+};
+use meerkat_ls::workspace::{find_dataset_files, is_binary, parse_document, WorkspaceIndex};
+use ropey::Rope;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use tower_lsp::jsonrpc::Result;
+use tower_lsp::jsonrpc::{Error, Result};
 use tower_lsp::lsp_types::*;
 use tower_lsp::{Client, LanguageServer, LspService, Server};
 
@@ -28,21 +63,82 @@ struct Backend {
     client: Client,
     ast_map: DashMap<String, AST>,
     document_map: DashMap<String, Rope>,
+    /// The latest text of every document, the changes are applied to it, while
+    /// the text in `document_map` is only replaced along with its AST
+    latest_documents: DashMap<String, Rope>,
+    document_versions: DashMap<String, i32>,
     semantic_token_map: DashMap<String, Vec<ImCompleteSemanticToken>>,
     keywords: HashMap<String, Keyword>, 
-    port_variables: HashSet<String>,
-    address_variables: HashSet<String>,
-    language_server_settings: LanguageServerSettings
+    /// The version of the installed Suricata (None if it could not be detected)
+    suricata_version: Option<SuricataVersion>,
+    /// The events raised by the installed Suricata, along with the bundled ones
+    events: EventTable,
+    /// The variables used by the open documents and the workspace
+    variables: Arc<RwLock<VariableIndex>>,
+    language_server_settings: ArcSwap<ServerSettings>,
+    /// The settings given on the command line, which take precedence over the
+    /// settings of the client
+    command_line_settings: Value,
+    /// The settings of every workspace folder, merged over the global settings
+    folder_settings: DashMap<PathBuf, Arc<ServerSettings>>,
+    /// Whether the client answers `workspace/configuration` requests
+    supports_configuration: AtomicBool,
+    /// How the client counts the columns, negotiated when it initializes
+    position_encoding: OnceLock<PositionEncoding>,
+    workspace_folders: Mutex<Vec<PathBuf>>,
+    workspace_index: Arc<WorkspaceIndex>,
+    /// The usages of the variables inside the workspace, cleared whenever a
+    /// file of the workspace changes
+    variable_usages: Arc<DashMap<(VariableKind, String), VariableUsages>>,
+    /// The values used inside the workspace, built on the first completion
+    /// after a file of the workspace changed
+    completion_index: Arc<Mutex<Option<Arc<CompletionIndex>>>>,
+    /// The files inside every directory of the datasets, cleared whenever the
+    /// settings or the watched files change
+    dataset_files: DashMap<PathBuf, Arc<Vec<String>>>,
+    /// The diagnostics of every source, published together
+    diagnostics: Arc<DiagnosticsStore>,
 }
 
 #[tower_lsp::async_trait]
 impl LanguageServer for Backend {
-    async fn initialize(&self, _: InitializeParams) -> Result<InitializeResult> {
+    async fn initialize(&self, params: InitializeParams) -> Result<InitializeResult> {
+        // The settings provided by the client take precedence over the settings file
+        if let Some(options) = params.initialization_options {
+            log::info!("using the settings from the initialization options");
+            self.update_settings(client_settings(options, &self.command_line_settings))
+                .await;
+        }
+        let supports_configuration = params
+            .capabilities
+            .workspace
+            .as_ref()
+            .and_then(|workspace| workspace.configuration)
+            .unwrap_or(false);
+        self.supports_configuration
+            .store(supports_configuration, Ordering::Relaxed);
+        let position_encodings = params.capabilities.general.as_ref();
+        let position_encodings =
+            position_encodings.and_then(|general| general.position_encodings.as_deref());
+        let position_encoding = PositionEncoding::negotiate(position_encodings);
+        let _ = self.position_encoding.set(position_encoding);
+        // Remember the workspace folders, so they can be indexed once initialized
+        let folders = match params.workspace_folders {
+            Some(folders) => folders.into_iter().map(|folder| folder.uri).collect(),
+            None => params.root_uri.into_iter().collect::<Vec<_>>(),
+        };
+        if let Ok(mut workspace_folders) = self.workspace_folders.lock() {
+            *workspace_folders = folders
+                .into_iter()
+                .filter_map(|uri| uri.to_file_path().ok())
+                .collect();
+        }
         Ok(InitializeResult {
             server_info: None,
             capabilities: ServerCapabilities {
+                position_encoding: Some(position_encoding.kind()),
                 text_document_sync: Some(TextDocumentSyncCapability::Kind(
-                    TextDocumentSyncKind::FULL,
+                    TextDocumentSyncKind::INCREMENTAL,
                 )),
                 completion_provider: Some(CompletionOptions {
                     resolve_provider: Some(false),
@@ -50,6 +146,8 @@ impl LanguageServer for Backend {
                         "$".to_string(),
                         " ".to_string(),
                         "(".to_string(),
+                        ":".to_string(),
+                        ",".to_string(),
                     ]),
                     work_done_progress_options: Default::default(),
                     all_commit_characters: None,
@@ -67,18 +165,27 @@ impl LanguageServer for Backend {
                         SemanticTokensRegistrationOptions {
                             text_document_registration_options: {
                                 TextDocumentRegistrationOptions {
-                                    document_selector: Some(vec![DocumentFilter {
-                                        language: Some("suricata".to_string()),
-                                        scheme: Some("file".to_string()),
-                                        pattern: None,
-                                    }]),
+                                    // Clients without a language for .rules files
+                                    // are matched by the pattern
+                                    document_selector: Some(vec![
+                                        DocumentFilter {
+                                            language: Some("suricata".to_string()),
+                                            scheme: Some("file".to_string()),
+                                            pattern: None,
+                                        },
+                                        DocumentFilter {
+                                            language: None,
+                                            scheme: Some("file".to_string()),
+                                            pattern: Some("**/*.rules".to_string()),
+                                        },
+                                    ]),
                                 }
                             },
                             semantic_tokens_options: SemanticTokensOptions {
                                 work_done_progress_options: WorkDoneProgressOptions::default(),
                                 legend: SemanticTokensLegend {
                                     // TODO
-                                    token_types: LEGEND_TYPE.clone().into(),
+                                    token_types: LEGEND_TYPE.into(),
                                     token_modifiers: vec![],
                                 },
                                 range: Some(true),
@@ -93,6 +200,23 @@ impl LanguageServer for Backend {
                 document_formatting_provider: Some(OneOf::Left(true)),
                 hover_provider: Some(HoverProviderCapability::Simple(true)),
                 document_range_formatting_provider: Some(OneOf::Left(true)),
+                code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
+                execute_command_provider: Some(ExecuteCommandOptions {
+                    commands: vec![
+                        SELECT_VERIFICATION_PROFILE.to_string(),
+                        RENUMBER_SIDS.to_string(),
+                        DUPLICATE_RULE.to_string(),
+                        NEW_RULE.to_string(),
+                    ],
+                    work_done_progress_options: Default::default(),
+                }),
+                definition_provider: Some(OneOf::Left(true)),
+                declaration_provider: Some(DeclarationCapability::Simple(true)),
+                document_link_provider: Some(DocumentLinkOptions {
+                    resolve_provider: Some(true),
+                    work_done_progress_options: Default::default(),
+                }),
+                inlay_hint_provider: Some(OneOf::Left(true)),
                 ..ServerCapabilities::default()
             },
         })
@@ -106,42 +230,14 @@ impl LanguageServer for Backend {
             .log_message(MessageType::LOG, "semantic_token_full")
             .await;
         let semantic_tokens = || -> Option<Vec<SemanticToken>> {
-            let mut im_complete_tokens = self.semantic_token_map.get_mut(&uri)?;
+            // The stored tokens of every rule are updated along with the document,
+            // so they are copied
+            let mut im_complete_tokens = self.semantic_token_map.get(&uri)?.clone();
             let rope = self.document_map.get(&uri)?;
-            let ast = self.ast_map.get(&uri)?;
-            let mut extends_tokens = vec![];
-            ast.rules.iter().for_each(|(line, rule)| {
-                let line_offset = rope.line_to_char(line.clone() as usize);
-                semantic_token_from_rule(rule, &line_offset, &mut extends_tokens);
-            });
-            im_complete_tokens.extend(extends_tokens);
-            im_complete_tokens.sort_by(|a, b| a.start.cmp(&b.start));
-            let mut pre_line = 0;
-            let mut pre_start = 0;
-            let semantic_tokens = im_complete_tokens
-                .iter()
-                .filter_map(|token| {
-                    let line = rope.try_byte_to_line(token.start as usize).ok()? as u32;
-                    let first = rope.try_line_to_char(line as usize).ok()? as u32;
-                    let start = rope.try_byte_to_char(token.start as usize).ok()? as u32 - first;
-                    let delta_line = line - pre_line;
-                    let delta_start = if delta_line == 0 {
-                        start - pre_start
-                    } else {
-                        start
-                    };
-                    let ret = Some(SemanticToken {
-                        delta_line,
-                        delta_start,
-                        length: token.length as u32,
-                        token_type: token.token_type as u32,
-                        token_modifiers_bitset: 0,
-                    });
-                    pre_line = line;
-                    pre_start = start;
-                    ret
-                })
-                .collect::<Vec<_>>();
+            im_complete_tokens.retain(|token| token.length > 0);
+            im_complete_tokens.sort_by_key(|a| a.start);
+            let encoding = self.position_encoding();
+            let semantic_tokens = encode_semantic_tokens(&rope, &im_complete_tokens, encoding);
             Some(semantic_tokens)
         }();
         if let Some(semantic_token) = semantic_tokens {
@@ -161,30 +257,13 @@ impl LanguageServer for Backend {
         let semantic_tokens = || -> Option<Vec<SemanticToken>> {
             let im_complete_tokens = self.semantic_token_map.get(&uri)?;
             let rope = self.document_map.get(&uri)?;
-            let mut pre_line = 0;
-            let mut pre_start = 0;
-            let semantic_tokens = im_complete_tokens
+            let encoding = self.position_encoding();
+            let start = position_to_char(&rope, params.range.start, encoding)?;
+            let end = position_to_char(&rope, params.range.end, encoding)?;
+            let tokens = im_complete_tokens
                 .iter()
-                .filter_map(|token| {
-                    let line = rope.try_byte_to_line(token.start as usize).ok()? as u32;
-                    let first = rope.try_line_to_char(line as usize).ok()? as u32;
-                    let start = rope.try_byte_to_char(token.start as usize).ok()? as u32 - first;
-                    let ret = Some(SemanticToken {
-                        delta_line: line - pre_line,
-                        delta_start: if start >= pre_start {
-                            start - pre_start
-                        } else {
-                            start
-                        },
-                        length: token.length as u32,
-                        token_type: token.token_type as u32,
-                        token_modifiers_bitset: 0,
-                    });
-                    pre_line = line;
-                    pre_start = start;
-                    ret
-                })
-                .collect::<Vec<_>>();
+                .filter(|token| (start..end).contains(&token.start));
+            let semantic_tokens = encode_semantic_tokens(&rope, tokens, encoding);
             Some(semantic_tokens)
         }();
         if let Some(semantic_token) = semantic_tokens {
@@ -200,29 +279,61 @@ impl LanguageServer for Backend {
         self.client
             .log_message(MessageType::INFO, "initialized!")
             .await;
+        self.send_status().await;
+        // Index the workspace in the background
+        let folders = self
+            .workspace_folders
+            .lock()
+            .map(|folders| folders.clone())
+            .unwrap_or_default();
+        let index = self.workspace_index.clone();
+        let client = self.client.clone();
+        let variable_usages = self.variable_usages.clone();
+        let completion_index = self.completion_index.clone();
+        let variables = self.variables.clone();
+        tokio::spawn(async move {
+            let indexed = index.clone().index_folders(folders).await;
+            // The documents opened in the meantime are newer than their indexed version
+            if let Ok(mut variables) = variables.write() {
+                for file in index.files.iter() {
+                    if !variables.contains(file.key()) {
+                        variables.update(file.key(), file.value());
+                    }
+                }
+            }
+            variable_usages.clear();
+            if let Ok(mut completion_index) = completion_index.lock() {
+                *completion_index = None;
+            }
+            client
+                .log_message(MessageType::INFO, format!("indexed {} rule files", indexed))
+                .await;
+        });
     }
 
     async fn shutdown(&self) -> Result<()> {
+        self.workspace_index.cancel();
         Ok(())
     }
 
     async fn references(&self, params: ReferenceParams) -> Result<Option<Vec<Location>>> {
         let reference_list = || -> Option<Vec<Location>> {
             let uri = params.text_document_position.text_document.uri;
+            let position = self.char_position(&uri, params.text_document_position.position);
             let ast = self.ast_map.get(&uri.to_string())?;
 
-            let position = params.text_document_position.position;
             let col = position.character as usize;
-            let reference_list = get_reference(&ast, &position.line, &col)?;
+            let reference_list = get_reference(&ast, &position.line, &col)
+                .or_else(|| get_bit_reference(&ast, &position.line, &col))?;
             let ret = reference_list
                 .into_iter()
-                .filter_map(|(line, (_, range))| {
+                .map(|(line, (_, range))| {
                     let start_position = Position::new(line, range.start as u32);
                     let end_position = Position::new(line, range.end as u32);
 
                     let range = Range::new(start_position, end_position);
 
-                    Some(Location::new(uri.clone(), range))
+                    Location::new(uri.clone(), self.client_range(&uri, range))
                 })
                 .collect::<Vec<_>>();
             Some(ret)
@@ -231,37 +342,27 @@ impl LanguageServer for Backend {
     }
 
     async fn formatting(&self, params: DocumentFormattingParams) -> Result<Option<Vec<TextEdit>>> {
+        let uri = params.text_document.uri;
+        let settings = self.settings_for(&uri).await;
         let text_edits = || -> Option<Vec<TextEdit>> {
-            let uri = params.text_document.uri;
             let ast = self.ast_map.get(&uri.to_string())?;
             let rope = self.document_map.get(&uri.to_string())?;
-            let mut ret = vec![];
-            ast.rules.iter().for_each(|(line_nr, (rule, _))| {
-                let line = rope.get_line(line_nr.clone() as usize);
-                if let Some(line) = line {
-                    let formatted_rule = rule.to_string();
-                    if line.to_string() != formatted_rule {
-                        let start_range = Position {
-                            line: line_nr.clone(),
-                            character: 0,
-                        };
-                        let end_range = Position {
-                            line: line_nr.clone(),
-                            character: u32::MAX,
-                        };
-                        ret.push(TextEdit {
-                            range: Range {
-                                start: start_range,
-                                end: end_range,
-                            },
-                            new_text: formatted_rule,
-                        })
-                    }
-                } else {
-                    return;
-                }
-            });
-            Some(ret)
+            if !settings.formatting.enabled {
+                return None;
+            }
+            let style = FormatStyle::from(&settings.formatting);
+            let edits = format_lines(&rope, &ast, &style);
+            let mut text_edits = to_text_edits(&rope, edits);
+            // Append the missing line ending after the last line
+            if let Some(final_newline) = final_newline(&rope, &style) {
+                let last_line = rope.len_lines() - 1;
+                let end = Position::new(last_line as u32, rope.line(last_line).len_chars() as u32);
+                text_edits.push(TextEdit {
+                    range: Range::new(end, end),
+                    new_text: final_newline.to_string(),
+                });
+            }
+            Some(self.client_text_edits(&uri, text_edits))
         }();
         Ok(text_edits)
     }
@@ -270,60 +371,239 @@ impl LanguageServer for Backend {
         &self,
         params: DocumentRangeFormattingParams,
     ) -> Result<Option<Vec<TextEdit>>> {
-        let line_range = params.range.start.line..params.range.end.line;
+        let line_range = params.range.start.line..=params.range.end.line;
+        let uri = params.text_document.uri;
+        let settings = self.settings_for(&uri).await;
         let text_edits = || -> Option<Vec<TextEdit>> {
-            let uri = params.text_document.uri;
             let ast = self.ast_map.get(&uri.to_string())?;
             let rope = self.document_map.get(&uri.to_string())?;
-            let mut ret = vec![];
-            ast.rules
-                .iter()
-                .filter(|(_, (_, rule_span))| {
-                    let rule_line = rope.char_to_line(rule_span.start) as u32;
-                    line_range.contains(&rule_line)
-                })
-                .for_each(|(_, (rule, rule_span))| {
-                    let line_nr = rope.char_to_line(rule_span.start);
-                    let line = rope.get_line(line_nr);
-                    if let Some(line) = line {
-                        let formatted_rule = rule.to_string();
-                        if line.to_string() != formatted_rule {
-                            let start_range = Position {
-                                line: line_nr as u32,
-                                character: 0,
-                            };
-                            let end_range = Position {
-                                line: line_nr as u32,
-                                character: 0,
-                            };
-                            ret.push(TextEdit {
-                                range: Range {
-                                    start: start_range,
-                                    end: end_range,
-                                },
-                                new_text: formatted_rule,
-                            })
+            if !settings.formatting.enabled {
+                return None;
+            }
+            let edits = format_lines(&rope, &ast, &FormatStyle::from(&settings.formatting))
+                .into_iter()
+                .filter(|edit| line_range.contains(&edit.line))
+                .collect();
+            Some(self.client_text_edits(&uri, to_text_edits(&rope, edits)))
+        }();
+        Ok(text_edits)
+    }
+
+    async fn code_action(&self, params: CodeActionParams) -> Result<Option<CodeActionResponse>> {
+        let uri = params.text_document.uri;
+        // Lints which are turned off do not offer their fixes
+        let context = ValidationContext::new(&self.settings_for(&uri).await.lints)
+            .with_suricata_version(self.suricata_version)
+            .with_events(self.events.clone());
+        let code_actions = || -> Option<CodeActionResponse> {
+            let ast = self.ast_map.get(&uri.to_string())?;
+            let rope = self.document_map.get(&uri.to_string())?;
+            // The diagnostics of a lint on the given line, which are fixed by an action
+            let diagnostics = |line: u32, code: LintCode| {
+                params
+                    .context
+                    .diagnostics
+                    .iter()
+                    .filter(|diagnostic| {
+                        diagnostic.range.start.line == line
+                            && diagnostic.code
+                                == Some(NumberOrString::String(code.code().to_string()))
+                    })
+                    .cloned()
+                    .collect::<Vec<_>>()
+            };
+            let code_actions = (params.range.start.line..=params.range.end.line)
+                .flat_map(|line| {
+                    let mut code_actions = vec![];
+                    let text = rope.get_line(line as usize).map(line_content);
+                    // Signatures are enabled and disabled by (un)commenting their line
+                    let toggle = |title: &str, range: Range, new_text: &str| {
+                        let edits = vec![TextEdit::new(range, new_text.to_string())];
+                        CodeActionOrCommand::CodeAction(CodeAction {
+                            title: title.to_string(),
+                            kind: Some(CodeActionKind::REFACTOR_REWRITE),
+                            edit: Some(WorkspaceEdit::new(HashMap::from([(uri.clone(), edits)]))),
+                            ..Default::default()
+                        })
+                    };
+                    if ast.disabled.contains_key(&line) {
+                        if let Some(prefix) = text.as_deref().and_then(comment_prefix) {
+                            let range = Range::new(
+                                Position::new(line, prefix.start as u32),
+                                Position::new(line, prefix.end as u32),
+                            );
+                            code_actions.push(toggle("Enable rule", range, ""));
                         }
-                    } else {
-                        return;
+                        return code_actions;
                     }
-                });
-            Some(ret)
+                    let Some((rule, _)) = ast.rules.get(&line) else {
+                        return code_actions;
+                    };
+                    let violations = rule.validate(&context);
+                    let has_violation =
+                        |code: LintCode| violations.iter().any(|violation| violation.code == code);
+                    // Only offer the conversion if the lint found Snort 2 modifiers
+                    if has_violation(LintCode::LegacyModifier) {
+                        let conversion = convert_rule(rule);
+                        let edits = to_text_edits(
+                            &rope,
+                            vec![LineEdit {
+                                line,
+                                new_text: conversion.rule.to_string(),
+                            }],
+                        );
+                        code_actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+                            title: "Convert Snort-style options".to_string(),
+                            kind: Some(CodeActionKind::QUICKFIX),
+                            diagnostics: Some(diagnostics(line, LintCode::LegacyModifier)),
+                            edit: Some(WorkspaceEdit::new(HashMap::from([(uri.clone(), edits)]))),
+                            ..Default::default()
+                        }));
+                    }
+                    // Fixes, which rewrite the line of the signature
+                    let line_fixes: [(LintCode, String, fn(&Rule, &str) -> Option<String>); 6] = [
+                        (
+                            LintCode::MissingFlow,
+                            format!("Add \"flow:{};\"", rule.suggested_flow()),
+                            Rule::insert_flow,
+                        ),
+                        (
+                            LintCode::MissingNoalert,
+                            "Add \"flowbits:noalert;\"".to_string(),
+                            Rule::insert_noalert,
+                        ),
+                        (
+                            LintCode::OrphanNoalert,
+                            "Remove noalert".to_string(),
+                            Rule::remove_noalert,
+                        ),
+                        (
+                            LintCode::DeprecatedKeyword,
+                            "Replace the deprecated keywords".to_string(),
+                            Rule::replace_deprecated_keywords,
+                        ),
+                        (
+                            LintCode::UnquotedMsg,
+                            match rule.unquoted_msg() {
+                                Some((msg, _)) if msg.trim().is_empty() => {
+                                    "Remove the empty msg".to_string()
+                                }
+                                _ => "Wrap in quotes".to_string(),
+                            },
+                            Rule::quote_msg,
+                        ),
+                        (
+                            LintCode::ReversedDirection,
+                            "Swap the source and the destination".to_string(),
+                            Rule::swap_direction,
+                        ),
+                    ];
+                    line_fixes
+                        .into_iter()
+                        .filter(|(code, _, _)| has_violation(*code))
+                        .for_each(|(code, title, fix)| {
+                            let Some(new_text) = text.as_ref().and_then(|text| fix(rule, text))
+                            else {
+                                return;
+                            };
+                            let edits = to_text_edits(&rope, vec![LineEdit { line, new_text }]);
+                            code_actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+                                title,
+                                kind: Some(CodeActionKind::QUICKFIX),
+                                diagnostics: Some(diagnostics(line, code)),
+                                edit: Some(WorkspaceEdit::new(HashMap::from([(
+                                    uri.clone(),
+                                    edits,
+                                )]))),
+                                ..Default::default()
+                            }));
+                        });
+                    // Offered on every signature, independent of the formatting settings
+                    let reordered = text.as_ref().and_then(|text| reorder_options(rule, text));
+                    if let Some((span, new_text)) = reordered {
+                        let range = Range::new(
+                            Position::new(line, span.start as u32),
+                            Position::new(line, span.end as u32),
+                        );
+                        let edits = vec![TextEdit::new(range, new_text)];
+                        code_actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+                            title: "Reorder options (msg first, sid/rev last)".to_string(),
+                            kind: Some(CodeActionKind::REFACTOR_REWRITE),
+                            edit: Some(WorkspaceEdit::new(HashMap::from([(uri.clone(), edits)]))),
+                            ..Default::default()
+                        }));
+                    }
+                    let start = Position::new(line, 0);
+                    code_actions.push(toggle("Disable rule", Range::new(start, start), "#"));
+                    // The sid of the copy is only looked up once it is chosen
+                    code_actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+                        title: "Duplicate rule".to_string(),
+                        kind: Some(CodeActionKind::REFACTOR),
+                        command: Some(tower_lsp::lsp_types::Command::new(
+                            "Duplicate rule".to_string(),
+                            DUPLICATE_RULE.to_string(),
+                            Some(vec![Value::String(uri.to_string()), Value::from(line)]),
+                        )),
+                        ..Default::default()
+                    }));
+                    code_actions
+                })
+                .collect();
+            Some(code_actions)
         }();
-        Ok(text_edits)
+        // The edits are converted, once the document is not borrowed anymore
+        let code_actions = code_actions.map(|code_actions| {
+            code_actions
+                .into_iter()
+                .map(|code_action| match code_action {
+                    CodeActionOrCommand::CodeAction(code_action) => {
+                        CodeActionOrCommand::CodeAction(CodeAction {
+                            edit: code_action
+                                .edit
+                                .map(|edit| self.client_workspace_edit(edit)),
+                            ..code_action
+                        })
+                    }
+                    command => command,
+                })
+                .collect()
+        });
+        Ok(code_actions)
     }
 
     async fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
+        let uri = params.text_document_position_params.text_document.uri;
+        let variables = Variables::new(&self.settings_for(&uri).await.variables);
+        let position = self.char_position(&uri, params.text_document_position_params.position);
+        let offset = position.character as usize;
+        // The usages are looked up before the document is borrowed for the hover
+        let variable = self.ast_map.get(&uri.to_string()).and_then(|ast| {
+            let (rule, _) = ast.rule_at(&position.line)?;
+            variable_at(rule, offset)
+        });
+        let usages = variable.map(|(kind, (name, _))| self.variable_usages(kind, &name));
         let hover_content = || -> Option<Hover> {
-            let uri = params.text_document_position_params.text_document.uri;
             let ast = self.ast_map.get(&uri.to_string())?;
 
-            let position = params.text_document_position_params.position;
-            let offset = position.character as usize;
-
-            let (hover, span) = get_hover(&ast, &position.line, &offset, &self.keywords)?;
-            let start_position = Position::new(position.line.clone(), span.start as u32);
-            let end_position = Position::new(position.line.clone(), span.end as u32);
+            // The lua scripts are resolved against the directory of the rule file
+            let directory = uri.to_file_path().ok();
+            let directory = directory.as_ref().and_then(|path| path.parent());
+            let lua_hover = directory
+                .and_then(|directory| get_lua_hover(&ast, &position.line, &offset, directory));
+            let (hover, span) = match lua_hover {
+                Some(hover) => hover,
+                None => get_hover(
+                    &ast,
+                    &position.line,
+                    &offset,
+                    &self.keywords,
+                    &variables,
+                    &self.events,
+                    usages.as_ref(),
+                )?,
+            };
+            let start_position = Position::new(position.line, span.start as u32);
+            let end_position = Position::new(position.line, span.end as u32);
             let hover_range = Range {
                 start: start_position,
                 end: end_position,
@@ -333,39 +613,290 @@ impl LanguageServer for Backend {
                 range: Some(hover_range),
             })
         }();
+        let hover_content = hover_content.map(|hover| Hover {
+            range: hover.range.map(|range| self.client_range(&uri, range)),
+            ..hover
+        });
         Ok(hover_content)
     }
 
-    async fn did_change_workspace_folders(&self, _: DidChangeWorkspaceFoldersParams) {
-        self.client
-            .log_message(MessageType::INFO, "workspace folders changed!")
-            .await;
+    async fn goto_definition(
+        &self,
+        params: GotoDefinitionParams,
+    ) -> Result<Option<GotoDefinitionResponse>> {
+        let uri = params.text_document_position_params.text_document.uri;
+        let position = self.char_position(&uri, params.text_document_position_params.position);
+        let variable = || {
+            let ast = self.ast_map.get(&uri.to_string())?;
+            let (rule, _) = ast.rules.get(&position.line)?;
+            variable_at(rule, position.character as usize)
+        };
+        let Some((kind, (name, _))) = variable() else {
+            return Ok(None);
+        };
+        let settings = self.settings_for(&uri).await;
+        let location = |path: &Path, line: u32, span: Span| {
+            Some(Location {
+                uri: Url::from_file_path(path).ok()?,
+                range: Range::new(
+                    Position::new(line, span.start as u32),
+                    Position::new(line, span.end as u32),
+                ),
+            })
+        };
+        let mut locations = vec![];
+        // The variables of the settings point into the settings file, if the
+        // client told us where the settings come from
+        let variables = Variables::new(&settings.variables);
+        let defined_in_settings = variables
+            .get(kind, &name)
+            .is_some_and(|definition| definition.source == VariableSource::Settings);
+        match settings.settings_file.as_ref() {
+            Some(settings_file) if defined_in_settings => {
+                let settings_file = PathBuf::from(settings_file);
+                match tokio::fs::read_to_string(&settings_file).await {
+                    Ok(text) => locations.extend(
+                        find_definition(&text, kind, &name)
+                            .and_then(|(line, span)| location(&settings_file, line, span)),
+                    ),
+                    Err(_) => {
+                        log::warn!(
+                            "could not read the settings file {}",
+                            settings_file.display()
+                        )
+                    }
+                }
+            }
+            None if defined_in_settings => {
+                locations.extend(Url::parse(SETTINGS_URI).ok().map(|uri| Location {
+                    uri,
+                    range: Range::default(),
+                }))
+            }
+            _ => (),
+        }
+        // The suricata.yaml used to verify the signatures
+        let (_, profile) = settings.verification.active();
+        if let Some(config_file) = &profile.config_file {
+            let config_file = PathBuf::from(config_file);
+            match tokio::fs::read_to_string(&config_file).await {
+                Ok(text) => locations.extend(
+                    parse_config_variables(&text)
+                        .into_iter()
+                        .filter(|variable| variable.kind == kind && variable.name.0 == name)
+                        .filter_map(|variable| {
+                            location(&config_file, variable.line, variable.name.1)
+                        }),
+                ),
+                Err(_) => log::warn!("could not read the config file {}", config_file.display()),
+            }
+        }
+        Ok(match locations.len() {
+            0 => None,
+            1 => locations.pop().map(GotoDefinitionResponse::Scalar),
+            _ => Some(GotoDefinitionResponse::Array(locations)),
+        })
     }
 
-    async fn did_change_configuration(&self, _: DidChangeConfigurationParams) {
-        self.client
-            .log_message(MessageType::INFO, "configuration changed!")
-            .await;
+    async fn goto_declaration(
+        &self,
+        params: request::GotoDeclarationParams,
+    ) -> Result<Option<request::GotoDeclarationResponse>> {
+        self.goto_definition(params).await
+    }
+
+    async fn document_link(&self, params: DocumentLinkParams) -> Result<Option<Vec<DocumentLink>>> {
+        let uri = params.text_document.uri;
+        let links = self
+            .ast_map
+            .get(&uri.to_string())
+            .map(|ast| get_document_links(&ast, &self.keywords));
+        Ok(links.map(|links| {
+            links
+                .into_iter()
+                .map(|link| DocumentLink {
+                    range: self.client_range(&uri, link.range),
+                    ..link
+                })
+                .collect()
+        }))
+    }
+
+    async fn document_link_resolve(&self, params: DocumentLink) -> Result<DocumentLink> {
+        Ok(resolve_document_link(params.clone(), &self.keywords).unwrap_or(params))
+    }
+
+    async fn inlay_hint(&self, params: InlayHintParams) -> Result<Option<Vec<InlayHint>>> {
+        let uri = params.text_document.uri;
+        let variables = Variables::new(&self.settings_for(&uri).await.variables);
+        let inlay_hints = || -> Option<Vec<InlayHint>> {
+            let ast = self.ast_map.get(&uri.to_string())?;
+            let lines = params.range.start.line..=params.range.end.line;
+            // Show the value after every variable
+            let mut inlay_hints = ast
+                .rules
+                .iter()
+                .filter(|(line, _)| lines.contains(line))
+                .flat_map(|(line, (rule, _))| {
+                    variable_usages(rule)
+                        .into_iter()
+                        .filter_map(|(kind, (name, span))| {
+                            let definition = variables.get(kind, &name)?;
+                            Some(InlayHint {
+                                position: Position::new(*line, span.end as u32),
+                                label: InlayHintLabel::String(format!("= {}", definition.value)),
+                                kind: None,
+                                text_edits: None,
+                                tooltip: Some(InlayHintTooltip::String(format!(
+                                    "{} defined in {}",
+                                    kind, definition.source
+                                ))),
+                                padding_left: Some(true),
+                                padding_right: Some(true),
+                                data: None,
+                            })
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>();
+            inlay_hints.sort_by_key(|hint| (hint.position.line, hint.position.character));
+            Some(inlay_hints)
+        }();
+        let inlay_hints = inlay_hints.map(|inlay_hints| {
+            inlay_hints
+                .into_iter()
+                .map(|hint| {
+                    let position = Range::new(hint.position, hint.position);
+                    InlayHint {
+                        position: self.client_range(&uri, position).start,
+                        ..hint
+                    }
+                })
+                .collect()
+        });
+        Ok(inlay_hints)
+    }
+
+    async fn did_change_workspace_folders(&self, params: DidChangeWorkspaceFoldersParams) {
+        let to_paths = |folders: Vec<WorkspaceFolder>| {
+            folders
+                .into_iter()
+                .filter_map(|folder| folder.uri.to_file_path().ok())
+                .collect::<Vec<_>>()
+        };
+        let removed = to_paths(params.event.removed);
+        let added = to_paths(params.event.added);
+        if let Ok(mut workspace_folders) = self.workspace_folders.lock() {
+            workspace_folders.retain(|folder| !removed.contains(folder));
+            workspace_folders.extend(added);
+        }
+        // Documents might belong to another folder now
+        self.folder_settings.clear();
+        self.revalidate_documents().await;
+    }
+
+    async fn did_change_configuration(&self, params: DidChangeConfigurationParams) {
+        // Clients usually send all of their settings, the ones of the server
+        // are inside their own section
+        let mut settings = match params.settings {
+            Value::Object(mut settings) if settings.contains_key(SETTINGS_SECTION) => {
+                settings.remove(SETTINGS_SECTION).unwrap_or_default()
+            }
+            settings => settings,
+        };
+        // Clients which support pulling the settings may only notify about the change
+        if settings.is_null() && self.supports_configuration.load(Ordering::Relaxed) {
+            let item = ConfigurationItem {
+                scope_uri: None,
+                section: Some(SETTINGS_SECTION.to_string()),
+            };
+            match self.client.configuration(vec![item]).await {
+                Ok(values) => settings = values.into_iter().next().unwrap_or_default(),
+                Err(err) => log::warn!("could not fetch the settings: {}", err),
+            }
+        }
+        log::info!("configuration changed");
+        if !settings.is_null() {
+            self.update_settings(client_settings(settings, &self.command_line_settings))
+                .await;
+        }
+        self.send_status().await;
+        self.revalidate_documents().await;
     }
 
     async fn did_change_watched_files(&self, _: DidChangeWatchedFilesParams) {
+        self.dataset_files.clear();
         self.client
             .log_message(MessageType::INFO, "watched files have changed!")
             .await;
     }
 
-    async fn execute_command(&self, _: ExecuteCommandParams) -> Result<Option<Value>> {
-        self.client
-            .log_message(MessageType::INFO, "command executed!")
-            .await;
-
-        match self.client.apply_edit(WorkspaceEdit::default()).await {
-            Ok(res) if res.applied => self.client.log_message(MessageType::INFO, "applied").await,
-            Ok(_) => self.client.log_message(MessageType::INFO, "rejected").await,
-            Err(err) => self.client.log_message(MessageType::ERROR, err).await,
+    async fn execute_command(&self, params: ExecuteCommandParams) -> Result<Option<Value>> {
+        match params.command.as_str() {
+            SELECT_VERIFICATION_PROFILE => {
+                let name = params
+                    .arguments
+                    .first()
+                    .and_then(|name| name.as_str())
+                    .map(|name| name.to_string());
+                self.select_verification_profile(name).await
+            }
+            RENUMBER_SIDS => {
+                let arguments = &params.arguments;
+                let uri = arguments
+                    .first()
+                    .and_then(|uri| Url::parse(uri.as_str()?).ok())
+                    .ok_or_else(|| Error::invalid_params("expected the URI of the document"))?;
+                let start = arguments
+                    .get(1)
+                    .and_then(|start| u32::try_from(start.as_u64()?).ok())
+                    .ok_or_else(|| Error::invalid_params("expected the first sid"))?;
+                let step = match arguments.get(2) {
+                    Some(step) => step
+                        .as_u64()
+                        .and_then(|step| u32::try_from(step).ok())
+                        .filter(|step| *step > 0)
+                        .ok_or_else(|| Error::invalid_params("step should be a positive number"))?,
+                    None => 1,
+                };
+                let bump_rev = arguments.get(3).and_then(Value::as_bool).unwrap_or(false);
+                let options = RenumberOptions {
+                    start,
+                    step,
+                    bump_rev,
+                };
+                self.renumber_sids(uri, options).await
+            }
+            DUPLICATE_RULE => {
+                let arguments = &params.arguments;
+                let uri = arguments
+                    .first()
+                    .and_then(|uri| Url::parse(uri.as_str()?).ok())
+                    .ok_or_else(|| Error::invalid_params("expected the URI of the document"))?;
+                let line = arguments
+                    .get(1)
+                    .and_then(|line| u32::try_from(line.as_u64()?).ok())
+                    .ok_or_else(|| Error::invalid_params("expected the line of the signature"))?;
+                self.duplicate_rule(uri, line).await
+            }
+            NEW_RULE => {
+                let arguments = &params.arguments;
+                let uri = arguments
+                    .first()
+                    .and_then(|uri| Url::parse(uri.as_str()?).ok())
+                    .ok_or_else(|| Error::invalid_params("expected the URI of the document"))?;
+                let options = match arguments.get(1) {
+                    Some(options) => serde_json::from_value::<SkeletonOptions>(options.clone())
+                        .map_err(|err| Error::invalid_params(err.to_string()))?,
+                    None => SkeletonOptions::default(),
+                };
+                self.new_rule(uri, &options).await
+            }
+            command => Err(Error::invalid_params(format!(
+                "unknown command {}",
+                command
+            ))),
         }
-
-        Ok(None)
     }
 
     async fn did_open(&self, params: DidOpenTextDocumentParams) {
@@ -380,13 +911,77 @@ impl LanguageServer for Backend {
         .await
     }
 
-    async fn did_change(&self, mut params: DidChangeTextDocumentParams) {
-        self.on_change(TextDocumentItem {
-            uri: params.text_document.uri,
-            text: std::mem::take(&mut params.content_changes[0].text),
-            version: params.text_document.version,
-        })
-        .await
+    async fn did_change(&self, params: DidChangeTextDocumentParams) {
+        let uri = params.text_document.uri;
+        let version = params.text_document.version;
+        let key = uri.to_string();
+        // Some clients send no changes at all (e.g. when saving)
+        if params.content_changes.is_empty() {
+            return;
+        }
+        // The changes are applied to the latest text before anything is awaited,
+        // so the next changes apply to this version
+        let Some(mut rope) = self.latest_documents.get(&key).map(|rope| rope.clone()) else {
+            log::warn!("ignoring the changes of {}, which was never opened", uri);
+            return;
+        };
+        // The stored AST can only be updated, if it was parsed from the latest
+        // text, otherwise another version is still parsed in full
+        let mut edits = self
+            .document_map
+            .get(&key)
+            .is_some_and(|stored| *stored == rope)
+            .then(Vec::new);
+        for change in params.content_changes {
+            let Some(range) = change.range else {
+                rope = Rope::from_str(&change.text);
+                edits = None;
+                continue;
+            };
+            // Only the edited lines are parsed again, along with the text after
+            // the edit (the rope is cheap to clone)
+            match apply_change(&mut rope, range, &change.text, self.position_encoding()) {
+                Some((lines, moved)) => {
+                    if let Some(edits) = &mut edits {
+                        edits.push((rope.clone(), lines, moved));
+                    }
+                }
+                // The texts of the client and the server differ, so the AST
+                // can not be updated and the document is parsed in full
+                None => {
+                    log::warn!("a change of {} is outside of the document", uri);
+                    edits = None;
+                }
+            }
+        }
+        self.latest_documents.insert(key.clone(), rope.clone());
+        self.document_versions.insert(key.clone(), version);
+        let parsed = edits.and_then(|edits| {
+            let ast = self.ast_map.get(&key)?.clone();
+            let semantic_tokens = self.semantic_token_map.get(&key)?.clone();
+            Some((ast, semantic_tokens, edits))
+        });
+        let Some((mut ast, mut semantic_tokens, edits)) = parsed else {
+            let text = rope.to_string();
+            return self
+                .on_change(TextDocumentItem { uri, text, version })
+                .await;
+        };
+        // The settings are only needed to lint the new version
+        let settings = self.settings_for(&uri).await;
+        // The stored AST answers other requests, while a copy of it is updated
+        // on the blocking thread pool
+        let reparse_rope = rope.clone();
+        let reparse_process = tokio::task::spawn_blocking(move || {
+            for (edited, lines, moved) in edits {
+                ast.reparse_lines(&mut semantic_tokens, &edited, lines, moved);
+            }
+            let syntax_errors = syntax_errors(&reparse_rope, &ast);
+            (ast, semantic_tokens, syntax_errors)
+        });
+        let parsed = async { reparse_process.await.ok() };
+        self.process_document(&uri, version, &rope, &settings, parsed)
+            .await
     }
 
     async fn did_save(&self, _: DidSaveTextDocumentParams) {
@@ -395,31 +990,37 @@ impl LanguageServer for Backend {
             .await;
     }
 
-    async fn did_close(&self, _: DidCloseTextDocumentParams) {
-        self.client
-            .log_message(MessageType::INFO, "file closed!")
-            .await;
+    async fn did_close(&self, params: DidCloseTextDocumentParams) {
+        let uri = params.text_document.uri;
+        // The text of a closed document might not be saved, so it is forgotten
+        // (the workspace index knows the saved version)
+        self.forget_document(uri.as_str());
+        self.document_versions.remove(uri.as_str());
+        self.client.publish_diagnostics(uri, vec![], None).await;
     }
 
     async fn rename(&self, params: RenameParams) -> Result<Option<WorkspaceEdit>> {
         let workspace_edit = || -> Option<WorkspaceEdit> {
             let uri = params.text_document_position.text_document.uri;
+            let position = self.char_position(&uri, params.text_document_position.position);
             let ast = self.ast_map.get(&uri.to_string())?;
 
-            let position = params.text_document_position.position;
             let col = position.character as usize;
-            let reference_list = get_reference(&ast, &position.line, &col)?;
-            let new_name = params.new_name;
-            if reference_list.len() > 0 {
+            // Variables keep their leading $, bits are renamed as they are
+            let (reference_list, new_name) = match get_reference(&ast, &position.line, &col) {
+                Some(reference_list) => (reference_list, format!("${}", params.new_name)),
+                None => (
+                    get_bit_reference(&ast, &position.line, &col)?,
+                    params.new_name,
+                ),
+            };
+            if !reference_list.is_empty() {
                 let edit_list = reference_list
                     .into_iter()
-                    .filter_map(|(line, (_, range))| {
+                    .map(|(line, (_, range))| {
                         let start_position = Position::new(line, range.start as u32);
                         let end_position = Position::new(line, range.end as u32);
-                        Some(TextEdit::new(
-                            Range::new(start_position, end_position),
-                            format!("${}", new_name),
-                        ))
+                        TextEdit::new(Range::new(start_position, end_position), new_name.clone())
                     })
                     .collect::<Vec<_>>();
                 let mut map = HashMap::new();
@@ -430,148 +1031,1126 @@ impl LanguageServer for Backend {
                 None
             }
         }();
-        Ok(workspace_edit)
+        Ok(workspace_edit.map(|edit| self.client_workspace_edit(edit)))
     }
 
     async fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
         let uri = params.text_document_position.text_document.uri;
-        let position = params.text_document_position.position;
+        let position = self.char_position(&uri, params.text_document_position.position);
+        let col = position.character as usize;
+        // The files of the datasets are listed from the disk
+        let dataset_file = self
+            .document_map
+            .get(&uri.to_string())
+            .and_then(|rope| dataset_file_at(&rope.get_line(position.line as usize)?, col));
+        if let Some((start, typed)) = dataset_file {
+            let files = self.dataset_files_for(&uri).await;
+            let completions =
+                get_completion_for_dataset_files(position.line, (start, &typed), col, &files);
+            let completions = self.client_completions(&uri, completions);
+            return Ok(Some(CompletionResponse::Array(completions)));
+        }
+        // Offer the variables of the settings, along with the ones used inside the workspace
+        let definitions = Variables::new(&self.settings_for(&uri).await.variables);
+        let (address_variables, port_variables) = match self.variables.read() {
+            Ok(variables) => (
+                variables.names(VariableKind::Address, &definitions),
+                variables.names(VariableKind::Port, &definitions),
+            ),
+            Err(_) => Default::default(),
+        };
+        let index = self.completion_index();
         let completions = || -> Option<Vec<CompletionItem>> {
             let rope = self.document_map.get(&uri.to_string())?;
             let line_text = rope.get_line(position.line as usize)?;
             let ast = self.ast_map.get(&uri.to_string())?;
-            let line = position.line as usize;
-            let offset = position.character as usize;
-            let completions =
-                get_completion(&ast, &line_text, line, offset, &self.address_variables, &self.port_variables, &self.keywords)?;
+            let completions = get_completion(
+                &ast,
+                &line_text,
+                col,
+                &address_variables,
+                &port_variables,
+                &self.keywords,
+                &index,
+            )?;
             Some(completions)
         }();
+        let completions = completions.map(|completions| self.client_completions(&uri, completions));
         Ok(completions.map(CompletionResponse::Array))
     }
 }
-#[derive(Debug, Deserialize, Serialize)]
+/// The section of the client settings, which belongs to the language server
+const SETTINGS_SECTION: &str = "meerkat";
+
+/// The virtual location of the settings, used when the client does not tell
+/// us the path of its settings file
+const SETTINGS_URI: &str = "meerkat:settings";
+
+/// The command, which switches the verification profile
+const SELECT_VERIFICATION_PROFILE: &str = "meerkat.selectVerificationProfile";
+
+/// The command, which renumbers the sids of a document
+const RENUMBER_SIDS: &str = "meerkat.renumberSids";
+
+/// The command, which inserts a copy of a signature
+const DUPLICATE_RULE: &str = "meerkat.duplicateRule";
+
+/// The command, which inserts the skeleton of a new signature
+const NEW_RULE: &str = "meerkat.newRule";
+
+/// The state of the language server, shown by the client (e.g. in the status bar)
+enum StatusNotification {}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StatusParams {
+    verification_enabled: bool,
+    /// The name of the active verification profile
+    active_profile: String,
+}
+
+impl notification::Notification for StatusNotification {
+    type Params = StatusParams;
+    const METHOD: &'static str = "meerkat/status";
+}
 
-enum CustomNotification {}
 struct TextDocumentItem {
     uri: Url,
     text: String,
     version: i32,
 }
+
+/// The AST of a document, along with its semantic tokens and syntax errors
+type ParsedDocument = (AST, Vec<ImCompleteSemanticToken>, Vec<SyntaxError>);
 impl Backend {
+    /// Create the language server
+    fn new(
+        client: Client,
+        keywords: HashMap<String, Keyword>,
+        suricata_version: Option<SuricataVersion>,
+        events: EventTable,
+        settings: ServerSettings,
+        command_line_settings: Value,
+    ) -> Backend {
+        Backend {
+            client,
+            ast_map: DashMap::new(),
+            document_map: DashMap::new(),
+            latest_documents: DashMap::new(),
+            document_versions: DashMap::new(),
+            semantic_token_map: DashMap::new(),
+            keywords,
+            suricata_version,
+            events,
+            variables: Arc::new(RwLock::new(VariableIndex::default())),
+            language_server_settings: ArcSwap::from_pointee(settings),
+            command_line_settings,
+            folder_settings: DashMap::new(),
+            supports_configuration: AtomicBool::new(false),
+            position_encoding: OnceLock::new(),
+            workspace_folders: Mutex::new(vec![]),
+            workspace_index: Arc::new(WorkspaceIndex::default()),
+            variable_usages: Arc::new(DashMap::new()),
+            completion_index: Arc::new(Mutex::new(None)),
+            dataset_files: DashMap::new(),
+            diagnostics: Arc::new(DiagnosticsStore::default()),
+        }
+    }
+
+    /// Check if no newer version of the document arrived (and it is still open)
+    fn is_latest_version(&self, uri: &str, version: i32) -> bool {
+        self.document_versions
+            .get(uri)
+            .is_some_and(|latest| *latest == version)
+    }
+
+    /// Drop the text, the AST, the diagnostics and the variables of a document
+    ///
+    /// The variables of the saved version are kept, if the document is a part
+    /// of the workspace.
+    fn forget_document(&self, uri: &str) {
+        self.document_map.remove(uri);
+        self.latest_documents.remove(uri);
+        self.ast_map.remove(uri);
+        self.semantic_token_map.remove(uri);
+        self.diagnostics.remove(uri);
+        if let Ok(mut variables) = self.variables.write() {
+            match self.workspace_index.files.get(uri) {
+                Some(indexed) => variables.update(uri, &indexed),
+                None => variables.remove(uri),
+            }
+        }
+        self.variable_usages.clear();
+        self.clear_completion_index();
+    }
+
+    /// Get the current settings of the language server
+    fn settings(&self) -> Arc<ServerSettings> {
+        self.language_server_settings.load_full()
+    }
+
+    /// Get how the client counts the columns
+    fn position_encoding(&self) -> PositionEncoding {
+        self.position_encoding.get().copied().unwrap_or_default()
+    }
+
+    /// Convert a position of the client inside a document, to count characters
+    fn char_position(&self, uri: &Url, position: Position) -> Position {
+        match self.document_map.get(uri.as_str()) {
+            Some(rope) => self.position_encoding().char_position(&rope, position),
+            None => position,
+        }
+    }
+
+    /// Convert a range inside a document, which counts characters, for the client
+    ///
+    /// The ranges inside documents, which are not open, are kept as they are.
+    fn client_range(&self, uri: &Url, range: Range) -> Range {
+        match self.document_map.get(uri.as_str()) {
+            Some(rope) => self.position_encoding().client_range(&rope, range),
+            None => range,
+        }
+    }
+
+    /// Convert the ranges of text edits inside a document for the client
+    fn client_text_edits(&self, uri: &Url, edits: Vec<TextEdit>) -> Vec<TextEdit> {
+        edits
+            .into_iter()
+            .map(|edit| TextEdit {
+                range: self.client_range(uri, edit.range),
+                ..edit
+            })
+            .collect()
+    }
+
+    /// Convert the ranges of the edits inside every document for the client
+    fn client_workspace_edit(&self, edit: WorkspaceEdit) -> WorkspaceEdit {
+        WorkspaceEdit {
+            changes: edit.changes.map(|changes| {
+                changes
+                    .into_iter()
+                    .map(|(uri, edits)| {
+                        let edits = self.client_text_edits(&uri, edits);
+                        (uri, edits)
+                    })
+                    .collect()
+            }),
+            ..edit
+        }
+    }
+
+    /// Convert the ranges of the completions inside a document for the client
+    fn client_completions(&self, uri: &Url, items: Vec<CompletionItem>) -> Vec<CompletionItem> {
+        items
+            .into_iter()
+            .map(|item| CompletionItem {
+                text_edit: item.text_edit.map(|text_edit| match text_edit {
+                    CompletionTextEdit::Edit(edit) => CompletionTextEdit::Edit(TextEdit {
+                        range: self.client_range(uri, edit.range),
+                        ..edit
+                    }),
+                    CompletionTextEdit::InsertAndReplace(edit) => {
+                        CompletionTextEdit::InsertAndReplace(InsertReplaceEdit {
+                            insert: self.client_range(uri, edit.insert),
+                            replace: self.client_range(uri, edit.replace),
+                            ..edit
+                        })
+                    }
+                }),
+                ..item
+            })
+            .collect()
+    }
+
+    /// Validate all open documents again (e.g. after the settings changed)
+    async fn revalidate_documents(&self) {
+        let documents = self
+            .latest_documents
+            .iter()
+            .map(|document| {
+                let version = self
+                    .document_versions
+                    .get(document.key())
+                    .map(|version| *version)
+                    .unwrap_or_default();
+                (
+                    document.key().clone(),
+                    document.value().to_string(),
+                    version,
+                )
+            })
+            .collect::<Vec<_>>();
+        for (uri, text, version) in documents {
+            let Ok(uri) = Url::parse(&uri) else {
+                continue;
+            };
+            self.on_change(TextDocumentItem { uri, text, version })
+                .await;
+        }
+    }
+
+    /// Replace the settings, warning the user about the invalid ones
+    async fn update_settings(&self, parsed: ParsedSettings) {
+        parsed
+            .unknown_keys
+            .iter()
+            .for_each(|key| log::info!("ignoring the unknown setting {}", key));
+        if !parsed.errors.is_empty() {
+            let errors = parsed
+                .errors
+                .iter()
+                .map(|error| error.to_string())
+                .collect::<Vec<_>>()
+                .join("; ");
+            self.client
+                .show_message(
+                    MessageType::WARNING,
+                    format!("Invalid settings, using the defaults instead: {}", errors),
+                )
+                .await;
+        }
+        self.store_settings(parsed.settings);
+    }
+
+    fn store_settings(&self, settings: ServerSettings) {
+        self.language_server_settings.store(Arc::new(settings));
+        // The settings of the folders are merged over the global ones
+        self.folder_settings.clear();
+        self.dataset_files.clear();
+    }
+
+    /// Get the files inside the directories of the datasets, for a document
+    ///
+    /// Relative directories are relative to the directory of the document.
+    /// Every directory is listed once and cached.
+    async fn dataset_files_for(&self, uri: &Url) -> Vec<String> {
+        let settings = self.settings_for(uri).await;
+        let document_directory = uri
+            .to_file_path()
+            .ok()
+            .and_then(|path| path.parent().map(Path::to_path_buf));
+        let mut files = BTreeSet::new();
+        for directory in &settings.datasets.directories {
+            let directory = match &document_directory {
+                Some(document_directory) => document_directory.join(directory),
+                None => PathBuf::from(directory),
+            };
+            let listed = match self.dataset_files.get(&directory) {
+                Some(listed) => listed.clone(),
+                None => {
+                    let root = directory.clone();
+                    let listed = tokio::task::spawn_blocking(move || find_dataset_files(&root))
+                        .await
+                        .unwrap_or_default();
+                    let listed = Arc::new(listed);
+                    self.dataset_files.insert(directory, listed.clone());
+                    listed
+                }
+            };
+            files.extend(listed.iter().cloned());
+        }
+        files.into_iter().collect()
+    }
+
+    /// Get the settings for a document, resolved for its workspace folder
+    ///
+    /// The settings of the folder are requested from the client and cached
+    /// until the settings or the workspace folders change.
+    async fn settings_for(&self, uri: &Url) -> Arc<ServerSettings> {
+        let global = self.settings();
+        if !self.supports_configuration.load(Ordering::Relaxed) {
+            return global;
+        }
+        let Some(folder) = self.folder_of(uri) else {
+            return global;
+        };
+        if let Some(settings) = self.folder_settings.get(&folder) {
+            return settings.clone();
+        }
+        let Ok(folder_uri) = Url::from_directory_path(&folder) else {
+            return global;
+        };
+        let item = ConfigurationItem {
+            scope_uri: Some(folder_uri),
+            section: Some(SETTINGS_SECTION.to_string()),
+        };
+        let settings = match self.client.configuration(vec![item]).await {
+            Ok(values) => {
+                // The flags take precedence over the settings of the folder too
+                let mut overrides = values.into_iter().next().unwrap_or_default();
+                merge_json(&mut overrides, &self.command_line_settings);
+                let parsed = global.with_overrides(&overrides);
+                parsed.errors.iter().for_each(|error| {
+                    log::warn!("invalid settings for {}: {}", folder.display(), error)
+                });
+                Arc::new(parsed.settings)
+            }
+            Err(err) => {
+                log::warn!(
+                    "could not fetch the settings for {}: {}",
+                    folder.display(),
+                    err
+                );
+                global
+            }
+        };
+        self.folder_settings.insert(folder, settings.clone());
+        settings
+    }
+
+    /// Get the (innermost) workspace folder, which contains the document
+    fn folder_of(&self, uri: &Url) -> Option<PathBuf> {
+        let path = uri.to_file_path().ok()?;
+        let workspace_folders = self.workspace_folders.lock().ok()?;
+        workspace_folders
+            .iter()
+            .filter(|folder| path.starts_with(folder))
+            .max_by_key(|folder| folder.components().count())
+            .cloned()
+    }
+
+    /// Switch to another verification profile, asking the user if no name is given
+    async fn select_verification_profile(&self, name: Option<String>) -> Result<Option<Value>> {
+        let mut settings = self.settings().as_ref().clone();
+        let mut names = settings
+            .verification
+            .profiles
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        names.sort();
+        names.insert(0, VerificationSettings::DEFAULT_PROFILE.to_string());
+
+        let name = match name {
+            Some(name) => name,
+            None => {
+                let actions = names
+                    .iter()
+                    .map(|name| MessageActionItem {
+                        title: name.clone(),
+                        properties: HashMap::new(),
+                    })
+                    .collect();
+                let selected = self
+                    .client
+                    .show_message_request(
+                        MessageType::INFO,
+                        "Select the verification profile",
+                        Some(actions),
+                    )
+                    .await?;
+                match selected {
+                    Some(action) => action.title,
+                    None => return Ok(None),
+                }
+            }
+        };
+        if !names.contains(&name) {
+            return Err(Error::invalid_params(format!(
+                "there is no verification profile named {}",
+                name
+            )));
+        }
+
+        log::info!("using the verification profile {}", name);
+        settings.verification.active_profile =
+            Some(name.clone()).filter(|name| name != VerificationSettings::DEFAULT_PROFILE);
+        self.store_settings(settings);
+        self.send_status().await;
+        self.revalidate_documents().await;
+        Ok(Some(Value::String(name)))
+    }
+
+    /// Give the signatures of a document consecutive sids
+    ///
+    /// The edit is applied by the client and returned. Nothing is changed, if
+    /// a new sid is used by another file of the workspace.
+    async fn renumber_sids(&self, uri: Url, options: RenumberOptions) -> Result<Option<Value>> {
+        // The sids of the other files are collected first, so the document is
+        // not borrowed while the workspace is read
+        let used = self.with_workspace_files(None, |files| {
+            let other_files = files
+                .iter()
+                .filter(|(file, _)| *file != uri.as_str())
+                .cloned()
+                .collect::<Vec<_>>();
+            used_sids(&other_files)
+        });
+        let renumbered = || -> Option<_> {
+            let ast = self.ast_map.get(&uri.to_string())?;
+            let rope = self.document_map.get(&uri.to_string())?;
+            let line_text = |line: u32| Some(line_content(rope.get_line(line as usize)?));
+            let edits = renumber_sids(&ast, line_text, options, &used);
+            Some(edits.map(|edits| to_text_edits(&rope, edits)))
+        }();
+        let edits = match renumbered {
+            Some(Ok(edits)) => edits,
+            Some(Err(err)) => {
+                let message = format!("Could not renumber the sids: {}", err);
+                self.client.show_message(MessageType::ERROR, &message).await;
+                return Err(Error::invalid_params(message));
+            }
+            None => return Err(Error::invalid_params(format!("{} is not open", uri))),
+        };
+        let edit = self.client_workspace_edit(WorkspaceEdit::new(HashMap::from([(uri, edits)])));
+        let applied = self.client.apply_edit(edit.clone()).await?;
+        if !applied.applied {
+            log::warn!(
+                "the client did not renumber the sids: {}",
+                applied.failure_reason.unwrap_or_default()
+            );
+        }
+        Ok(serde_json::to_value(edit).ok())
+    }
+
+    /// Insert a copy of a signature on the next line, with the next free sid
+    ///
+    /// The copy is formatted with the settings of the document. Returns the
+    /// position of the copy, so the client can move the cursor there.
+    async fn duplicate_rule(&self, uri: Url, line: u32) -> Result<Option<Value>> {
+        let settings = self.settings_for(&uri).await;
+        let style = FormatStyle::from(&settings.formatting);
+        let used = self.with_workspace_files(None, used_sids);
+        let duplicate = || -> Option<_> {
+            let ast = self.ast_map.get(&uri.to_string())?;
+            let rope = self.document_map.get(&uri.to_string())?;
+            let (rule, _) = ast.rules.get(&line)?;
+            let sid = rule.sid().map_or(0, |(sid, _)| sid);
+            let duplicate = rule.duplicate(next_free_sid(sid, &used)?);
+            let text = rope.get_line(line as usize)?;
+            // The last line may not end with a line ending
+            let edit = match line_ending(text) {
+                "" => TextEdit::new(
+                    Range::new(
+                        Position::new(line, text.len_chars() as u32),
+                        Position::new(line, text.len_chars() as u32),
+                    ),
+                    format!("\n{}", style.format_rule(&duplicate)),
+                ),
+                line_ending => TextEdit::new(
+                    Range::new(Position::new(line + 1, 0), Position::new(line + 1, 0)),
+                    format!("{}{}", style.format_rule(&duplicate), line_ending),
+                ),
+            };
+            Some(edit)
+        }();
+        let Some(edit) = duplicate else {
+            return Err(Error::invalid_params(format!(
+                "there is no signature on line {} of {}",
+                line + 1,
+                uri
+            )));
+        };
+        let edit = WorkspaceEdit::new(HashMap::from([(uri, vec![edit])]));
+        let edit = self.client_workspace_edit(edit);
+        let applied = self.client.apply_edit(edit).await?;
+        if !applied.applied {
+            log::warn!(
+                "the client did not duplicate the signature: {}",
+                applied.failure_reason.unwrap_or_default()
+            );
+            return Ok(None);
+        }
+        Ok(serde_json::to_value(Position::new(line + 1, 0)).ok())
+    }
+
+    /// Insert the skeleton of a new signature at the end of a document
+    ///
+    /// The skeleton gets the sid after the largest sid of the workspace and is
+    /// formatted with the settings of the document. Returns the applied edit.
+    async fn new_rule(&self, uri: Url, options: &SkeletonOptions) -> Result<Option<Value>> {
+        let settings = self.settings_for(&uri).await;
+        let style = FormatStyle::from(&settings.formatting);
+        let used = self.with_workspace_files(None, used_sids);
+        let sid = used
+            .keys()
+            .max()
+            .map_or(Some(1), |sid| next_free_sid(*sid, &used))
+            .ok_or_else(|| Error::invalid_params("there is no free sid left"))?;
+        let rule = rule_skeleton(options, sid).map_err(Error::invalid_params)?;
+        let text = style.format_rule(&rule);
+        let Some(rope) = self
+            .latest_documents
+            .get(&uri.to_string())
+            .map(|rope| rope.clone())
+        else {
+            return Err(Error::invalid_params(format!("{} is not open", uri)));
+        };
+        // Insert after the last line, which may not end with a line ending
+        let last_line = rope.len_lines().saturating_sub(1);
+        let last_text = rope.line(last_line);
+        let end = Position::new(last_line as u32, last_text.len_chars() as u32);
+        let new_text = match (rope.len_chars(), line_ending(rope.line(0))) {
+            (0, _) => format!("{}\n", text),
+            (_, line_ending) if last_text.len_chars() == 0 => format!("{}{}", text, line_ending),
+            (_, "") => format!("\n{}\n", text),
+            (_, line_ending) => format!("{}{}{}", line_ending, text, line_ending),
+        };
+        let edit = TextEdit::new(Range::new(end, end), new_text);
+        let edit = WorkspaceEdit::new(HashMap::from([(uri, vec![edit])]));
+        let edit = self.client_workspace_edit(edit);
+        let applied = self.client.apply_edit(edit.clone()).await?;
+        if !applied.applied {
+            log::warn!(
+                "the client did not insert the signature: {}",
+                applied.failure_reason.unwrap_or_default()
+            );
+        }
+        Ok(serde_json::to_value(edit).ok())
+    }
+
+    /// Tell the client about the state of the language server
+    async fn send_status(&self) {
+        let settings = self.settings();
+        let (active_profile, _) = settings.verification.active();
+        self.client
+            .send_notification::<StatusNotification>(StatusParams {
+                verification_enabled: settings.verification.enabled,
+                active_profile: active_profile.to_string(),
+            })
+            .await;
+    }
+
+    /// Run a function over the ASTs of all files inside the workspace
+    ///
+    /// The open documents take precedence over their indexed version, and the
+    /// given document over both of them.
+    fn with_workspace_files<T>(
+        &self,
+        document: Option<(&str, &AST)>,
+        f: impl FnOnce(&[(String, &AST)]) -> T,
+    ) -> T {
+        let uri = document.map(|(uri, _)| uri);
+        let open_documents = self
+            .ast_map
+            .iter()
+            .filter(|open_document| Some(open_document.key().as_str()) != uri)
+            .collect::<Vec<_>>();
+        let open_uris = open_documents
+            .iter()
+            .map(|open_document| open_document.key().as_str())
+            .collect::<HashSet<_>>();
+        let indexed_files = self
+            .workspace_index
+            .files
+            .iter()
+            .filter(|file| {
+                Some(file.key().as_str()) != uri && !open_uris.contains(file.key().as_str())
+            })
+            .collect::<Vec<_>>();
+        let files = open_documents
+            .iter()
+            .chain(indexed_files.iter())
+            .map(|file| (file.key().clone(), file.value()))
+            .chain(document.map(|(uri, ast)| (uri.to_string(), ast)))
+            .collect::<Vec<_>>();
+        f(&files)
+    }
+
+    /// Find the sids and msgs of the document, which are also used elsewhere
+    /// inside the workspace (including the document itself), along with the
+    /// flowbits, which are not set by any file
+    fn duplicates(&self, uri: &str, ast: &AST, context: &ValidationContext) -> Vec<Diagnostic> {
+        self.with_workspace_files(Some((uri, ast)), |files| {
+            let mut violations = validate_duplicate_sids(files, context);
+            violations.extend(validate_duplicate_msgs(files, context));
+            violations.extend(validate_unset_flowbits(files, context));
+            violations
+        })
+        .into_iter()
+        .filter(|(file, _, _)| file == uri)
+        .map(|(_, line, violation)| to_diagnostic(&violation, line))
+        .collect()
+    }
+
+    /// Get the values used inside the workspace, building the index if needed
+    fn completion_index(&self) -> Arc<CompletionIndex> {
+        if let Some(index) = self
+            .completion_index
+            .lock()
+            .ok()
+            .and_then(|index| index.clone())
+        {
+            return index;
+        }
+        let index = Arc::new(self.with_workspace_files(None, CompletionIndex::new));
+        if let Ok(mut completion_index) = self.completion_index.lock() {
+            *completion_index = Some(index.clone());
+        }
+        index
+    }
+
+    fn clear_completion_index(&self) {
+        if let Ok(mut completion_index) = self.completion_index.lock() {
+            *completion_index = None;
+        }
+    }
+
+    /// Get the usages of a variable inside the workspace
+    ///
+    /// The usages are cached, until a file of the workspace changes.
+    fn variable_usages(&self, kind: VariableKind, name: &str) -> VariableUsages {
+        let key = (kind, name.to_string());
+        if let Some(usages) = self.variable_usages.get(&key) {
+            return usages.clone();
+        }
+        let usages =
+            self.with_workspace_files(None, |files| VariableUsages::find(files, kind, name));
+        self.variable_usages.insert(key, usages.clone());
+        usages
+    }
+
     async fn on_change(&self, params: TextDocumentItem) {
+        let uri = params.uri.to_string();
+        // The text of an older version (e.g. while revalidating) is outdated
+        if self
+            .document_versions
+            .get(&uri)
+            .is_some_and(|latest| *latest > params.version)
+        {
+            return;
+        }
+        // The language id is not checked, but binary files are not rule files
+        if is_binary(&params.text) {
+            log::warn!("ignoring {}, since it looks like a binary file", uri);
+            self.forget_document(&uri);
+            self.client
+                .publish_diagnostics(params.uri, vec![], Some(params.version))
+                .await;
+            return;
+        }
         // Get the rope (text) for the file
         let rope = ropey::Rope::from_str(&params.text);
-        // Run suricata already in the background
+        self.latest_documents.insert(uri.clone(), rope.clone());
+        self.document_versions.insert(uri.clone(), params.version);
+        let settings = self.settings_for(&params.uri).await;
+        // Parse the signatures on the blocking thread pool, so other requests
+        // can be answered in the meantime. Until the parsing is done, they are
+        // answered from the last version, which was parsed completely, and the
+        // parsing is abandoned once a newer version arrives.
+        let is_outdated = || !self.is_latest_version(&uri, params.version);
+        let parse_process = parse_document(rope.clone(), is_outdated);
+        let parsed = async {
+            let (ast, semantic_tokens) = parse_process.await?;
+            // The syntax errors are also found on the blocking thread pool
+            let syntax_rope = rope.clone();
+            let syntax_process = tokio::task::spawn_blocking(move || {
+                let syntax_errors = syntax_errors(&syntax_rope, &ast);
+                (ast, semantic_tokens, syntax_errors)
+            });
+            syntax_process.await.ok()
+        };
+        self.process_document(&params.uri, params.version, &rope, &settings, parsed)
+            .await
+    }
+
+    /// Publish the diagnostics of a document and store its AST, once it is parsed
+    async fn process_document(
+        &self,
+        document: &Url,
+        version: i32,
+        rope: &Rope,
+        settings: &ServerSettings,
+        parsed: impl Future<Output = Option<ParsedDocument>>,
+    ) {
+        let uri = document.to_string();
+        // Run suricata in the background
         let suricata_process = async {
+            // Large files are not verified on every change, since it takes too long
+            let verify =
+                settings.verification.enabled && rope.len_lines() <= settings.large_file.max_lines;
+            // Wait for the user to stop typing, only the latest version is verified
+            if verify && settings.diagnostics.debounce_ms > 0 {
+                let debounce = Duration::from_millis(settings.diagnostics.debounce_ms);
+                tokio::time::sleep(debounce).await;
+                if !self.is_latest_version(&uri, version) {
+                    return;
+                }
+            }
             // Get the diagnostics from Suricata
-            let diagnostics = match verify_rule(&rope, &self.language_server_settings).await {
-                Ok(diagnostics) => {
-                    diagnostics
-                },
-                Err(_) => {
+            let diagnostics = match verify {
+                true => verify_rule(rope, settings).await.unwrap_or_else(|err| {
+                    log::warn!("could not verify {} with Suricata: {}", uri, err);
                     vec![]
-                },
+                }),
+                false => vec![],
             };
-            // Publish the diagnostics
-            self.client
-                .publish_diagnostics(params.uri.clone(), diagnostics, Some(params.version))
+            let source = DiagnosticSource::Suricata;
+            self.publish_diagnostics(document, version, source, diagnostics, settings)
                 .await;
         };
-        // let diagnostics = vec![];
-
-        self.document_map
-            .insert(params.uri.to_string(), rope.clone());
-        // Create an empty vector for the semantic tokens
-        let mut semantic_tokens = vec![];
-        // Create an AST for the signatures from the file
-        let mut ast = AST {
-            rules: HashMap::with_capacity(rope.len_lines()),
-        };
-        // Go trough each line and parse the signature
-        rope.lines().enumerate().for_each(|(line_num, line)| {
-            // Return if the line is empty
-            if line_length_padded(line) <= 1 {
+        // The lints are published without waiting for Suricata
+        let lints_process = async {
+            let Some((ast, semantic_tokens, syntax_errors)) = parsed.await else {
                 return;
-            }
-            // If the line starts with a #, treat is as a comment
-            if line.to_string().trim().starts_with("#") {
-                let line_offset = rope.line_to_char(line_num);
-                let line_length = line.len_chars();
-                semantic_tokens.push(ImCompleteSemanticToken {
-                    start: line_offset,
-                    length: line_length,
-                    token_type: LEGEND_TYPE
-                        .iter()
-                        .position(|item| item == &SemanticTokenType::COMMENT)
-                        .unwrap(),
-                });
+            };
+            // Discard the result if a newer version of the document arrived meanwhile
+            if !self.is_latest_version(&uri, version) {
                 return;
             }
-            // Parse the signature
-            let (rule, _) = Rule::parser().parse_recovery(line.to_string());
-            if let Some(rule) = rule {
-                let line_offset = rope.line_to_char(line_num);
-                semantic_token_from_rule(&rule, &line_offset, &mut semantic_tokens);
+            let syntax_errors = syntax_errors
+                .iter()
+                .map(SyntaxError::to_diagnostic)
+                .collect();
+            let diagnostics = self.lint_diagnostics(document, &ast, settings);
+            // Store the text, the AST and the semantic tokens in the server, before
+            // the diagnostics are published, so the next change can update them
+            self.document_map.insert(uri.clone(), rope.clone());
+            if let Ok(mut variables) = self.variables.write() {
+                variables.update(&uri, &ast);
+            }
+            self.ast_map.insert(uri.clone(), ast);
+            self.semantic_token_map.insert(uri.clone(), semantic_tokens);
+            self.variable_usages.clear();
+            self.clear_completion_index();
+            let source = DiagnosticSource::Syntax;
+            self.publish_diagnostics(document, version, source, syntax_errors, settings)
+                .await;
+            let source = DiagnosticSource::Lints;
+            self.publish_diagnostics(document, version, source, diagnostics, settings)
+                .await;
+        };
+        tokio::join!(suricata_process, lints_process);
+    }
 
-                ast.rules.insert(line_num as u32, rule);
+    /// Run the lints of a document, along with the lints of the workspace
+    fn lint_diagnostics(&self, uri: &Url, ast: &AST, settings: &ServerSettings) -> Vec<Diagnostic> {
+        // The variables of the suricata.yaml used for the verification are defined as well
+        let mut variables = Variables::new(&settings.variables);
+        let (_, profile) = settings.verification.active();
+        if let Some(config_file) = &profile.config_file {
+            match std::fs::read_to_string(config_file) {
+                Ok(text) => {
+                    variables = variables.with_config_variables(parse_config_variables(&text))
+                }
+                Err(err) => log::warn!("could not read {}: {}", config_file, err),
+            }
+        }
+        let mut context = ValidationContext::new(&settings.lints)
+            .with_variables(variables)
+            .with_suricata_version(self.suricata_version)
+            .with_events(self.events.clone())
+            .with_keywords(&self.keywords);
+        if let Some(path) = &settings.lints.threshold_config {
+            match ThresholdConfig::from_file(Path::new(path)) {
+                Ok((threshold_config, _)) => {
+                    context = context.with_threshold_config(threshold_config)
+                }
+                Err(err) => log::warn!("could not load {}: {}", path, err),
+            }
+        }
+        let mut diagnostics = validate_document(ast, &context)
+            .into_iter()
+            .map(|(line, violation)| to_diagnostic(&violation.in_file(uri.as_str()), line))
+            .collect::<Vec<_>>();
+        if let Ok(path) = uri.to_file_path() {
+            // The lua scripts are relative to the rule file
+            diagnostics.extend(
+                validate_lua_scripts(&path, ast, &context)
+                    .into_iter()
+                    .map(|(line, violation)| to_diagnostic(&violation, line)),
+            );
+            // The globs of the sid ranges are relative to the workspace folder
+            let path = match self.folder_of(uri) {
+                Some(folder) => path.strip_prefix(folder).unwrap_or(&path).to_path_buf(),
+                None => path,
             };
-        });
-        // Store the AST and the semantic tokens in the server
-        self.ast_map.insert(params.uri.to_string(), ast);
-        self.semantic_token_map
-            .insert(params.uri.to_string(), semantic_tokens);
-        suricata_process.await;
+            diagnostics.extend(
+                validate_sid_range(&path, ast, &context)
+                    .into_iter()
+                    .map(|(line, violation)| to_diagnostic(&violation, line)),
+            );
+        }
+        diagnostics.extend(self.duplicates(uri.as_str(), ast, &context));
+        diagnostics
+    }
+
+    /// Publish the diagnostics of a source, along with the ones of the other sources
+    ///
+    /// Diagnostics computed for an outdated version are dropped. Publications
+    /// in quick succession are combined into one.
+    async fn publish_diagnostics(
+        &self,
+        uri: &Url,
+        version: i32,
+        source: DiagnosticSource,
+        diagnostics: Vec<Diagnostic>,
+        settings: &ServerSettings,
+    ) {
+        // The document was closed or changed in the meantime
+        if !self.is_latest_version(uri.as_str(), version) {
+            return;
+        }
+        let store = &self.diagnostics;
+        let diagnostics = diagnostics
+            .into_iter()
+            .map(|diagnostic| Diagnostic {
+                range: self.client_range(uri, diagnostic.range),
+                ..diagnostic
+            })
+            .collect();
+        if !store.update(
+            uri.as_str(),
+            version,
+            source,
+            diagnostics,
+            &settings.diagnostics,
+        ) {
+            return;
+        }
+        match store.schedule(uri.as_str(), Instant::now()) {
+            Publication::Now => publish_stored_diagnostics(&self.client, store, uri.clone()).await,
+            Publication::Later(delay) => {
+                let client = self.client.clone();
+                let store = store.clone();
+                let uri = uri.clone();
+                tokio::spawn(async move {
+                    tokio::time::sleep(delay).await;
+                    store.published(uri.as_str(), Instant::now());
+                    publish_stored_diagnostics(&client, &store, uri).await;
+                });
+            }
+            Publication::Scheduled => (),
+        }
+    }
+}
+
+/// Publish all stored diagnostics of a document
+async fn publish_stored_diagnostics(client: &Client, store: &DiagnosticsStore, uri: Url) {
+    if let Some((version, diagnostics)) = store.diagnostics(uri.as_str()) {
+        client
+            .publish_diagnostics(uri, diagnostics, Some(version))
+            .await;
     }
 }
 
+/// Language server and command line tools for Suricata signatures
 #[derive(CP, Debug)]
-#[command(version, about, long_about = None)]
+#[command(version, long_version = LONG_VERSION, about, long_about = None)]
 struct Args {
     /// Absolute path to the Suricata config file
     #[arg(short, long)]
     suricata_config: Option<String>,
+    /// Path to the settings file, used when the client does not provide any settings
+    #[arg(long)]
+    config: Option<PathBuf>,
+    /// The most verbose level of the logs (off, error, warn, info, debug, trace)
+    #[arg(long, default_value_t = LevelFilter::Info)]
+    log_level: LevelFilter,
+    /// Write the logs to a file (by default, the language server sends them to the client)
+    #[arg(long)]
+    log_file: Option<PathBuf>,
+    /// Run a command line tool instead of the language server
+    #[command(subcommand)]
+    command: Option<Command>,
 }
 
+// The version, along with the Suricata version of the bundled keywords
+include!(concat!(env!("OUT_DIR"), "/long_version.rs"));
+
 #[tokio::main]
-async fn main() {
-    env_logger::init();
+async fn main() -> ExitCode {
     let args = Args::parse();
 
+    // The command line tools log to stderr, the language server uses stdio for
+    // the communication, so its logs are sent to the client instead
+    let log_target = match (&args.log_file, &args.command) {
+        (Some(log_file), _) => LogTarget::File(log_file),
+        (None, Some(_)) => LogTarget::Stderr,
+        (None, None) => LogTarget::Client,
+    };
+    let client_logs = match logging::init(args.log_level, log_target) {
+        Ok(client_logs) => client_logs,
+        Err(err) => {
+            eprintln!("error: could not open the log file: {}", err);
+            return ExitCode::from(EXIT_IO_ERROR);
+        }
+    };
+
+    if let Some(command) = args.command {
+        return command.run().await;
+    }
+
+    // Load the settings file, the flags take precedence
+    let command_line_settings = command_line_settings(args.suricata_config.as_deref());
+    let mut server_settings = match &args.config {
+        Some(config) => match load_settings_file(config) {
+            Ok(settings) => settings,
+            Err(err) => {
+                eprintln!("error: could not load {}: {}", config.display(), err);
+                return ExitCode::from(EXIT_IO_ERROR);
+            }
+        },
+        None => ServerSettings::default(),
+    };
+    if let Some(suricata_config) = args.suricata_config {
+        server_settings.verification.profile.config_file = Some(suricata_config);
+    }
+
     let stdin = tokio::io::stdin();
     let stdout = tokio::io::stdout();
     let keywords = match get_keywords().await {
-        Ok(keywords) => {
-            keywords
-        },
-        Err(_) => {
-            HashMap::new()
-        },
+        Ok(keywords) => keywords,
+        Err(err) => {
+            log::warn!(
+                "could not fetch the keywords from Suricata, using the bundled ones: {}",
+                err
+            );
+            bundled_keywords()
+        }
+    };
+
+    let suricata_version = match get_suricata_version(&server_settings).await {
+        Ok(version) => Some(version),
+        Err(err) => {
+            log::warn!("could not detect the version of Suricata: {}", err);
+            None
+        }
     };
 
-    let server_settings = LanguageServerSettings{
-        suricata_config_file: args.suricata_config
+    let events = match get_events(&server_settings).await {
+        Ok(events) => events,
+        Err(err) => {
+            log::warn!("could not fetch the events from Suricata: {}", err);
+            EventTable::default()
+        }
     };
 
-    let (service, socket) = LspService::build(|client| Backend {
-        client,
-        ast_map: DashMap::new(),
-        document_map: DashMap::new(),
-        semantic_token_map: DashMap::new(),
-        keywords: keywords,
-        port_variables: HashSet::new(),
-        address_variables: HashSet::new(),
-        language_server_settings: server_settings
+    let (service, socket) = LspService::build(|client| {
+        if let Some(client_logs) = client_logs {
+            tokio::spawn(logging::forward_to_client(client.clone(), client_logs));
+        }
+        Backend::new(
+            client,
+            keywords,
+            suricata_version,
+            events,
+            server_settings,
+            command_line_settings,
+        )
     })
     .finish();
     Server::new(stdin, stdout, socket).serve(service).await;
+    ExitCode::SUCCESS
 }
 
-fn line_length_padded(line: RopeSlice) -> u32 {
-    let mut ret = 0;
-    line.chars().for_each(|c| {
-        if !c.is_whitespace() {
-            ret += 1;
+/// Get the settings set by the flags, which override all other settings
+fn command_line_settings(suricata_config: Option<&str>) -> Value {
+    match suricata_config {
+        Some(suricata_config) => {
+            serde_json::json!({"verification": {"profile": {"configFile": suricata_config}}})
         }
-    });
-    ret
+        None => Value::Null,
+    }
+}
+
+/// Load the settings file given with `--config`
+///
+/// The settings, which could not be read, are logged and keep their defaults.
+fn load_settings_file(
+    config: &Path,
+) -> std::result::Result<ServerSettings, Box<dyn std::error::Error>> {
+    let parsed = ServerSettings::from_file(config)?;
+    parsed
+        .errors
+        .iter()
+        .for_each(|error| log::warn!("{}: {}", config.display(), error));
+    Ok(parsed.settings)
+}
+
+/// Read the settings of the client, with the settings of the flags applied over them
+fn client_settings(mut options: Value, command_line_settings: &Value) -> ParsedSettings {
+    merge_json(&mut options, command_line_settings);
+    ServerSettings::parse(&options)
+}
+
+/// Convert a violation into a diagnostic, which points to the related locations
+/// (e.g. the entry of threshold.config)
+fn to_diagnostic(violation: &Violation, line: u32) -> Diagnostic {
+    let mut diagnostic = violation.to_diagnostic(line);
+    let related_information = violation
+        .related
+        .iter()
+        .filter_map(|related| {
+            // Workspace lints point to URIs, the others to paths
+            let uri = match Url::parse(&related.file) {
+                Ok(uri) if uri.scheme() == "file" => uri,
+                _ => Url::from_file_path(&related.file).ok()?,
+            };
+            let range = Range::new(
+                Position::new(related.line, related.span.start as u32),
+                Position::new(related.line, related.span.end as u32),
+            );
+            Some(DiagnosticRelatedInformation {
+                location: Location::new(uri, range),
+                message: related.message.clone(),
+            })
+        })
+        .collect::<Vec<_>>();
+    if !related_information.is_empty() {
+        diagnostic.related_information = Some(related_information);
+    }
+    diagnostic
+}
+
+/// Convert the formatted lines into edits, which replace the whole line
+/// (without the line ending)
+fn to_text_edits(rope: &Rope, edits: Vec<LineEdit>) -> Vec<TextEdit> {
+    edits
+        .into_iter()
+        .filter_map(|edit| {
+            let line = rope.get_line(edit.line as usize)?;
+            let line_length = line_content(line).chars().count() as u32;
+            Some(TextEdit {
+                range: Range::new(
+                    Position::new(edit.line, 0),
+                    Position::new(edit.line, line_length),
+                ),
+                new_text: edit.new_text,
+            })
+        })
+        .collect()
 }
+
+/// Apply an incremental change to the text of a document
+///
+/// Returns the lines of the changed text, which contain the change, along with
+/// the number of lines and characters the change added (negative if removed).
+/// None is returned, if the range is outside of the document.
+fn apply_change(
+    rope: &mut Rope,
+    range: Range,
+    text: &str,
+    encoding: PositionEncoding,
+) -> Option<(std::ops::Range<usize>, (isize, isize))> {
+    let start = position_to_char(rope, range.start, encoding)?;
+    let end = position_to_char(rope, range.end, encoding)?;
+    if start > end {
+        return None;
+    }
+    let first_line = rope.char_to_line(start);
+    let removed_lines = rope.char_to_line(end) - first_line;
+    rope.remove(start..end);
+    rope.insert(start, text);
+    let inserted = text.chars().count();
+    let inserted_lines = rope.char_to_line(start + inserted) - first_line;
+    let moved_lines = inserted_lines as isize - removed_lines as isize;
+    let moved_chars = inserted as isize - (end - start) as isize;
+    Some((
+        first_line..first_line + inserted_lines + 1,
+        (moved_lines, moved_chars),
+    ))
+}
+
+/// Get the character index of a position of the client, a column after the
+/// end of the line points to the end of the line
+fn position_to_char(rope: &Rope, position: Position, encoding: PositionEncoding) -> Option<usize> {
+    let line_start = rope.try_line_to_char(position.line as usize).ok()?;
+    let column = rope
+        .get_line(position.line as usize)
+        .map_or(0, |line| encoding.to_char(line, position.character));
+    Some(line_start + column)
+}
+
+#[cfg(test)]
+mod tests;
@@ -5,17 +5,310 @@
 //!
 //! [chumsky]: https://docs.rs/chumsky/latest/chumsky/
 use chumsky::prelude::*;
+use chumsky::Stream;
+use ropey::{Rope, RopeSlice};
+use std::collections::HashMap;
+use std::panic;
 use std::net::Ipv4Addr;
 use std::net::Ipv6Addr;
 use std::{net::IpAddr::V4, net::IpAddr::V6, ops::Range};
 
+use crate::rule::action::Action;
 use crate::rule::header::Header;
 use crate::rule::header::NetworkAddress;
 use crate::rule::header::NetworkDirection;
 use crate::rule::header::NetworkPort;
+use crate::rule::header::Protocol;
 use crate::rule::options::OptionsVariable;
 use crate::rule::options::RuleOption;
-use crate::rule::{Rule, Span};
+use crate::rule::{Rule, Span, Spanned, AST};
+use crate::semantic_token::{semantic_token_from_rule, ImCompleteSemanticToken, LEGEND_TYPE};
+use lsp_types::SemanticTokenType;
+
+/// The number of lines a thread parses at least, smaller files are parsed
+/// on the calling thread, since starting the threads would take longer
+const LINES_PER_THREAD: usize = 2000;
+
+impl AST {
+    /// Parses every line of a rule file
+    ///
+    /// Returns the AST of the file, along with the semantic tokens for the
+    /// signatures and the comments inside it. This is CPU-heavy for large files,
+    /// so async callers should run it on the blocking thread pool.
+    ///
+    /// Large files are split into chunks of lines, which are parsed in parallel
+    /// and merged in the order of the lines, so the result is the same.
+    pub fn parse_rope(rope: &Rope) -> (AST, Vec<ImCompleteSemanticToken>) {
+        let lines = rope.len_lines();
+        // Threads are not available everywhere (e.g. wasm)
+        let threads = std::thread::available_parallelism()
+            .map_or(1, |threads| threads.get())
+            .min(lines / LINES_PER_THREAD)
+            .max(1);
+        if threads == 1 {
+            return AST::parse_lines(rope, 0..lines);
+        }
+        AST::parse_chunks(rope, threads)
+    }
+
+    /// Parses the lines of a rule file on the given number of threads
+    fn parse_chunks(rope: &Rope, threads: usize) -> (AST, Vec<ImCompleteSemanticToken>) {
+        let lines = rope.len_lines();
+        let chunk = lines.div_ceil(threads);
+        let parsed = std::thread::scope(|scope| {
+            let handles = (0..lines)
+                .step_by(chunk)
+                .map(|start| {
+                    let end = lines.min(start + chunk);
+                    scope.spawn(move || AST::parse_lines(rope, start..end))
+                })
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .map(|handle| {
+                    handle
+                        .join()
+                        .unwrap_or_else(|panic| panic::resume_unwind(panic))
+                })
+                .collect::<Vec<_>>()
+        });
+        // The semantic tokens of every chunk are sorted, so they stay sorted
+        let mut ast = AST {
+            rules: HashMap::with_capacity(lines),
+            disabled: HashMap::new(),
+            incomplete: HashMap::new(),
+        };
+        let mut semantic_tokens = vec![];
+        parsed.into_iter().for_each(|(chunk, tokens)| {
+            ast.rules.extend(chunk.rules);
+            ast.disabled.extend(chunk.disabled);
+            ast.incomplete.extend(chunk.incomplete);
+            semantic_tokens.extend(tokens);
+        });
+        (ast, semantic_tokens)
+    }
+
+    /// Parses the lines of a rule file, which are in the range
+    pub fn parse_lines(rope: &Rope, lines: Range<usize>) -> (AST, Vec<ImCompleteSemanticToken>) {
+        // Create an empty vector for the semantic tokens
+        let mut semantic_tokens = vec![];
+        // Create an AST for the signatures from the file
+        let mut ast = AST {
+            rules: HashMap::with_capacity(lines.len()),
+            disabled: HashMap::new(),
+            incomplete: HashMap::new(),
+        };
+        // Go trough each line and parse the signature
+        let first_line = lines.start;
+        rope.lines_at(first_line)
+            .take(lines.len())
+            .enumerate()
+            .for_each(|(index, line)| {
+                let line_num = first_line + index;
+                // Return if the line is empty
+                if line_length_padded(line) <= 1 {
+                    return;
+                }
+                // If the line starts with a #, treat is as a comment
+                if line.chars().find(|char| !char.is_whitespace()) == Some('#') {
+                    let line_offset = rope.line_to_char(line_num);
+                    // The line ending (\n or \r\n) is not a part of the comment
+                    let line_length = line
+                        .chars()
+                        .take_while(|char| !matches!(char, '\n' | '\r'))
+                        .count();
+                    semantic_tokens.push(ImCompleteSemanticToken {
+                        start: line_offset,
+                        length: line_length,
+                        token_type: LEGEND_TYPE
+                            .iter()
+                            .position(|item| item == &SemanticTokenType::COMMENT)
+                            .unwrap(),
+                    });
+                    // The comment might be a signature, which was commented out
+                    if let Some(rule) = parse_disabled(line) {
+                        ast.disabled.insert(line_num as u32, rule);
+                    }
+                    return;
+                }
+                // Parse the signature
+                let (rule, errors) = Rule::parse_slice(line);
+                if let Some(rule) = rule {
+                    let line_offset = rope.line_to_char(line_num);
+                    semantic_token_from_rule(&rule, &line_offset, &mut semantic_tokens);
+
+                    match errors.is_empty() {
+                        true => ast.rules.insert(line_num as u32, rule),
+                        false => ast.incomplete.insert(line_num as u32, rule),
+                    };
+                };
+            });
+        // Empty tokens highlight nothing, however at the end of a line they
+        // would start at the next line, which confuses the updates of a line
+        semantic_tokens.retain(|token| token.length > 0);
+        (ast, semantic_tokens)
+    }
+
+    /// Add the signatures of other lines of the same file (e.g. parsed separately)
+    pub fn merge(&mut self, other: AST) {
+        let AST {
+            rules,
+            disabled,
+            incomplete,
+        } = other;
+        self.rules.extend(rules);
+        self.disabled.extend(disabled);
+        self.incomplete.extend(incomplete);
+    }
+
+    /// Same as [AST::parse_rope], however the file is provided as a string
+    pub fn parse_str(text: &str) -> (AST, Vec<ImCompleteSemanticToken>) {
+        AST::parse_rope(&Rope::from_str(text))
+    }
+
+    /// Update the AST and the semantic tokens of a file after an edit
+    ///
+    /// Only the lines of the edited file in `lines` are parsed again, the
+    /// signatures and the semantic tokens after them are moved by the number of
+    /// lines and characters the edit added (or removed, when negative).
+    pub fn reparse_lines(
+        &mut self,
+        semantic_tokens: &mut Vec<ImCompleteSemanticToken>,
+        rope: &Rope,
+        lines: Range<usize>,
+        (added_lines, added_chars): (isize, isize),
+    ) {
+        let lines = lines.start..lines.end.min(rope.len_lines());
+        // The lines of the edit, before it was made
+        let edited_end = lines.end.saturating_add_signed(-added_lines) as u32;
+        let move_line = |line: u32| match line {
+            line if line < lines.start as u32 => Some(line),
+            line if line < edited_end => None,
+            line => Some(line.saturating_add_signed(added_lines as i32)),
+        };
+        for signatures in [&mut self.rules, &mut self.disabled, &mut self.incomplete] {
+            *signatures = std::mem::take(signatures)
+                .into_iter()
+                .filter_map(|(line, rule)| Some((move_line(line)?, rule)))
+                .collect();
+        }
+        // The semantic tokens are sorted, the ones of the edited lines are replaced
+        let start = rope.line_to_char(lines.start);
+        let end = match lines.end < rope.len_lines() {
+            true => rope.line_to_char(lines.end),
+            false => usize::MAX,
+        };
+        let edited_end = end.saturating_add_signed(-added_chars);
+        let (parsed, tokens) = AST::parse_lines(rope, lines);
+        let first = semantic_tokens.partition_point(|token| token.start < start);
+        let last = semantic_tokens.partition_point(|token| token.start < edited_end);
+        let moved = semantic_tokens.split_off(last);
+        semantic_tokens.truncate(first);
+        semantic_tokens.extend(tokens);
+        semantic_tokens.extend(moved.into_iter().map(|token| ImCompleteSemanticToken {
+            start: token.start.saturating_add_signed(added_chars),
+            ..token
+        }));
+        self.rules.extend(parsed.rules);
+        self.disabled.extend(parsed.disabled);
+        self.incomplete.extend(parsed.incomplete);
+    }
+}
+
+impl Rule {
+    /// Parse a single signature, with recovery
+    ///
+    /// The parser is built for every line and not cached: building the
+    /// combinators costs little next to parsing a line (see `benches/parse.rs`),
+    /// and they are reference counted ([std::rc::Rc]), so a cached parser could
+    /// not be shared between the threads anyway.
+    pub fn parse_line(line: &str) -> (Option<Spanned<Rule>>, Vec<Simple<char>>) {
+        Rule::parser().parse_recovery(line)
+    }
+
+    /// Parse a single line of a rope, with recovery
+    ///
+    /// The line is borrowed when it lies in a single chunk of the rope and
+    /// streamed from its characters otherwise, so no [String] is allocated.
+    pub fn parse_slice(line: RopeSlice) -> (Option<Spanned<Rule>>, Vec<Simple<char>>) {
+        match line.as_str() {
+            Some(text) => Rule::parse_line(text),
+            None => {
+                let length = line.len_chars();
+                let chars = line
+                    .chars()
+                    .enumerate()
+                    .map(|(index, char)| (char, index..index + 1));
+                Rule::parser().parse_recovery(Stream::from_iter(length..length, chars))
+            }
+        }
+    }
+}
+
+/// Get the span of the prefix, which comments out a line: the # and the
+/// whitespace after it (e.g. "# " of "# alert ...")
+pub fn comment_prefix(line: &str) -> Option<Span> {
+    prefix_span(line.chars())
+}
+
+/// Same as [comment_prefix], however the line is provided as its characters
+fn prefix_span(chars: impl Iterator<Item = char> + Clone) -> Option<Span> {
+    let start = chars
+        .clone()
+        .take_while(|char| char.is_whitespace())
+        .count();
+    let length = chars
+        .clone()
+        .skip(start)
+        .take_while(|char| *char == '#')
+        .count();
+    if length == 0 {
+        return None;
+    }
+    let whitespace = chars
+        .skip(start + length)
+        .take_while(|char| *char == ' ' || *char == '\t')
+        .count();
+    Some(start..start + length + whitespace)
+}
+
+/// Parse a comment as a signature, which was commented out
+///
+/// The # are replaced by spaces, so the spans point into the original line.
+/// Only complete signatures (with a known action, a direction and options) are
+/// accepted, so regular comments are not mistaken for signatures. The line is
+/// streamed from the chunks of the rope, so no [String] is allocated.
+fn parse_disabled(line: RopeSlice) -> Option<(Rule, Span)> {
+    let prefix = prefix_span(line.chars())?;
+    let length = line.len_chars();
+    let chars = line.chars().enumerate().map(|(index, char)| {
+        let char = match prefix.contains(&index) {
+            true => ' ',
+            false => char,
+        };
+        (char, index..index + 1)
+    });
+    let (rule, span) = Rule::parser()
+        .parse(Stream::from_iter(length..length, chars))
+        .ok()?;
+    let complete = matches!(&rule.action, Some((action, _)) if !matches!(action, Action::Other(_)))
+        && matches!(rule.direction(), Some((direction, _))
+            if !matches!(direction, NetworkDirection::Unrecognized(_)))
+        && rule
+            .options
+            .as_ref()
+            .is_some_and(|options| !options.is_empty());
+    complete.then_some((rule, span))
+}
+
+/// The action, the header, the options and the comment of a signature, as they are parsed
+type RuleParts = (
+    (
+        (Option<Spanned<String>>, Spanned<Header>),
+        Option<Spanned<Vec<Spanned<RuleOption>>>>,
+    ),
+    Option<Spanned<String>>,
+);
 
 impl Rule {
     /// Provides a parser for a signature
@@ -28,30 +321,66 @@ impl Rule {
             .allow_trailing()
             .padded()
             .delimited_by(just("("), just(")"))
+            .map_with_span(|options, span| (options, span))
             .padded();
+        // A comment after the signature (e.g. "# reviewed"), it may contain anything
+        let comment = just('#')
+            .chain(any().repeated())
+            .collect::<String>()
+            .map_with_span(|comment, span: Span| {
+                let comment = comment.trim_end().to_string();
+                let end = span.start + comment.chars().count();
+                (comment, span.start..end)
+            });
+        let rule = |(((action, header), options), comment): RuleParts, span: Span| {
+            let (options, options_span) = options.unzip();
+            (
+                Rule {
+                    action: action.map(|(str, span)| (str.parse().unwrap(), span)),
+                    header,
+                    options,
+                    options_span,
+                    comment,
+                },
+                span,
+            )
+        };
+        // On a mistake, keep the options before it and skip the rest of the line,
+        // so an incomplete signature still has its action and its header
+        let recovered_rule = action
+            .or_not()
+            .then(Header::parser().padded())
+            .then(
+                just("(")
+                    .ignore_then(
+                        RuleOption::parser()
+                            .separated_by(just(";"))
+                            .allow_trailing()
+                            .padded(),
+                    )
+                    .map_with_span(|options, span| (options, span))
+                    .or_not(),
+            )
+            .then_ignore(any().repeated())
+            .map(|parts| (parts, None))
+            .map_with_span(rule);
 
         action
             .or_not()
             .then(Header::parser().padded())
             .then(options.or_not())
+            .then(comment.or_not())
             .then_ignore(end())
-            .map_with_span(|((action, header), options), span| {
-                (
-                    Rule {
-                        action: action.and_then(|(str, span)| Some((str.parse().unwrap(), span))),
-                        header: header,
-                        options: options,
-                    },
-                    span,
-                )
-            })
+            .map_with_span(rule)
+            .recover_with(skip_parser(recovered_rule))
     }
 }
 
 impl Header {
     /// Provides a parser for a header
     fn parser() -> impl Parser<char, (Header, Span), Error = Simple<char>> {
-        let protocol = text::ident().map_with_span(|protocol, span| (protocol, span));
+        let protocol = text::ident()
+            .map_with_span(|protocol: String, span| (Protocol::parse(&protocol), span));
         let address_port_combined = NetworkAddress::parser()
             .or_not()
             .padded()
@@ -70,10 +399,10 @@ impl Header {
             .map_with_span(|(((protocol, source), direction), destination), span| {
                 (
                     Header {
-                        protocol: protocol,
+                        protocol,
                         source: source.0,
                         source_port: source.1,
-                        direction: direction,
+                        direction,
                         destination: destination.0,
                         destination_port: destination.1,
                     },
@@ -84,6 +413,16 @@ impl Header {
 }
 
 impl NetworkAddress {
+    /// Parses a network address on its own (e.g. the value of an address group)
+    pub fn parse_str(text: &str) -> Result<NetworkAddress, String> {
+        NetworkAddress::parser()
+            .padded()
+            .then_ignore(end())
+            .parse(text)
+            .map(|(address, _)| address)
+            .map_err(|errors| error_message(text, &errors))
+    }
+
     /// Provides a parser for a network address
     fn parser() -> impl Parser<char, (NetworkAddress, Span), Error = Simple<char>> {
         recursive(|ipaddress| {
@@ -114,10 +453,16 @@ impl NetworkAddress {
                         span,
                     )
                 });
-            // Simple IPv4 address
+            // Simple IPv6 address, which may end with an IPv4 (::ffff:1.2.3.4)
             let ipv6 = one_of::<_, _, Simple<char>>("0123456789abcdefABCDEF:")
                 .repeated()
                 .collect::<String>()
+                .try_map(|ipv6, span: Span| match ipv6.contains(':') {
+                    true => Ok(ipv6),
+                    false => Err(Simple::custom(span, "invalid IPv6 address syntax")),
+                })
+                .chain::<char, _, _>(one_of("0123456789.").repeated())
+                .collect::<String>()
                 .try_map(|ipv6, span: Span| {
                     let ip = ipv6.parse::<Ipv6Addr>();
                     match ip {
@@ -126,15 +471,27 @@ impl NetworkAddress {
                     }
                 });
 
-            let ip = ipv6.or(ipv4);
-            // CIDR IP Address (192.168.0.0/16)
-            let cidr = ip
+            // The mask of a CIDR, which can not be longer than the address
+            let mask = |bits: u8| {
+                just("/").ignore_then(text::int(10).try_map(move |mask: String, span: Span| {
+                    match mask.parse::<u8>() {
+                        Ok(mask) if mask <= bits => Ok((mask, span)),
+                        _ => Err(Simple::custom(
+                            span,
+                            format!("The mask should be at most {} (found {})", bits, mask),
+                        )),
+                    }
+                }))
+            };
+            // An address followed by a / is a CIDR, so an invalid mask is reported
+            let ip = ipv6
                 .clone()
-                .then_ignore(just("/"))
-                .then(
-                    text::int(10)
-                        .map_with_span(|mask: String, span| (mask.parse::<u8>().unwrap(), span)),
-                )
+                .or(ipv4)
+                .then_ignore(none_of("/").ignored().or(end()).rewind());
+            // CIDR IP Address (192.168.0.0/16 or 2001:db8::/32)
+            let cidr = ipv6
+                .then(mask(128))
+                .or(ipv4.then(mask(32)))
                 .try_map(|(ip, mask), span| match ip.0 {
                     NetworkAddress::IPAddr(ip) => Ok((NetworkAddress::CIDR(ip, mask), span)),
                     _ => Err(Simple::custom(
@@ -155,13 +512,14 @@ impl NetworkAddress {
                     (NetworkAddress::IPVariable((name, span.clone())), span)
                 });
 
-            // Negated IP: !192.168.0.1
+            // Negated IP: !192.168.0.1, the address follows the ! right away
             let negated_ip = just::<_, _, Simple<char>>('!')
                 .ignore_then(
                     ip_variable
                         .or(ip_group.clone())
                         .or(cidr.clone())
-                        .or(ip.clone()),
+                        .or(ip.clone())
+                        .or(any.clone()),
                 )
                 .map_with_span(|ip, span: Span| (NetworkAddress::NegIP(Box::new(ip)), span));
 
@@ -177,49 +535,46 @@ impl NetworkAddress {
 }
 
 impl NetworkPort {
+    /// Parses a network port on its own (e.g. the value of a port group)
+    pub fn parse_str(text: &str) -> Result<NetworkPort, String> {
+        NetworkPort::parser()
+            .padded()
+            .then_ignore(end())
+            .parse(text)
+            .map(|(port, _)| port)
+            .map_err(|errors| error_message(text, &errors))
+    }
+
     /// Provides a parser for a network port
     fn parser() -> impl Parser<char, (NetworkPort, Span), Error = Simple<char>> {
         recursive(|port| {
             let number = text::int(10).try_map(|num: String, span: Span| {
                 Ok((
                     num.parse::<u16>()
-                        .map_err(|e| Simple::<char>::custom(span.clone(), format!("{}", e)))
-                        .unwrap(),
+                        .map_err(|e| Simple::<char>::custom(span.clone(), format!("{}", e)))?,
                     span,
                 ))
             });
             let any = text::keyword::<_, _, Simple<char>>("any")
                 .map_with_span(|_, span: Span| (NetworkPort::Any(span.clone()), span));
             // Just a number
-            let port_number = number
-                .map(|(port, span)| (NetworkPort::Port((port, span.clone())), span))
-                .padded();
+            let port_number =
+                number.map(|(port, span)| (NetworkPort::Port((port, span.clone())), span));
             // Port range: 1:32
             let port_range = number
                 .or_not()
                 .then_ignore(just(":"))
                 .then(number.or_not())
-                .try_map(
-                    |a: (
-                        std::option::Option<(u16, Span)>,
-                        std::option::Option<(u16, Span)>,
-                    ),
-                     span| match a {
-                        (None, None) => Err(Simple::custom(span, "Port range cannot be \":\"")),
-                        (None, Some((port, span))) => Ok((
-                            NetworkPort::PortOpenRange((port, span.clone()), false),
-                            span,
-                        )),
-                        (Some((port, span)), None) => Ok((
-                            NetworkPort::PortOpenRange((port, span.clone()), false),
-                            span,
-                        )),
-                        (Some((port_from, span_from)), Some((port_to, span_to))) => Ok((
-                            NetworkPort::PortRange((port_from, span_from), (port_to, span_to)),
-                            span,
-                        )),
-                    },
-                );
+                .try_map(|a, span| match a {
+                    (None, None) => Err(Simple::custom(span, "Port range cannot be \":\"")),
+                    // The span of the range includes the colon
+                    (None, Some(port)) => Ok((NetworkPort::PortOpenRange(port, false), span)),
+                    (Some(port), None) => Ok((NetworkPort::PortOpenRange(port, true), span)),
+                    (Some((port_from, span_from)), Some((port_to, span_to))) => Ok((
+                        NetworkPort::PortRange((port_from, span_from), (port_to, span_to)),
+                        span,
+                    )),
+                });
             // Port group: [1,2,3]
             let port_group = port
                 .separated_by(just(","))
@@ -234,13 +589,14 @@ impl NetworkPort {
                     (NetworkPort::PortVar((name, span.clone())), span)
                 });
 
-            // Negated port: !5
+            // Negated port: !5, the port follows the ! right away
             let negated_port = just::<_, _, Simple<char>>('!')
                 .ignore_then(
                     port_variable
                         .or(port_group.clone())
-                        .or(port_range.clone())
-                        .or(port_number.clone()),
+                        .or(port_range)
+                        .or(port_number)
+                        .or(any.clone()),
                 )
                 .map_with_span(|ports, span: Span| (NetworkPort::NegPort(Box::new(ports)), span));
 
@@ -257,8 +613,11 @@ impl NetworkPort {
 
 impl NetworkDirection {
     /// Provides a parser for a network direction
+    ///
+    /// Common typos (e.g. "=>") are parsed as unrecognized directions, so the
+    /// rest of the signature can still be checked.
     fn parser() -> impl Parser<char, (NetworkDirection, Span), Error = Simple<char>> {
-        one_of::<_, _, Simple<char>>("<->")
+        one_of::<_, _, Simple<char>>("<->=")
             .repeated()
             .collect::<String>()
             .map_with_span(|dir, span| match dir.as_ref() {
@@ -281,42 +640,60 @@ impl RuleOption {
     ///
     /// [suricata docs]: https://suricata.readthedocs.io/en/suricata-6.0.0/rules/intro.html#rule-options
     fn parser() -> impl Parser<char, (RuleOption, Span), Error = Simple<char>> {
-        let escaped_chars = one_of::<_, _, Simple<char>>("\";\\").delimited_by(just("\\"), empty());
+        // The escapes of an unquoted value are kept as they are written (e.g. \;), so
+        // the value is written back unchanged
+        let escaped_chars = just::<_, _, Simple<char>>('\\').chain(one_of("\";\\"));
         let unescaped_value = escaped_chars
-            .clone()
-            .or(none_of::<_, _, Simple<char>>(";,"))
+            .or(none_of::<_, _, Simple<char>>(";,").map(|char| vec![char]))
             .repeated()
+            .flatten()
             .collect::<String>()
             .map_with_span(|options, span: Span| {
                 (OptionsVariable::Other((options, span.clone())), span)
             });
 
-        let string_value = escaped_chars
-            .or(none_of::<_, _, Simple<char>>("\""))
+        // Inside quotes only the escaped " and ; are replaced, other escapes are kept
+        // as they are (e.g. \\ or \d of a pcre), so they are written back unchanged
+        let string_escape = just('\\').ignore_then(any()).map(|char| match char {
+            '"' | ';' => vec![char],
+            char => vec!['\\', char],
+        });
+        let quoted = string_escape
+            .or(none_of::<_, _, Simple<char>>("\"").map(|char| vec![char]))
             .repeated()
+            .flatten()
             .delimited_by(just("\""), just("\""))
-            .collect::<String>()
+            .collect::<String>();
+        let string_value = quoted.clone().padded().map_with_span(|value, span: Span| {
+            (OptionsVariable::String((value, span.clone())), span)
+        });
+        // A negated string (content:!"...";)
+        let negated_string = just('!')
+            .map_with_span(|_, span: Span| span)
+            .padded()
+            .then(quoted.map_with_span(|value, span: Span| (value, span)))
             .padded()
-            .map_with_span(|value, span: Span| {
-                (OptionsVariable::String((value, span.clone())), span)
+            .map_with_span(|(negation, value), span: Span| {
+                (OptionsVariable::NegatedString(negation, value), span)
             });
 
-        // Keyword (fast_pattern;)
-        let keyword = none_of::<_, _, Simple<char>>(":;) ")
-            .padded()
-            .repeated()
-            .at_least(1) // Otherwise an empty input is a valid keyword
-            .collect::<String>()
-            .padded()
-            .map_with_span(|keyword, span| (keyword, span));
+        // Keyword (fast_pattern; or http.uri;), the span does not cover the whitespace
+        let keyword = filter::<_, _, Simple<char>>(|char: &char| {
+            char.is_alphanumeric() || matches!(char, '.' | '_' | '-')
+        })
+        .repeated()
+        .at_least(1) // Otherwise an empty input is a valid keyword
+        .collect::<String>()
+        .map_with_span(|keyword, span| (keyword, span))
+        .padded();
 
         // Keyword pair (msg: "...";)
         let keyword_pair = keyword
-            .clone()
             .padded()
             .then_ignore(just(":"))
             .then(
                 string_value
+                    .or(negated_string)
                     .or(unescaped_value)
                     .padded()
                     .separated_by(just(",")),
@@ -333,3 +710,359 @@ impl RuleOption {
         keyword_pair.or(buffer)
     }
 }
+
+/// Counts the non-whitespace characters inside a line
+fn line_length_padded(line: RopeSlice) -> u32 {
+    let mut ret = 0;
+    line.chars().for_each(|c| {
+        if !c.is_whitespace() {
+            ret += 1;
+        }
+    });
+    ret
+}
+
+/// Describes the first parsing error of a value
+fn error_message(text: &str, errors: &[Simple<char>]) -> String {
+    match errors.first() {
+        Some(error) => format!(
+            "invalid value {:?} at character {}: {}",
+            text,
+            error.span().start,
+            error
+        ),
+        None => format!("invalid value {:?}", text),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chumsky::error::SimpleReason;
+    use lsp_types::HoverContents;
+
+    use super::*;
+    use crate::rule::Hover;
+
+    #[test]
+    fn headers_are_kept_when_the_options_are_garbage() {
+        let lines = [
+            "alert tcp 10.0.0.0/8 any -> any 80 (msg:\"x\"; @@@ ((",
+            "alert tcp 10.0.0.0/8 any -> any 80 (msg:\"x\" sid:1;)",
+            "alert tcp 10.0.0.0/8 any -> any 80 (msg:\"x\"; sid:1;",
+            "alert tcp 10.0.0.0/8 any -> any 80 )))",
+        ];
+        for line in lines {
+            let (ast, _) = AST::parse_str(line);
+            assert!(ast.rules.is_empty(), "{}", line);
+            let (rule, _) = ast.incomplete.get(&0).expect(line);
+            assert_eq!(rule.action.as_ref().unwrap().0.to_string(), "alert");
+            assert_eq!(
+                rule.header.0.to_string().trim_end(),
+                "tcp 10.0.0.0/8 any -> any 80",
+                "{}",
+                line
+            );
+            assert_eq!(ast.rule_at(&0).map(|(rule, _)| rule), Some(rule));
+        }
+        // The options before the mistake are kept
+        let (ast, _) = AST::parse_str(lines[0]);
+        let (rule, _) = &ast.incomplete[&0];
+        assert_eq!(rule.options_with_keyword("msg").len(), 1);
+    }
+
+    #[test]
+    fn ipv6_addresses_survive_a_round_trip() {
+        let addresses = [
+            "2001:db8::/32",
+            "::1",
+            "::/0",
+            "fe80::1/128",
+            "10.0.0.0/8",
+            "[2001:db8::/32, 10.0.0.0/8]",
+            "[10.0.0.1, ::ffff:10.0.0.1, !2001:db8::1/64]",
+            "![2001:db8::/32, $HOME_NET]",
+        ];
+        for text in addresses {
+            let address = NetworkAddress::parse_str(text).expect(text);
+            assert_eq!(address.to_string(), text);
+            let reparsed = NetworkAddress::parse_str(&address.to_string()).unwrap();
+            assert_eq!(reparsed, address, "{}", text);
+        }
+        let address = NetworkAddress::parse_str("2001:db8::/32").unwrap();
+        let NetworkAddress::CIDR((ip, ip_span), (mask, mask_span)) = address else {
+            panic!("{:?} is not a CIDR", address);
+        };
+        assert_eq!((ip.to_string(), ip_span), ("2001:db8::".to_string(), 0..10));
+        assert_eq!((mask, mask_span), (32, 11..13));
+    }
+
+    #[test]
+    fn masks_are_checked_against_the_ip_version() {
+        for text in ["2001:db8::/128", "10.0.0.0/32", "[::/0, 0.0.0.0/0]"] {
+            assert!(NetworkAddress::parse_str(text).is_ok(), "{}", text);
+        }
+        for text in [
+            "2001:db8::/129",
+            "10.0.0.0/33",
+            "[2001:db8::/32, 10.0.0.0/64]",
+        ] {
+            assert!(NetworkAddress::parse_str(text).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn mixed_groups_are_parsed_inside_signatures() {
+        let line = "alert tcp [2001:db8::/32, 10.0.0.0/8] any -> 2001:db8::1 any (sid:1;)";
+        let (ast, _) = AST::parse_str(line);
+        let (rule, _) = &ast.rules[&0];
+        assert_eq!(rule.to_string(), line.replace("(sid:1;)", "(sid: 1;)"));
+        let Some((NetworkAddress::IPGroup(group), _)) = &rule.header.0.source else {
+            panic!("{:?} is not a group", rule.header.0.source);
+        };
+        let spans = group
+            .iter()
+            .map(|(_, span)| span.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(spans, vec![11..24, 26..36]);
+    }
+
+    #[test]
+    fn open_port_ranges_survive_a_round_trip() {
+        let ports = ["80:", ":80", "[1024:, 80]", "!1024:", "![:80, 443]", "1:1024"];
+        for text in ports {
+            let port = NetworkPort::parse_str(text).expect(text);
+            assert_eq!(port.to_string(), text);
+            let reparsed = NetworkPort::parse_str(&port.to_string()).unwrap();
+            assert_eq!(reparsed, port, "{}", text);
+        }
+        // The span of an open range covers its colon
+        let line = "alert tcp any [1024:, :80] -> any any (sid:1;)";
+        let (ast, _) = AST::parse_str(line);
+        let (rule, _) = &ast.rules[&0];
+        let Some((NetworkPort::PortGroup(group), _)) = &rule.header.0.source_port else {
+            panic!("{:?} is not a group", rule.header.0.source_port);
+        };
+        let spans = group
+            .iter()
+            .map(|(_, span)| &line[span.clone()])
+            .collect::<Vec<_>>();
+        assert_eq!(spans, vec!["1024:", ":80"]);
+    }
+
+    #[test]
+    fn colons_of_port_ranges_are_operators() {
+        let operator = LEGEND_TYPE
+            .iter()
+            .position(|item| item == &SemanticTokenType::OPERATOR)
+            .unwrap();
+        let line = "alert tcp any 1:2 -> any [80:, :90] (sid:1;)";
+        let (_, tokens) = AST::parse_str(line);
+        let colons = tokens
+            .iter()
+            .filter(|token| token.token_type == operator && line[token.start..].starts_with(':'))
+            .map(|token| (token.start, token.length))
+            .collect::<Vec<_>>();
+        assert_eq!(colons, vec![(15, 1), (28, 1), (31, 1)]);
+    }
+
+    #[test]
+    fn port_ranges_are_hovered_with_their_bounds() {
+        let hover = |text: &str, col: usize| {
+            let port = NetworkPort::parse_str(text).unwrap();
+            let (contents, span) = port.get_hover(&col, &HashMap::new())?;
+            let HoverContents::Markup(markup) = contents else {
+                panic!("{:?} is not markup", contents);
+            };
+            Some((markup.value, span))
+        };
+        assert_eq!(
+            hover("80:", 0),
+            Some(("**80:**\n\n80 - 65535".to_string(), 0..3))
+        );
+        assert_eq!(
+            hover(":80", 2),
+            Some(("**:80**\n\n0 - 80".to_string(), 0..3))
+        );
+        assert_eq!(
+            hover("![1024:,22]", 3),
+            Some(("**1024:**\n\n1024 - 65535".to_string(), 2..7))
+        );
+        assert_eq!(hover("![1024:,22]", 8), None);
+    }
+
+    #[test]
+    fn numbers_out_of_range_are_errors() {
+        let lines = [
+            (
+                "alert tcp any any -> any 70000 (msg:\"x\"; sid:1;)",
+                "number too large to fit in target type",
+                25..30,
+            ),
+            (
+                "alert tcp any any -> any [80,70000] (msg:\"x\"; sid:1;)",
+                "number too large to fit in target type",
+                29..34,
+            ),
+            (
+                "alert tcp 10.0.0.0/999 any -> any any (msg:\"x\"; sid:1;)",
+                "The mask should be at most 32 (found 999)",
+                19..22,
+            ),
+            (
+                "alert tcp 2001:db8::/200 any -> any any (msg:\"x\"; sid:1;)",
+                "The mask should be at most 128 (found 200)",
+                21..24,
+            ),
+        ];
+        for (line, message, span) in lines {
+            let (rule, errors) = Rule::parser().parse_recovery(line);
+            let errors = errors
+                .iter()
+                .map(|error| (error.reason().clone(), error.span()))
+                .collect::<Vec<_>>();
+            let reason = SimpleReason::Custom(message.to_string());
+            assert_eq!(errors, vec![(reason, span)], "{}", line);
+            // The signature is kept, so its header can still be navigated
+            let (rule, _) = rule.expect(line);
+            assert_eq!(rule.action.unwrap().0.to_string(), "alert");
+        }
+    }
+
+    #[test]
+    fn tabs_between_the_header_fields_keep_the_spans() {
+        let line = "alert\ttcp \t10.0.0.1\tany\t->  any\t\t80\t(sid:1;)";
+        let (ast, _) = AST::parse_str(line);
+        let (rule, _) = &ast.rules[&0];
+        let header = &rule.header.0;
+        let text = |span: &Span| &line[span.clone()];
+        assert_eq!(text(&header.source.as_ref().unwrap().1), "10.0.0.1");
+        assert_eq!(text(&header.direction.as_ref().unwrap().1), "->");
+        assert_eq!(text(&header.destination_port.as_ref().unwrap().1), "80");
+        assert_eq!(
+            rule.to_string(),
+            "alert tcp 10.0.0.1 any -> any 80 (sid: 1;)"
+        );
+    }
+
+    #[test]
+    fn comments_after_a_signature_are_kept() {
+        let line = "alert tcp any any -> any any (content:\"#1\"; sid:1;)  # reviewed  ";
+        let (ast, tokens) = AST::parse_str(line);
+        let (rule, _) = &ast.rules[&0];
+        let (comment, span) = rule.comment.as_ref().unwrap();
+        assert_eq!(comment, "# reviewed");
+        assert_eq!(&line[span.clone()], "# reviewed");
+        assert_eq!(
+            rule.to_string(),
+            "alert tcp any any -> any any (content: \"#1\"; sid: 1;) # reviewed"
+        );
+        let comment_type = LEGEND_TYPE
+            .iter()
+            .position(|item| item == &SemanticTokenType::COMMENT)
+            .unwrap();
+        assert!(tokens
+            .iter()
+            .any(|token| token.token_type == comment_type && token.start == span.start));
+
+        // A # inside the options is not a comment
+        let (ast, _) = AST::parse_str("alert tcp any any -> any any (msg:\"a # b\"; sid:1;)");
+        assert_eq!(ast.rules[&0].0.comment, None);
+        // Any other text after the signature is a mistake
+        let (ast, _) = AST::parse_str("alert tcp any any -> any any (sid:1;) reviewed");
+        assert!(ast.rules.is_empty());
+        assert_eq!(ast.incomplete[&0].0.comment, None);
+    }
+
+    #[test]
+    fn option_values_with_colons_survive_a_round_trip() {
+        let options = [
+            (r#"pcre:"/foo:bar/i";"#, r#"pcre: "/foo:bar/i";"#),
+            (
+                "reference:url,example.com/a:b;",
+                "reference: url, example.com/a:b;",
+            ),
+            (r#"content:"a:b";"#, r#"content: "a:b";"#),
+            (r#"msg:"a, b\; c: d";"#, r#"msg: "a, b\; c: d";"#),
+            (r"content:a\;b;", r"content: a\;b;"),
+        ];
+        for (option, expected) in options {
+            let line = format!("alert tcp any any -> any any ({} sid:1;)", option);
+            let (rule, _) = Rule::parser().parse(line.as_str()).expect(option);
+            let written = rule.to_string();
+            assert_eq!(
+                written,
+                format!("alert tcp any any -> any any ({} sid: 1;)", expected)
+            );
+            let (reparsed, _) = Rule::parser().parse(written.as_str()).unwrap();
+            assert_eq!(reparsed.to_string(), written, "{}", option);
+        }
+        // The value after the first colon keeps its span
+        let line = "alert tcp any any -> any any (reference:url,example.com/a:b;)";
+        let (rule, _) = Rule::parser().parse(line).unwrap();
+        let values = rule.option_values("reference").remove(0);
+        let spans = values
+            .iter()
+            .map(|(_, span)| &line[span.clone()])
+            .collect::<Vec<_>>();
+        assert_eq!(spans, vec!["url", "example.com/a:b"]);
+    }
+
+    const CORPUS: &str = include_str!("../tests/fixtures/corpus/rules.rules");
+
+    #[test]
+    fn borrowed_and_copied_lines_are_parsed_the_same() {
+        // Repeating the corpus makes some lines cross the chunks of the rope,
+        // so both ways of parse_slice are taken
+        let text = format!(
+            "{}alert tcp any any -> any 70000 (msg:\"x\"; sid:1;\nalert tcp any any any\n",
+            CORPUS
+        )
+        .repeat(50);
+        let rope = Rope::from_str(&text);
+        let mut streamed = 0;
+        rope.lines().for_each(|line| {
+            streamed += line.as_str().is_none() as usize;
+            let copied = Rule::parser().parse_recovery(line.to_string());
+            assert_eq!(Rule::parse_slice(line), copied, "{}", line);
+        });
+        assert!(streamed > 0);
+    }
+
+    #[test]
+    fn disabled_signatures_are_streamed_from_the_rope() {
+        // A long msg makes the line cross the chunks of the rope
+        let line = format!(
+            "#  alert tcp any any -> any any (msg:\"{}\"; sid:1;)\n",
+            "x".repeat(3000)
+        );
+        let rope = Rope::from_str(&format!("{}{}# a comment\n", line, line));
+        assert!(rope.line(0).as_str().is_none());
+        let (ast, _) = AST::parse_rope(&rope);
+        assert_eq!(ast.disabled.len(), 2);
+        // The spans point into the line, past the # of the comment
+        let (rule, _) = &ast.disabled[&1];
+        let (sid, span) = rule.sid().unwrap();
+        assert_eq!((sid, &line[span]), (1, "1"));
+        let (_, span) = rule.protocol().as_ref().unwrap();
+        assert_eq!(&line[span.clone()], "tcp");
+    }
+
+    #[test]
+    fn parallel_and_sequential_parsing_give_the_same_ast() {
+        // More lines than a thread parses at least, with every kind of signature
+        let text = format!(
+            "{}alert tcp any any -> any 70000 (msg:\"x\"; sid:1;\n#alert tcp any any -> any any (sid:2;)\n\n",
+            CORPUS
+        )
+        .repeat(LINES_PER_THREAD / 14);
+        let rope = Rope::from_str(&text);
+        assert!(rope.len_lines() > 2 * LINES_PER_THREAD);
+        let sequential = AST::parse_lines(&rope, 0..rope.len_lines());
+        for threads in [2, 3] {
+            let (ast, tokens) = AST::parse_chunks(&rope, threads);
+            assert!(ast == sequential.0, "{} threads", threads);
+            assert!(tokens == sequential.1, "{} threads", threads);
+        }
+        assert!(AST::parse_rope(&rope) == sequential);
+    }
+}
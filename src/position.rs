@@ -0,0 +1,103 @@
+//! Provides the conversion between the columns of the client and the server
+//!
+//! The server counts the columns of a line in characters, while the clients
+//! count them in UTF-16 code units, unless another encoding is negotiated
+//! (`positionEncoding`). Both are the same for most rules, however they
+//! differ once a line contains characters outside of the Basic Multilingual
+//! Plane (e.g. an emoji inside a msg).
+use lsp_types::{Position, PositionEncodingKind, Range};
+use ropey::{Rope, RopeSlice};
+
+/// How the client counts the columns of a line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PositionEncoding {
+    /// UTF-16 code units, which every client supports
+    #[default]
+    Utf16,
+    /// Characters, which is how the server counts the columns
+    Utf32,
+}
+
+impl PositionEncoding {
+    /// Choose the encoding from the encodings the client supports
+    ///
+    /// Characters are preferred, since they need no conversion, otherwise the
+    /// columns are converted to UTF-16.
+    pub fn negotiate(supported: Option<&[PositionEncodingKind]>) -> PositionEncoding {
+        match supported {
+            Some(supported) if supported.contains(&PositionEncodingKind::UTF32) => {
+                PositionEncoding::Utf32
+            }
+            _ => PositionEncoding::Utf16,
+        }
+    }
+
+    /// Get the encoding, as it is announced to the client
+    pub fn kind(&self) -> PositionEncodingKind {
+        match self {
+            PositionEncoding::Utf16 => PositionEncodingKind::UTF16,
+            PositionEncoding::Utf32 => PositionEncodingKind::UTF32,
+        }
+    }
+
+    /// Convert a column of the client to the index of a character inside the line
+    ///
+    /// A column after the end of the line points to the end of the line.
+    pub fn to_char(&self, line: RopeSlice, column: u32) -> usize {
+        let chars = line.chars().take_while(|char| !matches!(char, '\n' | '\r'));
+        match self {
+            PositionEncoding::Utf32 => chars.count().min(column as usize),
+            PositionEncoding::Utf16 => {
+                let mut units = 0;
+                chars
+                    .take_while(|char| {
+                        units += char.len_utf16() as u32;
+                        units <= column
+                    })
+                    .count()
+            }
+        }
+    }
+
+    /// Convert the index of a character inside the line to a column of the client
+    pub fn to_column(&self, line: RopeSlice, char: usize) -> u32 {
+        match self {
+            PositionEncoding::Utf32 => char as u32,
+            PositionEncoding::Utf16 => line
+                .chars()
+                .take(char)
+                .map(|char| char.len_utf16() as u32)
+                .sum::<u32>()
+                .saturating_add(char.saturating_sub(line.len_chars()) as u32),
+        }
+    }
+
+    /// Convert a position of the client to a position, which counts characters
+    pub fn char_position(&self, rope: &Rope, position: Position) -> Position {
+        match rope.get_line(position.line as usize) {
+            Some(line) if *self == PositionEncoding::Utf16 => {
+                Position::new(position.line, self.to_char(line, position.character) as u32)
+            }
+            _ => position,
+        }
+    }
+
+    /// Convert a position, which counts characters, to a position of the client
+    pub fn client_position(&self, rope: &Rope, position: Position) -> Position {
+        match rope.get_line(position.line as usize) {
+            Some(line) => Position::new(
+                position.line,
+                self.to_column(line, position.character as usize),
+            ),
+            None => position,
+        }
+    }
+
+    /// Convert a range, which counts characters, to a range of the client
+    pub fn client_range(&self, rope: &Rope, range: Range) -> Range {
+        Range::new(
+            self.client_position(rope, range.start),
+            self.client_position(rope, range.end),
+        )
+    }
+}
@@ -10,10 +10,11 @@ pub fn get_reference(
     line: &u32,
     col: &usize
 ) -> Option<Vec<(u32, Spanned<String>)>> {
-    let (rule, _) = ast.rules.get(line)?; // Retrieve rule
+    let (rule, _) = ast.rule_at(line)?; // Retrieve rule
     let (variable_name, _) = get_variable_from_offset(rule, col)?;
     let mut ret = vec![];
-    ast.rules.iter().for_each(|(rule_line, (rule, _))| {// go over each rule
+    ast.all_rules().for_each(|(rule_line, (rule, _))| {
+        // go over each rule
         // get the header of the rule
         let (header, _) = &rule.header;
 
@@ -24,7 +25,7 @@ pub fn get_reference(
 
         // Push all references
         reference_list.into_iter().for_each(|var| {
-            ret.push((rule_line.clone(), var));
+            ret.push((*rule_line, var));
         });
     });
     Some(ret)
@@ -39,3 +40,32 @@ fn get_variable_from_offset(rule: &Rule, col: &usize) -> Option<Spanned<String>>
         .into_iter()
         .find(|(_, var_span)| var_span.contains(col))
 }
+
+/// Get the references of the flowbit, xbit or hostbit under the cursor
+///
+/// Only bits of the same kind are referenced, a flowbit and an xbit with the
+/// same name are not related.
+pub fn get_bit_reference(
+    ast: &AST,
+    line: &u32,
+    col: &usize,
+) -> Option<Vec<(u32, Spanned<String>)>> {
+    let (rule, _) = ast.rule_at(line)?;
+    let (kind, name) = rule.bits_operations().into_iter().find_map(|operation| {
+        let (name, _) = operation
+            .names
+            .into_iter()
+            .find(|(_, span)| span.contains(col))?;
+        Some((operation.kind, name))
+    })?;
+    let mut ret = vec![];
+    ast.all_rules().for_each(|(rule_line, (rule, _))| {
+        rule.bits_operations()
+            .into_iter()
+            .filter(|operation| operation.kind == kind)
+            .flat_map(|operation| operation.names)
+            .filter(|(other, _)| *other == name)
+            .for_each(|reference| ret.push((*rule_line, reference)));
+    });
+    Some(ret)
+}
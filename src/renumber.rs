@@ -0,0 +1,312 @@
+//! Provides the renumbering of the signature ids inside a file
+//!
+//! The signatures get consecutive sids in the order they appear (e.g. 1000100,
+//! 1000110, ...), signatures without a sid get one inserted. The new sids are
+//! checked against the sids used by the other files, before anything changes.
+use std::{collections::HashMap, fmt};
+
+use crate::format::LineEdit;
+use crate::rule::{Rule, Span, AST};
+
+/// How the signatures are renumbered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenumberOptions {
+    /// The sid of the first signature
+    pub start: u32,
+    /// The difference between the sids of two consecutive signatures
+    pub step: u32,
+    /// Increase the rev of every signature, whose sid changes
+    pub bump_rev: bool,
+}
+
+/// A new sid, which is already used by a signature of another file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SidConflict {
+    pub sid: u32,
+    pub file: String,
+    pub line: u32,
+}
+
+/// Why the signatures can not be renumbered
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RenumberError {
+    /// The sid of the signature on the line would be larger than the largest sid
+    OutOfRange(u32),
+    /// The new sids are already used by the other files
+    Conflicts(Vec<SidConflict>),
+}
+
+impl fmt::Display for RenumberError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RenumberError::OutOfRange(line) => write!(
+                f,
+                "the sid of the signature on line {} would be larger than {}",
+                line + 1,
+                u32::MAX
+            ),
+            RenumberError::Conflicts(conflicts) => {
+                let conflicts = conflicts
+                    .iter()
+                    .map(|conflict| {
+                        let file = conflict.file.rsplit('/').next().unwrap_or(&conflict.file);
+                        format!("{} ({}:{})", conflict.sid, file, conflict.line + 1)
+                    })
+                    .collect::<Vec<_>>();
+                write!(
+                    f,
+                    "sids already used by other files: {}",
+                    conflicts.join(", ")
+                )
+            }
+        }
+    }
+}
+
+/// Get the sids used by the files, along with where they are used: (file, line)
+pub fn used_sids(files: &[(String, &AST)]) -> HashMap<u32, (String, u32)> {
+    files
+        .iter()
+        .flat_map(|(file, ast)| {
+            ast.rules.iter().filter_map(|(line, (rule, _))| {
+                let (sid, _) = rule.sid()?;
+                Some((sid, (file.clone(), *line)))
+            })
+        })
+        .collect()
+}
+
+/// Get the first sid after the given one, which is not used yet
+pub fn next_free_sid(after: u32, used: &HashMap<u32, (String, u32)>) -> Option<u32> {
+    (after.checked_add(1)?..=u32::MAX).find(|sid| !used.contains_key(sid))
+}
+
+/// Renumber the signatures of a file
+///
+/// The lines are looked up by their number. Fails if any new sid is used by
+/// the other files (see [used_sids]), or if the sids run out of range.
+pub fn renumber_sids(
+    ast: &AST,
+    line_text: impl Fn(u32) -> Option<String>,
+    options: RenumberOptions,
+    used: &HashMap<u32, (String, u32)>,
+) -> Result<Vec<LineEdit>, RenumberError> {
+    let mut lines = ast.rules.keys().copied().collect::<Vec<_>>();
+    lines.sort();
+    let mut sids = vec![];
+    for (position, line) in lines.iter().enumerate() {
+        let sid = (position as u32)
+            .checked_mul(options.step)
+            .and_then(|offset| options.start.checked_add(offset));
+        sids.push((*line, sid.ok_or(RenumberError::OutOfRange(*line))?));
+    }
+    let mut conflicts = sids
+        .iter()
+        .filter_map(|(_, sid)| {
+            let (file, line) = used.get(sid)?;
+            Some(SidConflict {
+                sid: *sid,
+                file: file.clone(),
+                line: *line,
+            })
+        })
+        .collect::<Vec<_>>();
+    if !conflicts.is_empty() {
+        conflicts.sort_by_key(|conflict| conflict.sid);
+        return Err(RenumberError::Conflicts(conflicts));
+    }
+    let edits = sids
+        .into_iter()
+        .filter_map(|(line, sid)| {
+            let (rule, _) = ast.rules.get(&line)?;
+            let new_text = rule.with_sid(&line_text(line)?, sid, options.bump_rev)?;
+            Some(LineEdit { line, new_text })
+        })
+        .collect();
+    Ok(edits)
+}
+
+impl Rule {
+    /// Set the sid of the signature, inserting it if there is none
+    ///
+    /// Returns None if the sid does not change. If the rev is bumped, a
+    /// signature without a rev gets "rev:2;" (the missing rev counts as 1).
+    pub fn with_sid(&self, line: &str, sid: u32, bump_rev: bool) -> Option<String> {
+        let options = self.options.as_ref()?;
+        let raw_sid = self.raw_sid();
+        if raw_sid
+            .as_ref()
+            .is_some_and(|(current, _)| current.parse() == Ok(sid))
+        {
+            return None;
+        }
+        // Values are replaced from the back, so the spans stay valid
+        let mut replacements: Vec<(Span, String)> = vec![];
+        let mut inserted = vec![];
+        match raw_sid {
+            Some((_, span)) => replacements.push((span, sid.to_string())),
+            None => inserted.push(format!("sid:{};", sid)),
+        }
+        if bump_rev {
+            match self.number_value("rev") {
+                Some(Ok((rev, span))) => {
+                    replacements.push((span, rev.saturating_add(1).to_string()))
+                }
+                // A rev, which is not a number, is left as it is
+                Some(Err(_)) => (),
+                None => inserted.push("rev:2;".to_string()),
+            }
+        }
+        let mut chars = line.chars().collect::<Vec<_>>();
+        if !inserted.is_empty() {
+            // Insert after the semicolon of the last option
+            let (_, last) = options.last()?;
+            let end = last.end.min(chars.len());
+            let column = chars[end..]
+                .iter()
+                .position(|char| *char == ';')
+                .map_or(end, |semicolon| end + semicolon + 1);
+            let text = format!(" {}", inserted.join(" "));
+            chars.splice(column..column, text.chars());
+        }
+        replacements.sort_by_key(|(span, _)| span.start);
+        for (span, text) in replacements.into_iter().rev() {
+            let span = span.start.min(chars.len())..span.end.min(chars.len());
+            chars.splice(span, text.chars());
+        }
+        Some(chars.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPTIONS: RenumberOptions = RenumberOptions {
+        start: 1000100,
+        step: 10,
+        bump_rev: false,
+    };
+
+    fn renumber(
+        text: &str,
+        options: RenumberOptions,
+        used: &HashMap<u32, (String, u32)>,
+    ) -> Result<Vec<LineEdit>, RenumberError> {
+        let (ast, _) = AST::parse_str(text);
+        let lines = text.lines().map(str::to_string).collect::<Vec<_>>();
+        renumber_sids(
+            &ast,
+            |line| lines.get(line as usize).cloned(),
+            options,
+            used,
+        )
+    }
+
+    #[test]
+    fn signatures_get_consecutive_sids() {
+        let text = "\
+# local signatures
+alert tcp any any -> any any (msg:\"a\"; sid:7; rev:3;)
+
+alert tcp any any -> any any (msg:\"b\"; sid:1000110; rev:1;)
+alert tcp any any -> any any (msg:\"c\";)
+alert tcp any any -> any any (msg:\"d\"; sid:x; rev:1;)
+";
+        let edits = renumber(text, OPTIONS, &HashMap::new()).unwrap();
+        assert_eq!(
+            edits,
+            vec![
+                LineEdit {
+                    line: 1,
+                    new_text: "alert tcp any any -> any any (msg:\"a\"; sid:1000100; rev:3;)"
+                        .to_string(),
+                },
+                LineEdit {
+                    line: 4,
+                    new_text: "alert tcp any any -> any any (msg:\"c\"; sid:1000120;)".to_string(),
+                },
+                LineEdit {
+                    line: 5,
+                    new_text: "alert tcp any any -> any any (msg:\"d\"; sid:1000130; rev:1;)"
+                        .to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn revisions_are_bumped_with_the_sid() {
+        let text = "\
+alert tcp any any -> any any (msg:\"a\"; sid:7; rev:3;)
+alert tcp any any -> any any (msg:\"b\"; sid:8;)
+alert tcp any any -> any any (msg:\"c\"; sid:9; rev:draft;)
+alert tcp any any -> any any (msg:\"d\"; rev:1; sid:1000130;)
+";
+        let options = RenumberOptions {
+            bump_rev: true,
+            ..OPTIONS
+        };
+        let edits = renumber(text, options, &HashMap::new())
+            .unwrap()
+            .into_iter()
+            .map(|edit| edit.new_text)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            edits,
+            vec![
+                "alert tcp any any -> any any (msg:\"a\"; sid:1000100; rev:4;)",
+                "alert tcp any any -> any any (msg:\"b\"; sid:1000110; rev:2;)",
+                "alert tcp any any -> any any (msg:\"c\"; sid:1000120; rev:draft;)",
+            ]
+        );
+    }
+
+    #[test]
+    fn sids_of_other_files_are_not_reused() {
+        let (other, _) = AST::parse_str(
+            "\
+alert tcp any any -> any any (msg:\"x\"; sid:1000110;)
+alert tcp any any -> any any (msg:\"y\"; sid:5;)
+",
+        );
+        let used = used_sids(&[("/rules/other.rules".to_string(), &other)]);
+        assert_eq!(used[&5], ("/rules/other.rules".to_string(), 1));
+
+        let text = "\
+alert tcp any any -> any any (msg:\"a\"; sid:1;)
+alert tcp any any -> any any (msg:\"b\"; sid:2;)
+";
+        let err = renumber(text, OPTIONS, &used).unwrap_err();
+        assert_eq!(
+            err,
+            RenumberError::Conflicts(vec![SidConflict {
+                sid: 1000110,
+                file: "/rules/other.rules".to_string(),
+                line: 0,
+            }])
+        );
+        assert_eq!(
+            err.to_string(),
+            "sids already used by other files: 1000110 (other.rules:1)"
+        );
+    }
+
+    #[test]
+    fn sids_can_not_run_out_of_range() {
+        let text = "\
+alert tcp any any -> any any (msg:\"a\"; sid:1;)
+alert tcp any any -> any any (msg:\"b\"; sid:2;)
+";
+        let options = RenumberOptions {
+            start: u32::MAX,
+            ..OPTIONS
+        };
+        let err = renumber(text, options, &HashMap::new()).unwrap_err();
+        assert_eq!(err, RenumberError::OutOfRange(1));
+        assert_eq!(
+            err.to_string(),
+            "the sid of the signature on line 2 would be larger than 4294967295"
+        );
+    }
+}
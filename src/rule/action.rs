@@ -1,11 +1,24 @@
 use ropey::Error;
 use std::str::FromStr;
 use std::{collections::HashSet, fmt};
-use tower_lsp::lsp_types::{CompletionItem, CompletionItemKind};
+use lsp_types::{CompletionItem, CompletionItemKind};
+use serde::Serialize;
 
 use super::Completions;
 
-#[derive(Debug, Hash, Clone, PartialEq, Eq)]
+/// The actions accepted by Suricata (compared ignoring the case)
+pub const ACTIONS: [&str; 8] = [
+    "alert",
+    "pass",
+    "drop",
+    "reject",
+    "rejectsrc",
+    "rejectdst",
+    "rejectboth",
+    "config",
+];
+
+#[derive(Debug, Hash, Clone, PartialEq, Eq, Serialize)]
 pub enum Action {
     Alert,      // generate an alert
     Pass,       // stop further inspection of the packet
@@ -52,12 +65,12 @@ impl FromStr for Action {
 
 impl Completions for Action {
     fn get_completion(
-        address_variables: &HashSet<String>,
-        port_variables: &HashSet<String>,
+        _address_variables: &HashSet<String>,
+        _port_variables: &HashSet<String>,
         completion_tokens: &mut Vec<CompletionItem>,
     ) {
         // Create an array with all possible actions
-        let possible_strings = vec![
+        let possible_strings = [
             "alert",
             "pass",
             "drop",
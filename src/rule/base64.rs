@@ -0,0 +1,66 @@
+//! Structured view of the base64_decode and base64_data options
+//!
+//! base64_decode decodes a part of the buffer, e.g.
+//! `base64_decode:bytes 150,offset 0,relative;`, and base64_data moves the
+//! inspection to the decoded data, like a sticky buffer.
+use crate::rule::{bits::words, options::RuleOption, Rule, Span, Spanned};
+
+/// A single base64_decode option
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Base64Decode {
+    /// The number of bytes to decode (e.g. "150" of "bytes 150")
+    pub bytes: Option<Spanned<String>>,
+    /// Where the decoding starts (e.g. "0" of "offset 0")
+    pub offset: Option<Spanned<String>>,
+    /// The span of "relative", if the offset is relative to the previous match
+    pub relative: Option<Span>,
+    /// The arguments, which are not known, kept as they were written
+    pub unknown: Vec<Spanned<String>>,
+    /// The span of the whole option
+    pub span: Span,
+}
+
+impl Base64Decode {
+    fn parse(option: &RuleOption, span: &Span) -> Base64Decode {
+        let mut decode = Base64Decode {
+            bytes: None,
+            offset: None,
+            relative: None,
+            unknown: vec![],
+            span: span.clone(),
+        };
+        option.values().into_iter().for_each(|value| {
+            let words = words(&value);
+            match words.as_slice() {
+                [(key, _), number] if key == "bytes" => decode.bytes = Some(number.clone()),
+                [(key, _), number] if key == "offset" => decode.offset = Some(number.clone()),
+                [(key, span)] if key == "relative" => decode.relative = Some(span.clone()),
+                [] => (),
+                _ => decode.unknown.push(value),
+            }
+        });
+        decode
+    }
+}
+
+impl Rule {
+    /// Get every base64_decode option of the signature
+    pub fn base64_decodes(&self) -> Vec<Base64Decode> {
+        self.options_with_keyword("base64_decode")
+            .into_iter()
+            .map(|(option, span)| Base64Decode::parse(option, span))
+            .collect()
+    }
+
+    /// Get the base64_data options, which do not follow a base64_decode
+    ///
+    /// There is no decoded data to inspect, without a base64_decode before it.
+    pub fn orphan_base64_data(&self) -> Vec<&Spanned<RuleOption>> {
+        self.options
+            .iter()
+            .flatten()
+            .take_while(|(option, _)| option.keyword().0 != "base64_decode")
+            .filter(|(option, _)| option.keyword().0 == "base64_data")
+            .collect()
+    }
+}
@@ -0,0 +1,268 @@
+//! Structured view of the flowbits, xbits and hostbits options
+//!
+//! All three options share the same layout: an operation, followed by the
+//! name of the bit and (for xbits and hostbits) the scope the bit is tracked
+//! in, e.g. `xbits:set,bad.actor,track ip_src,expire 3600;`.
+use std::fmt;
+
+use crate::rule::{Rule, Span, Spanned};
+
+/// The options, which operate on named bits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum BitsKind {
+    Flowbits,
+    Xbits,
+    Hostbits,
+}
+
+impl BitsKind {
+    pub const ALL: [BitsKind; 3] = [BitsKind::Flowbits, BitsKind::Xbits, BitsKind::Hostbits];
+
+    /// Get the keyword of the option (e.g. "xbits")
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            BitsKind::Flowbits => "flowbits",
+            BitsKind::Xbits => "xbits",
+            BitsKind::Hostbits => "hostbits",
+        }
+    }
+
+    /// Get the operations accepted by Suricata
+    pub fn operations(&self) -> &'static [&'static str] {
+        match self {
+            BitsKind::Flowbits => &["set", "unset", "toggle", "isset", "isnotset", "noalert"],
+            BitsKind::Xbits | BitsKind::Hostbits => {
+                &["set", "unset", "toggle", "isset", "isnotset"]
+            }
+        }
+    }
+
+    /// Get the scopes, a bit can be tracked in (empty for flowbits)
+    pub fn tracks(&self) -> &'static [&'static str] {
+        match self {
+            BitsKind::Flowbits => &[],
+            BitsKind::Xbits => &["ip_src", "ip_dst", "ip_pair"],
+            BitsKind::Hostbits => &["ip_src", "ip_dst", "to_src", "to_dst", "both"],
+        }
+    }
+}
+
+impl fmt::Display for BitsKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BitsKind::Flowbits => write!(f, "flowbit"),
+            BitsKind::Xbits => write!(f, "xbit"),
+            BitsKind::Hostbits => write!(f, "hostbit"),
+        }
+    }
+}
+
+/// A single flowbits, xbits or hostbits option
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BitsOperation {
+    pub kind: BitsKind,
+    /// The operation (e.g. "set")
+    pub operation: Spanned<String>,
+    /// The names of the bits, flowbits can combine them with | or &
+    pub names: Vec<Spanned<String>>,
+    /// The scope of the bit (e.g. "ip_src" of "track ip_src")
+    pub track: Option<Spanned<String>>,
+    /// The seconds after which the bit expires (e.g. "3600" of "expire 3600")
+    pub expire: Option<Spanned<String>>,
+    /// The span of the whole option
+    pub span: Span,
+}
+
+impl BitsOperation {
+    /// Check if the operation sets the bit
+    pub fn sets(&self) -> bool {
+        self.operation.0 == "set" || self.operation.0 == "toggle"
+    }
+
+    /// Check if the operation checks the bit
+    pub fn checks(&self) -> bool {
+        self.operation.0 == "isset" || self.operation.0 == "isnotset"
+    }
+
+    /// Check if the operation unsets the bit
+    pub fn unsets(&self) -> bool {
+        self.operation.0 == "unset"
+    }
+
+    /// Get the track, if the option does not accept it (e.g. "ip_any" of an xbit)
+    ///
+    /// The track is kept as it was written, so it can still be reported.
+    pub fn unknown_track(&self) -> Option<&Spanned<String>> {
+        self.track
+            .as_ref()
+            .filter(|(track, _)| !self.kind.tracks().contains(&track.as_str()))
+    }
+}
+
+impl Rule {
+    /// Get every flowbits, xbits and hostbits option of the signature
+    pub fn bits_operations(&self) -> Vec<BitsOperation> {
+        self.options
+            .iter()
+            .flatten()
+            .filter_map(|(option, span)| {
+                let (keyword, _) = option.keyword();
+                let kind = BitsKind::ALL
+                    .into_iter()
+                    .find(|kind| kind.keyword() == keyword)?;
+                let mut values = option.values().into_iter();
+                let operation = values.next()?;
+                let names = values
+                    .next()
+                    .map(|names| split_names(&names))
+                    .unwrap_or_default();
+                let mut track = None;
+                let mut expire = None;
+                values.for_each(|value| {
+                    let mut words = words(&value).into_iter();
+                    match (words.next(), words.next()) {
+                        (Some((word, _)), argument) if word == "track" => track = argument,
+                        (Some((word, _)), argument) if word == "expire" => expire = argument,
+                        // Hostbits accept the direction without the track keyword
+                        (Some(direction), None) => track = Some(direction),
+                        _ => (),
+                    }
+                });
+                Some(BitsOperation {
+                    kind,
+                    operation,
+                    names,
+                    track,
+                    expire,
+                    span: span.clone(),
+                })
+            })
+            .collect()
+    }
+}
+
+impl Rule {
+    /// Get every flowbits option of the signature
+    pub fn flowbits(&self) -> Vec<BitsOperation> {
+        self.bits_operations()
+            .into_iter()
+            .filter(|operation| operation.kind == BitsKind::Flowbits)
+            .collect()
+    }
+
+    /// Get every xbits option of the signature
+    pub fn xbits(&self) -> Vec<BitsOperation> {
+        self.bits_operations()
+            .into_iter()
+            .filter(|operation| operation.kind == BitsKind::Xbits)
+            .collect()
+    }
+}
+
+/// Split the names combined with | or &, keeping the span of every name
+fn split_names((names, span): &Spanned<String>) -> Vec<Spanned<String>> {
+    let mut start = 0;
+    names
+        .split(['|', '&'])
+        .map(|name| {
+            let leading = name.len() - name.trim_start().len();
+            let name_start = span.start + start + leading;
+            start += name.len() + 1;
+            let name = name.trim().to_string();
+            let name_span = name_start..name_start + name.len();
+            (name, name_span)
+        })
+        .collect()
+}
+
+/// Split a value into its words, keeping the span of every word
+pub(super) fn words((value, span): &Spanned<String>) -> Vec<Spanned<String>> {
+    let mut words = vec![];
+    let mut start = None;
+    for (index, char) in value.char_indices().chain([(value.len(), ' ')]) {
+        match (char.is_whitespace(), start) {
+            (true, Some(word_start)) => {
+                let word = value[word_start..index].to_string();
+                words.push((word, span.start + word_start..span.start + index));
+                start = None;
+            }
+            (false, None) => start = Some(index),
+            _ => (),
+        }
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use chumsky::Parser;
+
+    use super::*;
+
+    fn operations(options: &str) -> Vec<BitsOperation> {
+        let line = format!("alert tcp any any -> any any ({} sid:1;)", options);
+        let (rule, _) = Rule::parser().parse(line.as_str()).unwrap();
+        rule.bits_operations()
+    }
+
+    fn text<T: Clone>(spanned: &[Spanned<T>]) -> Vec<T> {
+        spanned.iter().map(|(value, _)| value.clone()).collect()
+    }
+
+    #[test]
+    fn flowbits_names_are_split() {
+        let options = "flowbits:isset,a|b; flowbits:set, c & d; flowbits:noalert;";
+        let operations = operations(options);
+        assert_eq!(operations.len(), 3);
+        assert_eq!(operations[0].kind, BitsKind::Flowbits);
+        assert_eq!(text(&operations[0].names), vec!["a", "b"]);
+        assert!(operations[0].checks());
+        assert_eq!(text(&operations[1].names), vec!["c", "d"]);
+        assert!(operations[1].sets());
+        // The spans point to the names inside the signature
+        let line = format!("alert tcp any any -> any any ({} sid:1;)", options);
+        operations[..2]
+            .iter()
+            .flat_map(|operation| operation.names.iter())
+            .for_each(|(name, span)| assert_eq!(&line[span.clone()], name));
+        assert!(operations[2].names.is_empty());
+        assert!(!operations[2].sets() && !operations[2].checks());
+    }
+
+    #[test]
+    fn xbits_and_hostbits_are_tracked() {
+        let operations =
+            operations("xbits:set,bad.actor,track ip_src,expire 3600; hostbits:isset,seen,to_dst;");
+        assert_eq!(operations[0].kind, BitsKind::Xbits);
+        assert_eq!(text(&operations[0].names), vec!["bad.actor"]);
+        assert_eq!(operations[0].track.as_ref().unwrap().0, "ip_src");
+        assert_eq!(operations[0].expire.as_ref().unwrap().0, "3600");
+        assert_eq!(operations[1].kind, BitsKind::Hostbits);
+        assert_eq!(operations[1].track.as_ref().unwrap().0, "to_dst");
+        assert_eq!(operations[1].expire, None);
+    }
+
+    #[test]
+    fn flowbits_are_told_apart_from_the_other_bits() {
+        let line =
+            "alert tcp any any -> any any (xbits:set,a,track ip_src; flowbits:set,b; sid:1;)";
+        let (rule, _) = Rule::parser().parse(line).unwrap();
+        let flowbits = rule.flowbits();
+        assert_eq!(flowbits.len(), 1);
+        assert_eq!(text(&flowbits[0].names), vec!["b"]);
+    }
+
+    #[test]
+    fn unknown_tracks_of_xbits_are_kept() {
+        let line = "alert tcp any any -> any any (flowbits:set,a; xbits:set,b,track ip_any; xbits:isset,c,track ip_pair; sid:1;)";
+        let (rule, _) = Rule::parser().parse(line).unwrap();
+        let xbits = rule.xbits();
+        assert_eq!(xbits.len(), 2);
+        assert_eq!(text(&xbits[0].names), vec!["b"]);
+        let (track, span) = xbits[0].unknown_track().unwrap();
+        assert_eq!(track, "ip_any");
+        assert_eq!(&line[span.clone()], "ip_any");
+        assert_eq!(xbits[1].unknown_track(), None);
+        assert_eq!(xbits[1].track.as_ref().unwrap().0, "ip_pair");
+    }
+}
@@ -0,0 +1,311 @@
+//! Structured view of the byte_test, byte_jump and byte_extract options
+//!
+//! These options convert a number of bytes of the payload and compare it,
+//! jump over it or store it, e.g. `byte_test:4,>,1000,0,relative,string,dec;`.
+//! Their arguments are positional, followed by flags in any order.
+use std::fmt;
+
+use crate::rule::{bits::words, options::RuleOption, Rule, Span, Spanned};
+
+/// The largest number of bytes, which can be converted
+pub const MAX_BYTE_COUNT: u32 = 10;
+
+/// The operators of byte_test, each of them can be negated with a "!"
+pub const BYTE_TEST_OPERATORS: [&str; 7] = ["<", ">", "=", "<=", ">=", "&", "^"];
+
+/// The flags, which are followed by a value (e.g. "multiplier 2")
+const VALUE_FLAGS: [&str; 3] = ["bitmask", "multiplier", "post_offset"];
+
+/// The keyword of a byte option
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ByteKeyword {
+    Test,
+    Jump,
+    Extract,
+}
+
+impl ByteKeyword {
+    pub const ALL: [ByteKeyword; 3] = [ByteKeyword::Test, ByteKeyword::Jump, ByteKeyword::Extract];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ByteKeyword::Test => "byte_test",
+            ByteKeyword::Jump => "byte_jump",
+            ByteKeyword::Extract => "byte_extract",
+        }
+    }
+
+    /// The flags the keyword accepts after its positional arguments
+    pub fn flags(&self) -> &'static [&'static str] {
+        match self {
+            ByteKeyword::Test => &[
+                "relative", "big", "little", "string", "hex", "dec", "oct", "dce", "bitmask",
+            ],
+            ByteKeyword::Jump => &[
+                "relative",
+                "multiplier",
+                "big",
+                "little",
+                "string",
+                "hex",
+                "dec",
+                "oct",
+                "align",
+                "from_beginning",
+                "from_end",
+                "post_offset",
+                "dce",
+                "bitmask",
+            ],
+            ByteKeyword::Extract => &[
+                "relative",
+                "multiplier",
+                "big",
+                "little",
+                "dce",
+                "string",
+                "hex",
+                "dec",
+                "oct",
+                "align",
+            ],
+        }
+    }
+
+    /// Describe the positional arguments of the keyword
+    fn usage(&self) -> &'static str {
+        match self {
+            ByteKeyword::Test => "the number of bytes, an operator, a value and an offset",
+            ByteKeyword::Jump => "the number of bytes and an offset",
+            ByteKeyword::Extract => "the number of bytes, an offset and the name of a variable",
+        }
+    }
+}
+
+impl fmt::Display for ByteKeyword {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// A single byte_test, byte_jump or byte_extract option
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ByteOperation {
+    pub keyword: ByteKeyword,
+    /// The number of bytes to convert (or a variable of byte_extract)
+    pub count: Option<Spanned<String>>,
+    /// The operator of byte_test, along with its "!" (e.g. "!=")
+    pub operator: Option<Spanned<String>>,
+    /// The value byte_test compares to
+    pub value: Option<Spanned<String>>,
+    pub offset: Option<Spanned<String>>,
+    /// The name of the variable byte_extract stores to
+    pub name: Option<Spanned<String>>,
+    /// The flags after the positional arguments (e.g. "multiplier" and "2")
+    pub flags: Vec<(Spanned<String>, Option<Spanned<String>>)>,
+    /// The span of the whole option
+    pub span: Span,
+}
+
+impl ByteOperation {
+    /// Split the values of the option into the positional arguments and the flags
+    fn parse(keyword: ByteKeyword, option: &RuleOption, span: &Span) -> ByteOperation {
+        let mut values = option
+            .values()
+            .into_iter()
+            .filter(|(value, _)| !value.is_empty());
+        let count = values.next();
+        let (operator, value) = match keyword {
+            ByteKeyword::Test => (values.next(), values.next()),
+            _ => (None, None),
+        };
+        let offset = values.next();
+        let name = match keyword {
+            ByteKeyword::Extract => values.next(),
+            _ => None,
+        };
+        let flags = values
+            .filter_map(|value| {
+                let mut words = words(&value).into_iter();
+                Some((words.next()?, words.next()))
+            })
+            .collect();
+        ByteOperation {
+            keyword,
+            count,
+            operator,
+            value,
+            offset,
+            name,
+            flags,
+            span: span.clone(),
+        }
+    }
+
+    /// Get a flag by its name, along with its value
+    pub fn flag(&self, name: &str) -> Option<&(Spanned<String>, Option<Spanned<String>>)> {
+        self.flags.iter().find(|((flag, _), _)| flag == name)
+    }
+
+    /// Find the mistakes of the arguments: missing arguments, byte counts out
+    /// of range, unknown operators and unknown flags
+    pub fn errors(&self) -> Vec<Spanned<String>> {
+        let mut errors = vec![];
+        let positional = match self.keyword {
+            ByteKeyword::Test => vec![&self.count, &self.operator, &self.value, &self.offset],
+            ByteKeyword::Jump => vec![&self.count, &self.offset],
+            ByteKeyword::Extract => vec![&self.count, &self.offset, &self.name],
+        };
+        if positional.iter().any(|argument| argument.is_none()) {
+            errors.push((
+                format!("{} needs {}", self.keyword, self.keyword.usage()),
+                self.span.clone(),
+            ));
+        }
+        if let Some((count, span)) = &self.count {
+            // byte_test and byte_jump accept a variable of byte_extract as well
+            let is_variable = self.keyword != ByteKeyword::Extract
+                && count.starts_with(|char: char| char.is_alphabetic() || char == '_');
+            let in_range = count
+                .parse::<u32>()
+                .is_ok_and(|count| (1..=MAX_BYTE_COUNT).contains(&count));
+            if !in_range && !is_variable {
+                errors.push((
+                    format!(
+                        "the number of bytes should be between 1 and {}, found \"{}\"",
+                        MAX_BYTE_COUNT, count
+                    ),
+                    span.clone(),
+                ));
+            }
+        }
+        if let Some((operator, span)) = &self.operator {
+            let unnegated = operator.strip_prefix('!').unwrap_or(operator).trim_start();
+            if !BYTE_TEST_OPERATORS.contains(&unnegated) {
+                errors.push((
+                    format!(
+                        "unknown operator \"{}\", expected one of {}",
+                        operator,
+                        BYTE_TEST_OPERATORS.join(" ")
+                    ),
+                    span.clone(),
+                ));
+            }
+        }
+        self.flags.iter().for_each(|((flag, span), value)| {
+            if !self.keyword.flags().contains(&flag.as_str()) {
+                errors.push((
+                    format!("unknown {} flag \"{}\"", self.keyword, flag),
+                    span.clone(),
+                ));
+            } else if VALUE_FLAGS.contains(&flag.as_str()) && value.is_none() {
+                errors.push((format!("{} needs a value", flag), span.clone()));
+            }
+        });
+        errors
+    }
+}
+
+impl Rule {
+    /// Get every byte_test, byte_jump and byte_extract option of the signature,
+    /// in the order of the signature
+    pub fn byte_operations(&self) -> Vec<ByteOperation> {
+        self.options
+            .iter()
+            .flatten()
+            .filter_map(|(option, span)| {
+                let (keyword, _) = option.keyword();
+                let keyword = ByteKeyword::ALL
+                    .into_iter()
+                    .find(|known| known.as_str() == keyword)?;
+                Some(ByteOperation::parse(keyword, option, span))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chumsky::Parser;
+
+    use super::*;
+
+    fn operations(options: &str) -> Vec<ByteOperation> {
+        let line = format!("alert tcp any any -> any any ({} sid:1;)", options);
+        let (rule, _) = Rule::parser().parse(line.as_str()).unwrap();
+        rule.byte_operations()
+    }
+
+    fn errors(options: &str) -> Vec<String> {
+        operations(options)
+            .iter()
+            .flat_map(|operation| operation.errors())
+            .map(|(message, _)| message)
+            .collect()
+    }
+
+    fn text(spanned: &Option<Spanned<String>>) -> Option<&str> {
+        spanned.as_ref().map(|(value, _)| value.as_str())
+    }
+
+    #[test]
+    fn positional_arguments_are_split_from_the_flags() {
+        let operations = operations(
+            "byte_extract:2,0,len,relative,multiplier 2; byte_test:4,!=,len,0,relative,string,dec;",
+        );
+        let extract = &operations[0];
+        assert_eq!(extract.keyword, ByteKeyword::Extract);
+        assert_eq!(text(&extract.count), Some("2"));
+        assert_eq!(text(&extract.offset), Some("0"));
+        assert_eq!(text(&extract.name), Some("len"));
+        let ((_, _), value) = extract.flag("multiplier").unwrap();
+        assert_eq!(text(value), Some("2"));
+        assert!(extract
+            .flag("relative")
+            .is_some_and(|(_, value)| value.is_none()));
+
+        let test = &operations[1];
+        assert_eq!(test.keyword, ByteKeyword::Test);
+        assert_eq!(text(&test.operator), Some("!="));
+        assert_eq!(text(&test.value), Some("len"));
+        assert_eq!(test.flags.len(), 3);
+        assert!(operations
+            .iter()
+            .all(|operation| operation.errors().is_empty()));
+    }
+
+    #[test]
+    fn flags_are_checked_against_the_keyword() {
+        assert_eq!(
+            errors("byte_test:1,=,1,0,align;"),
+            vec!["unknown byte_test flag \"align\""]
+        );
+        assert!(errors("byte_jump:1,0,align,from_beginning,post_offset 2;").is_empty());
+        assert_eq!(
+            errors("byte_jump:1,0,post_offset;"),
+            vec!["post_offset needs a value"]
+        );
+    }
+
+    #[test]
+    fn arguments_are_validated() {
+        assert_eq!(
+            errors("byte_test:4,>,1000;"),
+            vec!["byte_test needs the number of bytes, an operator, a value and an offset"]
+        );
+        assert_eq!(
+            errors("byte_jump:11,0;"),
+            vec!["the number of bytes should be between 1 and 10, found \"11\""]
+        );
+        assert_eq!(
+            errors("byte_test:2,=>,1,0;"),
+            vec!["unknown operator \"=>\", expected one of < > = <= >= & ^"]
+        );
+        // Only byte_test and byte_jump take a variable as the number of bytes
+        assert!(errors("byte_jump:len,0;").is_empty());
+        assert_eq!(
+            errors("byte_extract:len,0,name;"),
+            vec!["the number of bytes should be between 1 and 10, found \"len\""]
+        );
+    }
+}
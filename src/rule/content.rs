@@ -0,0 +1,357 @@
+//! Structured view of the pattern of a content
+//!
+//! A pattern mixes text with hex bytes between pipes, e.g.
+//! `content:"|00 01 02|GET|0d 0a|";`. An escaped pipe (`\|`) is part of the
+//! text.
+use std::fmt;
+
+use crate::rule::{options::unescape, options::ContentMatch, pcre::Columns, Span, Spanned};
+
+/// A part of the pattern of a content
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContentSegment {
+    /// Text, escapes are kept as they are written (e.g. "GET\|")
+    Text(Spanned<String>),
+    /// The hex digits between two pipes (e.g. "0d 0a"), the span does not
+    /// include the pipes
+    Bytes {
+        digits: Spanned<String>,
+        /// The closing pipe is missing
+        unclosed: bool,
+    },
+}
+
+impl ContentSegment {
+    pub fn span(&self) -> &Span {
+        match self {
+            ContentSegment::Text((_, span)) => span,
+            ContentSegment::Bytes { digits, .. } => &digits.1,
+        }
+    }
+
+    /// Get the bytes the segment matches
+    ///
+    /// Returns the error along with its span, if the hex bytes are not closed,
+    /// have an odd number of digits or characters other than hex digits.
+    pub fn bytes(&self) -> Result<Vec<u8>, Spanned<String>> {
+        let ((digits, span), unclosed) = match self {
+            ContentSegment::Text((text, _)) => return Ok(unescape(text).into_bytes()),
+            ContentSegment::Bytes { digits, unclosed } => (digits, unclosed),
+        };
+        if *unclosed {
+            let message = "the hex bytes are never closed with a \"|\"".to_string();
+            return Err((message, span.start.saturating_sub(1)..span.end));
+        }
+        let invalid = digits
+            .chars()
+            .enumerate()
+            .find(|(_, char)| !char.is_whitespace() && !char.is_ascii_hexdigit());
+        if let Some((index, char)) = invalid {
+            let column = span.start + index;
+            let message = format!("\"{}\" is not a hex digit", char);
+            return Err((message, column..column + 1));
+        }
+        let digits = digits
+            .chars()
+            .filter(|char| !char.is_whitespace())
+            .collect::<Vec<_>>();
+        if digits.len() % 2 != 0 {
+            let message = format!("odd number of hex digits ({})", digits.len());
+            return Err((message, span.clone()));
+        }
+        Ok(digits
+            .chunks(2)
+            .filter_map(|pair| u8::from_str_radix(&pair.iter().collect::<String>(), 16).ok())
+            .collect())
+    }
+
+    /// Find every mistake of the hex bytes, along with the span of the bad character
+    ///
+    /// Unlike [ContentSegment::bytes], every character other than a hex digit
+    /// is reported. An unclosed segment points to its opening pipe, an odd
+    /// number of digits to the digit without a pair.
+    pub fn errors(&self) -> Vec<Spanned<String>> {
+        let ContentSegment::Bytes {
+            digits: (digits, span),
+            unclosed,
+        } = self
+        else {
+            return vec![];
+        };
+        if *unclosed {
+            let pipe = span.start.saturating_sub(1);
+            let message = "the hex bytes are never closed with a \"|\"".to_string();
+            return vec![(message, pipe..pipe + 1)];
+        }
+        let errors = digits
+            .chars()
+            .enumerate()
+            .filter(|(_, char)| !char.is_whitespace() && !char.is_ascii_hexdigit())
+            .map(|(index, char)| {
+                let column = span.start + index;
+                (
+                    format!("\"{}\" is not a hex digit", char),
+                    column..column + 1,
+                )
+            })
+            .collect::<Vec<_>>();
+        if !errors.is_empty() {
+            return errors;
+        }
+        let digits = digits
+            .chars()
+            .enumerate()
+            .filter(|(_, char)| !char.is_whitespace())
+            .collect::<Vec<_>>();
+        match digits.last() {
+            Some((index, _)) if digits.len() % 2 != 0 => {
+                let column = span.start + index;
+                let message = format!(
+                    "odd number of hex digits ({}), this digit has no pair",
+                    digits.len()
+                );
+                vec![(message, column..column + 1)]
+            }
+            _ => vec![],
+        }
+    }
+}
+
+/// Writes the segment back as it was written, along with its pipes
+impl fmt::Display for ContentSegment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ContentSegment::Text((text, _)) => write!(f, "{}", text),
+            ContentSegment::Bytes {
+                digits: (digits, _),
+                unclosed: true,
+            } => write!(f, "|{}", digits),
+            ContentSegment::Bytes {
+                digits: (digits, _),
+                ..
+            } => write!(f, "|{}|", digits),
+        }
+    }
+}
+
+impl ContentMatch {
+    /// Split the pattern into its text and its hex bytes
+    ///
+    /// Empty text between two hex parts is skipped, so writing the segments
+    /// one after another gives back the pattern.
+    pub fn segments(&self) -> Vec<ContentSegment> {
+        let (pattern, span) = &self.pattern;
+        let chars = pattern.chars().collect::<Vec<_>>();
+        // The escapes of an unquoted pattern are kept, so no column is skipped
+        let columns = Columns {
+            start: span.start + self.quoted as usize,
+            chars: match self.quoted {
+                true => chars.clone(),
+                false => vec![],
+            },
+        };
+        let mut segments = vec![];
+        let mut start = 0;
+        let mut in_bytes = false;
+        let mut index = 0;
+        while index < chars.len() {
+            match chars[index] {
+                // The escaped character is part of the text (e.g. \|)
+                '\\' if !in_bytes => index += 1,
+                '|' => {
+                    segments.extend(segment(&chars[start..index], &columns, start, in_bytes));
+                    in_bytes = !in_bytes;
+                    start = index + 1;
+                }
+                _ => (),
+            }
+            index += 1;
+        }
+        let end = chars.len();
+        let last = segment(&chars[start.min(end)..], &columns, start.min(end), in_bytes);
+        segments.extend(last.map(|last| match last {
+            ContentSegment::Bytes { digits, .. } => ContentSegment::Bytes {
+                digits,
+                unclosed: true,
+            },
+            text => text,
+        }));
+        segments
+    }
+}
+
+/// Create a segment from its characters, text without any characters is skipped
+fn segment(chars: &[char], columns: &Columns, start: usize, bytes: bool) -> Option<ContentSegment> {
+    let value = (
+        chars.iter().collect::<String>(),
+        columns.span(start..start + chars.len()),
+    );
+    match bytes {
+        true => Some(ContentSegment::Bytes {
+            digits: value,
+            unclosed: false,
+        }),
+        false if chars.is_empty() => None,
+        false => Some(ContentSegment::Text(value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rule::AST;
+
+    /// The column of the first character of the options
+    const START: usize = 31;
+
+    fn signature(options: &str) -> String {
+        format!("alert http any any -> any any ({} sid:1;)", options)
+    }
+
+    /// Get the segments of the first content of a signature with the given options
+    fn segments(options: &str) -> Vec<ContentSegment> {
+        let (ast, _) = AST::parse_str(&signature(options));
+        let (rule, _) = ast.rules.get(&0).expect("the signature is not valid");
+        rule.content_matches().remove(0).segments()
+    }
+
+    fn text(text: &str, span: Span) -> ContentSegment {
+        ContentSegment::Text((text.to_string(), span))
+    }
+
+    fn bytes(digits: &str, span: Span) -> ContentSegment {
+        ContentSegment::Bytes {
+            digits: (digits.to_string(), span),
+            unclosed: false,
+        }
+    }
+
+    #[test]
+    fn hex_bytes_and_text_alternate() {
+        // The pattern starts after content:"
+        let pattern = START + 9;
+        assert_eq!(
+            segments("content:\"|00 01 02|GET|0d 0a|\";"),
+            vec![
+                bytes("00 01 02", pattern + 1..pattern + 9),
+                text("GET", pattern + 10..pattern + 13),
+                bytes("0d 0a", pattern + 14..pattern + 19),
+            ]
+        );
+        let decoded = segments("content:\"|00 01 02|GET|0d 0a|\";")
+            .iter()
+            .map(|segment| segment.bytes().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            decoded,
+            vec![vec![0, 1, 2], b"GET".to_vec(), vec![0x0d, 0x0a]]
+        );
+        assert_eq!(
+            segments("content:\"a||b\";"),
+            vec![
+                text("a", pattern..pattern + 1),
+                bytes("", pattern + 2..pattern + 2),
+                text("b", pattern + 3..pattern + 4),
+            ]
+        );
+    }
+
+    #[test]
+    fn escaped_pipes_are_text() {
+        let pattern = START + 9;
+        let segments = segments("content:\"a\\|b|41|\";");
+        assert_eq!(
+            segments,
+            vec![
+                text("a\\|b", pattern..pattern + 4),
+                bytes("41", pattern + 5..pattern + 7),
+            ]
+        );
+        assert_eq!(segments[0].bytes().unwrap(), b"a|b".to_vec());
+    }
+
+    #[test]
+    fn escaped_quotes_keep_their_columns() {
+        // The parser removes the backslash of \", the spans still point into the line
+        let pattern = START + 9;
+        assert_eq!(
+            segments("content:\"say \\\"hi\\\"|0a|\";"),
+            vec![
+                text("say \"hi\"", pattern..pattern + 10),
+                bytes("0a", pattern + 11..pattern + 13),
+            ]
+        );
+    }
+
+    #[test]
+    fn hex_errors_point_to_the_bad_character() {
+        let pattern = START + 9;
+        let errors = |options: &str| {
+            segments(options)
+                .iter()
+                .flat_map(|segment| segment.errors())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            errors("content:\"|0a 0g|x|zz|\";"),
+            vec![
+                (
+                    "\"g\" is not a hex digit".to_string(),
+                    pattern + 5..pattern + 6
+                ),
+                (
+                    "\"z\" is not a hex digit".to_string(),
+                    pattern + 9..pattern + 10
+                ),
+                (
+                    "\"z\" is not a hex digit".to_string(),
+                    pattern + 10..pattern + 11
+                ),
+            ]
+        );
+        assert_eq!(
+            errors("content:\"|0a 0|\";"),
+            vec![(
+                "odd number of hex digits (3), this digit has no pair".to_string(),
+                pattern + 4..pattern + 5
+            )]
+        );
+        assert_eq!(
+            errors("content:\"GET |0d 0a\";"),
+            vec![(
+                "the hex bytes are never closed with a \"|\"".to_string(),
+                pattern + 4..pattern + 5
+            )]
+        );
+        assert!(errors("content:\"a\\|b|41 42|c\";").is_empty());
+    }
+
+    #[test]
+    fn segments_give_back_the_pattern() {
+        let patterns = [
+            "|00 01 02|GET|0d 0a|",
+            "GET",
+            "|41|",
+            "a||b",
+            "a\\|b|41|",
+            "|0D 0A|Host: |20|",
+            "|41 42",
+        ];
+        for pattern in patterns {
+            let options = format!("content:\"{}\";", pattern);
+            let written = segments(&options)
+                .iter()
+                .map(ToString::to_string)
+                .collect::<String>();
+            assert_eq!(written, pattern);
+            // The signature is written back with the same pipes
+            let (ast, _) = AST::parse_str(&signature(&options));
+            let (rule, _) = ast.rules.get(&0).unwrap();
+            assert!(
+                rule.to_string().contains(&format!("\"{}\"", pattern)),
+                "{}",
+                rule
+            );
+        }
+    }
+}
@@ -0,0 +1,145 @@
+//! Structured view of the dataset and datarep options
+//!
+//! A dataset is a named set of values, which the signature checks or adds the
+//! buffer before it to, e.g.
+//! `dataset:isset,ua-seen,type string,load ua-seen.lst;`. A datarep assigns a
+//! reputation to every value and compares it instead, e.g.
+//! `datarep:dns.query,>,20,load dns.csv,type string;`.
+use crate::rule::{bits::words, options::RuleOption, Rule, Span, Spanned};
+
+/// The arguments of a dataset, which name a file
+pub const DATASET_FILE_ARGUMENTS: [&str; 3] = ["load", "save", "state"];
+
+/// The arguments accepted after the name of a dataset
+pub const DATASET_ARGUMENTS: [&str; 6] = ["type", "state", "load", "save", "memcap", "hashsize"];
+
+/// The keywords, which use a dataset
+pub const DATASET_KEYWORDS: [&str; 2] = ["dataset", "datarep"];
+
+/// A single dataset or datarep option
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DatasetOperation {
+    /// The keyword of the option (dataset or datarep)
+    pub keyword: Spanned<String>,
+    /// The operation of a dataset (e.g. "isset"), a datarep has none
+    pub operation: Option<Spanned<String>>,
+    /// The name of the dataset
+    pub name: Spanned<String>,
+    /// The operator and the value, the reputation of a datarep is compared to
+    /// (e.g. ">" and "20")
+    pub comparison: Option<(Spanned<String>, Spanned<String>)>,
+    /// The arguments after the name (e.g. "load" and "ua-seen.lst")
+    pub arguments: Vec<(Spanned<String>, Option<Spanned<String>>)>,
+    /// The span of the whole option
+    pub span: Span,
+}
+
+impl DatasetOperation {
+    fn parse(option: &RuleOption, span: &Span) -> Option<DatasetOperation> {
+        let keyword = option.keyword().clone();
+        let mut values = option.values().into_iter();
+        let (operation, name, comparison) = match keyword.0.as_str() {
+            "dataset" => (Some(values.next()?), values.next()?, None),
+            _ => {
+                let name = values.next()?;
+                let comparison = values.next().zip(values.next());
+                (None, name, comparison)
+            }
+        };
+        let arguments = values
+            .filter_map(|value| {
+                let mut words = words(&value).into_iter();
+                Some((words.next()?, words.next()))
+            })
+            .collect();
+        Some(DatasetOperation {
+            keyword,
+            operation,
+            name,
+            comparison,
+            arguments,
+            span: span.clone(),
+        })
+    }
+
+    /// Get the value of an argument (e.g. the file of "load")
+    pub fn argument(&self, key: &str) -> Option<&Spanned<String>> {
+        self.arguments
+            .iter()
+            .find(|((argument, _), _)| argument == key)
+            .and_then(|(_, value)| value.as_ref())
+    }
+
+    /// Get the files of the load, save and state arguments, along with their spans
+    pub fn files(&self) -> Vec<&Spanned<String>> {
+        self.arguments
+            .iter()
+            .filter(|((argument, _), _)| DATASET_FILE_ARGUMENTS.contains(&argument.as_str()))
+            .filter_map(|(_, file)| file.as_ref())
+            .collect()
+    }
+}
+
+impl Rule {
+    /// Get every dataset and datarep option of the signature
+    pub fn datasets(&self) -> Vec<DatasetOperation> {
+        self.options
+            .iter()
+            .flatten()
+            .filter(|(option, _)| DATASET_KEYWORDS.contains(&option.keyword().0.as_str()))
+            .filter_map(|(option, span)| DatasetOperation::parse(option, span))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chumsky::Parser;
+
+    use super::*;
+
+    const LINE: &str = "alert dns any any -> any any (dataset:isset,ua-seen,type string,load ua.lst; datarep:dns.query, >, 20, load dns.csv, type string; sid:1;)";
+
+    #[test]
+    fn datasets_and_datareps_are_parsed() {
+        let (rule, _) = Rule::parser().parse(LINE).unwrap();
+        let datasets = rule.datasets();
+        assert_eq!(datasets.len(), 2);
+        let dataset = &datasets[0];
+        assert_eq!(dataset.keyword.0, "dataset");
+        assert_eq!(dataset.operation.as_ref().unwrap().0, "isset");
+        assert_eq!(dataset.name.0, "ua-seen");
+        assert_eq!(dataset.comparison, None);
+        assert_eq!(dataset.argument("type").unwrap().0, "string");
+
+        let datarep = &datasets[1];
+        assert_eq!(datarep.operation, None);
+        assert_eq!(datarep.name.0, "dns.query");
+        let ((operator, _), (value, _)) = datarep.comparison.as_ref().unwrap();
+        assert_eq!((operator.as_str(), value.as_str()), (">", "20"));
+        assert_eq!(datarep.argument("memcap"), None);
+    }
+
+    #[test]
+    fn files_point_inside_the_signature() {
+        let (rule, _) = Rule::parser().parse(LINE).unwrap();
+        let files = rule
+            .datasets()
+            .iter()
+            .flat_map(|dataset| {
+                dataset
+                    .files()
+                    .into_iter()
+                    .map(|(file, span)| (file.clone(), LINE[span.clone()].to_string()))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            files,
+            vec![
+                ("ua.lst".to_string(), "ua.lst".to_string()),
+                ("dns.csv".to_string(), "dns.csv".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,109 @@
+//! Structured view of the fast_pattern modifier
+//!
+//! The modifier selects the content the engine prefilters on. It is written
+//! without a value (`fast_pattern;`), to only use the content for the
+//! prefilter (`fast_pattern:only;`) or to use a part of the content
+//! (`fast_pattern:10,20;` for 20 bytes after the first 10).
+use crate::rule::{options::ContentMatch, options::RuleOption, Rule, Span, Spanned};
+
+/// How the content is used as the fast pattern
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FastPatternKind {
+    /// fast_pattern;
+    Content,
+    /// fast_pattern:only;, along with the span of "only"
+    Only(Span),
+    /// fast_pattern:offset,length;
+    Chop {
+        offset: Spanned<u32>,
+        length: Spanned<u32>,
+    },
+}
+
+/// A single fast_pattern modifier
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FastPattern {
+    pub kind: FastPatternKind,
+    /// The span of the whole option
+    pub span: Span,
+}
+
+impl FastPattern {
+    /// Parse a fast_pattern modifier, None for any other option
+    ///
+    /// Returns the error along with its span, if the value is not "only" or
+    /// an offset and a length.
+    pub fn parse(option: &RuleOption, span: &Span) -> Option<Result<FastPattern, Spanned<String>>> {
+        let (keyword, keyword_span) = option.keyword();
+        if keyword != "fast_pattern" {
+            return None;
+        }
+        let values = option.values();
+        let kind = match values.as_slice() {
+            [] => Ok(FastPatternKind::Content),
+            [(value, span)] if value == "only" => Ok(FastPatternKind::Only(span.clone())),
+            [(value, span)] if value.is_empty() => Err((
+                "fast_pattern needs \"only\" or an offset and a length".to_string(),
+                span.clone(),
+            )),
+            [(value, span)] => Err((
+                format!(
+                    "expected \"only\" or an offset and a length, found \"{}\"",
+                    value
+                ),
+                span.clone(),
+            )),
+            [offset, length] => number(offset, "offset").and_then(|offset| {
+                let length = number(length, "length")?;
+                Ok(FastPatternKind::Chop { offset, length })
+            }),
+            [_, _, extra, ..] => Err((
+                "fast_pattern takes at most an offset and a length".to_string(),
+                extra.1.start..values.last().map_or(keyword_span.end, |(_, span)| span.end),
+            )),
+        };
+        Some(kind.map(|kind| FastPattern {
+            kind,
+            span: span.clone(),
+        }))
+    }
+}
+
+/// Parse a number of fast_pattern:offset,length;
+fn number((value, span): &Spanned<String>, name: &str) -> Result<Spanned<u32>, Spanned<String>> {
+    match value.parse() {
+        Ok(number) => Ok((number, span.clone())),
+        Err(_) => Err((
+            format!(
+                "the {} of fast_pattern should be a number, found \"{}\"",
+                name, value
+            ),
+            span.clone(),
+        )),
+    }
+}
+
+impl ContentMatch {
+    /// Get the fast_pattern of the content, if it has one
+    pub fn fast_pattern(&self) -> Option<Result<FastPattern, Spanned<String>>> {
+        self.modifiers
+            .iter()
+            .find_map(|(option, span)| FastPattern::parse(option, span))
+    }
+}
+
+impl Rule {
+    /// Get every fast_pattern modifier of the signature
+    pub fn fast_patterns(&self) -> Vec<Result<FastPattern, Spanned<String>>> {
+        self.options
+            .iter()
+            .flatten()
+            .filter_map(|(option, span)| FastPattern::parse(option, span))
+            .collect()
+    }
+
+    /// Check if the signature chooses its fast pattern, instead of the engine
+    pub fn has_fast_pattern(&self) -> bool {
+        !self.options_with_keyword("fast_pattern").is_empty()
+    }
+}
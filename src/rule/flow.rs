@@ -0,0 +1,112 @@
+//! Structured view of the flow option
+//!
+//! The flow option restricts the signature to a direction and a state of the
+//! flow, e.g. `flow:established,to_server;`. Unknown values are kept, so the
+//! signature still parses.
+use std::fmt;
+
+use crate::rule::{Rule, Spanned};
+
+/// A single value of the flow option
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FlowValue {
+    ToServer,
+    ToClient,
+    FromServer,
+    FromClient,
+    Established,
+    NotEstablished,
+    Stateless,
+    OnlyStream,
+    NoStream,
+    OnlyFrag,
+    NoFrag,
+    Other(String),
+}
+
+impl FlowValue {
+    /// All values known to Suricata
+    pub const ALL: [FlowValue; 11] = [
+        FlowValue::ToServer,
+        FlowValue::ToClient,
+        FlowValue::FromServer,
+        FlowValue::FromClient,
+        FlowValue::Established,
+        FlowValue::NotEstablished,
+        FlowValue::Stateless,
+        FlowValue::OnlyStream,
+        FlowValue::NoStream,
+        FlowValue::OnlyFrag,
+        FlowValue::NoFrag,
+    ];
+
+    /// Parse a value of the flow option, unknown values are kept as they are
+    pub fn parse(value: &str) -> FlowValue {
+        FlowValue::ALL
+            .into_iter()
+            .find(|known| known.as_str() == value)
+            .unwrap_or_else(|| FlowValue::Other(value.to_string()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            FlowValue::ToServer => "to_server",
+            FlowValue::ToClient => "to_client",
+            FlowValue::FromServer => "from_server",
+            FlowValue::FromClient => "from_client",
+            FlowValue::Established => "established",
+            FlowValue::NotEstablished => "not_established",
+            FlowValue::Stateless => "stateless",
+            FlowValue::OnlyStream => "only_stream",
+            FlowValue::NoStream => "no_stream",
+            FlowValue::OnlyFrag => "only_frag",
+            FlowValue::NoFrag => "no_frag",
+            FlowValue::Other(value) => value,
+        }
+    }
+
+    /// Describe what the value matches (None for unknown values)
+    pub fn description(&self) -> Option<&'static str> {
+        Some(match self {
+            FlowValue::ToServer => "Match on packets from the client to the server",
+            FlowValue::ToClient => "Match on packets from the server to the client",
+            FlowValue::FromServer => {
+                "Match on packets from the server to the client (same as to_client)"
+            }
+            FlowValue::FromClient => {
+                "Match on packets from the client to the server (same as to_server)"
+            }
+            FlowValue::Established => "Match on established connections",
+            FlowValue::NotEstablished => {
+                "Match on packets, which are not part of an established connection"
+            }
+            FlowValue::Stateless => {
+                "Match on packets, which are and are not part of an established connection"
+            }
+            FlowValue::OnlyStream => "Match on packets, which are reassembled by the stream engine",
+            FlowValue::NoStream => {
+                "Match on packets, which are not reassembled by the stream engine"
+            }
+            FlowValue::OnlyFrag => "Match on packets, which are reassembled from fragments",
+            FlowValue::NoFrag => "Match on packets, which are not reassembled from fragments",
+            FlowValue::Other(_) => return None,
+        })
+    }
+}
+
+impl fmt::Display for FlowValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl Rule {
+    /// Get the values of every flow option of the signature
+    pub fn flow(&self) -> Vec<Spanned<FlowValue>> {
+        self.option_values("flow")
+            .into_iter()
+            .flatten()
+            .map(|(value, span)| (FlowValue::parse(&value), span))
+            .collect()
+    }
+}
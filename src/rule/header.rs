@@ -1,9 +1,8 @@
 use ipnet::IpNet;
 use std::collections::{HashMap, HashSet};
 use std::{fmt, net::IpAddr};
-use tower_lsp::lsp_types::{
-    CompletionItem, CompletionItemKind, HoverContents, MarkupContent, SemanticTokenType,
-};
+use lsp_types::{CompletionItem, CompletionItemKind, HoverContents, MarkupContent, SemanticTokenType};
+use serde::Serialize;
 
 use crate::rule::Span;
 use crate::rule::Spanned;
@@ -16,9 +15,9 @@ use super::Hover;
 use super::Semantics;
 
 /// Represents a signature header
-#[derive(Debug, Hash, Clone, PartialEq, Eq)]
+#[derive(Debug, Hash, Clone, PartialEq, Eq, Serialize)]
 pub struct Header {
-    pub protocol: Option<Spanned<String>>,
+    pub protocol: Option<Spanned<Protocol>>,
     pub source: Option<Spanned<NetworkAddress>>,
     pub source_port: Option<Spanned<NetworkPort>>,
     pub direction: Option<Spanned<NetworkDirection>>,
@@ -27,30 +26,38 @@ pub struct Header {
 }
 impl fmt::Display for Header {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        // Go trough every part and print if the part is Some() and not None()
-        if let Some((protocol, _)) = &self.protocol {
-            write!(f, "{} ", protocol)?
-        };
-        if let Some((source, _)) = &self.source {
-            write!(f, "{} ", source)?
-        };
-        if let Some((source_port, _)) = &self.source_port {
-            write!(f, "{} ", source_port)?
-        };
-        if let Some((direction, _)) = &self.direction {
-            write!(f, "{} ", direction)?
-        };
-        if let Some((destination, _)) = &self.destination {
-            write!(f, "{} ", destination)?
-        };
-        if let Some((destination_port, _)) = &self.destination_port {
-            write!(f, "{} ", destination_port)
-        } else {
-            write!(f, "")
-        }
+        // Go trough every part and print if the part is Some() and not None(),
+        // the parts are separated by a single space, without one at the end
+        let parts = [
+            self.protocol
+                .as_ref()
+                .map(|(protocol, _)| protocol.to_string()),
+            self.source.as_ref().map(|(source, _)| source.to_string()),
+            self.source_port
+                .as_ref()
+                .map(|(source_port, _)| source_port.to_string()),
+            self.direction
+                .as_ref()
+                .map(|(direction, _)| direction.to_string()),
+            self.destination
+                .as_ref()
+                .map(|(destination, _)| destination.to_string()),
+            self.destination_port
+                .as_ref()
+                .map(|(port, _)| port.to_string()),
+        ];
+        let parts = parts.into_iter().flatten().collect::<Vec<_>>();
+        write!(f, "{}", parts.join(" "))
     }
 }
 impl Header {
+    /// Check if the direction of the header is `->` or `<>`
+    pub fn has_forward_direction(&self) -> bool {
+        matches!(
+            self.direction,
+            Some((NetworkDirection::SrcToDst, _)) | Some((NetworkDirection::Both, _))
+        )
+    }
     /// Find all variables, which are located inside the source or the destiantion
     /// fields of the header
     pub fn find_address_variables(
@@ -120,7 +127,7 @@ impl Hover for Header {
         &self,
         col: &usize,
         keywords: &HashMap<String, Keyword>,
-    ) -> Option<Spanned<tower_lsp::lsp_types::HoverContents>> {
+    ) -> Option<Spanned<lsp_types::HoverContents>> {
         // Check if col is inside the source address
         if let Some((source, span)) = &self.source {
             if span.contains(col) {
@@ -157,7 +164,7 @@ impl Hover for Header {
 }
 
 /// Represents a network address (IP, CIDR range, groups of IPs, variables, etc.)
-#[derive(Debug, Hash, Clone, PartialEq, Eq)]
+#[derive(Debug, Hash, Clone, PartialEq, Eq, Serialize)]
 pub enum NetworkAddress {
     Any(Span),
     IPAddr(Spanned<IpAddr>),
@@ -171,7 +178,7 @@ impl fmt::Display for NetworkAddress {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             NetworkAddress::Any(_) => write!(f, "any"),
-            NetworkAddress::IPAddr(ip) => write!(f, "{}", ip.0.to_string()),
+            NetworkAddress::IPAddr(ip) => write!(f, "{}", ip.0),
             NetworkAddress::CIDR(ip, mask) => write!(f, "{}/{}", ip.0, mask.0),
             NetworkAddress::IPGroup(ips) => write!(
                 f,
@@ -190,20 +197,20 @@ impl fmt::Display for NetworkAddress {
     }
 }
 impl NetworkAddress {
+    /// Check if the address matches any host (`any`)
+    pub fn is_any(&self) -> bool {
+        matches!(self, NetworkAddress::Any(_))
+    }
     pub fn find_variables(&self, name: &Option<String>) -> Option<Vec<Spanned<String>>> {
         let mut ret: Vec<Spanned<String>> = vec![];
         self.find_variables_with_array(name, &mut ret);
-        if ret.len() == 0 {
-            return None;
+        if ret.is_empty() {
+            None
         } else {
             Some(ret)
         }
     }
-    fn find_variables_with_array(
-        &self,
-        name: &Option<String>,
-        vector: &mut Vec<Spanned<String>>,
-    ) -> () {
+    fn find_variables_with_array(&self, name: &Option<String>, vector: &mut Vec<Spanned<String>>) {
         match &self {
             NetworkAddress::Any(_) => (),
             NetworkAddress::IPAddr(_) => (),
@@ -269,22 +276,14 @@ impl Semantics for NetworkAddress {
                 });
             }
             NetworkAddress::IPGroup(ips) => {
-                ips.iter().for_each(|(_, span)| {
-                    semantic_tokens.push(ImCompleteSemanticToken {
-                        start: span.start + col,
-                        length: span.len(),
-                        token_type: LEGEND_TYPE
-                            .iter()
-                            .position(|item| item == &SemanticTokenType::KEYWORD)
-                            .unwrap(),
-                    })
-                });
+                ips.iter()
+                    .for_each(|(ip, _)| ip.get_semantics(col, semantic_tokens));
             }
             NetworkAddress::NegIP(address) => {
                 let (address, span) = address.as_ref();
-                // Put the negation as a semantic token
+                // Put the negation as a semantic token, it is right before the address
                 semantic_tokens.push(ImCompleteSemanticToken {
-                    start: span.start + col,
+                    start: span.start.saturating_sub(1) + col,
                     length: 1,
                     token_type: LEGEND_TYPE
                         .iter()
@@ -311,17 +310,23 @@ impl Hover for NetworkAddress {
     fn get_hover(
         &self,
         col: &usize,
-        keywords: &HashMap<String, Keyword>,
-    ) -> Option<Spanned<tower_lsp::lsp_types::HoverContents>> {
+        _keywords: &HashMap<String, Keyword>,
+    ) -> Option<Spanned<lsp_types::HoverContents>> {
+        self.hover_at(col)
+    }
+}
+impl NetworkAddress {
+    /// Get the hover of the address under the column (the keywords are not needed)
+    fn hover_at(&self, col: &usize) -> Option<Spanned<lsp_types::HoverContents>> {
         match self {
             NetworkAddress::Any(_) => None,
             NetworkAddress::IPAddr(_) => None,
             NetworkAddress::CIDR((ip, ip_span), (mask, mask_span)) => {
-                let range = IpNet::new(ip.clone(), mask.clone());
+                let range = IpNet::new(*ip, *mask);
                 match range {
                     Ok(range) => Some((
                         HoverContents::Markup(MarkupContent {
-                            kind: tower_lsp::lsp_types::MarkupKind::Markdown,
+                            kind: lsp_types::MarkupKind::Markdown,
                             value: [
                                 format!("**{}**", range),
                                 format!("{} - {}", range.network(), range.broadcast()),
@@ -338,11 +343,11 @@ impl Hover for NetworkAddress {
             }
             NetworkAddress::IPGroup(group) => {
                 let (ip, _) = group.iter().find(|(_, span)| span.contains(col))?;
-                ip.get_hover(col, keywords)
+                ip.hover_at(col)
             }
             NetworkAddress::NegIP(ip) => {
                 let (ip, _) = ip.as_ref();
-                ip.get_hover(col, keywords)
+                ip.hover_at(col)
             }
             NetworkAddress::IPVariable(_) => None,
         }
@@ -352,11 +357,11 @@ impl Hover for NetworkAddress {
 impl Completions for NetworkAddress {
     fn get_completion(
         address_variables: &HashSet<String>,
-        port_variables: &HashSet<String>,
+        _port_variables: &HashSet<String>,
         completion_tokens: &mut Vec<CompletionItem>,
     ) {
         // Push regularly used IPs
-        let regular_ips = vec![
+        let regular_ips = [
             (
                 "192.168.0.0/16".to_string(),
                 "RFC 1918 16-bit block".to_string(),
@@ -394,7 +399,7 @@ impl Completions for NetworkAddress {
 }
 
 /// Represents a network port (along with ranges of ports, variables, etc.)
-#[derive(Debug, Hash, Clone, PartialEq, Eq)]
+#[derive(Debug, Hash, Clone, PartialEq, Eq, Serialize)]
 pub enum NetworkPort {
     Any(Span),
     Port(Spanned<u16>),
@@ -421,7 +426,7 @@ impl fmt::Display for NetworkPort {
                     .iter()
                     .map(|(port, _)| port.to_string())
                     .collect::<Vec<String>>()
-                    .join(",")
+                    .join(", ")
             ),
             NetworkPort::PortRange(from, to) => write!(f, "{}:{}", from.0, to.0),
             NetworkPort::PortOpenRange(port, up) => {
@@ -442,22 +447,22 @@ impl fmt::Display for NetworkPort {
     }
 }
 impl NetworkPort {
+    /// Check if the port matches any port (`any`)
+    pub fn is_any(&self) -> bool {
+        matches!(self, NetworkPort::Any(_))
+    }
     /// Find all variables inside the network port struct
     pub fn find_variables(&self, name: &Option<String>) -> Option<Vec<Spanned<String>>> {
         let mut ret: Vec<Spanned<String>> = vec![];
         self.find_variables_with_array(name, &mut ret);
-        if ret.len() == 0 {
-            return None;
+        if ret.is_empty() {
+            None
         } else {
             Some(ret)
         }
     }
     /// Same as [find_variables], however all results are pushed to the array.
-    fn find_variables_with_array(
-        &self,
-        name: &Option<String>,
-        vector: &mut Vec<Spanned<String>>,
-    ) -> () {
+    fn find_variables_with_array(&self, name: &Option<String>, vector: &mut Vec<Spanned<String>>) {
         match &self {
             NetworkPort::PortGroup(group) => group.iter().for_each(|(port, _)| {
                 port.find_variables_with_array(name, vector);
@@ -517,6 +522,15 @@ impl Semantics for NetworkPort {
                         .position(|item| item == &SemanticTokenType::NUMBER)
                         .unwrap(),
                 });
+                // Push the colon as a semantic token
+                semantic_tokens.push(ImCompleteSemanticToken {
+                    start: from_span.end + col,
+                    length: 1,
+                    token_type: LEGEND_TYPE
+                        .iter()
+                        .position(|item| item == &SemanticTokenType::OPERATOR)
+                        .unwrap(),
+                });
                 // Push the to port as a semantic token
                 semantic_tokens.push(ImCompleteSemanticToken {
                     start: to_span.start + col,
@@ -527,7 +541,7 @@ impl Semantics for NetworkPort {
                         .unwrap(),
                 });
             }
-            NetworkPort::PortOpenRange((_, port_span), _) => {
+            NetworkPort::PortOpenRange((_, port_span), up) => {
                 semantic_tokens.push(ImCompleteSemanticToken {
                     start: port_span.start + col,
                     length: port_span.len(),
@@ -536,12 +550,26 @@ impl Semantics for NetworkPort {
                         .position(|item| item == &SemanticTokenType::NUMBER)
                         .unwrap(),
                 });
+                // Push the colon as a semantic token, it is after the port for
+                // ranges open towards up (80:) and before it otherwise (:80)
+                let colon = match up {
+                    true => port_span.end,
+                    false => port_span.start.saturating_sub(1),
+                };
+                semantic_tokens.push(ImCompleteSemanticToken {
+                    start: colon + col,
+                    length: 1,
+                    token_type: LEGEND_TYPE
+                        .iter()
+                        .position(|item| item == &SemanticTokenType::OPERATOR)
+                        .unwrap(),
+                });
             }
             NetworkPort::NegPort(port) => {
                 let (port, span) = port.as_ref();
-                // Put the negation as a semantic token
+                // Put the negation as a semantic token, it is right before the port
                 semantic_tokens.push(ImCompleteSemanticToken {
-                    start: span.start + col,
+                    start: span.start.saturating_sub(1) + col,
                     length: 1,
                     token_type: LEGEND_TYPE
                         .iter()
@@ -569,22 +597,51 @@ impl Hover for NetworkPort {
     fn get_hover(
         &self,
         col: &usize,
-        keywords: &HashMap<String, Keyword>,
-    ) -> Option<Spanned<tower_lsp::lsp_types::HoverContents>> {
+        _keywords: &HashMap<String, Keyword>,
+    ) -> Option<Spanned<lsp_types::HoverContents>> {
+        self.hover_at(col)
+    }
+}
+impl NetworkPort {
+    /// Get the hover of the port under the column (the keywords are not needed)
+    fn hover_at(&self, col: &usize) -> Option<Spanned<lsp_types::HoverContents>> {
+        // Show the ports of a range, along with the span of the whole range
+        let range = |from: u16, to: u16, span: Span| {
+            Some((
+                HoverContents::Markup(MarkupContent {
+                    kind: lsp_types::MarkupKind::Markdown,
+                    value: format!("**{}**\n\n{} - {}", self, from, to),
+                }),
+                span,
+            ))
+        };
         match self {
             NetworkPort::Any(_) => None,
             NetworkPort::Port(_) => None,
-            NetworkPort::PortGroup(_) => None,
-            NetworkPort::PortRange(_, _) => None,
-            NetworkPort::PortOpenRange(_, _) => None,
-            NetworkPort::NegPort(_) => None,
+            NetworkPort::PortGroup(group) => {
+                let (port, _) = group.iter().find(|(_, span)| span.contains(col))?;
+                port.hover_at(col)
+            }
+            NetworkPort::PortRange((from, from_span), (to, to_span)) => {
+                range(*from, *to, from_span.start..to_span.end)
+            }
+            NetworkPort::PortOpenRange((port, span), true) => {
+                range(*port, u16::MAX, span.start..span.end + 1)
+            }
+            NetworkPort::PortOpenRange((port, span), false) => {
+                range(0, *port, span.start.saturating_sub(1)..span.end)
+            }
+            NetworkPort::NegPort(port) => {
+                let (port, _) = port.as_ref();
+                port.hover_at(col)
+            }
             NetworkPort::PortVar(_) => None,
         }
     }
 }
 impl Completions for NetworkPort {
     fn get_completion(
-        address_variables: &HashSet<String>,
+        _address_variables: &HashSet<String>,
         port_variables: &HashSet<String>,
         completion_tokens: &mut Vec<CompletionItem>,
     ) {
@@ -599,7 +656,7 @@ impl Completions for NetworkPort {
         common_ports.into_iter().for_each(|(description, port)| {
             completion_tokens.push(CompletionItem {
                 label: String::from(description),
-                insert_text: Some(String::from(port.to_string())),
+                insert_text: Some(port.to_string()),
                 kind: Some(CompletionItemKind::VALUE),
                 ..Default::default()
             })
@@ -613,7 +670,7 @@ impl Completions for NetworkPort {
         });
 
         // Push the port variables
-        port_variables.into_iter().for_each(|variable| {
+        port_variables.iter().for_each(|variable| {
             completion_tokens.push(CompletionItem {
                 label: format!("${}", variable),
                 insert_text: Some(variable.clone()),
@@ -625,8 +682,186 @@ impl Completions for NetworkPort {
     }
 }
 
+/// Represents the protocol of a signature
+#[derive(Debug, Hash, Clone, PartialEq, Eq, Serialize)]
+pub enum Protocol {
+    Ip,
+    Ipv4,
+    Ipv6,
+    Tcp,
+    TcpPkt,
+    TcpStream,
+    Udp,
+    Sctp,
+    Icmp,
+    Icmpv4,
+    Icmpv6,
+    Pkthdr,
+    Http,
+    Http1,
+    Http2,
+    Ftp,
+    FtpData,
+    Tls,
+    Smb,
+    Dcerpc,
+    Dhcp,
+    Dnp3,
+    Dns,
+    Enip,
+    Nfs,
+    Ike,
+    Krb5,
+    Ntp,
+    Rdp,
+    Rfb,
+    Sip,
+    Smtp,
+    Imap,
+    Pop3,
+    Snmp,
+    Ssh,
+    Tftp,
+    Modbus,
+    Mqtt,
+    Pgsql,
+    Quic,
+    Telnet,
+    BittorrentDht,
+    /// represents any unrecognized protocol, as it was written
+    Other(String),
+}
+
+impl Protocol {
+    /// Every recognized protocol
+    pub const ALL: [Protocol; 43] = [
+        Protocol::Ip,
+        Protocol::Ipv4,
+        Protocol::Ipv6,
+        Protocol::Tcp,
+        Protocol::TcpPkt,
+        Protocol::TcpStream,
+        Protocol::Udp,
+        Protocol::Sctp,
+        Protocol::Icmp,
+        Protocol::Icmpv4,
+        Protocol::Icmpv6,
+        Protocol::Pkthdr,
+        Protocol::Http,
+        Protocol::Http1,
+        Protocol::Http2,
+        Protocol::Ftp,
+        Protocol::FtpData,
+        Protocol::Tls,
+        Protocol::Smb,
+        Protocol::Dcerpc,
+        Protocol::Dhcp,
+        Protocol::Dnp3,
+        Protocol::Dns,
+        Protocol::Enip,
+        Protocol::Nfs,
+        Protocol::Ike,
+        Protocol::Krb5,
+        Protocol::Ntp,
+        Protocol::Rdp,
+        Protocol::Rfb,
+        Protocol::Sip,
+        Protocol::Smtp,
+        Protocol::Imap,
+        Protocol::Pop3,
+        Protocol::Snmp,
+        Protocol::Ssh,
+        Protocol::Tftp,
+        Protocol::Modbus,
+        Protocol::Mqtt,
+        Protocol::Pgsql,
+        Protocol::Quic,
+        Protocol::Telnet,
+        Protocol::BittorrentDht,
+    ];
+
+    /// Parse a protocol, ignoring its case
+    pub fn parse(protocol: &str) -> Protocol {
+        Protocol::ALL
+            .into_iter()
+            .find(|known| known.as_str().eq_ignore_ascii_case(protocol))
+            .unwrap_or_else(|| Protocol::Other(protocol.to_string()))
+    }
+
+    /// Get the protocol as Suricata names it (e.g. "ftp-data")
+    pub fn as_str(&self) -> &str {
+        match self {
+            Protocol::Ip => "ip",
+            Protocol::Ipv4 => "ipv4",
+            Protocol::Ipv6 => "ipv6",
+            Protocol::Tcp => "tcp",
+            Protocol::TcpPkt => "tcp-pkt",
+            Protocol::TcpStream => "tcp-stream",
+            Protocol::Udp => "udp",
+            Protocol::Sctp => "sctp",
+            Protocol::Icmp => "icmp",
+            Protocol::Icmpv4 => "icmpv4",
+            Protocol::Icmpv6 => "icmpv6",
+            Protocol::Pkthdr => "pkthdr",
+            Protocol::Http => "http",
+            Protocol::Http1 => "http1",
+            Protocol::Http2 => "http2",
+            Protocol::Ftp => "ftp",
+            Protocol::FtpData => "ftp-data",
+            Protocol::Tls => "tls",
+            Protocol::Smb => "smb",
+            Protocol::Dcerpc => "dcerpc",
+            Protocol::Dhcp => "dhcp",
+            Protocol::Dnp3 => "dnp3",
+            Protocol::Dns => "dns",
+            Protocol::Enip => "enip",
+            Protocol::Nfs => "nfs",
+            Protocol::Ike => "ike",
+            Protocol::Krb5 => "krb5",
+            Protocol::Ntp => "ntp",
+            Protocol::Rdp => "rdp",
+            Protocol::Rfb => "rfb",
+            Protocol::Sip => "sip",
+            Protocol::Smtp => "smtp",
+            Protocol::Imap => "imap",
+            Protocol::Pop3 => "pop3",
+            Protocol::Snmp => "snmp",
+            Protocol::Ssh => "ssh",
+            Protocol::Tftp => "tftp",
+            Protocol::Modbus => "modbus",
+            Protocol::Mqtt => "mqtt",
+            Protocol::Pgsql => "pgsql",
+            Protocol::Quic => "quic",
+            Protocol::Telnet => "telnet",
+            Protocol::BittorrentDht => "bittorrent-dht",
+            Protocol::Other(protocol) => protocol,
+        }
+    }
+}
+
+/// Recognized protocols are printed in lowercase, others as they were written
+impl fmt::Display for Protocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl Completions for Protocol {
+    fn get_completion(
+        _address_variables: &HashSet<String>,
+        _port_variables: &HashSet<String>,
+        completion_tokens: &mut Vec<CompletionItem>,
+    ) {
+        completion_tokens.extend(Protocol::ALL.iter().map(|protocol| CompletionItem {
+            label: protocol.to_string(),
+            kind: Some(CompletionItemKind::KEYWORD),
+            ..Default::default()
+        }));
+    }
+}
+
 /// Represents the networking direction
-#[derive(Debug, Hash, Clone, PartialEq, Eq)]
+#[derive(Debug, Hash, Clone, PartialEq, Eq, Serialize)]
 pub enum NetworkDirection {
     SrcToDst,
     Both,
@@ -649,17 +884,17 @@ impl fmt::Display for NetworkDirection {
 impl Hover for NetworkDirection {
     fn get_hover(
         &self,
-        col: &usize,
-        keywords: &HashMap<String, Keyword>,
-    ) -> Option<Spanned<tower_lsp::lsp_types::HoverContents>> {
+        _col: &usize,
+        _keywords: &HashMap<String, Keyword>,
+    ) -> Option<Spanned<lsp_types::HoverContents>> {
         None
     }
 }
 
 impl Completions for NetworkDirection {
     fn get_completion(
-        address_variables: &HashSet<String>,
-        port_variables: &HashSet<String>,
+        _address_variables: &HashSet<String>,
+        _port_variables: &HashSet<String>,
         completion_tokens: &mut Vec<CompletionItem>,
     ) {
         let all_directions = vec![
@@ -0,0 +1,69 @@
+//! Structured view of the lua and luajit options
+//!
+//! Both options name a script, which decides if the signature matches, e.g.
+//! `lua:my_script.lua;`. A relative script is looked up in the directory of
+//! the rule file.
+use std::path::{Path, PathBuf};
+
+use crate::rule::{Rule, Span, Spanned};
+
+/// The keywords, which run a lua script
+pub const LUA_KEYWORDS: [&str; 2] = ["lua", "luajit"];
+
+/// A single lua or luajit option
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LuaScript {
+    /// The keyword of the option (lua or luajit)
+    pub keyword: Spanned<String>,
+    /// The file of the script, as it is written
+    pub file: Spanned<String>,
+    /// The result of the script is negated (lua:!script.lua;)
+    pub negated: bool,
+    /// The span of the whole option
+    pub span: Span,
+}
+
+impl LuaScript {
+    /// Get the path of the script, relative paths are joined to the directory
+    pub fn path(&self, directory: &Path) -> PathBuf {
+        directory.join(&self.file.0)
+    }
+}
+
+impl Rule {
+    /// Get every lua and luajit option of the signature
+    pub fn lua_scripts(&self) -> Vec<LuaScript> {
+        self.options
+            .iter()
+            .flatten()
+            .filter(|(option, _)| LUA_KEYWORDS.contains(&option.keyword().0.as_str()))
+            .filter_map(|(option, span)| {
+                let values = option.values();
+                let ((_, first), (_, last)) = (values.first()?, values.last()?);
+                // A comma is part of the file
+                let file = values
+                    .iter()
+                    .map(|(value, _)| value.as_str())
+                    .collect::<Vec<_>>()
+                    .join(",");
+                let (file, start, negated) = match file.strip_prefix('!') {
+                    Some(unnegated) => {
+                        let trimmed = unnegated.trim_start();
+                        let negation = file.chars().count() - trimmed.chars().count();
+                        (trimmed.to_string(), first.start + negation, true)
+                    }
+                    None => (file, first.start, false),
+                };
+                if file.is_empty() {
+                    return None;
+                }
+                Some(LuaScript {
+                    keyword: option.keyword().clone(),
+                    file: (file, start..last.end),
+                    negated,
+                    span: span.clone(),
+                })
+            })
+            .collect()
+    }
+}
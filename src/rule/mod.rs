@@ -17,7 +17,8 @@ use std::{
     fmt,
 };
 
-use tower_lsp::lsp_types::{CompletionItem, HoverContents, SemanticTokenType};
+use lsp_types::{CompletionItem, HoverContents, SemanticTokenType};
+use serde::Serialize;
 
 use crate::{
     semantic_token::{ImCompleteSemanticToken, LEGEND_TYPE},
@@ -26,12 +27,28 @@ use crate::{
 
 use self::{
     action::Action,
-    header::{Header, NetworkAddress, NetworkPort, NetworkDirection},
-    options::RuleOption,
+    header::{Header, NetworkAddress, NetworkPort, NetworkDirection, Protocol},
+    options::{
+        is_sticky_buffer, metadata_entry, unescape, ContentMatch, KeywordComparison,
+        NumericComparison, OptionsVariable, RuleOption, COMPARISON_KEYWORDS, CONTENT_MODIFIERS,
+    },
 };
 pub mod action;
+pub mod base64;
+pub mod bits;
+pub mod bytes;
+pub mod content;
+pub mod datasets;
+pub mod fast_pattern;
+pub mod flow;
 pub mod header;
+pub mod lua;
 pub mod options;
+pub mod pcre;
+pub mod ranges;
+pub mod references;
+pub mod tag;
+pub mod threshold;
 
 /// Keeps data about the range in the signatures of the object (start/end char position)
 pub type Span = std::ops::Range<usize>;
@@ -58,44 +75,84 @@ pub trait Completions {
 ///
 /// As every file has a number of signatures and there could be only one signature by line, it is
 /// only logical that the storage structure also is represented in the same way.
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
 pub struct AST {
     pub rules: HashMap<u32, (Rule, Span)>,
+    /// Signatures, which are commented out (e.g. "#alert ..."), keyed by their line
+    ///
+    /// They can be navigated (hover, references), but are ignored by the lints,
+    /// the sids and the verification.
+    pub disabled: HashMap<u32, (Rule, Span)>,
+    /// Signatures with syntax errors, keyed by their line
+    ///
+    /// The parts before the mistake are kept (e.g. the header of a signature,
+    /// whose options are not closed), so the signature can still be navigated
+    /// and completed while it is typed. They are ignored like the commented
+    /// out signatures.
+    pub incomplete: HashMap<u32, (Rule, Span)>,
+}
+
+impl AST {
+    /// Get the signature on a line, whether it is active, commented out or incomplete
+    pub fn rule_at(&self, line: &u32) -> Option<&(Rule, Span)> {
+        self.rules
+            .get(line)
+            .or_else(|| self.disabled.get(line))
+            .or_else(|| self.incomplete.get(line))
+    }
+
+    /// Iterate over all signatures, including the commented out and the incomplete ones
+    pub fn all_rules(&self) -> impl Iterator<Item = (&u32, &(Rule, Span))> {
+        self.rules
+            .iter()
+            .chain(self.disabled.iter())
+            .chain(self.incomplete.iter())
+    }
 }
 
 /// Represents a single signature(or rule)
-#[derive(Debug, Hash, Clone, PartialEq, Eq)]
+#[derive(Debug, Hash, Clone, PartialEq, Eq, Serialize)]
 pub struct Rule {
     pub action: Option<Spanned<Action>>,
     pub header: Spanned<Header>,
     pub options: Option<Vec<Spanned<RuleOption>>>,
+    /// The span of the options, along with their parentheses
+    pub options_span: Option<Span>,
+    /// The comment after the signature, along with its # (e.g. "# reviewed")
+    pub comment: Option<Spanned<String>>,
 }
 
 /// Print formatted rule
 impl fmt::Display for Rule {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The action, the header, the options and the comment are separated
+        // by a single space, so the printed rule parses back to the same rule
+        let mut parts = vec![];
         if let Some((action, _)) = &self.action {
-            write!(f, "{} ", action)?
+            parts.push(action.to_string());
         }
-        write!(f, "{}", &self.header.0)?;
+        parts.push(self.header.0.to_string());
         if let Some(option) = &self.options {
             // If the array is empty, skip this step
             if option.is_empty() {
-                return write!(f, "()");
+                parts.push("()".to_string());
+            } else {
+                let options = option
+                    .iter()
+                    .map(|(option, _)| option.to_string())
+                    .collect::<Vec<String>>();
+                parts.push(format!("({};)", options.join("; ")));
             }
-
-            let options = option
-                .iter()
-                .map(|(option, _)| option.to_string())
-                .collect::<Vec<String>>();
-            write!(f, "({};)", options.join("; "))
-        } else {
-            write!(f, "")
         }
+        if let Some((comment, _)) = &self.comment {
+            parts.push(comment.clone());
+        }
+        parts.retain(|part| !part.is_empty());
+        write!(f, "{}", parts.join(" "))
     }
 }
 impl Rule {
-    pub fn protocol(&self) -> &Option<Spanned<String>> {
+    pub fn protocol(&self) -> &Option<Spanned<Protocol>> {
         let (header, _) = &self.header;
         &header.protocol
 
@@ -139,6 +196,247 @@ impl Rule {
             .chain(self.destination_port().iter())
             .collect()
     }
+    /// Get all options with the given keyword
+    pub fn options_with_keyword(&self, keyword: &str) -> Vec<&Spanned<RuleOption>> {
+        self.options
+            .iter()
+            .flatten()
+            .filter(|(option, _)| option.keyword().0 == keyword)
+            .collect()
+    }
+    /// Get the values of every option with the given keyword
+    pub fn option_values(&self, keyword: &str) -> Vec<Vec<Spanned<String>>> {
+        self.options_with_keyword(keyword)
+            .into_iter()
+            .map(|(option, _)| option.values())
+            .collect()
+    }
+    /// Get the first value of the first option with the given keyword
+    pub fn first_value(&self, keyword: &str) -> Option<Spanned<String>> {
+        self.option_values(keyword)
+            .into_iter()
+            .next()
+            .and_then(|values| values.into_iter().next())
+    }
+    /// Get the raw value of the signature id (the first sid option)
+    pub fn raw_sid(&self) -> Option<Spanned<String>> {
+        self.first_value("sid")
+    }
+    /// Get the first value of an option, which holds a number (e.g. sid)
+    ///
+    /// Returns None if there is no such option, or the raw value if it is not a
+    /// valid number (e.g. "abc" of "sid:abc;"), so the caller can report it.
+    pub fn number_value(&self, keyword: &str) -> Option<Result<Spanned<u32>, Spanned<String>>> {
+        let (value, span) = self.first_value(keyword)?;
+        Some(match value.parse() {
+            Ok(number) => Ok((number, span)),
+            Err(_) => Err((value, span)),
+        })
+    }
+    /// Get the priority of the signature (1 is the highest, 255 the lowest)
+    ///
+    /// The number is returned even if it is outside of 1-255, so the caller can
+    /// report it. A value, which is not a number, is returned as the error
+    /// (empty if the priority has no value).
+    pub fn priority(&self) -> Option<Result<Spanned<u32>, Spanned<String>>> {
+        let (option, span) = self.options_with_keyword("priority").into_iter().next()?;
+        if option.values().is_empty() {
+            return Some(Err((String::new(), span.clone())));
+        }
+        self.number_value("priority")
+    }
+    /// Get the options, which disable the alert (noalert or flowbits:noalert)
+    pub fn noalert(&self) -> Vec<&Spanned<RuleOption>> {
+        self.options
+            .iter()
+            .flatten()
+            .filter(|(option, _)| option.is_noalert())
+            .collect()
+    }
+    /// Check if the signature does not alert (e.g. it only sets flowbits)
+    pub fn is_noalert(&self) -> bool {
+        !self.noalert().is_empty()
+    }
+    /// Get the comparisons of dsize, bsize, urilen, ttl, itype, icode and
+    /// ip_proto, along with their keyword
+    ///
+    /// A comparison, which could not be parsed, is returned as the error along
+    /// with its span. Protocols given by their name (e.g. ip_proto:tcp;) are
+    /// skipped.
+    pub fn comparisons(&self) -> Vec<KeywordComparison> {
+        self.options
+            .iter()
+            .flatten()
+            .filter(|(option, _)| COMPARISON_KEYWORDS.contains(&option.keyword().0.as_str()))
+            .filter_map(|(option, span)| {
+                let keyword = option.keyword().clone();
+                let is_name = |(value, _): &Spanned<String>| {
+                    value
+                        .trim_start_matches(['!', '<', '>', '='])
+                        .starts_with(char::is_alphabetic)
+                };
+                let comparison = match option.values().first() {
+                    Some(value) if keyword.0 == "ip_proto" && is_name(value) => return None,
+                    Some(value) => NumericComparison::parse(value),
+                    None => Err((format!("{} needs a value", keyword.0), span.clone())),
+                };
+                Some((keyword, comparison))
+            })
+            .collect()
+    }
+    /// Get the comparison of the first option with the given keyword (e.g. ttl)
+    pub fn comparison(&self, keyword: &str) -> Option<Result<NumericComparison, Spanned<String>>> {
+        self.comparisons()
+            .into_iter()
+            .find(|((option, _), _)| option == keyword)
+            .map(|(_, comparison)| comparison)
+    }
+    /// Get the signature id, if it is a valid number
+    pub fn sid(&self) -> Option<Spanned<u32>> {
+        self.number_value("sid")?.ok()
+    }
+    /// Get the group id, if it is a valid number
+    pub fn gid(&self) -> Option<Spanned<u32>> {
+        self.number_value("gid")?.ok()
+    }
+    /// Get the revision, if it is a valid number
+    pub fn rev(&self) -> Option<Spanned<u32>> {
+        self.number_value("rev")?.ok()
+    }
+    /// Get the message of the signature, without the escapes (e.g. \" or \;)
+    pub fn msg(&self) -> Option<Spanned<String>> {
+        let (msg, span) = self.first_value("msg")?;
+        Some((unescape(&msg), span))
+    }
+    /// Get the classtype of the signature
+    pub fn classtype(&self) -> Option<Spanned<String>> {
+        self.first_value("classtype")
+    }
+    /// Get every metadata entry of the signature as a key and a value
+    /// (e.g. "deployment" and "Perimeter" of "metadata:deployment Perimeter;")
+    pub fn metadata(&self) -> Vec<(Spanned<String>, Spanned<String>)> {
+        self.option_values("metadata")
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| metadata_entry(&entry))
+            .collect()
+    }
+    /// Get the pattern of every content inside the signature
+    pub fn contents(&self) -> Vec<Spanned<String>> {
+        self.option_values("content")
+            .into_iter()
+            .filter_map(|values| values.into_iter().next())
+            .collect()
+    }
+    /// Get every content of the signature, along with the modifiers which follow it
+    ///
+    /// A sticky buffer or a pcre ends the content, the modifiers after them
+    /// do not apply to it (see [Rule::orphan_modifiers]).
+    pub fn content_matches(&self) -> Vec<ContentMatch> {
+        let mut matches: Vec<ContentMatch> = vec![];
+        let mut follows_content = false;
+        self.options.iter().flatten().for_each(|(option, span)| {
+            let (keyword, _) = option.keyword();
+            if keyword == "content" {
+                follows_content = false;
+                let RuleOption::KeywordPair(_, values) = option else {
+                    return;
+                };
+                let Some((value, _)) = values.first() else {
+                    return;
+                };
+                let (pattern, negated) = match value {
+                    OptionsVariable::NegatedString(_, pattern) => (pattern.clone(), true),
+                    value => (value.trimmed(), false),
+                };
+                matches.push(ContentMatch {
+                    pattern,
+                    negated,
+                    quoted: !matches!(value, OptionsVariable::Other(_)),
+                    modifiers: vec![],
+                });
+                follows_content = true;
+            } else if CONTENT_MODIFIERS.contains(&keyword.as_str()) {
+                if let Some(content) = matches.last_mut().filter(|_| follows_content) {
+                    content.modifiers.push((option.clone(), span.clone()));
+                }
+            } else if ends_content(option) {
+                follows_content = false;
+            }
+        });
+        matches
+    }
+    /// Get the content modifiers, which do not follow a content
+    ///
+    /// This includes the modifiers after a sticky buffer or a pcre (e.g. the
+    /// nocase of `content:"a"; http.uri; nocase;`).
+    pub fn orphan_modifiers(&self) -> Vec<&Spanned<RuleOption>> {
+        let mut follows_content = false;
+        self.options
+            .iter()
+            .flatten()
+            .filter(|(option, _)| {
+                let (keyword, _) = option.keyword();
+                if keyword == "content" {
+                    follows_content = true;
+                } else if ends_content(option) {
+                    follows_content = false;
+                }
+                !follows_content && CONTENT_MODIFIERS.contains(&keyword.as_str())
+            })
+            .collect()
+    }
+    /// Copy the signature as the start of a variant
+    ///
+    /// The copy gets the given sid and rev 1 (both are added if missing), its
+    /// first msg is suffixed with " (copy)", so it is not reported as a duplicate.
+    pub fn duplicate(&self, sid: u32) -> Rule {
+        let mut duplicate = self.clone();
+        let options = duplicate.options.get_or_insert_with(Vec::new);
+        let mut missing = vec![("sid", sid.to_string()), ("rev", "1".to_string())];
+        let mut msg_copied = false;
+        options.iter_mut().for_each(|(option, _)| {
+            let RuleOption::KeywordPair((keyword, _), values) = option else {
+                return;
+            };
+            let value = match keyword.as_str() {
+                "msg" if !msg_copied => {
+                    msg_copied = true;
+                    let Some((OptionsVariable::String((msg, _)), _)) = values.first() else {
+                        return;
+                    };
+                    OptionsVariable::String((format!("{} (copy)", msg), 0..0))
+                }
+                "sid" | "rev" => {
+                    // Only the first sid and rev are replaced
+                    let Some((_, value)) = missing.iter().find(|(missing, _)| missing == keyword)
+                    else {
+                        return;
+                    };
+                    OptionsVariable::Other((value.clone(), 0..0))
+                }
+                _ => return,
+            };
+            missing.retain(|(missing, _)| missing != keyword);
+            *values = vec![(value, 0..0)];
+        });
+        missing.into_iter().for_each(|(keyword, value)| {
+            let values = vec![(OptionsVariable::Other((value, 0..0)), 0..0)];
+            options.push((
+                RuleOption::KeywordPair((keyword.to_string(), 0..0), values),
+                0..0,
+            ));
+        });
+        duplicate
+    }
+}
+
+/// Check if the option ends the content before it (a sticky buffer or a pcre)
+fn ends_content(option: &RuleOption) -> bool {
+    match option {
+        RuleOption::Buffer((keyword, _)) => is_sticky_buffer(keyword),
+        RuleOption::KeywordPair((keyword, _), _) => keyword == "pcre",
+    }
 }
 
 impl Semantics for Rule {
@@ -162,6 +460,17 @@ impl Semantics for Rule {
                 option.get_semantics(col, semantic_tokens);
             });
         }
+        // Push the comment token
+        if let Some((_, span)) = &self.comment {
+            semantic_tokens.push(ImCompleteSemanticToken {
+                start: span.start + col,
+                length: span.len(),
+                token_type: LEGEND_TYPE
+                    .iter()
+                    .position(|item| item == &SemanticTokenType::COMMENT)
+                    .unwrap(),
+            });
+        }
     }
 }
 
@@ -201,3 +510,197 @@ impl Hover for Rule {
         hover_options().or(hover_action())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chumsky::Parser;
+
+    fn parse(line: &str) -> Rule {
+        let (rule, _) = Rule::parser().parse(line).unwrap();
+        rule
+    }
+
+    #[test]
+    fn numbers_are_read_from_the_first_option() {
+        let rule = parse("alert tcp any any -> any any (sid: 12 ; gid:3; sid:13; rev:x;)");
+        assert_eq!(rule.sid(), Some((12, 35..37)));
+        assert_eq!(rule.raw_sid(), Some(("12".to_string(), 35..37)));
+        assert_eq!(rule.gid(), Some((3, 44..45)));
+        // A value, which is not a number, is returned by number_value
+        assert_eq!(rule.rev(), None);
+        assert_eq!(
+            rule.number_value("rev"),
+            Some(Err(("x".to_string(), 59..60)))
+        );
+    }
+
+    #[test]
+    fn values_are_trimmed_by_characters() {
+        // The ideographic space takes three bytes, but a single column
+        let rule = parse("alert tcp any any -> any any (sid:\u{3000}12\u{3000};)");
+        assert_eq!(rule.raw_sid(), Some(("12".to_string(), 35..37)));
+    }
+
+    #[test]
+    fn missing_numbers_are_none() {
+        let rule = parse("alert tcp any any -> any any (msg:\"x\";)");
+        assert_eq!(rule.sid(), None);
+        assert_eq!(rule.raw_sid(), None);
+        assert_eq!(rule.number_value("rev"), None);
+        let rule = parse("alert tcp any any -> any any (sid:99999999999;)");
+        assert_eq!(rule.sid(), None);
+        assert!(rule.raw_sid().is_some());
+    }
+
+    #[test]
+    fn msgs_are_unescaped() {
+        let line = r#"alert tcp any any -> any any (msg:"say \"hi\"\; C:\\temp"; sid:1;)"#;
+        let rule = parse(line);
+        assert_eq!(
+            rule.msg(),
+            Some((r#"say "hi"; C:\temp"#.to_string(), 34..57))
+        );
+        // The escapes are kept, when the signature is written again
+        assert_eq!(
+            rule.to_string(),
+            r#"alert tcp any any -> any any (msg: "say \"hi\"\; C:\\temp"; sid: 1;)"#
+        );
+    }
+
+    #[test]
+    fn metadata_entries_are_split_into_keys_and_values() {
+        let rule = parse(
+            "alert tcp any any -> any any (metadata:deployment  Perimeter, tag; metadata:a b c; sid:1;)",
+        );
+        assert_eq!(
+            rule.metadata(),
+            vec![
+                (
+                    ("deployment".to_string(), 39..49),
+                    ("Perimeter".to_string(), 51..60)
+                ),
+                (("a".to_string(), 76..77), ("b c".to_string(), 78..81)),
+            ]
+        );
+    }
+
+    fn duplicate(line: &str, sid: u32) -> String {
+        let (rule, _) = Rule::parser().parse(line).unwrap();
+        rule.duplicate(sid).to_string()
+    }
+
+    #[test]
+    fn duplicates_get_a_new_sid_and_the_first_rev() {
+        assert_eq!(
+            duplicate(
+                "alert tcp any any -> any any (msg:\"x\"; sid:1; rev:7;)",
+                1000
+            ),
+            "alert tcp any any -> any any (msg: \"x (copy)\"; sid: 1000; rev: 1;)"
+        );
+        assert_eq!(
+            duplicate("alert tcp any any -> any any (content:\"a\";)", 1000),
+            "alert tcp any any -> any any (content: \"a\"; sid: 1000; rev: 1;)"
+        );
+    }
+
+    #[test]
+    fn only_the_first_msg_is_suffixed() {
+        assert_eq!(
+            duplicate(
+                "alert tcp any any -> any any (msg:\"a\"; msg:\"b\"; sid:1; sid:2;)",
+                1000
+            ),
+            "alert tcp any any -> any any (msg: \"a (copy)\"; msg: \"b\"; sid: 1000; sid: 2; rev: 1;)"
+        );
+        // An unquoted msg is kept, the next msg is not suffixed instead
+        assert_eq!(
+            duplicate(
+                "alert tcp any any -> any any (msg:a; msg:\"b\"; sid:1;)",
+                1000
+            ),
+            "alert tcp any any -> any any (msg: a; msg: \"b\"; sid: 1000; rev: 1;)"
+        );
+    }
+
+    #[test]
+    fn negated_contents_are_unescaped() {
+        let rule =
+            parse(r#"alert tcp any any -> any any (content: ! "a\;b"; nocase; content:"c";)"#);
+        let matches = rule.content_matches();
+        assert_eq!(matches.len(), 2);
+        assert!(matches[0].negated);
+        assert_eq!(matches[0].pattern, ("a;b".to_string(), 41..47));
+        assert_eq!(matches[0].modifiers.len(), 1);
+        assert!(!matches[1].negated);
+        let (option, _) = &rule.options.as_ref().unwrap()[0];
+        assert_eq!(option.to_string(), r#"content: !"a\;b""#);
+    }
+
+    #[test]
+    fn noalert_is_found_in_both_forms() {
+        let header = "alert tcp any any -> any any";
+        let noalert = |options: &str| {
+            parse(&format!("{} ({} sid:1;)", header, options))
+                .noalert()
+                .len()
+        };
+        assert_eq!(noalert("noalert;"), 1);
+        assert_eq!(noalert("flowbits:set,a; flowbits:noalert;"), 1);
+        assert_eq!(noalert("noalert; flowbits:noalert;"), 2);
+        assert_eq!(noalert("flowbits:set,noalert;"), 0);
+        assert_eq!(noalert("xbits:noalert,a,track ip_src;"), 0);
+    }
+
+    const CORPUS: &str = include_str!("../../tests/fixtures/corpus/rules.rules");
+
+    /// Replace every span of a value with null, so signatures written
+    /// differently can be compared
+    fn without_spans(value: serde_json::Value) -> serde_json::Value {
+        use serde_json::Value;
+        match value {
+            Value::Object(object)
+                if object.len() == 2
+                    && object.contains_key("start")
+                    && object.contains_key("end") =>
+            {
+                Value::Null
+            }
+            Value::Object(object) => Value::Object(
+                object
+                    .into_iter()
+                    .map(|(key, value)| (key, without_spans(value)))
+                    .collect(),
+            ),
+            Value::Array(values) => Value::Array(values.into_iter().map(without_spans).collect()),
+            value => value,
+        }
+    }
+
+    #[test]
+    fn signatures_parse_back_to_the_same_signature() {
+        let (ast, _) = AST::parse_str(CORPUS);
+        let signatures = CORPUS
+            .lines()
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .count();
+        assert_eq!(ast.rules.len(), signatures);
+        assert!(ast.incomplete.is_empty());
+        for (line, (rule, _)) in &ast.rules {
+            let printed = rule.to_string();
+            let (reparsed, errors) = Rule::parser().parse_recovery(printed.as_str());
+            assert!(errors.is_empty(), "line {}: {}", line, printed);
+            let (reparsed, _) = reparsed.unwrap();
+            assert_eq!(
+                without_spans(serde_json::to_value(&reparsed).unwrap()),
+                without_spans(serde_json::to_value(rule).unwrap()),
+                "line {}: {}",
+                line,
+                printed
+            );
+            // Printing is stable, so the formatter does not keep editing
+            assert_eq!(reparsed.to_string(), printed);
+        }
+    }
+}
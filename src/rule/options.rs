@@ -1,11 +1,13 @@
 use std::collections::HashMap;
 use std::fmt;
-use tower_lsp::lsp_types::HoverContents;
-use tower_lsp::lsp_types::MarkupContent;
-use tower_lsp::lsp_types::SemanticTokenType;
+use lsp_types::HoverContents;
+use serde::Serialize;
+use lsp_types::MarkupContent;
+use lsp_types::SemanticTokenType;
 
 use crate::rule::Span;
 use crate::rule::Spanned;
+use crate::rule::threshold::Threshold;
 use crate::semantic_token::ImCompleteSemanticToken;
 use crate::semantic_token::LEGEND_TYPE;
 use crate::suricata::Keyword;
@@ -16,31 +18,29 @@ use super::Semantics;
 /// Represents a variable inside the options of a signature
 ///
 /// This is not based on the suricata docs, but based of regular observations
-/// The current enum has only two possibilities:
+/// The current enum has three possibilities:
 /// - a string, in the format "..."
+/// - a negated string, in the format !"..." (the span of the ! comes first)
 /// - anything else
 ///
 /// This destinction was made since inside a string the special chars are escaped
 /// For more info, please see the [surcata docs].
 ///
 /// [surcata docs]: https://suricata.readthedocs.io/en/suricata-6.0.0/rules/meta.html?highlight=escaped#msg-message
-#[derive(Debug, Hash, Clone, PartialEq, Eq)]
+#[derive(Debug, Hash, Clone, PartialEq, Eq, Serialize)]
 pub enum OptionsVariable {
     String(Spanned<String>),
+    NegatedString(Span, Spanned<String>),
     Other(Spanned<String>),
 }
 
 impl fmt::Display for OptionsVariable {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            OptionsVariable::String((string, _)) => write!(
-                f,
-                "\"{}\"",
-                string
-                    .replace("\\", "\\\\")
-                    .replace("\"", "\\\"")
-                    .replace(";", "\\;")
-            ),
+            OptionsVariable::String((string, _)) => write!(f, "\"{}\"", escape(string)),
+            OptionsVariable::NegatedString(_, (string, _)) => {
+                write!(f, "!\"{}\"", escape(string))
+            }
             OptionsVariable::Other((string, _)) => write!(f, "{}", string),
         }
     }
@@ -59,6 +59,24 @@ impl Semantics for OptionsVariable {
                         .unwrap(),
                 });
             }
+            OptionsVariable::NegatedString(negation, (_, span)) => {
+                semantic_tokens.push(ImCompleteSemanticToken {
+                    start: negation.start + col,
+                    length: negation.len(),
+                    token_type: LEGEND_TYPE
+                        .iter()
+                        .position(|item| item == &SemanticTokenType::OPERATOR)
+                        .unwrap(),
+                });
+                semantic_tokens.push(ImCompleteSemanticToken {
+                    start: span.start + col,
+                    length: span.len(),
+                    token_type: LEGEND_TYPE
+                        .iter()
+                        .position(|item| item == &SemanticTokenType::STRING)
+                        .unwrap(),
+                });
+            }
             OptionsVariable::Other((_, span)) => {
                 semantic_tokens.push(ImCompleteSemanticToken {
                     start: span.start + col,
@@ -73,24 +91,363 @@ impl Semantics for OptionsVariable {
     }
 }
 
+impl OptionsVariable {
+    /// Get the value without the surrounding whitespace, along with its span
+    pub fn trimmed(&self) -> Spanned<String> {
+        match self {
+            OptionsVariable::String(string) => string.clone(),
+            // The negation is kept, as it is written (e.g. !"evil")
+            OptionsVariable::NegatedString(negation, (_, span)) => {
+                (self.to_string(), negation.start..span.end)
+            }
+            OptionsVariable::Other((value, span)) => {
+                // The spans count characters, so does the whitespace
+                let chars = value.chars().count();
+                let leading = chars - value.trim_start().chars().count();
+                let trailing = chars - value.trim_end().chars().count();
+                let start = (span.start + leading).min(span.end);
+                let end = (span.end - trailing).max(start);
+                (value.trim().to_string(), start..end)
+            }
+        }
+    }
+}
+
+/// Escape the value of a quoted string
+///
+/// The " and ; are escaped, a backslash is kept as it is, unless it would
+/// escape the following character (or the closing quote). Escaping a parsed
+/// value gives back the text it was parsed from (up to the escaped ; and ").
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    let mut backslashes = 0;
+    for char in value.chars() {
+        // An odd backslash in front of a " or ; would escape it
+        if matches!(char, '"' | ';') {
+            if backslashes % 2 == 1 {
+                escaped.push('\\');
+            }
+            escaped.push('\\');
+        }
+        escaped.push(char);
+        backslashes = match char {
+            '\\' => backslashes + 1,
+            _ => 0,
+        };
+    }
+    if backslashes % 2 == 1 {
+        escaped.push('\\');
+    }
+    escaped
+}
+
+/// Get the content of an unquoted value, as it would be stored inside quotes
+///
+/// Like the parser does for quoted strings, the backslash of an escaped " or ;
+/// is removed and other escapes are kept as they are.
+pub fn quoted_content(value: &str) -> String {
+    let mut content = String::with_capacity(value.len());
+    let mut chars = value.chars().peekable();
+    while let Some(char) = chars.next() {
+        match (char, chars.peek()) {
+            ('\\', Some('"' | ';')) => content.extend(chars.next()),
+            ('\\', Some(_)) => content.extend([char].into_iter().chain(chars.next())),
+            (char, _) => content.push(char),
+        }
+    }
+    content
+}
+
+/// Remove the escapes of a quoted value (e.g. of a msg or a content), Suricata
+/// keeps the character after a backslash
+pub fn unescape(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(char) = chars.next() {
+        match char {
+            '\\' => unescaped.extend(chars.next()),
+            char => unescaped.push(char),
+        }
+    }
+    unescaped
+}
+
+/// Split an entry of the metadata into its key and its value
+/// (e.g. "deployment" and "Perimeter" of "deployment Perimeter")
+///
+/// The entry is expected to be trimmed, entries without a value are skipped.
+pub fn metadata_entry(entry: &Spanned<String>) -> Option<(Spanned<String>, Spanned<String>)> {
+    let (entry, span) = entry;
+    let (key, value) = entry.split_once(char::is_whitespace)?;
+    let value = value.trim_start();
+    let value_start = span.start + entry.chars().count() - value.chars().count();
+    let key_span = span.start..span.start + key.chars().count();
+    let value_span = value_start..span.end;
+    Some(((key.to_string(), key_span), (value.to_string(), value_span)))
+}
+
 /// Represents a single option inside the signature (buffer or key-value pair)
-#[derive(Debug, Hash, Clone, PartialEq, Eq)]
+#[derive(Debug, Hash, Clone, PartialEq, Eq, Serialize)]
 pub enum RuleOption {
     KeywordPair(Spanned<String>, Vec<Spanned<OptionsVariable>>),
     Buffer(Spanned<String>),
 }
 
+impl RuleOption {
+    /// Check if the option disables the alert (noalert or flowbits:noalert)
+    pub fn is_noalert(&self) -> bool {
+        match self {
+            RuleOption::Buffer((keyword, _)) => keyword == "noalert",
+            RuleOption::KeywordPair((keyword, _), _) => {
+                keyword == "flowbits"
+                    && self
+                        .values()
+                        .first()
+                        .is_some_and(|(operation, _)| operation == "noalert")
+            }
+        }
+    }
+    /// Get the keyword of the option
+    pub fn keyword(&self) -> &Spanned<String> {
+        match self {
+            RuleOption::KeywordPair(keyword, _) => keyword,
+            RuleOption::Buffer(keyword) => keyword,
+        }
+    }
+    /// Get the values of the option (trimmed), buffers do not have values
+    pub fn values(&self) -> Vec<Spanned<String>> {
+        match self {
+            RuleOption::KeywordPair(_, values) => {
+                values.iter().map(|(value, _)| value.trimmed()).collect()
+            }
+            RuleOption::Buffer(_) => vec![],
+        }
+    }
+}
+
 impl fmt::Display for RuleOption {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             RuleOption::KeywordPair((key, _), op) => {
+                if let Some(threshold) = Threshold::parse(self, &(0..0)) {
+                    return write!(f, "{}: {}", key, threshold);
+                }
                 let options = op
                     .iter()
                     .map(|(option, _)| option.to_string())
                     .collect::<Vec<String>>();
                 write!(f, "{}: {}", key, options.join(", "))
             }
-            RuleOption::Buffer((keyword, _)) => write!(f, "{}", keyword.to_string()),
+            RuleOption::Buffer((keyword, _)) => write!(f, "{}", keyword),
+        }
+    }
+}
+
+/// The keywords, which modify the preceding content
+pub const CONTENT_MODIFIERS: [&str; 9] = [
+    "nocase",
+    "depth",
+    "offset",
+    "distance",
+    "within",
+    "rawbytes",
+    "fast_pattern",
+    "startswith",
+    "endswith",
+];
+
+/// Check if the keyword selects a buffer for the following contents
+pub fn is_sticky_buffer(keyword: &str) -> bool {
+    keyword == "pkt_data"
+        || keyword == "file_data"
+        || keyword == "base64_data"
+        || keyword.contains('.')
+}
+
+/// A single content, along with its modifiers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentMatch {
+    /// The pattern, without the quotes and the negation (e.g. "GET|20|")
+    pub pattern: Spanned<String>,
+    /// The content is negated (content:!"...")
+    pub negated: bool,
+    /// The pattern is enclosed in quotes, which are part of its span
+    pub quoted: bool,
+    /// The modifiers following the content (depth, within, etc.)
+    pub modifiers: Vec<Spanned<RuleOption>>,
+}
+
+impl ContentMatch {
+    /// Get the length of the pattern in bytes
+    ///
+    /// Hex parts count as a byte per pair of digits (|41 42| is two bytes).
+    /// Returns None if a hex part is malformed.
+    pub fn byte_length(&self) -> Option<usize> {
+        self.segments()
+            .iter()
+            .map(|segment| segment.bytes().ok().map(|bytes| bytes.len()))
+            .sum()
+    }
+
+    /// Get the numeric value of a modifier (e.g. depth:5), along with the span
+    /// of the modifier
+    ///
+    /// Returns None if the modifier is missing or not a number (e.g. a variable
+    /// extracted with byte_extract).
+    pub fn numeric_modifier(&self, keyword: &str) -> Option<Spanned<i64>> {
+        self.modifiers
+            .iter()
+            .find(|(option, _)| option.keyword().0 == keyword)
+            .and_then(|(option, span)| {
+                let (value, _) = option.values().into_iter().next()?;
+                Some((value.parse().ok()?, span.clone()))
+            })
+    }
+
+    /// Get the modifiers, which were already given for this content
+    /// (e.g. the second depth of content:"x"; depth:4; depth:8;)
+    pub fn duplicate_modifiers(&self) -> Vec<&Spanned<RuleOption>> {
+        self.modifiers
+            .iter()
+            .enumerate()
+            .filter(|(index, (option, _))| {
+                self.modifiers[..*index]
+                    .iter()
+                    .any(|(previous, _)| previous.keyword().0 == option.keyword().0)
+            })
+            .map(|(_, modifier)| modifier)
+            .collect()
+    }
+}
+
+/// The keywords, which compare a number (e.g. dsize:>100; or ttl:<10;)
+pub const COMPARISON_KEYWORDS: [&str; 7] = [
+    "dsize", "bsize", "urilen", "ttl", "itype", "icode", "ip_proto",
+];
+
+/// Get the largest value a comparison of the keyword can match (None if unbounded)
+pub fn comparison_limit(keyword: &str) -> Option<u64> {
+    match keyword {
+        "dsize" | "urilen" => Some(u16::MAX as u64),
+        "ttl" | "itype" | "icode" | "ip_proto" => Some(u8::MAX as u64),
+        _ => None,
+    }
+}
+
+/// A comparison of an option, which may fail, along with the keyword of the option
+pub type KeywordComparison = (Spanned<String>, Result<NumericComparison, Spanned<String>>);
+
+/// Creates a comparison with a single operand
+type ComparisonVariant = fn(Spanned<u64>) -> NumericComparison;
+
+/// A comparison of a number (e.g. dsize:>100; or bsize:10<>20;)
+///
+/// Every operand keeps its span, the values are not checked against a limit,
+/// so the caller can report values above it (e.g. 65535 for dsize).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NumericComparison {
+    /// 5
+    Equal(Spanned<u64>),
+    /// !5
+    NotEqual(Spanned<u64>),
+    /// <5
+    Less(Spanned<u64>),
+    /// <=5
+    LessOrEqual(Spanned<u64>),
+    /// >5
+    Greater(Spanned<u64>),
+    /// >=5
+    GreaterOrEqual(Spanned<u64>),
+    /// 5<>10, the bounds are excluded
+    Range(Spanned<u64>, Spanned<u64>),
+}
+
+impl NumericComparison {
+    /// Parse a trimmed value (e.g. ">100" or "10<>20")
+    ///
+    /// Returns the error along with its span, if an operand is not a number.
+    pub fn parse((value, span): &Spanned<String>) -> Result<NumericComparison, Spanned<String>> {
+        // The operands are parsed along with their columns
+        let operand = |start: usize, text: &str| {
+            let trimmed = text.trim_start();
+            let start = span.start + start + text.chars().count() - trimmed.chars().count();
+            let trimmed = trimmed.trim_end();
+            let operand_span = start..start + trimmed.chars().count();
+            match trimmed.parse::<u64>() {
+                Ok(number) => Ok((number, operand_span)),
+                Err(_) if trimmed.is_empty() => {
+                    Err(("expected a number".to_string(), span.clone()))
+                }
+                Err(_) => Err((
+                    format!("expected a number, found \"{}\"", trimmed),
+                    operand_span,
+                )),
+            }
+        };
+        if let Some((min, max)) = value.split_once("<>") {
+            let max_start = min.chars().count() + 2;
+            return Ok(NumericComparison::Range(
+                operand(0, min)?,
+                operand(max_start, max)?,
+            ));
+        }
+        let operators: [(&str, ComparisonVariant); 6] = [
+            ("<=", NumericComparison::LessOrEqual),
+            (">=", NumericComparison::GreaterOrEqual),
+            ("<", NumericComparison::Less),
+            (">", NumericComparison::Greater),
+            ("!", NumericComparison::NotEqual),
+            ("=", NumericComparison::Equal),
+        ];
+        let operator = operators.into_iter().find_map(|(operator, variant)| {
+            Some((value.strip_prefix(operator)?, operator, variant))
+        });
+        match operator {
+            Some((rest, operator, variant)) => operand(operator.len(), rest).map(variant),
+            None => operand(0, value).map(NumericComparison::Equal),
+        }
+    }
+
+    /// Get the operands, along with their spans
+    pub fn operands(&self) -> Vec<&Spanned<u64>> {
+        match self {
+            NumericComparison::Equal(value)
+            | NumericComparison::NotEqual(value)
+            | NumericComparison::Less(value)
+            | NumericComparison::LessOrEqual(value)
+            | NumericComparison::Greater(value)
+            | NumericComparison::GreaterOrEqual(value) => vec![value],
+            NumericComparison::Range(min, max) => vec![min, max],
+        }
+    }
+
+    /// Get the operands above the limit (e.g. 300 of ttl:300;)
+    pub fn operands_above(&self, limit: u64) -> Vec<&Spanned<u64>> {
+        self.operands()
+            .into_iter()
+            .filter(|(value, _)| *value > limit)
+            .collect()
+    }
+
+    /// Check if the range can not match any number (e.g. 20<>10 or 5<>6)
+    pub fn is_inverted(&self) -> bool {
+        match self {
+            NumericComparison::Range((min, _), (max, _)) => min.saturating_add(1) >= *max,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for NumericComparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NumericComparison::Equal((value, _)) => write!(f, "{}", value),
+            NumericComparison::NotEqual((value, _)) => write!(f, "!{}", value),
+            NumericComparison::Less((value, _)) => write!(f, "<{}", value),
+            NumericComparison::LessOrEqual((value, _)) => write!(f, "<={}", value),
+            NumericComparison::Greater((value, _)) => write!(f, ">{}", value),
+            NumericComparison::GreaterOrEqual((value, _)) => write!(f, ">={}", value),
+            NumericComparison::Range((min, _), (max, _)) => write!(f, "{}<>{}", min, max),
         }
     }
 }
@@ -98,7 +455,7 @@ impl fmt::Display for RuleOption {
 impl Semantics for RuleOption {
     fn get_semantics(&self, col: &usize, semantic_tokens: &mut Vec<ImCompleteSemanticToken>) {
         match &self {
-            RuleOption::KeywordPair((_, span), values) => {
+            RuleOption::KeywordPair((keyword, span), values) => {
                 // Add the keyword semantic token
                 semantic_tokens.push(ImCompleteSemanticToken {
                     start: span.start + col,
@@ -108,6 +465,31 @@ impl Semantics for RuleOption {
                         .position(|item| item == &SemanticTokenType::KEYWORD)
                         .unwrap(),
                 });
+                // The keys and the values of the metadata get their own tokens
+                if keyword == "metadata" {
+                    let token_type = |token_type: SemanticTokenType| {
+                        LEGEND_TYPE
+                            .iter()
+                            .position(|item| *item == token_type)
+                            .unwrap()
+                    };
+                    values
+                        .iter()
+                        .filter_map(|(value, _)| metadata_entry(&value.trimmed()))
+                        .for_each(|((_, key), (_, value))| {
+                            semantic_tokens.push(ImCompleteSemanticToken {
+                                start: key.start + col,
+                                length: key.len(),
+                                token_type: token_type(SemanticTokenType::PROPERTY),
+                            });
+                            semantic_tokens.push(ImCompleteSemanticToken {
+                                start: value.start + col,
+                                length: value.len(),
+                                token_type: token_type(SemanticTokenType::STRING),
+                            });
+                        });
+                    return;
+                }
                 // Add the value semantic token
                 values.iter().for_each(|(options, _)| {
                     options.get_semantics(col, semantic_tokens);
@@ -155,10 +537,10 @@ fn get_contents_for_keyword(
     };
     Some((
         HoverContents::Markup(MarkupContent {
-            kind: tower_lsp::lsp_types::MarkupKind::Markdown,
+            kind: lsp_types::MarkupKind::Markdown,
             value: [
                 format!("**{}**", keyword.name),
-                format!("{}", keyword.description.clone()),
+                keyword.description.clone().to_string(),
                 format!("*Documentation: {}*", keyword.documentation.clone()),
             ]
             .join("\n\n"),
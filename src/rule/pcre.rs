@@ -0,0 +1,290 @@
+//! Structured view of the pcre option
+//!
+//! A pcre is written as a regular expression between slashes, followed by its
+//! flags, e.g. `pcre:"/foo[0-9]+/iR";`. Suricata hands the expression to PCRE2,
+//! the pattern is checked with the regex parser instead, so only mistakes,
+//! which PCRE2 rejects as well, are reported.
+use regex_syntax::ast::{self, ErrorKind};
+
+use crate::rule::{options::OptionsVariable, options::RuleOption, Rule, Span, Spanned};
+
+/// The flags of PCRE, along with the flags Suricata adds (e.g. R for relative)
+pub const PCRE_FLAGS: &str = "ismxAEGRUIPQHDMCSYBOVWZ";
+
+/// A single pcre option
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pcre {
+    /// The expression between the slashes (escapes are kept as they are)
+    pub pattern: Spanned<String>,
+    /// The flags after the closing slash (e.g. "iR")
+    pub flags: Spanned<String>,
+    /// The pcre is negated (pcre:!"/.../")
+    pub negated: bool,
+    /// The span of the whole option
+    pub span: Span,
+}
+
+/// Maps the characters of a value back to the columns of the line
+///
+/// The parser removes the backslash of an escaped " or ;, so every one of
+/// them inside the value was one character longer in the line.
+pub(super) struct Columns {
+    /// The column of the first character of the value
+    pub(super) start: usize,
+    pub(super) chars: Vec<char>,
+}
+
+impl Columns {
+    pub(super) fn column(&self, index: usize) -> usize {
+        let escaped = self.chars[..index.min(self.chars.len())]
+            .iter()
+            .filter(|char| matches!(char, '"' | ';'))
+            .count();
+        self.start + index + escaped
+    }
+
+    pub(super) fn span(&self, range: Span) -> Span {
+        self.column(range.start)..self.column(range.end)
+    }
+}
+
+impl Pcre {
+    /// Split the value of a pcre option into the pattern and the flags
+    ///
+    /// Returns the error along with its span, if the pattern is not enclosed
+    /// in slashes.
+    fn parse(option: &RuleOption, span: &Span) -> Option<Result<Pcre, Spanned<String>>> {
+        let RuleOption::KeywordPair(_, values) = option else {
+            return None;
+        };
+        let (value, value_span) = values.first()?;
+        let (text, columns, negated) = match value {
+            OptionsVariable::String((text, _)) => {
+                // The span starts at the opening quote
+                let start = value_span.start + 1;
+                let chars = text.chars().collect();
+                (text.clone(), Columns { start, chars }, false)
+            }
+            OptionsVariable::NegatedString(_, (text, span)) => {
+                let start = span.start + 1;
+                let chars = text.chars().collect();
+                (text.clone(), Columns { start, chars }, true)
+            }
+            OptionsVariable::Other(_) => {
+                let (text, span) = value.trimmed();
+                let negated = text.starts_with('!');
+                let unquoted = text.trim_start_matches('!').trim_start();
+                let start = span.start + text.chars().count() - unquoted.chars().count();
+                let (unquoted, start) = match unquoted.strip_prefix('"') {
+                    Some(unquoted) => (unquoted.strip_suffix('"').unwrap_or(unquoted), start + 1),
+                    None => (unquoted, start),
+                };
+                // The escapes of an unquoted value are kept, so no column is skipped
+                let chars = vec![];
+                (unquoted.to_string(), Columns { start, chars }, negated)
+            }
+        };
+        let chars = text.chars().collect::<Vec<_>>();
+        let whole = columns.span(0..chars.len());
+        if chars.first() != Some(&'/') {
+            let message = "the pcre should start with a \"/\"".to_string();
+            return Some(Err((message, whole)));
+        }
+        let end = match chars.iter().rposition(|char| *char == '/') {
+            Some(end) if end > 0 => end,
+            _ => {
+                return Some(Err((
+                    "the pcre is missing the closing \"/\"".to_string(),
+                    whole,
+                )))
+            }
+        };
+        let pattern = chars[1..end].iter().collect::<String>();
+        let flags = chars[end + 1..].iter().collect::<String>();
+        Some(Ok(Pcre {
+            pattern: (pattern, columns.span(1..end)),
+            flags: (flags, columns.span(end + 1..chars.len())),
+            negated,
+            span: span.clone(),
+        }))
+    }
+
+    /// Get the span of the R flag, if the pcre is relative to the previous match
+    pub fn relative_flag(&self) -> Option<Span> {
+        let (flags, flags_span) = &self.flags;
+        let column = flags_span.start + flags.chars().position(|flag| flag == 'R')?;
+        Some(column..column + 1)
+    }
+
+    /// Find the mistakes of the pcre: unknown flags and invalid expressions
+    pub fn errors(&self) -> Vec<Spanned<String>> {
+        let (flags, flags_span) = &self.flags;
+        let mut errors = flags
+            .chars()
+            .enumerate()
+            .filter(|(_, flag)| !PCRE_FLAGS.contains(*flag))
+            .map(|(index, flag)| {
+                let column = flags_span.start + index;
+                (
+                    format!("unknown pcre flag \"{}\"", flag),
+                    column..column + 1,
+                )
+            })
+            .collect::<Vec<_>>();
+        errors.extend(self.pattern_error());
+        errors
+    }
+
+    /// Check the expression, only errors which PCRE2 reports as well are kept
+    /// (e.g. look-arounds are valid for PCRE2, but not supported by the parser)
+    fn pattern_error(&self) -> Option<Spanned<String>> {
+        let (pattern, pattern_span) = &self.pattern;
+        let extended = self.flags.0.contains('x');
+        let error = ast::parse::ParserBuilder::new()
+            .ignore_whitespace(extended)
+            .build()
+            .parse(pattern)
+            .err()?;
+        let message = match error.kind() {
+            ErrorKind::ClassUnclosed => "the character class is never closed",
+            ErrorKind::GroupUnclosed => "the group is never closed",
+            ErrorKind::GroupUnopened => "the group is never opened",
+            ErrorKind::ClassRangeInvalid => "the range of the character class is reversed",
+            ErrorKind::RepetitionCountInvalid => {
+                "the minimum of the repetition is above the maximum"
+            }
+            ErrorKind::RepetitionCountUnclosed => "the repetition is never closed",
+            ErrorKind::EscapeUnexpectedEof => "the pattern ends with a \"\\\"",
+            // Possessive quantifiers (e.g. a++) are valid for PCRE2
+            ErrorKind::RepetitionMissing => {
+                let before = pattern[..error.span().start.offset].chars().last();
+                if before.is_some_and(|char| "*+?}".contains(char)) {
+                    return None;
+                }
+                "the repetition does not follow anything to repeat"
+            }
+            _ => return None,
+        };
+        // The offsets of the parser are in bytes
+        let chars = |offset: usize| pattern[..offset.min(pattern.len())].chars().count();
+        let length = pattern.chars().count();
+        let start = chars(error.span().start.offset).min(length.saturating_sub(1));
+        let end = chars(error.span().end.offset).clamp(start + 1, length.max(start + 1));
+        let columns = Columns {
+            start: pattern_span.start,
+            chars: pattern.chars().collect(),
+        };
+        Some((
+            format!("invalid pcre, {}", message),
+            columns.span(start..end),
+        ))
+    }
+}
+
+impl Rule {
+    /// Get every pcre option of the signature
+    ///
+    /// Options which are not enclosed in slashes are returned as an error,
+    /// along with its span.
+    pub fn pcres(&self) -> Vec<Result<Pcre, Spanned<String>>> {
+        self.options_with_keyword("pcre")
+            .into_iter()
+            .filter_map(|(option, span)| Pcre::parse(option, span))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rule::AST;
+
+    /// Get the pcres of a signature with the given options
+    fn pcres(options: &str) -> Vec<Result<Pcre, Spanned<String>>> {
+        let text = format!("alert http any any -> any any ({} sid:1;)", options);
+        let (ast, _) = AST::parse_str(&text);
+        let (rule, _) = ast.rules.get(&0).expect("the signature is not valid");
+        rule.pcres()
+    }
+
+    fn pcre(options: &str) -> Pcre {
+        pcres(options).remove(0).unwrap()
+    }
+
+    /// The column of the first character of the options
+    const START: usize = 31;
+
+    #[test]
+    fn flags_are_split_from_the_pattern() {
+        let pcre = pcre("pcre:\"/foo[0-9]+/iR\";");
+        assert_eq!(
+            pcre.pattern,
+            ("foo[0-9]+".to_string(), START + 7..START + 16)
+        );
+        assert_eq!(pcre.flags, ("iR".to_string(), START + 17..START + 19));
+        assert!(!pcre.negated);
+        assert!(pcre.errors().is_empty());
+    }
+
+    #[test]
+    fn suricata_flags_are_known() {
+        for flags in ["R", "U", "RU", "UR", "iURs", "P", "H", "C", "Bm"] {
+            let pcre = pcre(&format!("content:\"a\"; pcre:\"/a/{}\";", flags));
+            assert_eq!(pcre.flags.0, flags);
+            assert!(pcre.errors().is_empty(), "{}", flags);
+        }
+    }
+
+    #[test]
+    fn unknown_flags_point_to_the_flag() {
+        let pcre = pcre("pcre:\"/a/iqRk\";");
+        let flags = START + 9;
+        assert_eq!(
+            pcre.errors(),
+            vec![
+                ("unknown pcre flag \"q\"".to_string(), flags + 1..flags + 2),
+                ("unknown pcre flag \"k\"".to_string(), flags + 3..flags + 4),
+            ]
+        );
+    }
+
+    #[test]
+    fn escaped_slashes_are_part_of_the_pattern() {
+        let pcre = pcre("pcre:\"/\\/admin\\/[a-z]+/U\";");
+        assert_eq!(pcre.pattern.0, "\\/admin\\/[a-z]+");
+        assert_eq!(pcre.flags.0, "U");
+        assert!(pcre.errors().is_empty());
+    }
+
+    #[test]
+    fn negated_pcres_are_parsed() {
+        let pcre = pcre("pcre:!\"/a/R\";");
+        assert!(pcre.negated);
+        assert_eq!(pcre.flags.0, "R");
+    }
+
+    #[test]
+    fn invalid_patterns_are_reported() {
+        let pcre = pcre("pcre:\"/a[0-9/i\";");
+        let pattern = START + 7;
+        assert_eq!(
+            pcre.errors(),
+            vec![(
+                "invalid pcre, the character class is never closed".to_string(),
+                pattern + 1..pattern + 2
+            )]
+        );
+        // Possessive quantifiers and look-arounds are valid for PCRE2
+        assert!(self::pcre("pcre:\"/a++(?=b)/\";").errors().is_empty());
+        let missing_slash = "the pcre is missing the closing \"/\"".to_string();
+        assert_eq!(
+            pcres("pcre:\"/abc\";"),
+            vec![Err((missing_slash, START + 6..START + 10))]
+        );
+        let missing_slash = "the pcre should start with a \"/\"".to_string();
+        assert_eq!(
+            pcres("pcre:\"abc/\";"),
+            vec![Err((missing_slash, START + 6..START + 10))]
+        );
+    }
+}
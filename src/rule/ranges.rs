@@ -0,0 +1,317 @@
+//! Provides sets of ranges, to reason about the addresses and ports a header matches
+//!
+//! Only literal values are evaluated: as soon as a variable is involved, the
+//! set is unknown (None), since the variable might be defined anywhere.
+use std::net::IpAddr;
+
+use super::header::{NetworkAddress, NetworkPort};
+
+/// A set of numbers, stored as sorted and disjoint inclusive ranges
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RangeSet {
+    ranges: Vec<(u128, u128)>,
+}
+
+impl RangeSet {
+    /// Create a set with all numbers between start and end (inclusive)
+    pub fn range(start: u128, end: u128) -> Self {
+        if start > end {
+            return RangeSet::default();
+        }
+        RangeSet {
+            ranges: vec![(start, end)],
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    pub fn contains(&self, value: u128) -> bool {
+        self.ranges
+            .iter()
+            .any(|(start, end)| (*start..=*end).contains(&value))
+    }
+
+    /// Get the numbers, which are in either set
+    pub fn union(&self, other: &RangeSet) -> RangeSet {
+        let mut ranges = self
+            .ranges
+            .iter()
+            .chain(other.ranges.iter())
+            .copied()
+            .collect::<Vec<_>>();
+        ranges.sort();
+        let mut merged: Vec<(u128, u128)> = vec![];
+        for (start, end) in ranges {
+            match merged.last_mut() {
+                Some((_, last_end)) if start <= last_end.saturating_add(1) => {
+                    *last_end = (*last_end).max(end);
+                }
+                _ => merged.push((start, end)),
+            }
+        }
+        RangeSet { ranges: merged }
+    }
+
+    /// Get the numbers of this set, which are not in the other set
+    pub fn difference(&self, other: &RangeSet) -> RangeSet {
+        let mut ranges = vec![];
+        for (start, end) in &self.ranges {
+            let mut start = *start;
+            let mut remaining = true;
+            for (other_start, other_end) in &other.ranges {
+                if *other_end < start || *other_start > *end {
+                    continue;
+                }
+                if *other_start > start {
+                    ranges.push((start, other_start - 1));
+                }
+                if *other_end >= *end {
+                    remaining = false;
+                    break;
+                }
+                start = other_end + 1;
+            }
+            if remaining {
+                ranges.push((start, *end));
+            }
+        }
+        RangeSet { ranges }
+    }
+}
+
+/// The addresses matched by a network address, per IP version
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AddressSet {
+    pub v4: RangeSet,
+    pub v6: RangeSet,
+}
+
+impl AddressSet {
+    /// All IPv4 and IPv6 addresses
+    pub fn any() -> Self {
+        AddressSet {
+            v4: RangeSet::range(0, u32::MAX as u128),
+            v6: RangeSet::range(0, u128::MAX),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.v4.is_empty() && self.v6.is_empty()
+    }
+
+    /// All addresses of the IP versions used by this set
+    ///
+    /// A negation is relative to the IP version of the negated addresses, so
+    /// !10.0.0.0/8 does not match any IPv6 address.
+    pub fn versions(&self) -> AddressSet {
+        let any = AddressSet::any();
+        AddressSet {
+            v4: if self.v4.is_empty() {
+                RangeSet::default()
+            } else {
+                any.v4
+            },
+            v6: if self.v6.is_empty() {
+                RangeSet::default()
+            } else {
+                any.v6
+            },
+        }
+    }
+
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        match ip {
+            IpAddr::V4(ip) => self.v4.contains(u32::from(*ip) as u128),
+            IpAddr::V6(ip) => self.v6.contains(u128::from(*ip)),
+        }
+    }
+
+    pub fn union(&self, other: &AddressSet) -> AddressSet {
+        AddressSet {
+            v4: self.v4.union(&other.v4),
+            v6: self.v6.union(&other.v6),
+        }
+    }
+
+    pub fn difference(&self, other: &AddressSet) -> AddressSet {
+        AddressSet {
+            v4: self.v4.difference(&other.v4),
+            v6: self.v6.difference(&other.v6),
+        }
+    }
+
+    /// The addresses of a CIDR block (a single address without a mask)
+    fn cidr(ip: &IpAddr, mask: u8) -> Option<Self> {
+        let (value, bits) = match ip {
+            IpAddr::V4(ip) => (u32::from(*ip) as u128, 32),
+            IpAddr::V6(ip) => (u128::from(*ip), 128),
+        };
+        if mask > bits {
+            return None;
+        }
+        let host_bits = (bits - mask) as u32;
+        let host_mask = 1u128
+            .checked_shl(host_bits)
+            .map_or(u128::MAX, |size| size - 1);
+        let range = RangeSet::range(value & !host_mask, value | host_mask);
+        Some(match ip {
+            IpAddr::V4(_) => AddressSet {
+                v4: range,
+                ..Default::default()
+            },
+            IpAddr::V6(_) => AddressSet {
+                v6: range,
+                ..Default::default()
+            },
+        })
+    }
+}
+
+impl NetworkAddress {
+    /// Get the addresses matched by the expression (None if a variable is used)
+    ///
+    /// Inside a group, the negated members are removed from the positive ones.
+    /// A group without positive members starts from any address of the IP
+    /// versions of the negated members.
+    pub fn address_set(&self) -> Option<AddressSet> {
+        match self {
+            NetworkAddress::Any(_) => Some(AddressSet::any()),
+            NetworkAddress::IPAddr((ip, _)) => {
+                AddressSet::cidr(ip, if ip.is_ipv4() { 32 } else { 128 })
+            }
+            NetworkAddress::CIDR((ip, _), (mask, _)) => AddressSet::cidr(ip, *mask),
+            NetworkAddress::IPGroup(members) => {
+                let mut positive = None;
+                let mut negative = AddressSet::default();
+                for (member, _) in members {
+                    match member {
+                        NetworkAddress::NegIP(inner) => {
+                            negative = negative.union(&inner.0.address_set()?);
+                        }
+                        member => {
+                            let set = member.address_set()?;
+                            positive = Some(match positive {
+                                Some(positive) => set.union(&positive),
+                                None => set,
+                            });
+                        }
+                    }
+                }
+                let positive = match positive {
+                    Some(positive) => positive,
+                    None if negative.is_empty() => AddressSet::any(),
+                    None => negative.versions(),
+                };
+                Some(positive.difference(&negative))
+            }
+            NetworkAddress::NegIP(inner) => {
+                let negative = inner.0.address_set()?;
+                Some(negative.versions().difference(&negative))
+            }
+            NetworkAddress::IPVariable(_) => None,
+        }
+    }
+}
+
+impl NetworkPort {
+    /// Get the ports matched by the expression (None if a variable is used)
+    ///
+    /// Groups are evaluated like the groups of addresses.
+    pub fn port_set(&self) -> Option<RangeSet> {
+        let all_ports = || RangeSet::range(0, u16::MAX as u128);
+        match self {
+            NetworkPort::Any(_) => Some(all_ports()),
+            NetworkPort::Port((port, _)) => Some(RangeSet::range(*port as u128, *port as u128)),
+            NetworkPort::PortRange((from, _), (to, _)) => {
+                Some(RangeSet::range(*from as u128, *to as u128))
+            }
+            NetworkPort::PortOpenRange((port, _), true) => {
+                Some(RangeSet::range(*port as u128, u16::MAX as u128))
+            }
+            NetworkPort::PortOpenRange((port, _), false) => Some(RangeSet::range(0, *port as u128)),
+            NetworkPort::PortGroup(members) => {
+                let mut positive = None;
+                let mut negative = RangeSet::default();
+                for (member, _) in members {
+                    match member {
+                        NetworkPort::NegPort(inner) => {
+                            negative = negative.union(&inner.0.port_set()?);
+                        }
+                        member => {
+                            let set = member.port_set()?;
+                            positive = Some(match positive {
+                                Some(positive) => set.union(&positive),
+                                None => set,
+                            });
+                        }
+                    }
+                }
+                Some(positive.unwrap_or_else(all_ports).difference(&negative))
+            }
+            NetworkPort::NegPort(inner) => Some(all_ports().difference(&inner.0.port_set()?)),
+            NetworkPort::PortVar(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addresses(text: &str) -> Option<AddressSet> {
+        NetworkAddress::parse_str(text).unwrap().address_set()
+    }
+
+    fn ports(text: &str) -> Option<RangeSet> {
+        NetworkPort::parse_str(text).unwrap().port_set()
+    }
+
+    #[test]
+    fn nested_address_groups_are_evaluated() {
+        let set = addresses("[10.0.0.0/8,![10.1.0.0/16,!10.1.2.3]]").unwrap();
+        let contains = |ip: &str| set.contains(&ip.parse().unwrap());
+        assert!(contains("10.0.0.1"));
+        assert!(!contains("10.1.0.1"));
+        // Removed from the negated group, so matched again
+        assert!(contains("10.1.2.3"));
+        assert!(!contains("192.168.0.1"));
+        assert!(set.v6.is_empty());
+
+        assert!(addresses("[10.0.0.0/8,![10.0.0.0/9,10.128.0.0/9]]")
+            .unwrap()
+            .is_empty());
+        // A nested group is evaluated on its own, before it joins its parent
+        let set = addresses("[10.0.0.1,[!10.0.0.1]]").unwrap();
+        assert_eq!(set.v4, RangeSet::range(0, u32::MAX as u128));
+    }
+
+    #[test]
+    fn negations_keep_their_ip_version() {
+        let set = addresses("![10.0.0.0/8,[192.168.0.0/16]]").unwrap();
+        assert!(set.contains(&"11.0.0.1".parse().unwrap()));
+        assert!(!set.contains(&"192.168.1.1".parse().unwrap()));
+        assert!(set.v6.is_empty());
+        assert!(!addresses("!::1").unwrap().v6.is_empty());
+    }
+
+    #[test]
+    fn nested_port_groups_are_evaluated() {
+        let set = ports("[1:100,![50:60,!55]]").unwrap();
+        assert!(set.contains(1));
+        assert!(!set.contains(50));
+        assert!(set.contains(55));
+        assert!(!set.contains(101));
+
+        assert!(ports("[80,![1:100]]").unwrap().is_empty());
+        assert!(ports("[1024:,![:2000,2001:]]").unwrap().is_empty());
+        assert!(!ports("[![1:100]]").unwrap().is_empty());
+    }
+
+    #[test]
+    fn variables_make_the_set_unknown() {
+        assert_eq!(addresses("[10.0.0.0/8,![$HOME_NET]]"), None);
+        assert_eq!(ports("[80,[!$HTTP_PORTS]]"), None);
+    }
+}
@@ -0,0 +1,104 @@
+//! Structured view of the reference option
+//!
+//! A reference names a scheme of reference.config and a value, e.g.
+//! `reference:url,doc.emergingthreats.net/2010937;` or
+//! `reference:cve,2021-44228;`. A signature can have several references.
+use std::fmt;
+
+use crate::rule::{options::RuleOption, Rule, Span, Spanned};
+
+/// The schemes of the reference.config shipped with Suricata
+pub const REFERENCE_SCHEMES: [&str; 9] = [
+    "bugtraq",
+    "bid",
+    "cve",
+    "secunia",
+    "arachnids",
+    "mcafee",
+    "nessus",
+    "url",
+    "exploitdb",
+];
+
+/// A single reference option
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference {
+    /// The scheme of the reference (e.g. "cve")
+    pub scheme: Spanned<String>,
+    /// The value after the first comma (e.g. "2021-44228"), commas after the
+    /// first one are part of the value (e.g. of an URL)
+    pub value: Option<Spanned<String>>,
+    /// The span of the whole option
+    pub span: Span,
+}
+
+impl Reference {
+    fn parse(option: &RuleOption, span: &Span) -> Option<Reference> {
+        let values = option.values();
+        let (scheme, rest) = values.split_first()?;
+        let value = match (rest.first(), rest.last()) {
+            (Some((_, first)), Some((_, last))) => {
+                let value = rest
+                    .iter()
+                    .map(|(value, _)| value.as_str())
+                    .collect::<Vec<_>>()
+                    .join(",");
+                Some((value, first.start..last.end))
+            }
+            _ => None,
+        };
+        Some(Reference {
+            scheme: scheme.clone(),
+            value,
+            span: span.clone(),
+        })
+    }
+
+    /// Check if the scheme is one of the reference.config shipped with Suricata
+    pub fn is_known_scheme(&self) -> bool {
+        REFERENCE_SCHEMES.contains(&self.scheme.0.as_str())
+    }
+}
+
+impl fmt::Display for Reference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.value {
+            Some((value, _)) => write!(f, "{},{}", self.scheme.0, value),
+            None => write!(f, "{}", self.scheme.0),
+        }
+    }
+}
+
+impl Rule {
+    /// Get every reference of the signature (e.g. "url" and "example.com")
+    pub fn references(&self) -> Vec<Reference> {
+        self.options_with_keyword("reference")
+            .into_iter()
+            .filter_map(|(option, span)| Reference::parse(option, span))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chumsky::Parser;
+
+    use super::*;
+
+    #[test]
+    fn commas_after_the_scheme_are_part_of_the_value() {
+        let line =
+            "alert tcp any any -> any any (reference:url,example.com/a,b; reference:foo; sid:1;)";
+        let (rule, _) = Rule::parser().parse(line).unwrap();
+        let references = rule.references();
+        assert_eq!(references.len(), 2);
+        assert_eq!(references[0].to_string(), "url,example.com/a,b");
+        assert_eq!(
+            references[0].value,
+            Some(("example.com/a,b".to_string(), 44..59))
+        );
+        assert!(references[0].is_known_scheme());
+        assert_eq!(references[1].value, None);
+        assert!(!references[1].is_known_scheme());
+    }
+}
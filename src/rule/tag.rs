@@ -0,0 +1,84 @@
+//! Structured view of the tag option
+//!
+//! A tag logs the packets following the alert, e.g. `tag:session,5,packets;`
+//! or `tag:host,600,seconds,src;`. The count, the metric and the direction
+//! are optional.
+use crate::rule::{options::RuleOption, Rule, Span, Spanned};
+
+/// What the packets are tagged for
+pub const TAG_TYPES: [&str; 2] = ["session", "host"];
+
+/// What the count of a tag measures
+pub const TAG_METRICS: [&str; 3] = ["packets", "seconds", "bytes"];
+
+/// The host tagged by a host tag
+pub const TAG_DIRECTIONS: [&str; 2] = ["src", "dst"];
+
+/// A single tag option
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tag {
+    /// The type of the tag (e.g. "session")
+    pub kind: Spanned<String>,
+    /// How many metrics are tagged (e.g. "600")
+    pub count: Option<Spanned<String>>,
+    /// What the count measures (e.g. "seconds")
+    pub metric: Option<Spanned<String>>,
+    /// The host of a host tag (e.g. "src")
+    pub direction: Option<Spanned<String>>,
+    /// The span of the whole option
+    pub span: Span,
+}
+
+impl Tag {
+    fn parse(option: &RuleOption, span: &Span) -> Option<Tag> {
+        let mut values = option.values().into_iter();
+        Some(Tag {
+            kind: values.next()?,
+            count: values.next(),
+            metric: values.next(),
+            direction: values.next(),
+            span: span.clone(),
+        })
+    }
+}
+
+impl Rule {
+    /// Get every tag option of the signature
+    pub fn tags(&self) -> Vec<Tag> {
+        self.options_with_keyword("tag")
+            .into_iter()
+            .filter_map(|(option, span)| Tag::parse(option, span))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chumsky::Parser;
+
+    use super::*;
+
+    fn tags(options: &str) -> Vec<Tag> {
+        let line = format!("alert tcp any any -> any any ({} sid:1;)", options);
+        let (rule, _) = Rule::parser().parse(line.as_str()).unwrap();
+        rule.tags()
+    }
+
+    fn text(spanned: &Option<Spanned<String>>) -> Option<&str> {
+        spanned.as_ref().map(|(value, _)| value.as_str())
+    }
+
+    #[test]
+    fn optional_arguments_of_a_tag_are_none() {
+        let tags = tags("tag:host,600,seconds,src; tag:session;");
+        assert_eq!(tags.len(), 2);
+        assert_eq!(tags[0].kind.0, "host");
+        assert_eq!(text(&tags[0].count), Some("600"));
+        assert_eq!(text(&tags[0].metric), Some("seconds"));
+        assert_eq!(text(&tags[0].direction), Some("src"));
+        assert_eq!(tags[1].kind.0, "session");
+        assert_eq!(tags[1].count, None);
+        assert_eq!(tags[1].metric, None);
+        assert_eq!(tags[1].direction, None);
+    }
+}
@@ -0,0 +1,135 @@
+//! Structured view of the threshold and detection_filter options
+//!
+//! Both options are a list of `key value` fields, e.g.
+//! `threshold: type both, track by_src, count 5, seconds 60;`. The
+//! detection_filter has the same fields, except for the type.
+use std::fmt;
+
+use crate::rule::{bits::words, options::RuleOption, Rule, Span, Spanned};
+
+/// The keywords, which hold a threshold
+pub const THRESHOLD_KEYWORDS: [&str; 2] = ["threshold", "detection_filter"];
+
+/// A single threshold or detection_filter option
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Threshold {
+    /// The keyword of the option (threshold or detection_filter)
+    pub keyword: Spanned<String>,
+    /// The type of the threshold (e.g. "both"), the detection_filter has none
+    pub kind: Option<Spanned<String>>,
+    /// What the threshold counts by (e.g. "by_src")
+    pub track: Option<Spanned<String>>,
+    pub count: Option<Spanned<String>>,
+    pub seconds: Option<Spanned<String>>,
+    /// The fields, which are not known, kept as they were written
+    pub unknown: Vec<Spanned<String>>,
+    /// The span of the whole option
+    pub span: Span,
+}
+
+impl Threshold {
+    /// Split the values of a threshold or detection_filter option into its fields
+    ///
+    /// Returns None for any other option.
+    pub fn parse(option: &RuleOption, span: &Span) -> Option<Threshold> {
+        let RuleOption::KeywordPair(keyword, _) = option else {
+            return None;
+        };
+        if !THRESHOLD_KEYWORDS.contains(&keyword.0.as_str()) {
+            return None;
+        }
+        let mut threshold = Threshold {
+            keyword: keyword.clone(),
+            kind: None,
+            track: None,
+            count: None,
+            seconds: None,
+            unknown: vec![],
+            span: span.clone(),
+        };
+        for value in option.values() {
+            let words = words(&value);
+            let Some(((key, _), rest)) = words.split_first() else {
+                continue;
+            };
+            let field = match (key.as_str(), rest) {
+                ("type", [_, ..]) if !threshold.is_detection_filter() => &mut threshold.kind,
+                ("track", [_, ..]) => &mut threshold.track,
+                ("count", [_, ..]) => &mut threshold.count,
+                ("seconds", [_, ..]) => &mut threshold.seconds,
+                _ => {
+                    threshold.unknown.push(value);
+                    continue;
+                }
+            };
+            if field.is_some() {
+                threshold.unknown.push(value);
+                continue;
+            }
+            let text = rest
+                .iter()
+                .map(|(word, _)| word.as_str())
+                .collect::<Vec<_>>()
+                .join(" ");
+            let start = rest.first().map_or(0, |(_, span)| span.start);
+            let end = rest.last().map_or(0, |(_, span)| span.end);
+            *field = Some((text, start..end));
+        }
+        Some(threshold)
+    }
+
+    pub fn is_detection_filter(&self) -> bool {
+        self.keyword.0 == "detection_filter"
+    }
+
+    /// Get the names of the mandatory fields, which are missing
+    pub fn missing_fields(&self) -> Vec<&'static str> {
+        let mut fields = vec![];
+        if !self.is_detection_filter() {
+            fields.push(("type", &self.kind));
+        }
+        fields.extend([
+            ("track", &self.track),
+            ("count", &self.count),
+            ("seconds", &self.seconds),
+        ]);
+        fields
+            .into_iter()
+            .filter(|(_, value)| value.is_none())
+            .map(|(name, _)| name)
+            .collect()
+    }
+}
+
+/// Prints the fields in the order Suricata documents them, followed by the
+/// unknown fields (e.g. "type both, track by_src, count 5, seconds 60")
+impl fmt::Display for Threshold {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fields = [
+            ("type", &self.kind),
+            ("track", &self.track),
+            ("count", &self.count),
+            ("seconds", &self.seconds),
+        ];
+        let fields = fields
+            .into_iter()
+            .filter_map(|(name, value)| {
+                let (value, _) = value.as_ref()?;
+                Some(format!("{} {}", name, value))
+            })
+            .chain(self.unknown.iter().map(|(value, _)| value.clone()))
+            .collect::<Vec<_>>();
+        write!(f, "{}", fields.join(", "))
+    }
+}
+
+impl Rule {
+    /// Get every threshold and detection_filter option of the signature
+    pub fn thresholds(&self) -> Vec<Threshold> {
+        self.options
+            .iter()
+            .flatten()
+            .filter_map(|(option, span)| Threshold::parse(option, span))
+            .collect()
+    }
+}
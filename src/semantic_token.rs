@@ -9,7 +9,9 @@
 //! [Semantic Highlighting Overview]: https://github.com/microsoft/vscode/wiki/Semantic-Highlighting-Overview
 
 use crate::rule::{Rule, Spanned, Semantics};
-use tower_lsp::lsp_types::SemanticTokenType;
+use crate::position::PositionEncoding;
+use lsp_types::{SemanticToken, SemanticTokenType};
+use ropey::Rope;
 
 /// A struct which stores only the most important information about the token
 /// 
@@ -18,7 +20,7 @@ use tower_lsp::lsp_types::SemanticTokenType;
 /// positioning). This is explained in depth in the following [GitHub issue]
 /// 
 /// [GitHub issue]: https://github.com/microsoft/vscode/issues/86415#issuecomment-587327402
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ImCompleteSemanticToken {
     pub start: usize,
     pub length: usize,
@@ -45,4 +47,128 @@ pub fn semantic_token_from_rule(
 ) {
     let (rule, _) = rule;
     rule.get_semantics(col, semantic_tokens);
-}
\ No newline at end of file
+}
+/// Encode the tokens, which are sorted by their start, the way they are transported
+///
+/// The start of a token is a character index into the rope, so the tokens
+/// are placed on the same columns, whichever line endings the file uses. The
+/// columns and the lengths are converted to the encoding of the client.
+pub fn encode_semantic_tokens<'a>(
+    rope: &Rope,
+    tokens: impl IntoIterator<Item = &'a ImCompleteSemanticToken>,
+    encoding: PositionEncoding,
+) -> Vec<SemanticToken> {
+    let mut pre_line = 0;
+    let mut pre_start = 0;
+    tokens
+        .into_iter()
+        .filter_map(|token| {
+            let line = rope.try_char_to_line(token.start).ok()?;
+            let text = rope.line(line);
+            let first = token.start - rope.line_to_char(line);
+            let start = encoding.to_column(text, first);
+            let length = encoding.to_column(text, first + token.length) - start;
+            let line = line as u32;
+            let delta_start = match line == pre_line {
+                true => start.checked_sub(pre_start)?,
+                false => start,
+            };
+            let semantic_token = SemanticToken {
+                delta_line: line.checked_sub(pre_line)?,
+                delta_start,
+                length,
+                token_type: token.token_type as u32,
+                token_modifiers_bitset: 0,
+            };
+            pre_line = line;
+            pre_start = start;
+            Some(semantic_token)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rule::AST;
+
+    const CRLF: &str = include_str!("../tests/fixtures/crlf/input.rules");
+
+    /// Decode the tokens into their line, column, length and type
+    fn decoded(text: &str) -> Vec<(u32, u32, u32, SemanticTokenType)> {
+        decoded_with(text, PositionEncoding::Utf16)
+    }
+
+    fn decoded_with(
+        text: &str,
+        encoding: PositionEncoding,
+    ) -> Vec<(u32, u32, u32, SemanticTokenType)> {
+        let rope = Rope::from_str(text);
+        let (_, tokens) = AST::parse_rope(&rope);
+        let (mut line, mut column) = (0, 0);
+        encode_semantic_tokens(&rope, &tokens, encoding)
+            .into_iter()
+            .map(|token| {
+                if token.delta_line > 0 {
+                    column = 0;
+                }
+                line += token.delta_line;
+                column += token.delta_start;
+                let token_type = LEGEND_TYPE[token.token_type as usize].clone();
+                (line, column, token.length, token_type)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn crlf_tokens_are_on_the_same_columns() {
+        assert!(CRLF.contains("\r\n"));
+        let tokens = decoded(CRLF);
+        assert_eq!(tokens, decoded(&CRLF.replace("\r\n", "\n")));
+        // The comments do not include the \r
+        let comment = SemanticTokenType::COMMENT;
+        assert_eq!(tokens.first(), Some(&(0, 0, 14, comment.clone())));
+        assert_eq!(tokens.last(), Some(&(4, 0, 49, comment)));
+        // The tokens of the later lines do not drift
+        let strings = tokens
+            .iter()
+            .filter(|(_, _, _, token_type)| *token_type == SemanticTokenType::STRING)
+            .map(|(line, column, length, _)| (*line, *column, *length))
+            .collect::<Vec<_>>();
+        assert_eq!(strings, vec![(1, 39, 7), (3, 45, 8), (3, 63, 5)]);
+        assert!(tokens.contains(&(3, 22, 13, SemanticTokenType::VARIABLE)));
+    }
+
+    #[test]
+    fn non_ascii_columns_follow_the_encoding() {
+        let text = "alert tcp any any -> any any (msg:\"Отказ 🚨\"; sid:1;)\n";
+        let columns = |encoding| {
+            decoded_with(text, encoding)
+                .into_iter()
+                .filter(|(_, column, _, _)| *column >= 34)
+                .map(|(_, column, length, _)| (column, length))
+                .collect::<Vec<_>>()
+        };
+        // The emoji is a single character, but two UTF-16 code units
+        assert_eq!(
+            columns(PositionEncoding::Utf32),
+            vec![(34, 9), (45, 3), (49, 1)]
+        );
+        assert_eq!(
+            columns(PositionEncoding::Utf16),
+            vec![(34, 10), (46, 3), (50, 1)]
+        );
+        // The tokens of the next line do not drift
+        let text = format!(
+            "{}{}",
+            text, "alert tcp any any -> any any (msg:\"x\"; sid:2;)\n"
+        );
+        let next_line = decoded(&text)
+            .into_iter()
+            .filter(|(line, _, _, _)| *line == 1)
+            .map(|(_, column, _, _)| column)
+            .collect::<Vec<_>>();
+        assert_eq!(next_line.first(), Some(&0));
+        assert_eq!(next_line.last(), Some(&43));
+    }
+}
@@ -1,5 +1,894 @@
+//! Provides the settings of the language server
+//!
+//! The same settings (as a JSON file) are also read by the command line tools,
+//! so the editor and the command line behave the same way.
+//!
+//! The settings are read field by field: a field with a wrong type or an out
+//! of range value is reported as a [SettingError] and keeps its default, so one
+//! typo does not reset every other setting. Unknown keys are accepted, but
+//! reported separately.
+use std::{collections::HashMap, error::Error, fmt, fs, path::Path};
 
-#[derive(Debug)]
-pub struct LanguageServerSettings {
-    pub suricata_config_file: Option<String>
-}
\ No newline at end of file
+use lsp_types::{Diagnostic, DiagnosticSeverity};
+use regex::Regex;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::format::FormatStyle;
+use crate::lint::{AnyToAnyStrictness, LintLevel};
+use crate::rule::header::{NetworkAddress, NetworkPort};
+use crate::suricata::VerificationMode;
+
+/// All settings of the language server
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ServerSettings {
+    pub suricata: SuricataSettings,
+    pub verification: VerificationSettings,
+    pub lints: LintsSettings,
+    pub formatting: FormattingSettings,
+    pub variables: VariablesSettings,
+    pub large_file: LargeFileSettings,
+    pub diagnostics: DiagnosticsSettings,
+    pub datasets: DatasetsSettings,
+    /// Path of the file the settings come from, used to jump to the variables
+    /// defined inside it
+    pub settings_file: Option<String>,
+}
+
+/// How Suricata can be found
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SuricataSettings {
+    /// Path to the Suricata binary (searched inside PATH by default)
+    pub path: Option<String>,
+}
+
+/// How the signatures are verified with Suricata
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct VerificationSettings {
+    /// Verify the signatures with Suricata
+    pub enabled: bool,
+    /// The profile used when no named profile is active
+    pub profile: VerificationProfile,
+    /// Named profiles, which can be switched at runtime
+    pub profiles: HashMap<String, VerificationProfile>,
+    /// The name of the active profile (the default profile if not set)
+    pub active_profile: Option<String>,
+}
+
+impl Default for VerificationSettings {
+    fn default() -> Self {
+        VerificationSettings {
+            enabled: true,
+            profile: VerificationProfile::default(),
+            profiles: HashMap::new(),
+            active_profile: None,
+        }
+    }
+}
+
+impl VerificationSettings {
+    /// The name of the profile used when no named profile is active
+    pub const DEFAULT_PROFILE: &'static str = "default";
+
+    /// Get the active profile, along with its name
+    pub fn active(&self) -> (&str, &VerificationProfile) {
+        self.active_profile
+            .as_ref()
+            .and_then(|name| Some((name.as_str(), self.profiles.get(name)?)))
+            .unwrap_or((VerificationSettings::DEFAULT_PROFILE, &self.profile))
+    }
+}
+
+/// How Suricata is invoked to verify the signatures
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct VerificationProfile {
+    /// Path to the Suricata config file (suricata.yaml), passed with -c
+    pub config_file: Option<String>,
+    /// Overrides of the config, passed with --set (e.g. "app-layer.protocols.sip.enabled=no")
+    pub set: Vec<String>,
+    /// How Suricata verifies the signatures
+    pub mode: VerificationMode,
+}
+
+/// The configuration of the lints
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LintsSettings {
+    /// Overrides for the severity of the lints, keyed by the lint code
+    #[serde(flatten)]
+    pub levels: HashMap<String, LintLevel>,
+    pub sid_range: SidRangeSettings,
+    /// Path to the threshold.config, checked against the thresholds of the signatures
+    pub threshold_config: Option<String>,
+    /// A regex, which every msg should match (e.g. "^[A-Z]+ [A-Z]+ ")
+    pub msg_pattern: Option<String>,
+    /// The longest msg accepted by the msg-length lint
+    pub msg_max_length: usize,
+    /// Prefixes of the msgs, which are shared on purpose (e.g. "ET INFO")
+    pub duplicate_msg_ignore: Vec<String>,
+    /// The shortest content (in bytes) accepted as a fast pattern
+    pub fast_pattern_min_length: usize,
+    /// Which signatures matching any traffic are reported by the any-to-any lint
+    pub any_to_any_strictness: AnyToAnyStrictness,
+}
+
+impl Default for LintsSettings {
+    fn default() -> Self {
+        LintsSettings {
+            levels: HashMap::new(),
+            sid_range: SidRangeSettings::default(),
+            threshold_config: None,
+            msg_pattern: None,
+            msg_max_length: 200,
+            duplicate_msg_ignore: vec![],
+            fast_pattern_min_length: 4,
+            any_to_any_strictness: AnyToAnyStrictness::default(),
+        }
+    }
+}
+
+/// The sids reserved for local signatures, checked by the sid-range lint
+///
+/// The globs are matched against the path of the file, relative to the
+/// workspace folder. Files matching neither glob are not checked.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SidRangeSettings {
+    /// The first and the last sid of the local range
+    pub local: [u32; 2],
+    /// Files with local signatures, which should only use sids of the local range
+    pub local_files: Vec<String>,
+    /// Files of vendor rulesets, which should not use sids of the local range
+    pub vendor_files: Vec<String>,
+}
+
+impl Default for SidRangeSettings {
+    fn default() -> Self {
+        SidRangeSettings {
+            local: [1_000_000, 1_999_999],
+            local_files: vec![],
+            vendor_files: vec![],
+        }
+    }
+}
+
+/// How the signatures are formatted
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FormattingSettings {
+    /// Answer the formatting requests of the client
+    pub enabled: bool,
+    pub space_after_option_colon: bool,
+    pub space_after_semicolon: bool,
+    pub sort_options: bool,
+    pub sanitize_on_format: bool,
+    pub insert_final_newline: bool,
+}
+
+impl Default for FormattingSettings {
+    fn default() -> Self {
+        let style = FormatStyle::default();
+        FormattingSettings {
+            enabled: true,
+            space_after_option_colon: style.space_after_option_colon,
+            space_after_semicolon: style.space_after_semicolon,
+            sort_options: style.sort_options,
+            sanitize_on_format: style.sanitize,
+            insert_final_newline: style.insert_final_newline,
+        }
+    }
+}
+
+/// Variables, which are defined inside the settings (instead of suricata.yaml)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct VariablesSettings {
+    /// Address groups, e.g. "HOME_NET": "[192.168.0.0/16,10.0.0.0/8]"
+    pub address_groups: HashMap<String, String>,
+    /// Port groups, e.g. "HTTP_PORTS": "[80,8080]"
+    pub port_groups: HashMap<String, String>,
+}
+
+/// How large files are treated
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LargeFileSettings {
+    /// Files with more lines are not verified with Suricata on every change
+    pub max_lines: usize,
+}
+
+impl Default for LargeFileSettings {
+    fn default() -> Self {
+        LargeFileSettings { max_lines: 10_000 }
+    }
+}
+
+/// How the diagnostics are published
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DiagnosticsSettings {
+    /// The most diagnostics published for a single file (the most severe are kept)
+    pub max_per_file: usize,
+    /// How long to wait after a change, before Suricata verifies the file
+    pub debounce_ms: u64,
+    /// Publish the diagnostics with the hint severity
+    pub include_hints: bool,
+}
+
+impl Default for DiagnosticsSettings {
+    fn default() -> Self {
+        DiagnosticsSettings {
+            max_per_file: 1000,
+            debounce_ms: 200,
+            include_hints: true,
+        }
+    }
+}
+
+impl DiagnosticsSettings {
+    /// Drop the diagnostics, which should not be published
+    ///
+    /// Hints are dropped if they are not included, the most severe diagnostics
+    /// are kept if there are more than allowed. The result is sorted by position.
+    pub fn filter(&self, mut diagnostics: Vec<Diagnostic>) -> Vec<Diagnostic> {
+        if !self.include_hints {
+            diagnostics.retain(|diagnostic| diagnostic.severity != Some(DiagnosticSeverity::HINT));
+        }
+        if diagnostics.len() > self.max_per_file {
+            // Diagnostics without a severity are treated as errors, like the clients do
+            diagnostics
+                .sort_by_key(|diagnostic| diagnostic.severity.unwrap_or(DiagnosticSeverity::ERROR));
+            diagnostics.truncate(self.max_per_file);
+        }
+        diagnostics.sort_by_key(|diagnostic| {
+            (
+                diagnostic.range.start.line,
+                diagnostic.range.start.character,
+            )
+        });
+        diagnostics
+    }
+}
+
+/// Where the files of the datasets are searched
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DatasetsSettings {
+    /// Directories with the files loaded and saved by the datasets, relative
+    /// directories are relative to the directory of the signatures
+    pub directories: Vec<String>,
+}
+
+impl Default for DatasetsSettings {
+    fn default() -> Self {
+        DatasetsSettings {
+            directories: vec![".".to_string()],
+        }
+    }
+}
+
+/// A problem with a single setting
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SettingError {
+    /// The path of the setting (e.g. "verification.mode")
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for SettingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// The settings, along with all problems found while reading them
+#[derive(Debug, Clone, Default)]
+pub struct ParsedSettings {
+    pub settings: ServerSettings,
+    pub errors: Vec<SettingError>,
+    /// Keys, which are not known to the language server (e.g. "verification.foo")
+    pub unknown_keys: Vec<String>,
+}
+
+impl ServerSettings {
+    /// Read the settings, failing if any setting is invalid
+    ///
+    /// Unknown keys are accepted. Use [ServerSettings::parse] to get the valid
+    /// settings along with the problems.
+    pub fn from_json(value: Value) -> Result<Self, Vec<SettingError>> {
+        let parsed = ServerSettings::parse(&value);
+        if parsed.errors.is_empty() {
+            Ok(parsed.settings)
+        } else {
+            Err(parsed.errors)
+        }
+    }
+
+    /// Read the settings, every invalid setting keeps its default
+    pub fn parse(value: &Value) -> ParsedSettings {
+        let mut reader = SettingsReader::default();
+        let mut settings = ServerSettings::default();
+        if let Some(object) = reader.object(value) {
+            reader.section(object, "suricata", |reader, object| {
+                reader.field(object, "path", &mut settings.suricata.path);
+                reader.unknown_keys(object, &["path"]);
+            });
+            reader.section(object, "verification", |reader, object| {
+                let verification = &mut settings.verification;
+                reader.field(object, "enabled", &mut verification.enabled);
+                reader.section(object, "profile", |reader, object| {
+                    reader.field(object, "configFile", &mut verification.profile.config_file);
+                    reader.field(object, "set", &mut verification.profile.set);
+                    reader.field(object, "mode", &mut verification.profile.mode);
+                    reader.unknown_keys(object, &["configFile", "set", "mode"]);
+                    reader.validate_profile("", &mut verification.profile);
+                });
+                reader.field(object, "profiles", &mut verification.profiles);
+                verification
+                    .profiles
+                    .iter_mut()
+                    .for_each(|(name, profile)| {
+                        reader.validate_profile(&format!("profiles.{}.", name), profile)
+                    });
+                reader.field(object, "activeProfile", &mut verification.active_profile);
+                let unknown_profile = verification.active_profile.as_ref().filter(|name| {
+                    *name != VerificationSettings::DEFAULT_PROFILE
+                        && !verification.profiles.contains_key(*name)
+                });
+                if let Some(name) = unknown_profile {
+                    reader.error(
+                        "activeProfile",
+                        format!("there is no profile named {:?}", name),
+                    );
+                    verification.active_profile = None;
+                }
+                reader.unknown_keys(object, &["enabled", "profile", "profiles", "activeProfile"]);
+            });
+            reader.section(object, "lints", |reader, object| {
+                let lints = &mut settings.lints;
+                reader.section(object, "sidRange", |reader, object| {
+                    let sid_range = &mut lints.sid_range;
+                    reader.field(object, "local", &mut sid_range.local);
+                    if sid_range.local[0] > sid_range.local[1] {
+                        let message = format!(
+                            "the first sid ({}) should not be greater than the last one ({})",
+                            sid_range.local[0], sid_range.local[1]
+                        );
+                        reader.error("local", message);
+                        sid_range.local = SidRangeSettings::default().local;
+                    }
+                    reader.field(object, "localFiles", &mut sid_range.local_files);
+                    reader.field(object, "vendorFiles", &mut sid_range.vendor_files);
+                    reader.validate_globs("localFiles", &mut sid_range.local_files);
+                    reader.validate_globs("vendorFiles", &mut sid_range.vendor_files);
+                    reader.unknown_keys(object, &["local", "localFiles", "vendorFiles"]);
+                });
+                reader.field(object, "thresholdConfig", &mut lints.threshold_config);
+                let missing_config = lints
+                    .threshold_config
+                    .as_ref()
+                    .filter(|threshold_config| !Path::new(threshold_config).is_file());
+                if let Some(threshold_config) = missing_config {
+                    reader.error(
+                        "thresholdConfig",
+                        format!("{} does not exist", threshold_config),
+                    );
+                    lints.threshold_config = None;
+                }
+                reader.field(object, "msgPattern", &mut lints.msg_pattern);
+                let invalid_pattern = lints
+                    .msg_pattern
+                    .as_ref()
+                    .and_then(|msg_pattern| Regex::new(msg_pattern).err());
+                if let Some(err) = invalid_pattern {
+                    reader.error("msgPattern", err.to_string());
+                    lints.msg_pattern = None;
+                }
+                reader.field(object, "msgMaxLength", &mut lints.msg_max_length);
+                if lints.msg_max_length == 0 {
+                    reader.error("msgMaxLength", "should be greater than 0".to_string());
+                    lints.msg_max_length = LintsSettings::default().msg_max_length;
+                }
+                reader.field(
+                    object,
+                    "duplicateMsgIgnore",
+                    &mut lints.duplicate_msg_ignore,
+                );
+                reader.field(
+                    object,
+                    "fastPatternMinLength",
+                    &mut lints.fast_pattern_min_length,
+                );
+                reader.field(
+                    object,
+                    "anyToAnyStrictness",
+                    &mut lints.any_to_any_strictness,
+                );
+                // Every other key is the level of a lint
+                let settings_keys = [
+                    "sidRange",
+                    "thresholdConfig",
+                    "msgPattern",
+                    "msgMaxLength",
+                    "duplicateMsgIgnore",
+                    "fastPatternMinLength",
+                    "anyToAnyStrictness",
+                ];
+                object
+                    .iter()
+                    .filter(|(key, _)| !settings_keys.contains(&key.as_str()))
+                    .for_each(|(key, value)| match LintLevel::deserialize(value) {
+                        Ok(level) => {
+                            lints.levels.insert(key.clone(), level);
+                        }
+                        Err(err) => reader.error(key, err.to_string()),
+                    });
+            });
+            reader.section(object, "formatting", |reader, object| {
+                let formatting = &mut settings.formatting;
+                reader.field(object, "enabled", &mut formatting.enabled);
+                reader.field(
+                    object,
+                    "spaceAfterOptionColon",
+                    &mut formatting.space_after_option_colon,
+                );
+                reader.field(
+                    object,
+                    "spaceAfterSemicolon",
+                    &mut formatting.space_after_semicolon,
+                );
+                reader.field(object, "sortOptions", &mut formatting.sort_options);
+                reader.field(
+                    object,
+                    "sanitizeOnFormat",
+                    &mut formatting.sanitize_on_format,
+                );
+                reader.field(
+                    object,
+                    "insertFinalNewline",
+                    &mut formatting.insert_final_newline,
+                );
+                // The options stay on the line of the header (there is no layout
+                // over several lines), since the signatures are parsed line by line
+                reader.unknown_keys(
+                    object,
+                    &[
+                        "enabled",
+                        "spaceAfterOptionColon",
+                        "spaceAfterSemicolon",
+                        "sortOptions",
+                        "sanitizeOnFormat",
+                        "insertFinalNewline",
+                    ],
+                );
+            });
+            reader.section(object, "variables", |reader, object| {
+                reader.field(
+                    object,
+                    "addressGroups",
+                    &mut settings.variables.address_groups,
+                );
+                reader.field(object, "portGroups", &mut settings.variables.port_groups);
+                // Drop the values Suricata would not accept
+                let address_groups = &mut settings.variables.address_groups;
+                address_groups.retain(|name, value| match NetworkAddress::parse_str(value) {
+                    Ok(_) => true,
+                    Err(err) => {
+                        reader.error(&format!("addressGroups.{}", name), err);
+                        false
+                    }
+                });
+                let port_groups = &mut settings.variables.port_groups;
+                port_groups.retain(|name, value| match NetworkPort::parse_str(value) {
+                    Ok(_) => true,
+                    Err(err) => {
+                        reader.error(&format!("portGroups.{}", name), err);
+                        false
+                    }
+                });
+                reader.unknown_keys(object, &["addressGroups", "portGroups"]);
+            });
+            reader.section(object, "largeFile", |reader, object| {
+                reader.field(object, "maxLines", &mut settings.large_file.max_lines);
+                if settings.large_file.max_lines == 0 {
+                    reader.error("maxLines", "should be greater than 0".to_string());
+                    settings.large_file.max_lines = LargeFileSettings::default().max_lines;
+                }
+                reader.unknown_keys(object, &["maxLines"]);
+            });
+            reader.section(object, "diagnostics", |reader, object| {
+                let diagnostics = &mut settings.diagnostics;
+                reader.field(object, "maxPerFile", &mut diagnostics.max_per_file);
+                reader.field(object, "debounceMs", &mut diagnostics.debounce_ms);
+                reader.field(object, "includeHints", &mut diagnostics.include_hints);
+                reader.unknown_keys(object, &["maxPerFile", "debounceMs", "includeHints"]);
+            });
+            reader.section(object, "datasets", |reader, object| {
+                reader.field(object, "directories", &mut settings.datasets.directories);
+                reader.unknown_keys(object, &["directories"]);
+            });
+            reader.field(object, "settingsFile", &mut settings.settings_file);
+            reader.unknown_keys(
+                object,
+                &[
+                    "suricata",
+                    "verification",
+                    "lints",
+                    "formatting",
+                    "variables",
+                    "largeFile",
+                    "diagnostics",
+                    "datasets",
+                    "settingsFile",
+                ],
+            );
+        }
+        ParsedSettings {
+            settings,
+            errors: reader.errors,
+            unknown_keys: reader.unknown_keys,
+        }
+    }
+
+    /// Read the settings, which override some of these settings
+    ///
+    /// Objects are merged key by key (e.g. a single lint can be overridden),
+    /// every other value replaces the current one.
+    pub fn with_overrides(&self, overrides: &Value) -> ParsedSettings {
+        let mut value = serde_json::to_value(self).unwrap_or_default();
+        merge_json(&mut value, overrides);
+        ServerSettings::parse(&value)
+    }
+
+    /// Load the settings from a JSON file
+    ///
+    /// Fails only if the file can not be read or is not valid JSON, invalid
+    /// settings are reported inside the result.
+    pub fn from_file(path: &Path) -> Result<ParsedSettings, Box<dyn Error>> {
+        let content = fs::read_to_string(path)?;
+        let value = serde_json::from_str(&content)?;
+        let mut parsed = ServerSettings::parse(&value);
+        if parsed.settings.settings_file.is_none() {
+            parsed.settings.settings_file = Some(path.display().to_string());
+        }
+        Ok(parsed)
+    }
+}
+
+/// Merge the overrides into the value, section by section
+///
+/// See [ServerSettings::with_overrides].
+pub fn merge_json(value: &mut Value, overrides: &Value) {
+    match (value, overrides) {
+        (Value::Object(object), Value::Object(overrides)) => {
+            overrides
+                .iter()
+                .for_each(|(key, override_value)| match object.get_mut(key) {
+                    Some(value) => merge_json(value, override_value),
+                    None => {
+                        object.insert(key.clone(), override_value.clone());
+                    }
+                });
+        }
+        // A missing section does not override anything
+        (_, Value::Null) => (),
+        (value, overrides) => *value = overrides.clone(),
+    }
+}
+
+/// Reads the settings field by field, collecting the problems
+#[derive(Default)]
+struct SettingsReader {
+    /// The path of the current section (e.g. "verification.")
+    prefix: String,
+    errors: Vec<SettingError>,
+    unknown_keys: Vec<String>,
+}
+
+impl SettingsReader {
+    fn error(&mut self, key: &str, message: String) {
+        self.errors.push(SettingError {
+            path: format!("{}{}", self.prefix, key),
+            message,
+        });
+    }
+
+    /// Get the value as an object (null is treated as a missing section)
+    fn object<'a>(&mut self, value: &'a Value) -> Option<&'a Map<String, Value>> {
+        match value {
+            Value::Object(object) => Some(object),
+            Value::Null => None,
+            other => {
+                self.errors.push(SettingError {
+                    path: self.prefix.trim_end_matches('.').to_string(),
+                    message: format!("expected an object, found {}", other),
+                });
+                None
+            }
+        }
+    }
+
+    /// Read a nested section
+    fn section(
+        &mut self,
+        object: &Map<String, Value>,
+        key: &str,
+        read: impl FnOnce(&mut SettingsReader, &Map<String, Value>),
+    ) {
+        let Some(value) = object.get(key) else {
+            return;
+        };
+        let section_prefix = format!("{}{}.", self.prefix, key);
+        let prefix = std::mem::replace(&mut self.prefix, section_prefix);
+        if let Some(section) = self.object(value) {
+            read(self, section);
+        }
+        self.prefix = prefix;
+    }
+
+    /// Read a single field, the target is kept if the field is missing or invalid
+    fn field<T: DeserializeOwned>(
+        &mut self,
+        object: &Map<String, Value>,
+        key: &str,
+        target: &mut T,
+    ) {
+        let Some(value) = object.get(key) else {
+            return;
+        };
+        match T::deserialize(value) {
+            Ok(value) => *target = value,
+            Err(err) => self.error(key, err.to_string()),
+        }
+    }
+
+    /// Drop the parts of a profile, which Suricata would not accept
+    fn validate_profile(&mut self, prefix: &str, profile: &mut VerificationProfile) {
+        let missing_config = profile
+            .config_file
+            .as_ref()
+            .filter(|config_file| !Path::new(config_file).is_file());
+        if let Some(config_file) = missing_config {
+            let message = format!("{} does not exist", config_file);
+            self.error(&format!("{}configFile", prefix), message);
+            profile.config_file = None;
+        }
+        profile.set.retain(|set| {
+            let valid = set
+                .split_once('=')
+                .is_some_and(|(key, _)| !key.trim().is_empty());
+            if !valid {
+                let message = format!("expected \"name=value\", found {:?}", set);
+                self.error(&format!("{}set", prefix), message);
+            }
+            valid
+        });
+    }
+
+    /// Drop the glob patterns, which can not be compiled
+    fn validate_globs(&mut self, key: &str, patterns: &mut Vec<String>) {
+        patterns.retain(|pattern| match glob::Pattern::new(pattern) {
+            Ok(_) => true,
+            Err(err) => {
+                self.error(key, format!("invalid glob {:?}: {}", pattern, err));
+                false
+            }
+        });
+    }
+
+    /// Remember all keys of the object, which are not known
+    fn unknown_keys(&mut self, object: &Map<String, Value>, known: &[&str]) {
+        object
+            .keys()
+            .filter(|key| !known.contains(&key.as_str()))
+            .for_each(|key| self.unknown_keys.push(format!("{}{}", self.prefix, key)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lsp_types::{Position, Range};
+    use serde_json::json;
+
+    use super::*;
+
+    fn diagnostic(line: u32, severity: DiagnosticSeverity) -> Diagnostic {
+        Diagnostic {
+            range: Range::new(Position::new(line, 0), Position::new(line, 1)),
+            severity: Some(severity),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn missing_settings_keep_their_defaults() {
+        let parsed = ServerSettings::parse(&json!({
+            "verification": {"enabled": false},
+            "lints": {"missing-rev": "off", "anyToAnyStrictness": "strict"},
+            "variables": null,
+        }));
+        assert!(parsed.errors.is_empty());
+        assert!(parsed.unknown_keys.is_empty());
+
+        let mut expected = ServerSettings::default();
+        expected.verification.enabled = false;
+        expected
+            .lints
+            .levels
+            .insert("missing-rev".to_string(), LintLevel::Off);
+        expected.lints.any_to_any_strictness = AnyToAnyStrictness::Strict;
+        assert_eq!(parsed.settings, expected);
+    }
+
+    #[test]
+    fn invalid_settings_keep_their_defaults() {
+        let parsed = ServerSettings::parse(&json!({
+            "verification": {"enabled": "yes", "activeProfile": "strict"},
+            "lints": {
+                "missing-rev": "loud",
+                "sidRange": {"local": [5, 1]},
+                "msgPattern": "^[A-Z",
+                "msgMaxLength": 0,
+            },
+            "largeFile": {"maxLines": 0},
+            "formatting": [],
+            "variables": {"portGroups": {"HTTP_PORTS": "[80,8080]"}},
+        }));
+        let paths = parsed
+            .errors
+            .iter()
+            .map(|error| error.path.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            vec![
+                "verification.enabled",
+                "verification.activeProfile",
+                "lints.sidRange.local",
+                "lints.msgPattern",
+                "lints.msgMaxLength",
+                "lints.missing-rev",
+                "formatting",
+                "largeFile.maxLines",
+            ]
+        );
+        // The valid settings next to the invalid ones are kept
+        let mut expected = ServerSettings::default();
+        expected
+            .variables
+            .port_groups
+            .insert("HTTP_PORTS".to_string(), "[80,8080]".to_string());
+        assert_eq!(parsed.settings, expected);
+        assert!(ServerSettings::from_json(json!({"largeFile": {"maxLines": 0}})).is_err());
+    }
+
+    #[test]
+    fn unknown_keys_are_reported() {
+        let parsed = ServerSettings::parse(&json!({
+            "suricata": {"path": "/usr/bin/suricata", "version": "7"},
+            "verification": {"profile": {"mode": "test", "timeout": 5}},
+            "formatting": {"optionsLayout": "onePerLine"},
+            "theme": "dark",
+        }));
+        assert!(parsed.errors.is_empty());
+        assert_eq!(
+            parsed.unknown_keys,
+            vec![
+                "suricata.version",
+                "verification.profile.timeout",
+                "formatting.optionsLayout",
+                "theme"
+            ]
+        );
+        assert_eq!(
+            parsed.settings.suricata.path.as_deref(),
+            Some("/usr/bin/suricata")
+        );
+        // Unknown keys are not an error
+        assert!(ServerSettings::from_json(json!({"theme": "dark"})).is_ok());
+    }
+
+    #[test]
+    fn settings_survive_a_round_trip() {
+        let mut settings = ServerSettings::default();
+        settings.suricata.path = Some("/opt/suricata/bin/suricata".to_string());
+        settings.verification.enabled = false;
+        settings.verification.profile.set = vec!["vars.address-groups.HOME_NET=any".to_string()];
+        settings.verification.profiles.insert(
+            "strict".to_string(),
+            VerificationProfile {
+                mode: VerificationMode::Test,
+                ..Default::default()
+            },
+        );
+        settings.verification.active_profile = Some("strict".to_string());
+        settings
+            .lints
+            .levels
+            .insert("missing-rev".to_string(), LintLevel::Error);
+        settings.formatting.enabled = false;
+        settings
+            .variables
+            .address_groups
+            .insert("HOME_NET".to_string(), "[10.0.0.0/8]".to_string());
+        settings.large_file.max_lines = 500;
+
+        let value = serde_json::to_value(&settings).unwrap();
+        let parsed = ServerSettings::parse(&value);
+        assert!(parsed.errors.is_empty(), "{:?}", parsed.errors);
+        assert!(parsed.unknown_keys.is_empty(), "{:?}", parsed.unknown_keys);
+        assert_eq!(parsed.settings, settings);
+        assert_eq!(ServerSettings::from_json(value).unwrap(), settings);
+    }
+
+    #[test]
+    fn most_severe_diagnostics_are_kept() {
+        let settings = ServerSettings::parse(&json!({"diagnostics": {"maxPerFile": 2}}));
+        let filtered = settings.settings.diagnostics.filter(vec![
+            diagnostic(0, DiagnosticSeverity::HINT),
+            diagnostic(1, DiagnosticSeverity::WARNING),
+            diagnostic(2, DiagnosticSeverity::INFORMATION),
+            diagnostic(3, DiagnosticSeverity::ERROR),
+        ]);
+        let lines = filtered
+            .iter()
+            .map(|diagnostic| diagnostic.range.start.line)
+            .collect::<Vec<_>>();
+        assert_eq!(lines, vec![1, 3]);
+        // Diagnostics without a severity count as errors
+        let unknown = Diagnostic {
+            severity: None,
+            ..diagnostic(4, DiagnosticSeverity::ERROR)
+        };
+        let filtered = settings.settings.diagnostics.filter(vec![
+            diagnostic(0, DiagnosticSeverity::WARNING),
+            unknown.clone(),
+        ]);
+        assert_eq!(filtered.len(), 2);
+        let settings = DiagnosticsSettings {
+            max_per_file: 1,
+            ..Default::default()
+        };
+        let filtered = settings.filter(vec![diagnostic(0, DiagnosticSeverity::WARNING), unknown]);
+        assert_eq!(filtered[0].range.start.line, 4);
+        assert!(DiagnosticsSettings::default().filter(vec![]).is_empty());
+    }
+
+    #[test]
+    fn hints_can_be_excluded() {
+        let diagnostics = vec![
+            diagnostic(2, DiagnosticSeverity::HINT),
+            diagnostic(0, DiagnosticSeverity::WARNING),
+        ];
+        let included = DiagnosticsSettings::default().filter(diagnostics.clone());
+        let lines = included
+            .iter()
+            .map(|diagnostic| diagnostic.range.start.line)
+            .collect::<Vec<_>>();
+        assert_eq!(lines, vec![0, 2]);
+        let settings = ServerSettings::parse(&json!({"diagnostics": {"includeHints": false}}));
+        let excluded = settings.settings.diagnostics.filter(diagnostics);
+        assert_eq!(excluded, vec![diagnostic(0, DiagnosticSeverity::WARNING)]);
+    }
+
+    #[test]
+    fn diagnostics_settings_are_read() {
+        let parsed = ServerSettings::parse(&json!({
+            "diagnostics": {"maxPerFile": 5, "debounceMs": 0, "includeHints": false},
+        }));
+        let expected = DiagnosticsSettings {
+            max_per_file: 5,
+            debounce_ms: 0,
+            include_hints: false,
+        };
+        assert_eq!(parsed.settings.diagnostics, expected);
+        let parsed = ServerSettings::parse(&json!({"diagnostics": {"debounceMs": -1}}));
+        assert_eq!(parsed.errors[0].path, "diagnostics.debounceMs");
+        assert_eq!(parsed.settings.diagnostics, DiagnosticsSettings::default());
+    }
+}
@@ -0,0 +1,180 @@
+//! Provides the skeleton of a new signature
+//!
+//! The skeleton has the header chosen by the user, along with the options
+//! every signature needs, e.g.:
+//!
+//! ```text
+//! alert http $HOME_NET any -> $EXTERNAL_NET any (msg:"HTTP TODO"; flow:established,to_server; content:""; classtype:unknown; sid:1000001; rev:1;)
+//! ```
+use serde::Deserialize;
+
+use crate::rule::header::NetworkAddress;
+use crate::rule::{Rule, AST};
+
+/// Protocols, which are not tracked in flows
+const FLOWLESS_PROTOCOLS: [&str; 5] = ["ip", "icmp", "icmpv4", "icmpv6", "pkthdr"];
+
+/// The directions of a header
+const DIRECTIONS: [&str; 3] = ["->", "<>", "<-"];
+
+/// What the skeleton looks like, as chosen by the user
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SkeletonOptions {
+    pub protocol: String,
+    pub direction: String,
+    /// The source addresses (the $ of a variable is optional)
+    pub from_variable: String,
+    /// The destination addresses (the $ of a variable is optional)
+    pub to_variable: String,
+}
+
+impl Default for SkeletonOptions {
+    fn default() -> Self {
+        SkeletonOptions {
+            protocol: "tcp".to_string(),
+            direction: "->".to_string(),
+            from_variable: "$HOME_NET".to_string(),
+            to_variable: "$EXTERNAL_NET".to_string(),
+        }
+    }
+}
+
+/// Create the skeleton of a signature with the given sid
+///
+/// Fails if the protocol, the direction or the addresses are not valid.
+pub fn rule_skeleton(options: &SkeletonOptions, sid: u32) -> Result<Rule, String> {
+    let protocol = options.protocol.trim().to_lowercase();
+    if protocol.is_empty()
+        || !protocol
+            .chars()
+            .all(|char| char.is_alphanumeric() || "-_".contains(char))
+    {
+        return Err(format!("\"{}\" is not a valid protocol", options.protocol));
+    }
+    let direction = options.direction.trim();
+    if !DIRECTIONS.contains(&direction) {
+        return Err(format!(
+            "\"{}\" is not a valid direction, expected one of {}",
+            options.direction,
+            DIRECTIONS.join(", ")
+        ));
+    }
+    let from = address(&options.from_variable)?;
+    let to = address(&options.to_variable)?;
+    let mut text = format!(
+        "alert {} {} any {} {} any (msg:\"{} TODO\"; ",
+        protocol,
+        from,
+        direction,
+        to,
+        protocol.to_uppercase()
+    );
+    // The flow is guessed the same way as for the missing-flow lint
+    let header = AST::parse_str(&format!("{}sid:{};)", text, sid)).0;
+    let (guess, _) = header.rules.get(&0).ok_or("the header is not valid")?;
+    if !FLOWLESS_PROTOCOLS.contains(&protocol.as_str()) {
+        text.push_str(&format!("flow:{}; ", guess.suggested_flow()));
+    }
+    text.push_str(&format!(
+        "content:\"\"; classtype:unknown; sid:{}; rev:1;)",
+        sid
+    ));
+    let (mut ast, _) = AST::parse_str(&text);
+    ast.rules
+        .remove(&0)
+        .map(|(rule, _)| rule)
+        .ok_or_else(|| "the skeleton is not valid".to_string())
+}
+
+/// Get the addresses of the header, adding the $ to a variable if needed
+fn address(value: &str) -> Result<String, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Err("the addresses should not be empty".to_string());
+    }
+    if NetworkAddress::parse_str(value).is_ok() {
+        return Ok(value.to_string());
+    }
+    let variable = format!("${}", value);
+    match NetworkAddress::parse_str(&variable) {
+        Ok(_) => Ok(variable),
+        Err(_) => Err(format!("\"{}\" are not valid addresses", value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn skeleton(protocol: &str, direction: &str) -> String {
+        let options = SkeletonOptions {
+            protocol: protocol.to_string(),
+            direction: direction.to_string(),
+            ..Default::default()
+        };
+        rule_skeleton(&options, 1000001).unwrap().to_string()
+    }
+
+    #[test]
+    fn every_protocol_has_a_template() {
+        let header = "$HOME_NET any -> $EXTERNAL_NET any";
+        let cases = [
+            ("tcp", "flow: established, to_server; "),
+            ("udp", "flow: established, to_server; "),
+            ("http", "flow: established, to_server; "),
+            ("dns", "flow: established, to_server; "),
+            ("tls", "flow: established, to_server; "),
+            ("icmp", ""),
+            ("ip", ""),
+        ];
+        for (protocol, flow) in cases {
+            let expected = format!(
+                "alert {} {} (msg: \"{} TODO\"; {}content: \"\"; classtype: unknown; sid: 1000001; rev: 1;)",
+                protocol,
+                header,
+                protocol.to_uppercase(),
+                flow
+            );
+            assert_eq!(skeleton(protocol, "->"), expected, "{}", protocol);
+        }
+    }
+
+    #[test]
+    fn the_flow_follows_the_direction() {
+        assert!(skeleton("tcp", "->").contains("flow: established, to_server;"));
+        assert!(skeleton("tcp", "<>").contains("flow: established;"));
+    }
+
+    #[test]
+    fn variables_get_their_dollar() {
+        let options = SkeletonOptions {
+            from_variable: "HOME_NET".to_string(),
+            to_variable: "10.0.0.0/8".to_string(),
+            ..Default::default()
+        };
+        let rule = rule_skeleton(&options, 1).unwrap().to_string();
+        assert!(rule.starts_with("alert tcp $HOME_NET any -> 10.0.0.0/8 any ("));
+    }
+
+    #[test]
+    fn invalid_options_are_rejected() {
+        let invalid = [
+            SkeletonOptions {
+                protocol: "t c p".to_string(),
+                ..Default::default()
+            },
+            SkeletonOptions {
+                direction: "=>".to_string(),
+                ..Default::default()
+            },
+            SkeletonOptions {
+                from_variable: " ".to_string(),
+                ..Default::default()
+            },
+        ];
+        for options in invalid {
+            assert!(rule_skeleton(&options, 1).is_err(), "{:?}", options);
+        }
+    }
+}
@@ -0,0 +1,402 @@
+//! Runs Suricata
+//!
+//! Verifies signatures by running Suricata and parsing its logs, and fetches
+//! the keywords and the events Suricata supports.
+
+use chrono::{DateTime, FixedOffset, Local, NaiveDate};
+use chumsky::{
+    prelude::Simple,
+    primitive::{empty, end, just, take_until},
+    text::{self, TextParser},
+    Parser,
+};
+use csv::ReaderBuilder;
+use ropey::Rope;
+use std::io::Write;
+use std::path::Path;
+use std::{collections::HashMap, error::Error};
+use tempfile::{tempdir, NamedTempFile};
+use tokio::process::Command;
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, Position, Range};
+
+use crate::server_settings::{ServerSettings, VerificationProfile};
+
+use super::{narrow_span, EventTable, Keyword, KeywordRecord, SuricataVersion, VerificationMode};
+
+/// Verify a list of rules
+pub async fn verify_rule(
+    rope: &Rope,
+    settings: &ServerSettings,
+) -> Result<Vec<Diagnostic>, Box<dyn Error>> {
+    verify_text(&rope.to_string(), settings).await
+}
+
+/// Verify the rules inside a rule file, given as a string
+///
+/// The range of every diagnostic is narrowed down to the part of the line
+/// Suricata complains about, if it can be found.
+pub async fn verify_text(
+    text: &str,
+    settings: &ServerSettings,
+) -> Result<Vec<Diagnostic>, Box<dyn Error>> {
+    let temp_dir = tempdir()?;
+    let mut tempfile = NamedTempFile::new_in(&temp_dir)?;
+    tempfile.write_all(text.as_bytes())?;
+    let log_file = get_process_output(tempfile.path(), temp_dir.path(), settings).await?;
+    tempfile.close()?;
+    let logs = LogMessage::parse_logs().parse(log_file);
+    let lines = text.lines().collect::<Vec<&str>>();
+
+    let mut curr_line: u32 = 0;
+
+    // Go over each log
+    let diagnostics = match logs {
+        Ok(logs) => {
+            logs.iter()
+                .rev()
+                .filter_map(|error| -> Option<Diagnostic> {
+                    // Check if the log has an error code
+                    match &error.err_code {
+                        // Check it is the error code, which contains the line and file
+                        Some(_)
+                            if error.message.contains("at line ")
+                                && error.message.contains("from file ") =>
+                        {
+                            // Find the location of file name and line in output
+                            let line_loc = error.message.rfind("at line ")? + "at line ".len();
+
+                            // Get current line and file from logs
+                            let parsed_line = &error.message[line_loc..];
+                            // Check if parse was successfull
+                            if let Ok(line_num) = parsed_line.parse::<u32>() {
+                                curr_line = line_num;
+                            }
+                            // Return none
+                            None
+                        }
+                        // Else push error to the user
+                        Some(err_code) => {
+                            // Since lines are indexed at 0
+                            let line = curr_line.saturating_sub(1);
+                            let line_text = lines.get(line as usize).copied().unwrap_or_default();
+                            let span = narrow_span(&error.message, line_text);
+                            let range = Range::new(
+                                Position {
+                                    line,
+                                    character: span.start as u32,
+                                },
+                                Position {
+                                    line,
+                                    character: span.end as u32,
+                                },
+                            );
+                            let source = String::from("Suricata");
+                            Some(Diagnostic::new_with_code_number(
+                                range,
+                                DiagnosticSeverity::ERROR,
+                                err_code.err_code as i32,
+                                Some(source),
+                                error.message.clone(),
+                            ))
+                        }
+                        _ => None,
+                    }
+                })
+                .collect::<Vec<Diagnostic>>()
+        }
+        Err(_) => {
+            vec![]
+        }
+    };
+    Ok(diagnostics)
+}
+
+/// Gets the output that Suricata produced and returns it as a String
+async fn get_process_output(
+    rule_file: &Path,
+    log_path: &Path,
+    settings: &ServerSettings,
+) -> Result<String, Box<dyn Error>> {
+    let (_, profile) = settings.verification.active();
+    let args = suricata_arguments(profile, rule_file, log_path);
+    let suricata_binary = settings.suricata.path.as_deref().unwrap_or("suricata");
+    let suricata_process = Command::new(suricata_binary).args(args).output().await?;
+
+    // Get the output from the command
+    let log_file = String::from_utf8(suricata_process.stderr)?;
+    // A hacky method to fix suricata strange output
+    let log_file = log_file.replace("\n\"", "\"");
+    Ok(log_file)
+}
+
+/// Get the version of the installed Suricata (suricata -V)
+pub async fn get_suricata_version(
+    settings: &ServerSettings,
+) -> Result<SuricataVersion, Box<dyn Error>> {
+    let suricata_binary = settings.suricata.path.as_deref().unwrap_or("suricata");
+    let version_command = Command::new(suricata_binary).arg("-V").output().await?;
+    let output = String::from_utf8(version_command.stdout)?;
+    SuricataVersion::parse(&output)
+        .ok_or_else(|| format!("could not find the version in {:?}", output.trim()).into())
+}
+
+/// Get the events, which the installed Suricata raises
+///
+/// Suricata installs the rules matching its events (e.g. decoder-events.rules)
+/// into its data directory, which is found with `suricata --build-info`. The
+/// events are added to the bundled ones.
+pub async fn get_events(settings: &ServerSettings) -> Result<EventTable, Box<dyn Error>> {
+    let suricata_binary = settings.suricata.path.as_deref().unwrap_or("suricata");
+    let build_info = Command::new(suricata_binary)
+        .arg("--build-info")
+        .output()
+        .await?;
+    let output = String::from_utf8(build_info.stdout)?;
+    let data_dir = output
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("--datarootdir"))
+        .map(str::trim)
+        .ok_or("could not find the data directory in the build info")?;
+    let rules_dir = Path::new(data_dir).join("suricata").join("rules");
+    let mut events = EventTable::default();
+    let mut entries = tokio::fs::read_dir(&rules_dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "rules")
+        {
+            // Files which cannot be read are skipped
+            if let Ok(text) = tokio::fs::read_to_string(&path).await {
+                events.extend_from_rules(&text);
+            }
+        }
+    }
+    Ok(events)
+}
+
+/// The keywords of the Suricata version named by the build (see build.rs), used
+/// when Suricata is not installed
+const BUNDLED_KEYWORDS: &str = include_str!("../../resources/keywords.csv");
+
+pub async fn get_keywords() -> Result<HashMap<String, Keyword>, Box<dyn Error>> {
+    // Execute suricata
+    // -r pcap offline mode
+    let keywords_command = Command::new("suricata")
+        .arg("--list-keywords=csv")
+        .output()
+        .await?;
+
+    // Get the output from the command
+    let log_file = String::from_utf8(keywords_command.stdout)?;
+    Ok(parse_keywords(&log_file))
+}
+
+/// Get the keywords bundled with the language server
+pub fn bundled_keywords() -> HashMap<String, Keyword> {
+    parse_keywords(BUNDLED_KEYWORDS)
+}
+
+/// Parse the keywords listed by `suricata --list-keywords=csv`
+fn parse_keywords(output: &str) -> HashMap<String, Keyword> {
+    let mut ret = HashMap::new();
+    let mut log_file = output.to_string();
+    // Skip all the log files
+    let csv_start = log_file.find("name;description;app layer;features;documentation");
+    if let Some(csv_start) = csv_start {
+        log_file = log_file[csv_start..].to_string();
+    }
+    // Hacky solution to suricata adding ; after every record
+    log_file = log_file.replace("documentation", "documentation;");
+    // Hacky solution to suricata using non-standard header names
+    log_file = log_file.replace("app layer", "app_layer");
+
+    // Get all CSV records
+    let mut reader = ReaderBuilder::new()
+        .delimiter(b';')
+        .from_reader(log_file.as_bytes());
+    // Ignore errors
+    for keyword_record in reader.deserialize().flatten() {
+        let (name, keyword) = KeywordRecord::to_keyword(keyword_record);
+        ret.insert(name, keyword);
+    }
+    ret
+}
+
+#[allow(dead_code)]
+#[derive(Clone, Debug)]
+struct SuricataErrorCode {
+    err_type: String,
+    err_code: u32,
+}
+
+#[allow(dead_code)]
+#[derive(Clone, Debug)]
+struct LogMessage {
+    timestamp: DateTime<FixedOffset>,
+    log_level: String,
+    err_code: Option<SuricataErrorCode>,
+    message: String,
+}
+impl SuricataErrorCode {
+    // Example input: "[ERRCODE: SC_ERR_INVALID_SIGNATURE(39)]""
+    fn parser() -> impl Parser<char, SuricataErrorCode, Error = Simple<char>> {
+        let err_type = text::ident();
+        let err_code =
+            text::int(10)
+                .delimited_by(just("("), just(")"))
+                .try_map(|s: String, span| {
+                    s.parse::<u32>()
+                        .map_err(|e| Simple::custom(span, format!("Invalid error code: {}", e)))
+                });
+        just::<_, _, Simple<char>>("ERRCODE:")
+            .then(err_type.padded())
+            .then(err_code.padded())
+            .delimited_by(just("["), just("]"))
+            .padded()
+            .map(|((_, err_type), err_code)| SuricataErrorCode { err_type, err_code })
+    }
+}
+
+impl LogMessage {
+    pub fn parser() -> impl Parser<char, LogMessage, Error = Simple<char>> {
+        // Leading zeroes are allowed (e.g. "05" or "00")
+        let integer = text::digits::<_, Simple<char>>(10).try_map(|digits: String, span| {
+            digits
+                .parse::<u32>()
+                .map_err(|e| Simple::custom(span, format!("Invalid number: {}", e)))
+        });
+
+        let date = integer.separated_by(just("/")).exactly(3);
+        let time = integer.separated_by(just(":")).exactly(3);
+        let timestamp = date
+            .padded()
+            .then_ignore(just("--"))
+            .then(time.padded())
+            .try_map(|(date, time), span| {
+                let offset = Local::now().offset().to_owned();
+                i32::try_from(date[2])
+                    .ok()
+                    .and_then(|year| NaiveDate::from_ymd_opt(year, date[1], date[0]))
+                    .and_then(|a| a.and_hms_opt(time[0], time[1], time[2]))
+                    .and_then(|a| a.and_local_timezone(offset).earliest())
+                    .ok_or_else(|| Simple::custom(span, "Invalid timestamp"))
+            });
+
+        let log_level = text::ident::<_, Simple<char>>()
+            .delimited_by(just("<"), just(">"))
+            .padded();
+        let dash = just::<_, _, Simple<char>>("-").padded();
+
+        timestamp
+            .then_ignore(dash)
+            .then(log_level)
+            .then_ignore(dash)
+            .then(SuricataErrorCode::parser().or_not())
+            .then_ignore(dash.or_not())
+            .then(take_until(
+                text::newline::<char, Simple<char>>().or(end::<Simple<char>>()),
+            ))
+            .map(
+                |(((timestamp, log_level), err_code), (message, _))| LogMessage {
+                    timestamp,
+                    log_level,
+                    err_code,
+                    message: message.into_iter().collect(),
+                },
+            )
+    }
+    pub fn parse_logs() -> impl Parser<char, Vec<LogMessage>, Error = Simple<char>> {
+        LogMessage::parser()
+            .separated_by(empty())
+            .allow_leading()
+            .allow_trailing()
+    }
+}
+
+/// Build the arguments of Suricata for the profile
+fn suricata_arguments(
+    profile: &VerificationProfile,
+    rule_file: &Path,
+    log_path: &Path,
+) -> Vec<String> {
+    // -S loaded exclusively, -l log directory
+    let mut args = vec![
+        "-S".to_string(),
+        rule_file.display().to_string(),
+        "-l".to_string(),
+        log_path.display().to_string(),
+    ];
+    // --engine-analysis or -T (test mode)
+    match profile.mode {
+        VerificationMode::EngineAnalysis => args.push("--engine-analysis".to_string()),
+        VerificationMode::Test => args.push("-T".to_string()),
+    }
+    // -c Path to configuration file
+    if let Some(configuration) = &profile.config_file {
+        args.extend(["-c".to_string(), configuration.clone()]);
+    }
+    // --set overrides of the configuration
+    profile.set.iter().for_each(|set| {
+        args.extend(["--set".to_string(), set.clone()]);
+    });
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled_keywords_are_parsed() {
+        let keywords = bundled_keywords();
+        assert!(keywords.len() > 250);
+        assert!(matches!(keywords.get("nocase"), Some(Keyword::NoOption(_))));
+        let Some(Keyword::Other(content)) = keywords.get("content") else {
+            panic!("content should take an option");
+        };
+        assert_eq!(content.description, "match on payload content");
+        assert!(content
+            .documentation
+            .ends_with("/rules/payload-keywords.html#content"));
+    }
+
+    #[test]
+    fn logs_are_parsed() {
+        let log = "17/10/2026 -- 12:30:05 - <Error> - [ERRCODE: SC_ERR_INVALID_SIGNATURE(39)] - \
+                   error parsing signature\n";
+        let logs = LogMessage::parse_logs().parse(log).unwrap();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].log_level, "Error");
+        let err_code = logs[0].err_code.as_ref().unwrap();
+        assert_eq!(err_code.err_type, "SC_ERR_INVALID_SIGNATURE");
+        assert_eq!(err_code.err_code, 39);
+        assert_eq!(logs[0].message, "error parsing signature");
+    }
+
+    #[test]
+    fn numbers_out_of_range_are_parse_errors() {
+        let timestamps = [
+            "17/10/2026 -- 99999999999:30:05 - <Error> - message\n",
+            "17/10/2026 -- 25:30:05 - <Error> - message\n",
+            "31/02/2026 -- 12:30:05 - <Error> - message\n",
+        ];
+        timestamps.iter().for_each(|log| {
+            assert!(LogMessage::parser().parse(*log).is_err(), "{}", log);
+        });
+        // The error code is optional, so the log is kept without it
+        let log = "17/10/2026 -- 12:30:05 - <Error> - [ERRCODE: SC_ERR(99999999999)] - message\n";
+        let log = LogMessage::parser().parse(log).unwrap();
+        assert!(log.err_code.is_none());
+    }
+
+    #[test]
+    fn logs_before_the_keywords_are_skipped() {
+        let output = "[100] Notice: suricata: This is Suricata version 7.0.6 RELEASE\n\
+                      name;description;app layer;features;documentation\n\
+                      nocase;modify content match to be case insensitive;Unset;No option,content modifier;https://docs.suricata.io;\n";
+        let keywords = parse_keywords(output);
+        assert_eq!(keywords.len(), 1);
+        assert!(matches!(keywords.get("nocase"), Some(Keyword::NoOption(_))));
+    }
+}
@@ -0,0 +1,467 @@
+//! Provides the names of the events, raised by the decoders and the app-layer parsers
+//!
+//! Signatures match the events with `app-layer-event:http.invalid_request;` or
+//! `decode-event:ipv4.opt_invalid;`. Suricata does not load a signature with an
+//! unknown event, so the names are checked against a bundled table, which can
+//! be extended with the event rules shipped with the installed Suricata.
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::rule::{Rule, Spanned, AST};
+
+/// The keywords, which match events
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EventKeyword {
+    AppLayer,
+    Decode,
+}
+
+impl EventKeyword {
+    pub const ALL: [EventKeyword; 2] = [EventKeyword::AppLayer, EventKeyword::Decode];
+
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            EventKeyword::AppLayer => "app-layer-event",
+            EventKeyword::Decode => "decode-event",
+        }
+    }
+}
+
+impl fmt::Display for EventKeyword {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.keyword())
+    }
+}
+
+/// The events of the app-layer parsers, along with what triggers them
+#[rustfmt::skip]
+const APP_LAYER_EVENTS: &[(&str, &str)] = &[
+    ("applayer_mismatch_protocol_both_directions", "The protocol detected in both directions does not match"),
+    ("applayer_wrong_direction_first_data", "The first data of the protocol was sent in the unexpected direction"),
+    ("applayer_detect_protocol_only_one_direction", "The protocol was only detected in one direction"),
+    ("applayer_proto_detection_skipped", "The protocol detection was skipped"),
+    ("applayer_no_tls_after_starttls", "No TLS handshake followed the STARTTLS command"),
+    ("applayer_unexpected_protocol", "The protocol differs from the one expected (e.g. after a protocol change)"),
+    ("http.unknown_error", "The HTTP parser encountered an unknown error"),
+    ("http.gzip_decompression_failed", "The gzip compressed body could not be decompressed"),
+    ("http.request_field_missing_colon", "A request header is missing the colon"),
+    ("http.response_field_missing_colon", "A response header is missing the colon"),
+    ("http.invalid_request_chunk_len", "The length of a request chunk is invalid"),
+    ("http.invalid_response_chunk_len", "The length of a response chunk is invalid"),
+    ("http.invalid_transfer_encoding_value_in_request", "The Transfer-Encoding header of the request is invalid"),
+    ("http.invalid_transfer_encoding_value_in_response", "The Transfer-Encoding header of the response is invalid"),
+    ("http.invalid_content_length_field_in_request", "The Content-Length header of the request is invalid"),
+    ("http.invalid_content_length_field_in_response", "The Content-Length header of the response is invalid"),
+    ("http.100_continue_already_seen", "A second 100-continue response was seen"),
+    ("http.unable_to_match_response_to_request", "A response could not be matched to a request"),
+    ("http.invalid_server_port_in_request", "The port inside the Host header or the URI is invalid"),
+    ("http.invalid_authority_port", "The port of the authority is invalid"),
+    ("http.request_header_invalid", "A request header is invalid"),
+    ("http.response_header_invalid", "A response header is invalid"),
+    ("http.missing_host_header", "The request is missing the Host header"),
+    ("http.host_header_ambiguous", "The Host header and the host of the URI differ"),
+    ("http.invalid_request_field_folding", "A request header is folded in an invalid way"),
+    ("http.invalid_response_field_folding", "A response header is folded in an invalid way"),
+    ("http.request_field_too_long", "A request header is longer than the configured limit"),
+    ("http.response_field_too_long", "A response header is longer than the configured limit"),
+    ("http.request_server_port_tcp_port_mismatch", "The port of the Host header differs from the TCP port"),
+    ("http.request_uri_host_invalid", "The host inside the URI is invalid"),
+    ("http.request_header_host_invalid", "The host inside the Host header is invalid"),
+    ("http.uri_delim_non_compliant", "The URI is delimited by non-compliant whitespace"),
+    ("http.method_delim_non_compliant", "The method is delimited by non-compliant whitespace"),
+    ("http.request_line_leading_whitespace", "The request line starts with whitespace"),
+    ("http.request_line_invalid", "The request line is invalid"),
+    ("http.too_many_encoding_layers", "The body uses more encoding layers than allowed"),
+    ("http.abnormal_ce_header", "The Content-Encoding header has an abnormal value"),
+    ("http.request_too_many_headers", "The request has more headers than allowed"),
+    ("http.response_too_many_headers", "The response has more headers than allowed"),
+    ("http2.invalid_frame_header", "The header of an HTTP/2 frame is invalid"),
+    ("http2.invalid_client_magic", "The client connection preface is invalid"),
+    ("http2.invalid_frame_data", "The data of an HTTP/2 frame is invalid"),
+    ("http2.invalid_header", "An HTTP/2 header is invalid"),
+    ("http2.invalid_frame_length", "The length of an HTTP/2 frame is invalid"),
+    ("http2.extra_header_data", "A header frame has data after its headers"),
+    ("http2.long_frame_data", "An HTTP/2 frame is longer than the configured limit"),
+    ("http2.stream_id_reuse", "A stream identifier was reused"),
+    ("http2.invalid_http1_settings", "The HTTP2-Settings header of an upgrade is invalid"),
+    ("http2.failed_decompression", "The headers could not be decompressed"),
+    ("http2.invalid_range", "The Range header is invalid"),
+    ("http2.header_integer_overflow", "An integer inside the headers overflowed"),
+    ("http2.too_many_streams", "The connection has more streams than allowed"),
+    ("tls.invalid_sslv2_header", "The SSLv2 header is invalid"),
+    ("tls.invalid_tls_header", "The TLS header is invalid"),
+    ("tls.invalid_record_version", "The version of a record is invalid"),
+    ("tls.invalid_record_type", "The type of a record is invalid"),
+    ("tls.invalid_record_length", "The length of a record is invalid"),
+    ("tls.invalid_handshake_message", "A handshake message is invalid"),
+    ("tls.invalid_certificate", "A certificate is invalid"),
+    ("tls.certificate_invalid_length", "The length of a certificate is invalid"),
+    ("tls.certificate_invalid_version", "The version of a certificate is invalid"),
+    ("tls.certificate_invalid_serial", "The serial of a certificate is invalid"),
+    ("tls.certificate_invalid_algorithmidentifier", "The algorithm identifier of a certificate is invalid"),
+    ("tls.certificate_invalid_x509name", "The X.509 name of a certificate is invalid"),
+    ("tls.certificate_invalid_date", "The date of a certificate is invalid"),
+    ("tls.certificate_invalid_extensions", "The extensions of a certificate are invalid"),
+    ("tls.certificate_invalid_der", "A certificate is not valid DER"),
+    ("tls.certificate_invalid_subject", "The subject of a certificate is invalid"),
+    ("tls.certificate_invalid_issuer", "The issuer of a certificate is invalid"),
+    ("tls.certificate_invalid_validity", "The validity of a certificate is invalid"),
+    ("tls.error_message_encountered", "An alert message was sent"),
+    ("tls.invalid_ssl_record", "An SSL record is invalid"),
+    ("tls.heartbeat_message", "A heartbeat message was sent"),
+    ("tls.overflow_heartbeat_message", "A heartbeat message claims more data than it holds (Heartbleed)"),
+    ("tls.dataleak_heartbeat_mismatch", "The heartbeat response is larger than the request (Heartbleed)"),
+    ("tls.handshake_invalid_length", "The length of a handshake message is invalid"),
+    ("tls.multiple_sni_extensions", "The client hello has more than one SNI extension"),
+    ("tls.invalid_sni_type", "The type of the SNI extension is invalid"),
+    ("tls.invalid_sni_length", "The length of the SNI extension is invalid"),
+    ("tls.too_many_records_in_packet", "A packet holds more records than allowed"),
+    ("smtp.invalid_reply", "A reply of the server is invalid"),
+    ("smtp.unable_to_match_reply_with_request", "A reply could not be matched to a command"),
+    ("smtp.max_command_line_len_exceeded", "A command line is longer than allowed"),
+    ("smtp.max_reply_line_len_exceeded", "A reply line is longer than allowed"),
+    ("smtp.invalid_pipelined_sequence", "Commands were pipelined in an invalid sequence"),
+    ("smtp.bdat_chunk_len_exceeded", "A BDAT chunk is longer than announced"),
+    ("smtp.no_server_welcome_message", "The server did not send a welcome message"),
+    ("smtp.tls_rejected", "The server rejected the STARTTLS command"),
+    ("smtp.data_command_rejected", "The server rejected the DATA command"),
+    ("smtp.failed_protocol_change", "The protocol change after STARTTLS failed"),
+    ("smtp.mime_parse_failed", "The MIME message could not be parsed"),
+    ("smtp.mime_malformed_msg", "The MIME message is malformed"),
+    ("smtp.mime_invalid_base64", "A MIME part has invalid base64 data"),
+    ("smtp.mime_invalid_qp", "A MIME part has invalid quoted-printable data"),
+    ("smtp.mime_long_line", "A MIME line is longer than allowed"),
+    ("smtp.mime_long_enc_line", "An encoded MIME line is longer than allowed"),
+    ("smtp.mime_long_header_name", "A MIME header name is longer than allowed"),
+    ("smtp.mime_long_header_value", "A MIME header value is longer than allowed"),
+    ("smtp.mime_long_boundary", "A MIME boundary is longer than allowed"),
+    ("smtp.mime_long_filename", "A MIME filename is longer than allowed"),
+    ("smtp.unparsable_content", "The content of the session could not be parsed"),
+    ("dns.malformed_data", "A DNS message is malformed"),
+    ("dns.not_a_request", "A message sent to the server is not a request"),
+    ("dns.not_a_response", "A message sent to the client is not a response"),
+    ("dns.z_flag_set", "The reserved Z flag is set"),
+    ("ssh.invalid_banner", "The banner is invalid"),
+    ("ssh.long_banner", "The banner is longer than allowed"),
+    ("ssh.invalid_record", "A record is invalid"),
+    ("smb.internal_error", "The SMB parser encountered an internal error"),
+    ("smb.malformed_data", "An SMB message is malformed"),
+    ("smb.record_overflow", "A record is longer than the message"),
+    ("smb.malformed_ntlmssp_request", "The NTLMSSP request is malformed"),
+    ("smb.malformed_ntlmssp_response", "The NTLMSSP response is malformed"),
+    ("smb.duplicate_negotiate", "The dialect was negotiated more than once"),
+    ("smb.negotiate_malformed_dialects", "The negotiated dialects are malformed"),
+    ("smb.file_overlap", "The data of a file overlaps"),
+    ("smb.request_to_client", "A request was sent to the client"),
+    ("smb.response_to_server", "A response was sent to the server"),
+    ("ftp.request_command_too_long", "A command is longer than allowed"),
+    ("ftp.response_command_too_long", "A response is longer than allowed"),
+    ("nfs.malformed_data", "An NFS message is malformed"),
+    ("krb5.malformed_data", "A Kerberos message is malformed"),
+    ("krb5.weak_encryption", "A weak encryption type is used"),
+    ("dhcp.malformed_options", "The options of a DHCP message are malformed"),
+    ("dhcp.truncated_options", "The options of a DHCP message are truncated"),
+    ("modbus.invalid_protocol_id", "The protocol identifier is not 0"),
+    ("modbus.unsolicited_response", "A response was not requested"),
+    ("modbus.invalid_length", "The length of a message is invalid"),
+    ("modbus.invalid_unit_identifier", "The unit identifier is invalid"),
+    ("modbus.invalid_function_code", "The function code is invalid"),
+    ("modbus.invalid_value", "A value is invalid"),
+    ("modbus.invalid_exception_code", "The exception code is invalid"),
+    ("modbus.value_mismatch", "A value of the response does not match the request"),
+    ("modbus.flooded", "The session has more transactions than allowed"),
+    ("mqtt.missing_connect", "A message was sent before CONNECT"),
+    ("mqtt.missing_publish", "A PUBACK was sent without a PUBLISH"),
+    ("mqtt.missing_subscribe", "A SUBACK was sent without a SUBSCRIBE"),
+    ("mqtt.missing_unsubscribe", "An UNSUBACK was sent without an UNSUBSCRIBE"),
+    ("mqtt.double_connect", "CONNECT was sent more than once"),
+    ("mqtt.unintroduced_message", "A message was sent before the session was established"),
+    ("mqtt.invalid_qos_level", "The QoS level is invalid"),
+    ("mqtt.missing_msg_id", "The message identifier is missing"),
+    ("mqtt.unassigned_msg_type", "The message type is unassigned"),
+    ("mqtt.too_many_transactions", "The session has more transactions than allowed"),
+    ("mqtt.malformed_traffic", "A message is malformed"),
+];
+
+/// The events of the decoders, along with what triggers them
+#[rustfmt::skip]
+const DECODE_EVENTS: &[(&str, &str)] = &[
+    ("ipv4.pkt_too_small", "The packet is smaller than an IPv4 header"),
+    ("ipv4.hlen_too_small", "The IPv4 header length is smaller than 20 bytes"),
+    ("ipv4.iplen_smaller_than_hlen", "The total length is smaller than the header length"),
+    ("ipv4.trunc_pkt", "The packet is smaller than its total length"),
+    ("ipv4.opt_invalid", "An IPv4 option is invalid"),
+    ("ipv4.opt_invalid_len", "The length of an IPv4 option is invalid"),
+    ("ipv4.opt_malformed", "An IPv4 option is malformed"),
+    ("ipv4.opt_pad_required", "The IPv4 options are not padded"),
+    ("ipv4.opt_eol_required", "The IPv4 options are not terminated by EOL"),
+    ("ipv4.opt_duplicate", "An IPv4 option is used more than once"),
+    ("ipv4.opt_unknown", "An IPv4 option is unknown"),
+    ("ipv4.wrong_ip_version", "The version of the IPv4 header is not 4"),
+    ("ipv4.icmpv6", "An IPv4 packet carries ICMPv6"),
+    ("ipv4.frag_pkt_too_large", "The reassembled fragments are larger than 65535 bytes"),
+    ("ipv4.frag_overlap", "The fragments overlap"),
+    ("ipv4.frag_ignored", "The fragments were not reassembled"),
+    ("icmpv4.pkt_too_small", "The packet is smaller than an ICMPv4 header"),
+    ("icmpv4.unknown_type", "The ICMPv4 type is unknown"),
+    ("icmpv4.unknown_code", "The ICMPv4 code is unknown"),
+    ("icmpv4.ipv4_trunc_pkt", "The IPv4 packet inside the ICMPv4 message is truncated"),
+    ("icmpv4.ipv4_unknown_ver", "The IPv4 packet inside the ICMPv4 message has an unknown version"),
+    ("icmpv6.unknown_type", "The ICMPv6 type is unknown"),
+    ("icmpv6.unknown_code", "The ICMPv6 code is unknown"),
+    ("icmpv6.pkt_too_small", "The packet is smaller than an ICMPv6 header"),
+    ("icmpv6.ipv6_unknown_version", "The IPv6 packet inside the ICMPv6 message has an unknown version"),
+    ("icmpv6.ipv6_trunc_pkt", "The IPv6 packet inside the ICMPv6 message is truncated"),
+    ("icmpv6.mld_message_with_invalid_hl", "An MLD message has a hop limit other than 1"),
+    ("icmpv6.unassigned_type", "The ICMPv6 type is unassigned"),
+    ("icmpv6.experimentation_type", "The ICMPv6 type is reserved for experimentation"),
+    ("ipv6.pkt_too_small", "The packet is smaller than an IPv6 header"),
+    ("ipv6.trunc_pkt", "The packet is smaller than its payload length"),
+    ("ipv6.trunc_exthdr", "An extension header is truncated"),
+    ("ipv6.exthdr_dupl_fh", "The fragment header is used more than once"),
+    ("ipv6.exthdr_useless_fh", "The fragment header does not fragment the packet"),
+    ("ipv6.exthdr_dupl_rh", "The routing header is used more than once"),
+    ("ipv6.exthdr_dupl_hh", "The hop-by-hop header is used more than once"),
+    ("ipv6.exthdr_dupl_dh", "The destination options header is used more than twice"),
+    ("ipv6.exthdr_dupl_ah", "The authentication header is used more than once"),
+    ("ipv6.exthdr_dupl_eh", "The ESP header is used more than once"),
+    ("ipv6.exthdr_invalid_optlen", "The length of an extension header option is invalid"),
+    ("ipv6.wrong_ip_version", "The version of the IPv6 header is not 6"),
+    ("ipv6.exthdr_ah_res_not_null", "The reserved field of the authentication header is not zero"),
+    ("ipv6.hopopts_unknown_opt", "A hop-by-hop option is unknown"),
+    ("ipv6.hopopts_only_padding", "The hop-by-hop header only holds padding"),
+    ("ipv6.dstopts_unknown_opt", "A destination option is unknown"),
+    ("ipv6.dstopts_only_padding", "The destination options header only holds padding"),
+    ("ipv6.rh_type_0", "The deprecated routing header type 0 is used"),
+    ("ipv6.zero_len_padn", "A PadN option has a length of zero"),
+    ("ipv6.fh_non_zero_reserved_field", "The reserved field of the fragment header is not zero"),
+    ("ipv6.data_after_none_header", "Data follows the no next header value"),
+    ("ipv6.unknown_next_header", "The next header is unknown"),
+    ("ipv6.icmpv4", "An IPv6 packet carries ICMPv4"),
+    ("ipv6.frag_pkt_too_large", "The reassembled fragments are larger than 65535 bytes"),
+    ("ipv6.frag_overlap", "The fragments overlap"),
+    ("ipv6.frag_invalid_length", "The length of a fragment is not a multiple of 8"),
+    ("ipv6.frag_ignored", "The fragments were not reassembled"),
+    ("ipv6.ipv4_in_ipv6_too_small", "The IPv4 packet tunneled in IPv6 is too small"),
+    ("ipv6.ipv4_in_ipv6_wrong_version", "The IPv4 packet tunneled in IPv6 has the wrong version"),
+    ("ipv6.ipv6_in_ipv6_too_small", "The IPv6 packet tunneled in IPv6 is too small"),
+    ("ipv6.ipv6_in_ipv6_wrong_version", "The IPv6 packet tunneled in IPv6 has the wrong version"),
+    ("tcp.pkt_too_small", "The packet is smaller than a TCP header"),
+    ("tcp.hlen_too_small", "The TCP header length is smaller than 20 bytes"),
+    ("tcp.invalid_optlen", "The length of the TCP options is invalid"),
+    ("tcp.opt_invalid_len", "The length of a TCP option is invalid"),
+    ("tcp.opt_duplicate", "A TCP option is used more than once"),
+    ("udp.pkt_too_small", "The packet is smaller than a UDP header"),
+    ("udp.hlen_too_small", "The UDP header length is smaller than 8 bytes"),
+    ("udp.hlen_invalid", "The UDP length is invalid"),
+    ("udp.len_invalid", "The UDP length does not match the packet"),
+    ("sll.pkt_too_small", "The packet is smaller than an SLL header"),
+    ("ethernet.pkt_too_small", "The packet is smaller than an Ethernet header"),
+    ("ppp.pkt_too_small", "The packet is smaller than a PPP header"),
+    ("ppp.vju_pkt_too_small", "The Van Jacobson packet is too small"),
+    ("ppp.ip4_pkt_too_small", "The IPv4 packet inside PPP is too small"),
+    ("ppp.ip6_pkt_too_small", "The IPv6 packet inside PPP is too small"),
+    ("ppp.wrong_type", "The PPP protocol is invalid"),
+    ("ppp.unsup_proto", "The PPP protocol is not supported"),
+    ("pppoe.pkt_too_small", "The packet is smaller than a PPPoE header"),
+    ("pppoe.wrong_code", "The PPPoE code is invalid"),
+    ("pppoe.malformed_tags", "The PPPoE tags are malformed"),
+    ("gre.pkt_too_small", "The packet is smaller than a GRE header"),
+    ("gre.wrong_version", "The GRE version is invalid"),
+    ("gre.version0_recur", "The recursion control of GRE version 0 is set"),
+    ("gre.version0_flags", "The reserved flags of GRE version 0 are set"),
+    ("gre.version0_hdr_too_big", "The GRE version 0 header is larger than the packet"),
+    ("gre.version0_malformed_sre_hdr", "A source route entry of GRE version 0 is malformed"),
+    ("gre.version1_chksum", "The checksum flag of GRE version 1 is set"),
+    ("gre.version1_route", "The routing flag of GRE version 1 is set"),
+    ("gre.version1_ssr", "The strict source route flag of GRE version 1 is set"),
+    ("gre.version1_recur", "The recursion control of GRE version 1 is set"),
+    ("gre.version1_flags", "The reserved flags of GRE version 1 are set"),
+    ("gre.version1_no_key", "GRE version 1 is missing the key"),
+    ("gre.version1_wrong_protocol", "GRE version 1 does not carry PPP"),
+    ("gre.version1_malformed_sre_hdr", "A source route entry of GRE version 1 is malformed"),
+    ("gre.version1_hdr_too_big", "The GRE version 1 header is larger than the packet"),
+    ("vlan.header_too_small", "The packet is smaller than a VLAN header"),
+    ("vlan.unknown_type", "The type inside the VLAN header is unknown"),
+    ("vlan.too_many_layers", "The packet has more than 2 VLAN layers"),
+    ("ieee8021ah.header_too_small", "The packet is smaller than an IEEE 802.1ah header"),
+    ("ipraw.invalid_ip_version", "The raw packet has an invalid IP version"),
+    ("mpls.header_too_small", "The packet is smaller than an MPLS header"),
+    ("mpls.pkt_too_small", "The MPLS payload is too small"),
+    ("mpls.bad_label_router_alert", "The router alert label is used"),
+    ("mpls.bad_label_implicit_null", "The implicit null label is used"),
+    ("mpls.bad_label_reserved", "A reserved label is used"),
+    ("mpls.unknown_payload_type", "The MPLS payload type is unknown"),
+    ("erspan.header_too_small", "The packet is smaller than an ERSPAN header"),
+    ("erspan.unsupported_version", "The ERSPAN version is not supported"),
+    ("erspan.too_many_vlan_layers", "The ERSPAN packet has more than 2 VLAN layers"),
+    ("ltnull.pkt_too_small", "The packet is smaller than a null header"),
+    ("ltnull.unsupported_type", "The type of the null header is not supported"),
+    ("dce.pkt_too_small", "The packet is smaller than a DCE header"),
+    ("chdlc.pkt_too_small", "The packet is smaller than a Cisco HDLC header"),
+    ("nsh.header_too_small", "The packet is smaller than an NSH header"),
+    ("nsh.unsupported_version", "The NSH version is not supported"),
+    ("nsh.bad_header_length", "The NSH header length is invalid"),
+    ("nsh.reserved_type", "The NSH metadata type is reserved"),
+    ("nsh.unsupported_type", "The NSH metadata type is not supported"),
+    ("nsh.unknown_payload", "The NSH payload is unknown"),
+];
+
+/// The known events, along with what triggers them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventTable {
+    app_layer: HashMap<String, String>,
+    decode: HashMap<String, String>,
+}
+
+/// The bundled events
+impl Default for EventTable {
+    fn default() -> Self {
+        let table = |events: &[(&str, &str)]| {
+            events
+                .iter()
+                .map(|(name, description)| (name.to_string(), description.to_string()))
+                .collect()
+        };
+        EventTable {
+            app_layer: table(APP_LAYER_EVENTS),
+            decode: table(DECODE_EVENTS),
+        }
+    }
+}
+
+impl EventTable {
+    fn events(&self, keyword: EventKeyword) -> &HashMap<String, String> {
+        match keyword {
+            EventKeyword::AppLayer => &self.app_layer,
+            EventKeyword::Decode => &self.decode,
+        }
+    }
+
+    /// Get the description of an event
+    pub fn get(&self, keyword: EventKeyword, name: &str) -> Option<&str> {
+        self.events(keyword).get(name).map(String::as_str)
+    }
+
+    /// Check if an event belongs to a protocol (or decoder) with known events
+    ///
+    /// The table might not hold every event of a protocol it does not know.
+    pub fn knows_protocol(&self, keyword: EventKeyword, name: &str) -> bool {
+        let protocol = |name: &str| {
+            name.split_once('.')
+                .map(|(protocol, _)| protocol.to_string())
+        };
+        let Some(name_protocol) = protocol(name) else {
+            return true;
+        };
+        self.events(keyword)
+            .keys()
+            .any(|event| protocol(event).as_ref() == Some(&name_protocol))
+    }
+
+    /// Find the known event, which is the closest to a misspelled one
+    pub fn closest(&self, keyword: EventKeyword, name: &str) -> Option<&str> {
+        let max_distance = (name.chars().count() / 4).max(2);
+        self.events(keyword)
+            .keys()
+            .map(|event| (edit_distance(name, event), event))
+            .filter(|(distance, _)| *distance <= max_distance)
+            .min()
+            .map(|(_, event)| event.as_str())
+    }
+
+    /// Add the events matched by a rule file (e.g. decoder-events.rules)
+    ///
+    /// The msg of the signature describes events, which are not bundled.
+    pub fn extend_from_rules(&mut self, text: &str) {
+        let (ast, _) = AST::parse_str(text);
+        ast.rules.values().for_each(|(rule, _)| {
+            let description = rule.msg().map(|(msg, _)| msg).unwrap_or_default();
+            rule.events().into_iter().for_each(|(keyword, (name, _))| {
+                let events = match keyword {
+                    EventKeyword::AppLayer => &mut self.app_layer,
+                    EventKeyword::Decode => &mut self.decode,
+                };
+                events.entry(name).or_insert_with(|| description.clone());
+            });
+        });
+    }
+}
+
+impl Rule {
+    /// Get the events matched by the signature
+    pub fn events(&self) -> Vec<(EventKeyword, Spanned<String>)> {
+        EventKeyword::ALL
+            .into_iter()
+            .flat_map(|keyword| {
+                self.option_values(keyword.keyword())
+                    .into_iter()
+                    .filter_map(move |values| Some((keyword, values.into_iter().next()?)))
+            })
+            .collect()
+    }
+}
+
+/// The number of single character edits needed to turn one word into another
+pub(crate) fn edit_distance(from: &str, to: &str) -> usize {
+    let to = to.chars().collect::<Vec<_>>();
+    let mut previous = (0..=to.len()).collect::<Vec<_>>();
+    for (i, from_char) in from.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, to_char) in to.iter().enumerate() {
+            let substitution = previous[j] + usize::from(from_char != *to_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[to.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_are_described_by_the_msg_of_their_rule() {
+        let mut events = EventTable::default();
+        events.extend_from_rules(
+            "\
+alert acme any any -> any any (msg:\"SURICATA ACME Request too long\"; \
+app-layer-event:acme.request_too_long; sid:2232000; rev:1;)
+alert ipv4 any any -> any any (msg:\"SURICATA IPv4 packet too small\"; \
+decode-event:ipv4.pkt_too_small; sid:2200000; rev:2;)
+",
+        );
+        assert_eq!(
+            events.get(EventKeyword::AppLayer, "acme.request_too_long"),
+            Some("SURICATA ACME Request too long")
+        );
+        // Bundled descriptions are kept
+        assert_eq!(
+            events.get(EventKeyword::Decode, "ipv4.pkt_too_small"),
+            Some("The packet is smaller than an IPv4 header")
+        );
+        assert_eq!(
+            events.get(EventKeyword::Decode, "acme.request_too_long"),
+            None
+        );
+    }
+
+    #[test]
+    fn protocols_are_known_by_their_events() {
+        let events = EventTable::default();
+        assert!(events.knows_protocol(EventKeyword::AppLayer, "http.anything"));
+        assert!(!events.knows_protocol(EventKeyword::AppLayer, "acme.anything"));
+        // Events without a protocol are always checked
+        assert!(events.knows_protocol(EventKeyword::AppLayer, "applayer_anything"));
+    }
+
+    #[test]
+    fn edit_distances_count_single_character_edits() {
+        assert_eq!(edit_distance("http", "http"), 0);
+        assert_eq!(edit_distance("htpp", "http"), 1);
+        assert_eq!(edit_distance("tls", "stl"), 2);
+        assert_eq!(edit_distance("smtp", "smtps"), 1);
+        assert_eq!(edit_distance("", "dns"), 3);
+    }
+}
@@ -0,0 +1,161 @@
+//! Suricata API
+//!
+//! Provides the functions to use suricata if it is installed on the system
+//!
+//! These functions include:
+//! - Parsing logs
+//! - Fetching errors and generating diagnostics
+//! - Fetching keywords
+//! - Detecting the installed version
+//! - Listing the events of the decoders and the app-layer parsers
+//!
+//! Running Suricata requires a process and a file system, so the functions
+//! which do so are only available with the `server` feature.
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::rule::Span;
+
+#[cfg(feature = "server")]
+mod engine;
+mod events;
+#[cfg(feature = "server")]
+pub use engine::{
+    bundled_keywords, get_events, get_keywords, get_suricata_version, verify_rule, verify_text,
+};
+pub(crate) use events::edit_distance;
+pub use events::{EventKeyword, EventTable};
+
+/// A CSV record, obtained from the suricata cli
+#[derive(Debug, Clone, Deserialize)]
+pub struct KeywordRecord {
+    pub name: String,
+    pub description: String,
+    pub app_layer: String,
+    pub features: String,
+    pub documentation: String,
+}
+impl KeywordRecord {
+    /// Convert a Keywords record into a Keyword (adding an abstraction layer)
+    pub fn to_keyword(record: KeywordRecord) -> (String, Keyword) {
+        if record.features.starts_with("No option") {
+            return (record.name.clone(), Keyword::NoOption(record));
+        }
+        (record.name.clone(), Keyword::Other(record))
+    }
+}
+
+/// An abstraction layer for the [KeywordRecord] struct
+#[derive(Debug)]
+pub enum Keyword {
+    NoOption(KeywordRecord),
+    Other(KeywordRecord),
+}
+
+/// Find the part of the line, which the message is about
+///
+/// Suricata quotes the offending part of the signature inside the message
+/// (e.g. "unknown rule keyword 'contnet'"), if the quoted text can be found
+/// inside the line, the span points to it. Otherwise, the whole line
+/// (without the surrounding whitespace) is returned.
+pub fn narrow_span(message: &str, line: &str) -> Span {
+    let quoted = message
+        .split(['\'', '"'])
+        .skip(1)
+        .step_by(2)
+        .filter(|quoted| !quoted.trim().is_empty())
+        .find_map(|quoted| {
+            let start = line.find(quoted)?;
+            let start = line[..start].chars().count();
+            Some(start..start + quoted.chars().count())
+        });
+    quoted.unwrap_or_else(|| {
+        let start = line.chars().count() - line.trim_start().chars().count();
+        start..line.trim_end().chars().count().max(start)
+    })
+}
+
+/// The way Suricata is used to verify the signatures
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "server", derive(clap::ValueEnum))]
+#[serde(rename_all = "camelCase")]
+pub enum VerificationMode {
+    /// Run the engine analysis (--engine-analysis)
+    #[default]
+    EngineAnalysis,
+    /// Test the configuration and the signatures (-T)
+    Test,
+}
+
+/// A version of Suricata (e.g. 7.0.2)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SuricataVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl SuricataVersion {
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        SuricataVersion {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// Find the version inside the output of `suricata -V`
+    /// (e.g. "This is Suricata version 7.0.2 RELEASE")
+    pub fn parse(output: &str) -> Option<Self> {
+        let version = output
+            .split_whitespace()
+            .skip_while(|word| *word != "version")
+            .nth(1)?;
+        let mut numbers = version
+            .split(['.', '-'])
+            .map(|number| number.parse::<u32>().ok());
+        Some(SuricataVersion {
+            major: numbers.next()??,
+            minor: numbers.next().flatten().unwrap_or(0),
+            patch: numbers.next().flatten().unwrap_or(0),
+        })
+    }
+}
+
+impl fmt::Display for SuricataVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_are_found_inside_the_output() {
+        let parse = SuricataVersion::parse;
+        assert_eq!(
+            parse("This is Suricata version 7.0.2 RELEASE\n"),
+            Some(SuricataVersion::new(7, 0, 2))
+        );
+        assert_eq!(
+            parse("This is Suricata version 8.0.0-dev (1f3a2b4c 2024-05-01)"),
+            Some(SuricataVersion::new(8, 0, 0))
+        );
+        assert_eq!(
+            parse("This is Suricata version 6"),
+            Some(SuricataVersion::new(6, 0, 0))
+        );
+        assert_eq!(parse("suricata: command not found"), None);
+        assert_eq!(parse("This is Suricata version dev"), None);
+    }
+
+    #[test]
+    fn versions_are_ordered_by_their_numbers() {
+        assert!(SuricataVersion::new(6, 0, 10) < SuricataVersion::new(7, 0, 0));
+        assert!(SuricataVersion::new(7, 1, 0) > SuricataVersion::new(7, 0, 9));
+        assert_eq!(SuricataVersion::new(7, 0, 2).to_string(), "7.0.2");
+    }
+}
@@ -0,0 +1,325 @@
+//! Provides the syntax errors of the signatures
+//!
+//! The errors of the parser are described by the characters it expected, e.g.
+//! "expected one of '0', '[', '$', '<'". This module translates them into the
+//! part of the signature the parser was at (e.g. "expected a port, port range,
+//! or variable here"), merges the errors of the same mistake and narrows them
+//! down to the first offending character.
+use chumsky::error::SimpleReason;
+use chumsky::prelude::*;
+use lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, Position, Range};
+use ropey::Rope;
+
+use crate::rule::action::ACTIONS;
+use crate::rule::{Span, AST};
+
+/// The parts of the header, in their order
+const HEADER_PARTS: [HeaderPart; 6] = [
+    HeaderPart::Protocol,
+    HeaderPart::Address,
+    HeaderPart::Port,
+    HeaderPart::Direction,
+    HeaderPart::Address,
+    HeaderPart::Port,
+];
+
+/// The part of a signature, an error is found in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HeaderPart {
+    Protocol,
+    Address,
+    Port,
+    Direction,
+    Options,
+}
+
+impl HeaderPart {
+    /// Describe what is expected in this part of the signature
+    fn expected(&self) -> &'static str {
+        match self {
+            HeaderPart::Protocol => "a protocol (e.g. tcp or http)",
+            HeaderPart::Address => "an address, address group, or variable",
+            HeaderPart::Port => "a port, port range, or variable",
+            HeaderPart::Direction => "a direction (->, <> or <-)",
+            HeaderPart::Options => "an option or \")\"",
+        }
+    }
+
+    /// Describe a group inside this part of the signature
+    fn group(&self) -> &'static str {
+        match self {
+            HeaderPart::Port => "port group",
+            _ => "address group",
+        }
+    }
+}
+
+/// An error, which prevents a line from being parsed as a signature
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxError {
+    pub line: u32,
+    pub message: String,
+    /// The span of the error inside its line
+    pub span: Span,
+}
+
+impl SyntaxError {
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        Diagnostic {
+            range: Range::new(
+                Position::new(self.line, self.span.start as u32),
+                Position::new(self.line, self.span.end as u32),
+            ),
+            severity: Some(DiagnosticSeverity::ERROR),
+            code: Some(NumberOrString::String("syntax".to_string())),
+            // Set apart from the lints, which use "meerkat"
+            source: Some("meerkat-parser".to_string()),
+            message: self.message.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Find the syntax errors of a file
+///
+/// Lines which could not be parsed are parsed again to describe the errors.
+/// Comments and signatures spanning several lines (ending with a \) are skipped.
+pub fn syntax_errors(rope: &Rope, ast: &AST) -> Vec<SyntaxError> {
+    let mut errors = vec![];
+    let mut continued = false;
+    rope.lines().enumerate().for_each(|(line_nr, line)| {
+        let line_nr = line_nr as u32;
+        let text = line.to_string();
+        let content = text.trim_end_matches(['\n', '\r']);
+        let trimmed = content.trim();
+        // The lines of a multiline signature are not parsed on their own
+        let is_continued = continued;
+        continued = trimmed.ends_with('\\');
+        if is_continued || continued || trimmed.chars().count() <= 1 || trimmed.starts_with('#') {
+            return;
+        }
+        // The signatures, which could be parsed, are checked by the lints
+        // (e.g. an unrecognized direction)
+        if ast.rules.contains_key(&line_nr) {
+            return;
+        }
+        let (_, parse_errors) = crate::rule::Rule::parse_line(content);
+        errors.extend(
+            describe_errors(content, parse_errors)
+                .into_iter()
+                .map(|(message, span)| SyntaxError {
+                    line: line_nr,
+                    message,
+                    span,
+                }),
+        );
+    });
+    errors
+}
+
+/// Describe the errors of a line, merging the errors next to each other
+fn describe_errors(line: &str, mut errors: Vec<Simple<char>>) -> Vec<(String, Span)> {
+    errors.sort_by_key(|error| error.span().start);
+    let mut described: Vec<(String, Span)> = vec![];
+    for error in errors {
+        let (message, span) = describe_error(line, &error);
+        match described.last_mut() {
+            Some((_, last)) if span.start <= last.end => last.end = last.end.max(span.end),
+            _ => described.push((message, span)),
+        }
+    }
+    described
+}
+
+/// Describe a single error, along with the span it should point to
+fn describe_error(line: &str, error: &Simple<char>) -> (String, Span) {
+    let length = line.chars().count();
+    let span = error.span();
+    let start = span.start.min(length.saturating_sub(1));
+    let part = part_at(line, span.start);
+    let found = match error.found() {
+        Some(char) => format!("found \"{}\"", char),
+        None => "found the end of the line".to_string(),
+    };
+    let message = match error.reason() {
+        SimpleReason::Custom(message) => {
+            // The numbers of the ports are parsed as u16
+            let message = match (part, message.contains("too large")) {
+                (HeaderPart::Port, true) => "port should be between 0 and 65535",
+                // Keep the reason, without the error of the number parser
+                _ => message.split(" (Err:").next().unwrap_or(message),
+            };
+            // A custom error covers the whole value (e.g. the port)
+            let end = span.end.clamp(start + 1, length.max(start + 1));
+            return (message.to_string(), start..end);
+        }
+        SimpleReason::Unclosed { delimiter, .. } => {
+            format!("\"{}\" is never closed", delimiter)
+        }
+        SimpleReason::Unexpected => {
+            let expects = |char: char| error.expected().any(|expected| *expected == Some(char));
+            let expects_end = error.expected().any(|expected| expected.is_none());
+            match part {
+                _ if expects(']') => {
+                    format!("expected \"]\" to close the {}, {}", part.group(), found)
+                }
+                HeaderPart::Options if error.found().is_none() && expects(')') => {
+                    "expected \")\" to close the options".to_string()
+                }
+                HeaderPart::Options if expects(';') => {
+                    format!("expected \";\" after the option, {}", found)
+                }
+                // Only a comment may follow the signature
+                HeaderPart::Options
+                    if expects_end
+                        && error
+                            .expected()
+                            .all(|expected| matches!(expected, None | Some('#'))) =>
+                {
+                    format!("unexpected text after the signature, {}", found)
+                }
+                // The options start right after the last part of the header
+                HeaderPart::Options if expects('(') => {
+                    format!("expected \"(\" to start the options, {}", found)
+                }
+                part => format!("expected {} here, {}", part.expected(), found),
+            }
+        }
+    };
+    (message, start..(start + 1).min(length.max(1)))
+}
+
+/// Find the part of the signature a character belongs to
+///
+/// The header is split into its parts by whitespace, except for the
+/// whitespace inside a group (e.g. [80, 443]). The options start at the first
+/// "(" outside a group.
+fn part_at(line: &str, column: usize) -> HeaderPart {
+    let mut depth = 0;
+    let mut tokens: Vec<String> = vec![];
+    let mut in_token = false;
+    for char in line.chars().take(column + 1) {
+        match char {
+            '(' if depth == 0 => return HeaderPart::Options,
+            '[' => depth += 1,
+            ']' => depth -= 1,
+            _ => (),
+        }
+        if char.is_whitespace() && depth <= 0 {
+            in_token = false;
+            continue;
+        }
+        match (in_token, tokens.last_mut()) {
+            (true, Some(token)) => token.push(char),
+            _ => tokens.push(char.to_string()),
+        }
+        in_token = true;
+    }
+    // The action is optional for the parser
+    let has_action = tokens
+        .first()
+        .is_some_and(|token| tokens.len() > 1 && ACTIONS.contains(&token.to_lowercase().as_str()));
+    let index = tokens.len().saturating_sub(1 + has_action as usize);
+    HEADER_PARTS
+        .get(index)
+        .copied()
+        .unwrap_or(HeaderPart::Options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn errors(text: &str) -> Vec<(u32, String, Span)> {
+        let (ast, _) = AST::parse_str(text);
+        syntax_errors(&Rope::from_str(text), &ast)
+            .into_iter()
+            .map(|error| (error.line, error.message, error.span))
+            .collect()
+    }
+
+    #[test]
+    fn common_typos_are_described() {
+        let corpus = [
+            // Unclosed bracket
+            (
+                "alert tcp [10.0.0.1,10.0.0.2 any -> any any (msg:\"x\"; sid:1;)",
+                "expected \"]\" to close the address group, found \"a\"",
+                29..30,
+            ),
+            (
+                "alert tcp any [80,443 -> any any (msg:\"x\"; sid:1;)",
+                "expected \"]\" to close the port group, found \"-\"",
+                22..23,
+            ),
+            // Stray semicolon in the header
+            (
+                "alert tcp any any; -> any any (msg:\"x\"; sid:1;)",
+                "expected a port, port range, or variable here, found \";\"",
+                17..18,
+            ),
+            (
+                "alert tcp any any -> any any; (msg:\"x\"; sid:1;)",
+                "expected a port, port range, or variable here, found \";\"",
+                28..29,
+            ),
+            // Mistakes inside the options
+            (
+                "alert tcp any any -> any 70000 (msg:\"x\"; sid:1;)",
+                "port should be between 0 and 65535",
+                25..30,
+            ),
+            (
+                "alert tcp any any -> any any (msg:\"x\" sid:1;)",
+                "expected \";\" after the option, found \"s\"",
+                38..39,
+            ),
+            (
+                "alert tcp any any -> any any (msg:\"x\"; sid:1;",
+                "expected \")\" to close the options",
+                44..45,
+            ),
+            (
+                "alert tcp any any -> any any (msg:\"x\"; sid:1;) reviewed",
+                "unexpected text after the signature, found \"r\"",
+                47..48,
+            ),
+        ];
+        for (text, message, span) in corpus {
+            assert_eq!(
+                errors(text),
+                vec![(0, message.to_string(), span)],
+                "{}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn errors_stay_inside_their_line() {
+        let text = concat!(
+            "# alert tcp any any any any (msg:\"x\";)\r\n",
+            "alert tcp any any -> any any (msg:\"x\"; sid:1;\r\n",
+            "alert tcp any any -> any any (msg:\"x\"; \\\r\n",
+            "    sid:2;)\r\n",
+        );
+        let message = "expected \")\" to close the options".to_string();
+        assert_eq!(errors(text), vec![(1, message, 44..45)]);
+    }
+
+    #[test]
+    fn syntax_errors_are_set_apart_from_the_lints() {
+        let text = "alert tcp any any -> any any (msg:\"x\"; sid:1;\n";
+        let (ast, _) = AST::parse_str(text);
+        let diagnostics = syntax_errors(&Rope::from_str(text), &ast)
+            .iter()
+            .map(SyntaxError::to_diagnostic)
+            .collect::<Vec<_>>();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].source.as_deref(), Some("meerkat-parser"));
+        assert_eq!(
+            diagnostics[0].range,
+            Range::new(Position::new(0, 44), Position::new(0, 45))
+        );
+    }
+}
@@ -0,0 +1,1174 @@
+//! Tests of the language server, which talk to it through the protocol
+use std::fs;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use meerkat_ls::suricata::VerificationMode;
+use serde_json::{json, Value};
+use tokio::io::{
+    duplex, split, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader, DuplexStream, WriteHalf,
+};
+use tokio::sync::mpsc;
+use tower_lsp::{LspService, Server};
+
+use super::*;
+
+/// How long a test waits for a message of the server
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The answers to `workspace/configuration`, by the scope of the request
+type Configuration = Arc<Mutex<HashMap<Option<String>, Value>>>;
+
+/// A client, which talks to a language server running inside the test
+struct TestClient {
+    writer: Arc<tokio::sync::Mutex<WriteHalf<DuplexStream>>>,
+    /// The messages of the server, which are not answers to our requests
+    incoming: mpsc::UnboundedReceiver<Value>,
+    /// The notifications and the requests of the server received so far
+    received: Vec<Value>,
+    configuration: Configuration,
+    next_id: i64,
+}
+
+impl TestClient {
+    /// Start a language server with the given settings
+    fn start(settings: ServerSettings) -> TestClient {
+        let (service, socket) = LspService::build(|client| test_backend(client, settings)).finish();
+        let (client, server) = duplex(1 << 20);
+        let (server_reader, server_writer) = split(server);
+        tokio::spawn(Server::new(server_reader, server_writer, socket).serve(service));
+
+        let (reader, writer) = split(client);
+        let writer = Arc::new(tokio::sync::Mutex::new(writer));
+        let configuration = Configuration::default();
+        let (sender, incoming) = mpsc::unbounded_channel();
+        tokio::spawn(read_messages(
+            BufReader::new(reader),
+            writer.clone(),
+            configuration.clone(),
+            sender,
+        ));
+        TestClient {
+            writer,
+            incoming,
+            received: vec![],
+            configuration,
+            next_id: 0,
+        }
+    }
+
+    /// Answer `workspace/configuration` for the scope with the settings
+    fn configure(&self, scope: Option<&Url>, settings: Value) {
+        let scope = scope.map(Url::to_string);
+        self.configuration.lock().unwrap().insert(scope, settings);
+    }
+
+    /// Initialize the server with the given parameters
+    async fn initialize(&mut self, params: Value) -> Value {
+        let result = self.request("initialize", params).await;
+        self.notify("initialized", json!({})).await;
+        result
+    }
+
+    async fn notify(&mut self, method: &str, params: Value) {
+        let message = json!({"jsonrpc": "2.0", "method": method, "params": params});
+        write_message(&self.writer, &message).await;
+    }
+
+    /// Send a request and wait for its result
+    async fn request(&mut self, method: &str, params: Value) -> Value {
+        self.next_id += 1;
+        let id = self.next_id;
+        let mut message = json!({"jsonrpc": "2.0", "id": id, "method": method});
+        if !params.is_null() {
+            message["params"] = params;
+        }
+        write_message(&self.writer, &message).await;
+        loop {
+            let message = self.next_message().await;
+            if message.get("method").is_none() && message["id"] == id {
+                assert!(
+                    message.get("error").is_none(),
+                    "{} failed: {}",
+                    method,
+                    message
+                );
+                return message["result"].clone();
+            }
+            self.received.push(message);
+        }
+    }
+
+    async fn next_message(&mut self) -> Value {
+        tokio::time::timeout(TIMEOUT, self.incoming.recv())
+            .await
+            .expect("the server did not answer in time")
+            .expect("the server stopped")
+    }
+
+    /// Wait for a message of the server, which matches the predicate
+    async fn wait_for(&mut self, method: &str, predicate: impl Fn(&Value) -> bool) -> Value {
+        if let Some(position) = self
+            .received
+            .iter()
+            .position(|message| message["method"] == method && predicate(&message["params"]))
+        {
+            return self.received.remove(position)["params"].clone();
+        }
+        loop {
+            let message = self.next_message().await;
+            if message["method"] == method && predicate(&message["params"]) {
+                return message["params"].clone();
+            }
+            self.received.push(message);
+        }
+    }
+
+    async fn open(&mut self, uri: &str, text: &str) {
+        self.open_as(uri, "suricata", text).await;
+    }
+
+    /// Open a document, which the client gave the language id
+    async fn open_as(&mut self, uri: &str, language_id: &str, text: &str) {
+        let text_document =
+            json!({"uri": uri, "languageId": language_id, "version": 1, "text": text});
+        self.notify(
+            "textDocument/didOpen",
+            json!({ "textDocument": text_document }),
+        )
+        .await;
+    }
+
+    /// Wait for the diagnostics of a version of a document
+    ///
+    /// The diagnostics published last (once no more arrive) are returned.
+    async fn diagnostics(&mut self, uri: &str, version: i32) -> Vec<Value> {
+        let matches = |params: &Value| params["uri"] == uri && params["version"] == version;
+        let mut diagnostics = self
+            .wait_for("textDocument/publishDiagnostics", matches)
+            .await;
+        while let Ok(message) =
+            tokio::time::timeout(Duration::from_millis(300), self.incoming.recv()).await
+        {
+            let Some(message) = message else {
+                break;
+            };
+            if message["method"] == "textDocument/publishDiagnostics" && matches(&message["params"])
+            {
+                diagnostics = message["params"].clone();
+            } else {
+                self.received.push(message);
+            }
+        }
+        diagnostics["diagnostics"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    async fn hover(&mut self, uri: &str, line: u32, character: u32) -> Value {
+        let params = json!({
+            "textDocument": {"uri": uri},
+            "position": {"line": line, "character": character},
+        });
+        self.request("textDocument/hover", params).await
+    }
+
+    /// Hover until the answer matches the predicate (e.g. once a document is parsed)
+    async fn hover_until(
+        &mut self,
+        uri: &str,
+        line: u32,
+        character: u32,
+        predicate: impl Fn(&Value) -> bool,
+    ) -> Value {
+        let started = Instant::now();
+        loop {
+            let hover = self.hover(uri, line, character).await;
+            if predicate(&hover) {
+                return hover;
+            }
+            assert!(
+                started.elapsed() < TIMEOUT,
+                "the hover did not change in time"
+            );
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    }
+}
+
+/// Read the messages of the server, answering its requests on the way
+async fn read_messages(
+    mut reader: BufReader<tokio::io::ReadHalf<DuplexStream>>,
+    writer: Arc<tokio::sync::Mutex<WriteHalf<DuplexStream>>>,
+    configuration: Configuration,
+    sender: mpsc::UnboundedSender<Value>,
+) {
+    loop {
+        let mut length = 0;
+        loop {
+            let mut header = String::new();
+            if reader.read_line(&mut header).await.unwrap_or(0) == 0 {
+                return;
+            }
+            let header = header.trim();
+            if header.is_empty() {
+                break;
+            }
+            if let Some(value) = header.strip_prefix("Content-Length: ") {
+                length = value.parse().unwrap();
+            }
+        }
+        let mut content = vec![0; length];
+        if reader.read_exact(&mut content).await.is_err() {
+            return;
+        }
+        let message: Value = serde_json::from_slice(&content).unwrap();
+        if let (Some(id), Some(method)) = (message.get("id"), message["method"].as_str()) {
+            let result = match method {
+                "workspace/configuration" => {
+                    let configuration = configuration.lock().unwrap();
+                    let items = message["params"]["items"].as_array().cloned();
+                    let values = items.unwrap_or_default().into_iter().map(|item| {
+                        let scope = item["scopeUri"].as_str().map(str::to_string);
+                        configuration.get(&scope).cloned().unwrap_or_default()
+                    });
+                    Value::Array(values.collect())
+                }
+                "workspace/applyEdit" => json!({"applied": true}),
+                _ => Value::Null,
+            };
+            let answer = json!({"jsonrpc": "2.0", "id": id, "result": result});
+            write_message(&writer, &answer).await;
+        }
+        if sender.send(message).is_err() {
+            return;
+        }
+    }
+}
+
+async fn write_message(writer: &tokio::sync::Mutex<WriteHalf<DuplexStream>>, message: &Value) {
+    let content = message.to_string();
+    let mut writer = writer.lock().await;
+    let header = format!("Content-Length: {}\r\n\r\n", content.len());
+    writer.write_all(header.as_bytes()).await.unwrap();
+    writer.write_all(content.as_bytes()).await.unwrap();
+}
+
+/// The settings used by the tests, Suricata is not needed to run them
+fn test_settings() -> ServerSettings {
+    ServerSettings::default()
+}
+
+/// Create a language server without the keywords and the events of Suricata
+fn test_backend(client: Client, settings: ServerSettings) -> Backend {
+    let events = EventTable::default();
+    Backend::new(client, HashMap::new(), None, events, settings, Value::Null)
+}
+
+/// A rule file with the given number of signatures, one per line
+fn large_ruleset(signatures: u32) -> String {
+    (1..=signatures)
+        .map(|sid| {
+            format!(
+                "alert http $HOME_NET any -> $EXTERNAL_NET any (msg:\"rule {}\"; \
+                 flow:established,to_server; content:\"GET\"; http.method; sid:{}; rev:1;)\n",
+                sid, sid
+            )
+        })
+        .collect()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn hover_is_answered_while_the_workspace_is_indexed() {
+    let folder = tempfile::tempdir().unwrap();
+    fs::write(folder.path().join("large.rules"), large_ruleset(50_000)).unwrap();
+    let folder_uri = Url::from_directory_path(folder.path()).unwrap();
+    let uri = folder_uri.join("open.rules").unwrap().to_string();
+
+    let mut client = TestClient::start(test_settings());
+    let workspace_folders = json!([{"uri": folder_uri, "name": "rules"}]);
+    client
+        .initialize(json!({"capabilities": {}, "workspaceFolders": workspace_folders}))
+        .await;
+    client
+        .open(
+            &uri,
+            "alert tcp 10.0.0.0/8 any -> any any (msg:\"x\"; sid:900000; rev:1;)",
+        )
+        .await;
+    client.diagnostics(&uri, 1).await;
+
+    let started = Instant::now();
+    let hover = client.hover(&uri, 0, 12).await;
+    assert!(
+        started.elapsed() < Duration::from_secs(1),
+        "the hover took {:?}",
+        started.elapsed()
+    );
+    assert!(!hover.is_null());
+    let indexed = client
+        .received
+        .iter()
+        .any(|message| message["params"]["message"].as_str() == Some("indexed 1 rule files"));
+    assert!(!indexed, "the workspace was indexed before the hover");
+    // The indexing stops once the server shuts down
+    client.request("shutdown", Value::Null).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn hover_is_answered_from_the_last_parse_while_a_document_is_parsed_again() {
+    let uri = "file:///large.rules";
+    let rules = large_ruleset(20_000);
+    let first = "alert tcp 10.0.0.0/8 any -> any any (msg:\"x\"; sid:1; rev:1;)\n";
+    let changed = "alert tcp 192.168.0.0/16 any -> any any (msg:\"x\"; sid:1; rev:1;)\n";
+
+    let mut client = TestClient::start(test_settings());
+    client.initialize(json!({"capabilities": {}})).await;
+    client.open(uri, &format!("{}{}", first, rules)).await;
+    let parsed = client
+        .hover_until(uri, 0, 12, |hover| !hover.is_null())
+        .await;
+
+    let text_document = json!({"uri": uri, "version": 2});
+    let changes = json!([{ "text": format!("{}{}", changed, rules) }]);
+    client
+        .notify(
+            "textDocument/didChange",
+            json!({"textDocument": text_document, "contentChanges": changes}),
+        )
+        .await;
+    // The signatures of the first version answer, until the second is parsed
+    let started = Instant::now();
+    let hover = client.hover(uri, 0, 12).await;
+    assert!(
+        started.elapsed() < Duration::from_secs(1),
+        "the hover took {:?}",
+        started.elapsed()
+    );
+    assert_eq!(hover, parsed);
+    let reparsed = client
+        .hover_until(uri, 0, 12, |hover| *hover != parsed)
+        .await;
+    assert!(reparsed.to_string().contains("192.168.255.255"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn turning_a_lint_off_and_on_again_updates_the_diagnostics() {
+    let uri = "file:///legacy.rules";
+    let text =
+        "alert http any any -> any 80 (flow:to_server; content:\"x\"; http_uri; sid:1; rev:1;)";
+    // The severities of the legacy-modifier diagnostics
+    let legacy_modifier = |diagnostics: Vec<Value>| {
+        diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic["code"] == "legacy-modifier")
+            .map(|diagnostic| diagnostic["severity"].clone())
+            .collect::<Vec<_>>()
+    };
+
+    let mut client = TestClient::start(test_settings());
+    client.initialize(json!({"capabilities": {}})).await;
+    client.open(uri, text).await;
+    let diagnostics = client.diagnostics(uri, 1).await;
+    assert_eq!(legacy_modifier(diagnostics), vec![json!(2)]);
+
+    // The open documents are validated again with the new settings
+    let settings = json!({"meerkat": {"lints": {"legacy-modifier": "off"}}});
+    client
+        .notify(
+            "workspace/didChangeConfiguration",
+            json!({ "settings": settings }),
+        )
+        .await;
+    let diagnostics = client.diagnostics(uri, 1).await;
+    assert!(legacy_modifier(diagnostics).is_empty());
+
+    let settings = json!({"meerkat": {"lints": {"legacy-modifier": "error"}}});
+    client
+        .notify(
+            "workspace/didChangeConfiguration",
+            json!({ "settings": settings }),
+        )
+        .await;
+    let diagnostics = client.diagnostics(uri, 1).await;
+    assert_eq!(legacy_modifier(diagnostics), vec![json!(1)]);
+}
+
+/// Create a suricata.yaml inside the folder
+fn suricata_config(folder: &std::path::Path, name: &str) -> String {
+    let suricata_config = folder.join(name);
+    fs::write(&suricata_config, "%YAML 1.1\n").unwrap();
+    suricata_config.display().to_string()
+}
+
+#[test]
+fn command_line_settings_take_precedence_over_the_client_settings() {
+    let folder = tempfile::tempdir().unwrap();
+    let flag = suricata_config(folder.path(), "flag.yaml");
+    let flags = command_line_settings(Some(&flag));
+    let options = json!({
+        "suricata": {"path": "/usr/local/bin/suricata"},
+        "verification": {
+            "enabled": false,
+            "profile": {"configFile": suricata_config(folder.path(), "client.yaml"), "mode": "test"},
+        },
+    });
+    let parsed = client_settings(options, &flags);
+    assert!(parsed.errors.is_empty(), "{:?}", parsed.errors);
+    let settings = parsed.settings;
+    assert_eq!(settings.verification.profile.config_file, Some(flag));
+    // The settings, which are not set on the command line, are kept
+    assert_eq!(
+        settings.suricata.path.as_deref(),
+        Some("/usr/local/bin/suricata")
+    );
+    assert!(!settings.verification.enabled);
+    assert_eq!(settings.verification.profile.mode, VerificationMode::Test);
+}
+
+#[test]
+fn client_settings_are_used_without_flags() {
+    let folder = tempfile::tempdir().unwrap();
+    let client = suricata_config(folder.path(), "client.yaml");
+    let flags = command_line_settings(None);
+    let options = json!({"verification": {"profile": {"configFile": client}}});
+    let settings = client_settings(options, &flags).settings;
+    assert_eq!(settings.verification.profile.config_file, Some(client));
+}
+
+#[test]
+fn command_line_settings_replace_invalid_client_settings() {
+    let folder = tempfile::tempdir().unwrap();
+    let flag = suricata_config(folder.path(), "flag.yaml");
+    let parsed = client_settings(
+        json!({"verification": 1}),
+        &command_line_settings(Some(&flag)),
+    );
+    assert!(parsed.errors.is_empty(), "{:?}", parsed.errors);
+    assert_eq!(parsed.settings.verification.profile.config_file, Some(flag));
+}
+
+#[test]
+fn long_version_names_the_suricata_version_of_the_bundled_keywords() {
+    let keywords = include_str!("../resources/keywords.csv");
+    let version = keywords.lines().next().unwrap();
+    assert_eq!(version, "This is Suricata version 7.0.6 RELEASE");
+    assert!(LONG_VERSION.starts_with(env!("CARGO_PKG_VERSION")));
+    assert!(LONG_VERSION
+        .contains("\nkeywords: Suricata 7.0.6 (bundled), unless Suricata is installed\n"));
+    assert!(LONG_VERSION
+        .ends_with("events: bundled, extended with the event rules of the installed Suricata"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn suricata_waits_for_the_debounce() {
+    let debounce = Duration::from_millis(1000);
+    let mut settings = ServerSettings::default();
+    // Suricata can not be started, so the verification publishes no diagnostics
+    settings.suricata.path = Some("/nonexistent/suricata".to_string());
+    settings.diagnostics.debounce_ms = debounce.as_millis() as u64;
+    let mut client = TestClient::start(settings);
+    client.initialize(json!({"capabilities": {}})).await;
+
+    let uri = "file:///debounce.rules";
+    let opened = tokio::time::Instant::now();
+    client
+        .open(
+            uri,
+            "alert tcp any any -> any any (msg:\"x\"; sid:1; rev:1;)\n",
+        )
+        .await;
+    let mut published = vec![];
+    while opened.elapsed() < debounce * 2 {
+        let remaining = debounce * 2 - opened.elapsed();
+        let Ok(Some(message)) = tokio::time::timeout(remaining, client.incoming.recv()).await
+        else {
+            continue;
+        };
+        if message["method"] == "textDocument/publishDiagnostics" && message["params"]["uri"] == uri
+        {
+            published.push(opened.elapsed());
+        }
+    }
+    // The lints are published right away, the verification after the debounce
+    assert!(published.first().is_some_and(|elapsed| *elapsed < debounce));
+    assert!(published.last().is_some_and(|elapsed| *elapsed >= debounce));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn every_folder_has_its_own_lint_levels() {
+    let strict = tempfile::tempdir().unwrap();
+    let relaxed = tempfile::tempdir().unwrap();
+    let strict_uri = Url::from_directory_path(strict.path()).unwrap();
+    let relaxed_uri = Url::from_directory_path(relaxed.path()).unwrap();
+
+    let mut client = TestClient::start(test_settings());
+    client.configure(
+        Some(&strict_uri),
+        json!({"lints": {"legacy-modifier": "error"}}),
+    );
+    client.configure(
+        Some(&relaxed_uri),
+        json!({"lints": {"legacy-modifier": "off"}}),
+    );
+    let workspace_folders = json!([
+        {"uri": strict_uri, "name": "strict"},
+        {"uri": relaxed_uri, "name": "relaxed"},
+    ]);
+    let capabilities = json!({"workspace": {"configuration": true}});
+    client
+        .initialize(json!({"capabilities": capabilities, "workspaceFolders": workspace_folders}))
+        .await;
+
+    let rule = "alert http any any -> any any (content:\"x\"; http_uri; sid:1; rev:1;)\n";
+    let legacy_modifier = |diagnostics: &[Value]| {
+        diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic["code"] == "legacy-modifier")
+            .map(|diagnostic| diagnostic["severity"].clone())
+            .collect::<Vec<_>>()
+    };
+    let strict_file = strict_uri.join("a.rules").unwrap().to_string();
+    client.open(&strict_file, rule).await;
+    let diagnostics = client.diagnostics(&strict_file, 1).await;
+    assert_eq!(legacy_modifier(&diagnostics), vec![json!(1)]);
+
+    let relaxed_file = relaxed_uri.join("a.rules").unwrap().to_string();
+    client.open(&relaxed_file, rule).await;
+    let diagnostics = client.diagnostics(&relaxed_file, 1).await;
+    assert!(legacy_modifier(&diagnostics).is_empty());
+
+    // Files outside of the folders use the settings of the server
+    client.open("file:///elsewhere/a.rules", rule).await;
+    let diagnostics = client.diagnostics("file:///elsewhere/a.rules", 1).await;
+    assert_eq!(legacy_modifier(&diagnostics).len(), 1);
+    assert_ne!(legacy_modifier(&diagnostics), vec![json!(1)]);
+}
+
+/// Start a language server with a settings file, which turns off the legacy modifiers, and
+/// count the legacy modifiers of a signature
+async fn legacy_modifiers_with_settings_file(initialize: Value) -> usize {
+    let folder = tempfile::tempdir().unwrap();
+    let config = folder.path().join("settings.json");
+    fs::write(&config, r#"{"lints": {"legacy-modifier": "off"}}"#).unwrap();
+    let settings = load_settings_file(&config).unwrap();
+    assert_eq!(settings.settings_file, Some(config.display().to_string()));
+
+    let mut client = TestClient::start(settings);
+    client.initialize(initialize).await;
+    let rule = "alert http any any -> any any (content:\"x\"; http_uri; sid:1; rev:1;)\n";
+    client.open("file:///a.rules", rule).await;
+    let diagnostics = client.diagnostics("file:///a.rules", 1).await;
+    diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic["code"] == "legacy-modifier")
+        .count()
+}
+
+#[tokio::test]
+async fn the_settings_file_applies_without_initialization_options() {
+    let initialize = json!({"capabilities": {}});
+    assert_eq!(legacy_modifiers_with_settings_file(initialize).await, 0);
+}
+
+#[tokio::test]
+async fn the_settings_file_is_ignored_with_initialization_options() {
+    let initialize = json!({"capabilities": {}, "initializationOptions": {"lints": {}}});
+    assert_eq!(legacy_modifiers_with_settings_file(initialize).await, 1);
+}
+
+#[tokio::test]
+async fn variables_of_the_settings_are_defined_inside_the_settings() {
+    let definition = |settings: ServerSettings| async move {
+        let mut client = TestClient::start(settings);
+        client.initialize(json!({"capabilities": {}})).await;
+        let rule = "alert tcp $HOME_NET any -> any any (msg:\"x\"; sid:1; rev:1;)\n";
+        client.open("file:///a.rules", rule).await;
+        client.diagnostics("file:///a.rules", 1).await;
+        let params = json!({
+            "textDocument": {"uri": "file:///a.rules"},
+            "position": {"line": 0, "character": 12},
+        });
+        client.request("textDocument/definition", params).await
+    };
+    let mut settings = test_settings();
+    settings
+        .variables
+        .address_groups
+        .insert("HOME_NET".to_string(), "[10.0.0.0/8]".to_string());
+
+    // Without the path of the settings file, the definition is only virtual
+    let location = definition(settings.clone()).await;
+    assert_eq!(location["uri"], SETTINGS_URI);
+
+    let folder = tempfile::tempdir().unwrap();
+    let settings_file = folder.path().join("settings.json");
+    let text =
+        "{\n  \"variables\": {\n    \"addressGroups\": {\"HOME_NET\": \"[10.0.0.0/8]\"}\n  }\n}\n";
+    fs::write(&settings_file, text).unwrap();
+    settings.settings_file = Some(settings_file.display().to_string());
+    let location = definition(settings).await;
+    assert_eq!(
+        location["uri"],
+        Url::from_file_path(&settings_file).unwrap().to_string()
+    );
+    assert_eq!(location["range"]["start"]["line"], 2);
+
+    // The variables defined nowhere have no definition
+    let location = definition(test_settings()).await;
+    assert!(location.is_null());
+}
+
+/// Insert a new signature into a document and get the inserted text and its position
+async fn insert_new_rule(client: &mut TestClient, uri: &str) -> (Value, String) {
+    let params = json!({"command": NEW_RULE, "arguments": [uri, {"protocol": "http"}]});
+    let edit = client.request("workspace/executeCommand", params).await;
+    let applied = client
+        .wait_for("workspace/applyEdit", |params| params["edit"] == edit)
+        .await;
+    let edits = applied["edit"]["changes"][uri].as_array().cloned().unwrap();
+    assert_eq!(edits.len(), 1);
+    let range = &edits[0]["range"];
+    assert_eq!(range["start"], range["end"]);
+    let text = edits[0]["newText"].as_str().unwrap().to_string();
+    (range["start"].clone(), text)
+}
+
+#[tokio::test]
+async fn new_rules_are_inserted_at_the_end() {
+    let mut client = TestClient::start(test_settings());
+    client.initialize(json!({"capabilities": {}})).await;
+    let style = FormatStyle::default();
+    let options = SkeletonOptions {
+        protocol: "http".to_string(),
+        ..Default::default()
+    };
+    let skeleton = |sid| style.format_rule(&rule_skeleton(&options, sid).unwrap());
+
+    client.open("file:///empty.rules", "").await;
+    client.diagnostics("file:///empty.rules", 1).await;
+    let (position, text) = insert_new_rule(&mut client, "file:///empty.rules").await;
+    assert_eq!(position, json!({"line": 0, "character": 0}));
+    assert_eq!(text, format!("{}\n", skeleton(1)));
+
+    let rule = "alert tcp any any -> any any (msg:\"x\"; sid:5; rev:1;)";
+    client.open("file:///unterminated.rules", rule).await;
+    client.diagnostics("file:///unterminated.rules", 1).await;
+    let (position, text) = insert_new_rule(&mut client, "file:///unterminated.rules").await;
+    let end = rule.len();
+    assert_eq!(position, json!({"line": 0, "character": end}));
+    assert_eq!(text, format!("\n{}\n", skeleton(6)));
+
+    let text = format!("{}\r\n", rule);
+    client.open("file:///terminated.rules", &text).await;
+    client.diagnostics("file:///terminated.rules", 1).await;
+    let (position, text) = insert_new_rule(&mut client, "file:///terminated.rules").await;
+    assert_eq!(position, json!({"line": 1, "character": 0}));
+    assert_eq!(text, format!("{}\r\n", skeleton(6)));
+}
+
+#[tokio::test]
+async fn rule_files_are_selected_by_language_and_pattern() {
+    let mut client = TestClient::start(test_settings());
+    let result = client.initialize(json!({"capabilities": {}})).await;
+    let provider = &result["capabilities"]["semanticTokensProvider"];
+    assert_eq!(
+        provider["documentSelector"],
+        json!([
+            {"language": "suricata", "scheme": "file"},
+            {"scheme": "file", "pattern": "**/*.rules"},
+        ])
+    );
+}
+
+#[tokio::test]
+async fn documents_of_any_language_are_rule_files() {
+    let mut client = TestClient::start(test_settings());
+    client.initialize(json!({"capabilities": {}})).await;
+    let uri = "file:///plain.rules";
+    client
+        .open_as(
+            uri,
+            "plaintext",
+            "alert tcp 10.0.0.0/8 any -> any any (msg:\"x\"; content:\"a\"; sid:1;)\n",
+        )
+        .await;
+    let diagnostics = client.diagnostics(uri, 1).await;
+    assert!(diagnostics
+        .iter()
+        .any(|diagnostic| diagnostic["code"] == "missing-flow"));
+    assert!(!client.hover(uri, 0, 11).await.is_null());
+
+    // Binary content is refused, whatever its language
+    let uri = "file:///binary.rules";
+    client
+        .open_as(
+            uri,
+            "plaintext",
+            "alert tcp 10.0.0.0/8 any -> any any (sid:1;)\0\n",
+        )
+        .await;
+    assert!(client.diagnostics(uri, 1).await.is_empty());
+    assert!(client.hover(uri, 0, 11).await.is_null());
+}
+
+/// Send changes of a document to the server and get the stored text
+async fn change_document(backend: &Backend, version: i32, changes: Vec<Value>) -> String {
+    let params = json!({
+        "textDocument": {"uri": "file:///a.rules", "version": version},
+        "contentChanges": changes,
+    });
+    backend
+        .did_change(serde_json::from_value(params).unwrap())
+        .await;
+    let rope = backend.document_map.get("file:///a.rules").unwrap();
+    rope.to_string()
+}
+
+#[tokio::test]
+async fn every_change_is_applied_in_order() {
+    let (service, _) = LspService::new(|client| test_backend(client, test_settings()));
+    let backend = service.inner();
+    let text = "alert tcp any any -> any any (msg:\"x\"; sid:1;)\n";
+    let params = json!({
+        "textDocument": {"uri": "file:///a.rules", "languageId": "suricata", "version": 1, "text": text},
+    });
+    backend
+        .did_open(serde_json::from_value(params).unwrap())
+        .await;
+
+    // No changes (e.g. when saving)
+    assert_eq!(change_document(backend, 2, vec![]).await, text);
+
+    let range = |line, start, end| json!({"start": {"line": line, "character": start}, "end": {"line": line, "character": end}});
+    let changes = vec![json!({"range": range(0, 35, 36), "text": "y"})];
+    let expected = "alert tcp any any -> any any (msg:\"y\"; sid:1;)\n";
+    assert_eq!(change_document(backend, 3, changes).await, expected);
+
+    // Every change applies to the text left by the previous one
+    let changes = vec![
+        json!({"range": range(0, 43, 44), "text": "2"}),
+        json!({"range": range(1, 0, 0), "text": "alert udp any any -> any any (sid:3;)\n"}),
+        json!({"range": range(0, 6, 9), "text": "http"}),
+    ];
+    let expected = concat!(
+        "alert http any any -> any any (msg:\"y\"; sid:2;)\n",
+        "alert udp any any -> any any (sid:3;)\n",
+    );
+    assert_eq!(change_document(backend, 4, changes).await, expected);
+
+    // The last full text replaces the changes before it
+    let changes = vec![
+        json!({"text": "first\n"}),
+        json!({"range": range(0, 0, 5), "text": "second"}),
+        json!({"text": "alert ip any any -> any any (sid:4;)\n"}),
+    ];
+    let expected = "alert ip any any -> any any (sid:4;)\n";
+    assert_eq!(change_document(backend, 5, changes).await, expected);
+    assert_eq!(
+        *backend.document_versions.get("file:///a.rules").unwrap(),
+        5
+    );
+}
+
+#[tokio::test]
+async fn a_change_outside_of_the_document_parses_it_in_full() {
+    let (service, _) = LspService::new(|client| test_backend(client, test_settings()));
+    let backend = service.inner();
+    let text = "alert tcp any any -> any any (msg:\"x\"; sid:1;)\n";
+    let params = json!({
+        "textDocument": {"uri": "file:///a.rules", "languageId": "suricata", "version": 1, "text": text},
+    });
+    backend
+        .did_open(serde_json::from_value(params).unwrap())
+        .await;
+
+    // The change outside of the document is dropped, the others are applied
+    let range = |line, start, end| json!({"start": {"line": line, "character": start}, "end": {"line": line, "character": end}});
+    let changes = vec![
+        json!({"range": range(5, 0, 0), "text": "alert ip any any -> any any (sid:2;)\n"}),
+        json!({"range": range(0, 6, 9), "text": "udp"}),
+    ];
+    let expected = "alert udp any any -> any any (msg:\"x\"; sid:1;)\n";
+    assert_eq!(change_document(backend, 2, changes).await, expected);
+    let (expected_ast, expected_tokens) = AST::parse_str(expected);
+    assert_eq!(
+        *backend.ast_map.get("file:///a.rules").unwrap(),
+        expected_ast
+    );
+    assert_eq!(
+        *backend.semantic_token_map.get("file:///a.rules").unwrap(),
+        expected_tokens
+    );
+}
+
+/// Get the labels of the completions at a position
+async fn completions(client: &mut TestClient, uri: &str, line: u32, character: u32) -> Vec<String> {
+    let params = json!({
+        "textDocument": {"uri": uri},
+        "position": {"line": line, "character": character},
+    });
+    let result = client.request("textDocument/completion", params).await;
+    let items = result.as_array().cloned().unwrap_or_default();
+    items
+        .iter()
+        .filter_map(|item| Some(item["label"].as_str()?.to_string()))
+        .collect()
+}
+
+#[tokio::test]
+async fn variables_typed_in_one_document_are_completed_in_another() {
+    let mut client = TestClient::start(test_settings());
+    client.initialize(json!({"capabilities": {}})).await;
+    let (first, second) = ("file:///first.rules", "file:///second.rules");
+    client
+        .open(first, "alert tcp any any -> any any (sid:1;)\n")
+        .await;
+    client.diagnostics(first, 1).await;
+    client.open(second, "alert tcp $").await;
+    client.diagnostics(second, 1).await;
+    let labels = completions(&mut client, second, 0, 11).await;
+    assert!(labels.contains(&"$HOME_NET".to_string()), "{:?}", labels);
+    assert!(!labels.contains(&"$FOO_NET".to_string()));
+
+    let change = |version, text: &str| {
+        json!({
+            "textDocument": {"uri": first, "version": version},
+            "contentChanges": [{"text": text}],
+        })
+    };
+    let text = "alert tcp $FOO_NET any -> any any (sid:1;)\n";
+    client
+        .notify("textDocument/didChange", change(2, text))
+        .await;
+    client.diagnostics(first, 2).await;
+    let labels = completions(&mut client, second, 0, 11).await;
+    assert!(labels.contains(&"$FOO_NET".to_string()), "{:?}", labels);
+
+    // The variable is forgotten, once no document uses it
+    let text = "alert tcp any any -> any any (sid:1;)\n";
+    client
+        .notify("textDocument/didChange", change(3, text))
+        .await;
+    client.diagnostics(first, 3).await;
+    let labels = completions(&mut client, second, 0, 11).await;
+    assert!(!labels.contains(&"$FOO_NET".to_string()));
+}
+
+#[tokio::test]
+async fn closed_documents_are_forgotten() {
+    let mut client = TestClient::start(test_settings());
+    client.initialize(json!({"capabilities": {}})).await;
+    let (closed, open, typing) = (
+        "file:///closed.rules",
+        "file:///open.rules",
+        "file:///typing.rules",
+    );
+    client
+        .open(
+            closed,
+            "alert tcp $FOO_NET any -> any any (msg:\"a\"; sid:1; rev:1;)\n",
+        )
+        .await;
+    client.diagnostics(closed, 1).await;
+    let text = "alert tcp any any -> any any (msg:\"b\"; sid:1; rev:1;)\n";
+    client.open(open, text).await;
+    let is_duplicate = |diagnostic: &Value| diagnostic["code"] == "duplicate-sid";
+    let diagnostics = client.diagnostics(open, 1).await;
+    assert!(diagnostics.iter().any(is_duplicate), "{:?}", diagnostics);
+    client.open(typing, "alert tcp $").await;
+    client.diagnostics(typing, 1).await;
+    let labels = completions(&mut client, typing, 0, 11).await;
+    assert!(labels.contains(&"$FOO_NET".to_string()), "{:?}", labels);
+
+    // The diagnostics of the closed document are cleared
+    client
+        .notify(
+            "textDocument/didClose",
+            json!({"textDocument": {"uri": closed}}),
+        )
+        .await;
+    let cleared = client
+        .wait_for("textDocument/publishDiagnostics", |params| {
+            params["uri"] == closed && params["version"].is_null()
+        })
+        .await;
+    assert_eq!(cleared["diagnostics"], json!([]));
+
+    // Its signatures and variables are gone from the other documents
+    let labels = completions(&mut client, typing, 0, 11).await;
+    assert!(!labels.contains(&"$FOO_NET".to_string()), "{:?}", labels);
+    client
+        .notify(
+            "textDocument/didChange",
+            json!({
+                "textDocument": {"uri": open, "version": 2},
+                "contentChanges": [{"text": text}],
+            }),
+        )
+        .await;
+    let diagnostics = client.diagnostics(open, 2).await;
+    assert!(!diagnostics.iter().any(is_duplicate), "{:?}", diagnostics);
+    assert!(client.hover(closed, 0, 12).await.is_null());
+}
+
+#[tokio::test]
+async fn disabled_rules_are_navigable_but_not_linted() {
+    let mut client = TestClient::start(test_settings());
+    client.initialize(json!({"capabilities": {}})).await;
+
+    // The disabled signature misses a rev, a flow and a classtype
+    let disabled = "# alert tcp 10.0.0.0/8 any -> any 80 (msg:\"x\"; sid:1;)\n";
+    client.open("file:///disabled.rules", disabled).await;
+    assert!(client
+        .diagnostics("file:///disabled.rules", 1)
+        .await
+        .is_empty());
+    let hover = client.hover("file:///disabled.rules", 0, 13).await;
+    assert!(!hover.is_null());
+
+    // The enabled twin has the same sid, but is not a duplicate
+    let text = format!(
+        "{}{}",
+        disabled,
+        "alert tcp 10.0.0.0/8 any -> any 80 (msg:\"x\"; flow:established,to_server; \
+         classtype:unknown; sid:1; rev:1;)\n"
+    );
+    client.open("file:///twins.rules", &text).await;
+    let diagnostics = client.diagnostics("file:///twins.rules", 1).await;
+    assert!(
+        diagnostics.iter().all(|diagnostic| {
+            diagnostic["range"]["start"]["line"] == 1 && diagnostic["code"] != "duplicate-sid"
+        }),
+        "{:?}",
+        diagnostics
+    );
+    assert!(!client.hover("file:///twins.rules", 0, 13).await.is_null());
+}
+
+#[tokio::test]
+async fn headers_are_hovered_while_the_options_are_garbage() {
+    let mut client = TestClient::start(test_settings());
+    client.initialize(json!({"capabilities": {}})).await;
+    let uri = "file:///garbage.rules";
+    let text = concat!(
+        "alert tcp 10.0.0.0/8 any -> any 80 (msg:\"x\"; @@@ ((\n",
+        "alert tcp 10.0.0.0/8 any -> any 80 (msg:\"x\"; sid:1;\n",
+        "alert tcp 10.0.0.0/8 any -> any 80 )))\n",
+    );
+    client.open(uri, text).await;
+    let diagnostics = client.diagnostics(uri, 1).await;
+    assert!(diagnostics
+        .iter()
+        .all(|diagnostic| diagnostic["code"] == "syntax"));
+    for line in 0..3 {
+        let hover = client.hover(uri, line, 11).await;
+        let value = hover["contents"].to_string();
+        assert!(value.contains("10.0.0.0/8"), "{}: {}", line, hover);
+    }
+}
+
+#[tokio::test]
+async fn edited_lines_are_parsed_like_the_whole_document() {
+    let (service, _) = LspService::new(|client| test_backend(client, test_settings()));
+    let backend = service.inner();
+    let uri = "file:///large.rules";
+    let text = (1..=3000)
+        .map(|sid| match sid % 3 {
+            0 => format!("# comment {}\n", sid),
+            1 => format!(
+                "alert tcp $HOME_NET any -> any {} (msg:\"rule {}\"; sid:{}; rev:1;)\n",
+                sid % 1000,
+                sid,
+                sid
+            ),
+            _ => format!(
+                "#alert udp any any -> any any (msg:\"off\"; sid:{};)\n",
+                sid
+            ),
+        })
+        .collect::<String>();
+    let params = json!({
+        "textDocument": {"uri": uri, "languageId": "suricata", "version": 1, "text": text},
+    });
+    backend
+        .did_open(serde_json::from_value(params).unwrap())
+        .await;
+
+    let range = |(start_line, start), (end_line, end)| {
+        json!({
+            "start": {"line": start_line, "character": start},
+            "end": {"line": end_line, "character": end},
+        })
+    };
+    let edits = [
+        // Change a signature
+        (range((1500, 6), (1500, 9)), "udp"),
+        // Insert signatures
+        (
+            range((1200, 0), (1200, 0)),
+            "alert ip any any -> any any (sid:9001;)\nalert ip any any -> any any (sid:9002;)\n",
+        ),
+        // Remove lines
+        (range((1000, 0), (1010, 0)), ""),
+        // Join two lines and break a signature
+        (range((1600, 10), (1601, 20)), ""),
+        // Split a signature into two lines
+        (range((1700, 30), (1700, 30)), "\n"),
+        // Comment out a signature
+        (range((1800, 0), (1800, 0)), "#"),
+        // Replace the end of the file
+        (
+            range((2990, 0), (3100, 0)),
+            "alert tcp any any -> any any (sid:9003;)",
+        ),
+    ];
+    for (version, (range, text)) in edits.into_iter().enumerate() {
+        let version = version as i32 + 2;
+        let params = json!({
+            "textDocument": {"uri": uri, "version": version},
+            "contentChanges": [{"range": range, "text": text}],
+        });
+        backend
+            .did_change(serde_json::from_value(params).unwrap())
+            .await;
+        let rope = backend.document_map.get(uri).unwrap().clone();
+        let (expected_ast, expected_tokens) = AST::parse_rope(&rope);
+        let ast = backend.ast_map.get(uri).unwrap();
+        assert!(*ast == expected_ast, "the AST differs after {}", range);
+        let tokens = backend.semantic_token_map.get(uri).unwrap();
+        assert!(
+            *tokens == expected_tokens,
+            "the semantic tokens differ after {}",
+            range
+        );
+    }
+}
+
+#[tokio::test]
+async fn crlf_documents_have_the_positions_of_lf_documents() {
+    let crlf = include_str!("../tests/fixtures/crlf/input.rules");
+    let lf = crlf.replace("\r\n", "\n");
+    let mut client = TestClient::start(test_settings());
+    client.initialize(json!({"capabilities": {}})).await;
+    let mut answers = vec![];
+    for (uri, text) in [("file:///crlf.rules", crlf), ("file:///lf.rules", &lf)] {
+        client.open(uri, text).await;
+        client.diagnostics(uri, 1).await;
+        let first = client.hover(uri, 1, 12).await;
+        let second = client.hover(uri, 3, 25).await;
+        let params = json!({"textDocument": {"uri": uri}});
+        let tokens = client
+            .request("textDocument/semanticTokens/full", params)
+            .await;
+        answers.push((
+            first["range"].clone(),
+            second["range"].clone(),
+            tokens["data"].clone(),
+        ));
+    }
+    let range = |line, start, end| json!({"start": {"line": line, "character": start}, "end": {"line": line, "character": end}});
+    let (first, second, tokens) = &answers[0];
+    assert_eq!(*first, range(1, 10, 19));
+    assert_eq!(*second, range(3, 22, 35));
+    assert_eq!(answers[0], answers[1]);
+    assert!(!tokens.as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn every_token_is_sent_once() {
+    let text = "# rules\nalert tcp $HOME_NET any -> any 80 (msg:\"a\"; sid:1;)\n";
+    let mut client = TestClient::start(test_settings());
+    client.initialize(json!({"capabilities": {}})).await;
+    let uri = "file:///a.rules";
+    client.open(uri, text).await;
+    client.diagnostics(uri, 1).await;
+    // Decode the tokens into their line, column, length and type
+    let decode = |tokens: Value| {
+        let data = serde_json::from_value::<Vec<u32>>(tokens["data"].clone()).unwrap();
+        let (mut line, mut column) = (0, 0);
+        data.chunks(5)
+            .map(|token| {
+                if token[0] > 0 {
+                    column = 0;
+                }
+                line += token[0];
+                column += token[1];
+                (line, column, token[2], token[3])
+            })
+            .collect::<Vec<_>>()
+    };
+    let params = json!({"textDocument": {"uri": uri}});
+    let full = decode(
+        client
+            .request("textDocument/semanticTokens/full", params)
+            .await,
+    );
+    let range = json!({"start": {"line": 0, "character": 0}, "end": {"line": 2, "character": 0}});
+    let params = json!({"textDocument": {"uri": uri}, "range": range});
+    let in_range = decode(
+        client
+            .request("textDocument/semanticTokens/range", params)
+            .await,
+    );
+    // The comment, the header and the options, each of them once
+    let expected = vec![
+        (0, 0, 7, 1),
+        (1, 0, 6, 2),
+        (1, 6, 3, 2),
+        (1, 10, 9, 3),
+        (1, 20, 3, 5),
+        (1, 24, 2, 8),
+        (1, 27, 3, 8),
+        (1, 31, 2, 4),
+        (1, 35, 3, 5),
+        (1, 39, 3, 0),
+        (1, 44, 3, 5),
+        (1, 48, 1, 7),
+    ];
+    assert_eq!(full, expected);
+    let mut unique = full.clone();
+    unique.dedup();
+    assert_eq!(unique.len(), full.len());
+    assert_eq!(full, in_range);
+}
+
+#[tokio::test]
+async fn non_ascii_msgs_keep_the_positions_of_the_client() {
+    let text = "alert tcp any any -> any any \
+        (msg:\"Отказ 🚨\"; flow:established; flowbits:set,bad; sid:1;)\n";
+    // The columns after the msg in UTF-16 code units and in characters (the
+    // emoji takes two code units)
+    for (encodings, offset) in [(json!(["utf-16"]), 1), (json!(["utf-32", "utf-16"]), 0)] {
+        let mut client = TestClient::start(test_settings());
+        let capabilities = json!({"general": {"positionEncodings": encodings}});
+        client
+            .initialize(json!({"capabilities": capabilities}))
+            .await;
+        let uri = "file:///a.rules";
+        client.open(uri, text).await;
+        client.diagnostics(uri, 1).await;
+        let range = |start: u32, length: u32| {
+            json!({
+                "start": {"line": 0, "character": start + offset},
+                "end": {"line": 0, "character": start + offset + length},
+            })
+        };
+
+        let hover = client.hover(uri, 0, 52 + offset).await;
+        assert_eq!(hover["range"], range(50, 11), "{}", encodings);
+        let params = json!({
+            "textDocument": {"uri": uri},
+            "position": {"line": 0, "character": 77 + offset},
+            "newName": "worse",
+        });
+        let edit = client.request("textDocument/rename", params).await;
+        assert_eq!(
+            edit["changes"][uri],
+            json!([{"range": range(76, 3), "newText": "worse"}]),
+            "{}",
+            encodings
+        );
+    }
+}
@@ -0,0 +1,181 @@
+//! Provides the loader for threshold.config
+//!
+//! The file defines thresholds, event filters, rate filters and suppressions
+//! for signatures, outside of the signatures themselves, e.g.:
+//!
+//! ```text
+//! threshold gen_id 1, sig_id 2001, type limit, track by_src, count 1, seconds 60
+//! suppress gen_id 1, sig_id 2002, track by_src, ip 10.0.0.1
+//! ```
+use std::collections::HashMap;
+use std::{fs, io, path::Path};
+
+use crate::rule::Span;
+
+/// The kinds of entries inside threshold.config
+const ENTRY_KINDS: [&str; 4] = ["threshold", "event_filter", "suppress", "rate_filter"];
+
+/// A single entry of threshold.config
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThresholdEntry {
+    /// The kind of the entry (e.g. "threshold" or "suppress")
+    pub kind: String,
+    pub gen_id: u32,
+    pub sig_id: u32,
+    /// The line of the entry inside the file
+    pub line: u32,
+    /// The span of the entry inside its line
+    pub span: Span,
+}
+
+impl ThresholdEntry {
+    /// Check if the entry replaces the threshold of the signature
+    ///
+    /// Suppressions and rate filters are applied on top of the threshold.
+    pub fn overrides_threshold(&self) -> bool {
+        self.kind == "threshold" || self.kind == "event_filter"
+    }
+}
+
+/// The entries of a threshold.config, keyed by (gen_id, sig_id)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ThresholdConfig {
+    /// Path of the file, used to point to the entries
+    pub path: String,
+    pub entries: HashMap<(u32, u32), Vec<ThresholdEntry>>,
+}
+
+impl ThresholdConfig {
+    /// Load the entries from a file
+    ///
+    /// Returns the config along with the lines which could not be parsed.
+    pub fn from_file(path: &Path) -> io::Result<(Self, Vec<(u32, String)>)> {
+        let text = fs::read_to_string(path)?;
+        let (mut config, errors) = ThresholdConfig::parse(&text);
+        config.path = path.display().to_string();
+        Ok((config, errors))
+    }
+
+    /// Parse the entries, comments and empty lines are skipped
+    ///
+    /// Returns the config along with the lines which could not be parsed.
+    pub fn parse(text: &str) -> (Self, Vec<(u32, String)>) {
+        let mut config = ThresholdConfig::default();
+        let mut errors = vec![];
+        text.lines().enumerate().for_each(|(line_nr, line)| {
+            let content = line.trim();
+            if content.is_empty() || content.starts_with('#') {
+                return;
+            }
+            let start = line.len() - line.trim_start().len();
+            let span = start..start + content.len();
+            match parse_entry(content, line_nr as u32, span) {
+                Ok(entry) => config
+                    .entries
+                    .entry((entry.gen_id, entry.sig_id))
+                    .or_default()
+                    .push(entry),
+                Err(err) => errors.push((line_nr as u32, err)),
+            }
+        });
+        (config, errors)
+    }
+
+    /// Get the entries for a signature
+    pub fn get(&self, gen_id: u32, sig_id: u32) -> &[ThresholdEntry] {
+        self.entries
+            .get(&(gen_id, sig_id))
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+}
+
+/// Parse a single entry (e.g. "suppress gen_id 1, sig_id 2002")
+fn parse_entry(content: &str, line: u32, span: Span) -> Result<ThresholdEntry, String> {
+    let (kind, arguments) = content
+        .split_once(char::is_whitespace)
+        .unwrap_or((content, ""));
+    if !ENTRY_KINDS.contains(&kind) {
+        return Err(format!(
+            "unknown entry \"{}\", expected one of {}",
+            kind,
+            ENTRY_KINDS.join(", ")
+        ));
+    }
+    let arguments = arguments
+        .split(',')
+        .filter_map(|argument| argument.trim().split_once(char::is_whitespace))
+        .map(|(key, value)| (key, value.trim()))
+        .collect::<HashMap<_, _>>();
+    let number = |key: &str| -> Result<u32, String> {
+        let value = arguments
+            .get(key)
+            .ok_or_else(|| format!("{} is missing the {}", kind, key))?;
+        value
+            .parse()
+            .map_err(|_| format!("{} should be a number, found \"{}\"", key, value))
+    };
+    Ok(ThresholdEntry {
+        kind: kind.to_string(),
+        gen_id: number("gen_id")?,
+        sig_id: number("sig_id")?,
+        line,
+        span,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> (ThresholdConfig, Vec<(u32, String)>) {
+        let path =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/threshold/threshold.config");
+        ThresholdConfig::from_file(&path).unwrap()
+    }
+
+    #[test]
+    fn entries_are_keyed_by_the_signature() {
+        let (config, _) = fixture();
+        let kinds = |gen_id, sig_id| {
+            config
+                .get(gen_id, sig_id)
+                .iter()
+                .map(|entry| entry.kind.as_str())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(kinds(1, 2001), ["threshold", "event_filter"]);
+        assert_eq!(kinds(1, 2002), ["suppress"]);
+        assert_eq!(kinds(3, 2003), ["rate_filter"]);
+        assert!(kinds(1, 2003).is_empty());
+        assert!(config.path.ends_with("threshold.config"));
+    }
+
+    #[test]
+    fn entries_point_to_their_line() {
+        let (config, _) = fixture();
+        let entry = &config.get(1, 2001)[1];
+        assert_eq!(entry.line, 3);
+        assert_eq!(entry.span, 2..82);
+        assert!(entry.overrides_threshold());
+        assert!(!config.get(1, 2002)[0].overrides_threshold());
+    }
+
+    #[test]
+    fn invalid_entries_are_reported() {
+        let (_, errors) = fixture();
+        assert_eq!(
+            errors,
+            [
+                (
+                    6,
+                    "unknown entry \"limit\", expected one of threshold, event_filter, suppress, \
+                     rate_filter"
+                        .to_string()
+                ),
+                (7, "sig_id should be a number, found \"latest\"".to_string()),
+                (8, "threshold is missing the gen_id".to_string()),
+            ]
+        );
+    }
+}
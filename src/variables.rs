@@ -0,0 +1,716 @@
+//! Provides the variables (address and port groups) known to the language server
+//!
+//! The variables are defined in layers, where a later layer overrides an
+//! earlier one:
+//! - the defaults of the suricata.yaml shipped with Suricata
+//! - the `vars` section of the suricata.yaml used for the verification, when
+//!   it is added (see [Variables::with_config_variables])
+//! - the `variables` section of the settings
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::rule::{Rule, Span, Spanned, AST};
+use crate::server_settings::VariablesSettings;
+
+/// The address groups of the suricata.yaml shipped with Suricata
+const DEFAULT_ADDRESS_GROUPS: [(&str, &str); 15] = [
+    ("HOME_NET", "[192.168.0.0/16,10.0.0.0/8,172.16.0.0/12]"),
+    ("EXTERNAL_NET", "!$HOME_NET"),
+    ("HTTP_SERVERS", "$HOME_NET"),
+    ("SMTP_SERVERS", "$HOME_NET"),
+    ("SQL_SERVERS", "$HOME_NET"),
+    ("DNS_SERVERS", "$HOME_NET"),
+    ("TELNET_SERVERS", "$HOME_NET"),
+    ("AIM_SERVERS", "$EXTERNAL_NET"),
+    ("DC_SERVERS", "$HOME_NET"),
+    ("DNP3_SERVER", "$HOME_NET"),
+    ("DNP3_CLIENT", "$HOME_NET"),
+    ("MODBUS_CLIENT", "$HOME_NET"),
+    ("MODBUS_SERVER", "$HOME_NET"),
+    ("ENIP_CLIENT", "$HOME_NET"),
+    ("ENIP_SERVER", "$HOME_NET"),
+];
+
+/// The port groups of the suricata.yaml shipped with Suricata
+const DEFAULT_PORT_GROUPS: [(&str, &str); 11] = [
+    ("HTTP_PORTS", "80"),
+    ("SHELLCODE_PORTS", "!80"),
+    ("ORACLE_PORTS", "1521"),
+    ("SSH_PORTS", "22"),
+    ("DNP3_PORTS", "20000"),
+    ("MODBUS_PORTS", "502"),
+    ("FILE_DATA_PORTS", "[$HTTP_PORTS,110,143]"),
+    ("FTP_PORTS", "21"),
+    ("GENEVE_PORTS", "6081"),
+    ("VXLAN_PORTS", "4789"),
+    ("TEREDO_PORTS", "3544"),
+];
+
+/// Whether a variable is an address group or a port group
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VariableKind {
+    Address,
+    Port,
+}
+
+impl fmt::Display for VariableKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VariableKind::Address => write!(f, "address group"),
+            VariableKind::Port => write!(f, "port group"),
+        }
+    }
+}
+
+/// Where a variable is defined
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VariableSource {
+    /// The suricata.yaml shipped with Suricata
+    Default,
+    /// The suricata.yaml used for the verification
+    Config,
+    /// The settings of the language server
+    Settings,
+}
+
+impl fmt::Display for VariableSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VariableSource::Default => write!(f, "the default suricata.yaml"),
+            VariableSource::Config => write!(f, "the suricata.yaml"),
+            VariableSource::Settings => write!(f, "the settings"),
+        }
+    }
+}
+
+/// The definition of a variable
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VariableDefinition {
+    /// The value in the Suricata syntax (e.g. "[80,443]")
+    pub value: String,
+    pub source: VariableSource,
+}
+
+/// All variables known to the language server
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Variables {
+    pub addresses: HashMap<String, VariableDefinition>,
+    pub ports: HashMap<String, VariableDefinition>,
+}
+
+impl Variables {
+    /// Create the variables from the defaults, overridden by the settings
+    ///
+    /// The values of the settings are expected to be validated already (see
+    /// [crate::server_settings::ServerSettings::parse]).
+    pub fn new(settings: &VariablesSettings) -> Self {
+        let layer = |defaults: &[(&str, &str)], overrides: &HashMap<String, String>| {
+            let mut layer = defaults
+                .iter()
+                .map(|(name, value)| {
+                    let definition = VariableDefinition {
+                        value: value.to_string(),
+                        source: VariableSource::Default,
+                    };
+                    (name.to_string(), definition)
+                })
+                .collect::<HashMap<_, _>>();
+            overrides.iter().for_each(|(name, value)| {
+                let definition = VariableDefinition {
+                    value: value.clone(),
+                    source: VariableSource::Settings,
+                };
+                layer.insert(name.clone(), definition);
+            });
+            layer
+        };
+        Variables {
+            addresses: layer(&DEFAULT_ADDRESS_GROUPS, &settings.address_groups),
+            ports: layer(&DEFAULT_PORT_GROUPS, &settings.port_groups),
+        }
+    }
+
+    /// Add the variables defined inside a suricata.yaml, the ones of the
+    /// settings are kept
+    pub fn with_config_variables(mut self, variables: Vec<ConfigVariable>) -> Self {
+        variables.into_iter().for_each(|variable| {
+            let layer = match variable.kind {
+                VariableKind::Address => &mut self.addresses,
+                VariableKind::Port => &mut self.ports,
+            };
+            let definition = VariableDefinition {
+                value: variable.value,
+                source: VariableSource::Config,
+            };
+            match layer.get(&variable.name.0) {
+                Some(defined) if defined.source == VariableSource::Settings => (),
+                _ => {
+                    layer.insert(variable.name.0, definition);
+                }
+            }
+        });
+        self
+    }
+
+    /// Get the definition of a variable
+    pub fn get(&self, kind: VariableKind, name: &str) -> Option<&VariableDefinition> {
+        match kind {
+            VariableKind::Address => self.addresses.get(name),
+            VariableKind::Port => self.ports.get(name),
+        }
+    }
+}
+
+/// Get all variables used inside the header of a signature
+///
+/// The names are without the leading `$`, which is included in the spans.
+pub fn variable_usages(rule: &Rule) -> Vec<(VariableKind, Spanned<String>)> {
+    let mut addresses = vec![];
+    let mut ports = vec![];
+    rule.header.0.find_address_variables(&None, &mut addresses);
+    rule.header.0.find_port_variables(&None, &mut ports);
+    addresses
+        .into_iter()
+        .map(|variable| (VariableKind::Address, variable))
+        .chain(
+            ports
+                .into_iter()
+                .map(|variable| (VariableKind::Port, variable)),
+        )
+        .collect()
+}
+
+/// The signatures of the workspace, which use a variable
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VariableUsages {
+    /// The number of signatures using the variable
+    pub rules: usize,
+    /// The lines of the signatures using the variable, grouped by file
+    pub files: Vec<(String, Vec<u32>)>,
+}
+
+impl VariableUsages {
+    /// Find the signatures, which use a variable
+    pub fn find(files: &[(String, &AST)], kind: VariableKind, name: &str) -> Self {
+        let mut usages = VariableUsages::default();
+        files.iter().for_each(|(file, ast)| {
+            let mut lines = ast
+                .rules
+                .iter()
+                .filter(|(_, (rule, _))| {
+                    variable_usages(rule)
+                        .iter()
+                        .any(|(usage_kind, (usage, _))| *usage_kind == kind && usage == name)
+                })
+                .map(|(line, _)| *line)
+                .collect::<Vec<_>>();
+            if !lines.is_empty() {
+                lines.sort();
+                usages.rules += lines.len();
+                usages.files.push((file.clone(), lines));
+            }
+        });
+        usages.files.sort();
+        usages
+    }
+
+    /// Summarize the usages, listing up to `examples` lines
+    /// (e.g. "used in 14 rules across 3 files, e.g. a.rules:12, 87, 240")
+    ///
+    /// Lines are shown 1-based, files by their name.
+    pub fn summary(&self, examples: usize) -> String {
+        let plural = |count: usize, word: &str| match count {
+            1 => format!("1 {}", word),
+            count => format!("{} {}s", count, word),
+        };
+        if self.rules == 0 {
+            return "not used in the workspace".to_string();
+        }
+        let mut remaining = examples;
+        let listed = self
+            .files
+            .iter()
+            .map_while(|(file, lines)| {
+                if remaining == 0 {
+                    return None;
+                }
+                let shown = lines.len().min(remaining);
+                remaining -= shown;
+                let name = file.rsplit('/').next().unwrap_or(file);
+                let lines = lines[..shown]
+                    .iter()
+                    .map(|line| (line + 1).to_string())
+                    .collect::<Vec<_>>();
+                Some(format!("{}:{}", name, lines.join(", ")))
+            })
+            .collect::<Vec<_>>();
+        format!(
+            "used in {} across {}, e.g. {}",
+            plural(self.rules, "rule"),
+            plural(self.files.len(), "file"),
+            listed.join("; ")
+        )
+    }
+}
+
+/// The variables used by the files of the workspace
+///
+/// The index is updated with the variables of a file whenever it is parsed,
+/// so a variable typed in one file is known to the others right away.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VariableIndex {
+    /// The variables used by every file, keyed by the URI of the file
+    files: HashMap<String, HashSet<(VariableKind, String)>>,
+    /// The number of files using every variable
+    used: HashMap<(VariableKind, String), usize>,
+}
+
+impl VariableIndex {
+    /// Replace the variables used by a file with the ones of its AST
+    pub fn update(&mut self, uri: &str, ast: &AST) {
+        let variables = ast
+            .rules
+            .values()
+            .flat_map(|(rule, _)| variable_usages(rule))
+            .map(|(kind, (name, _))| (kind, name))
+            .collect::<HashSet<_>>();
+        let previous = self
+            .files
+            .insert(uri.to_string(), variables.clone())
+            .unwrap_or_default();
+        // Only the difference to the previous version of the file is counted
+        previous
+            .difference(&variables)
+            .for_each(|variable| self.forget(variable));
+        variables.difference(&previous).for_each(|variable| {
+            *self.used.entry(variable.clone()).or_default() += 1;
+        });
+    }
+
+    /// Check if the variables of a file are known
+    pub fn contains(&self, uri: &str) -> bool {
+        self.files.contains_key(uri)
+    }
+
+    /// Forget the variables used by a file
+    pub fn remove(&mut self, uri: &str) {
+        if let Some(previous) = self.files.remove(uri) {
+            previous.iter().for_each(|variable| self.forget(variable));
+        }
+    }
+
+    fn forget(&mut self, variable: &(VariableKind, String)) {
+        if let Some(count) = self.used.get_mut(variable) {
+            *count -= 1;
+            if *count == 0 {
+                self.used.remove(variable);
+            }
+        }
+    }
+
+    /// Get the names of all variables of a kind: the defined ones (by the
+    /// defaults or the settings), along with the ones used by any file
+    pub fn names(&self, kind: VariableKind, definitions: &Variables) -> HashSet<String> {
+        let defined = match kind {
+            VariableKind::Address => &definitions.addresses,
+            VariableKind::Port => &definitions.ports,
+        };
+        self.used
+            .keys()
+            .filter(|(used_kind, _)| *used_kind == kind)
+            .map(|(_, name)| name)
+            .chain(defined.keys())
+            .cloned()
+            .collect()
+    }
+}
+
+/// Get the variable under the cursor
+pub fn variable_at(rule: &Rule, col: usize) -> Option<(VariableKind, Spanned<String>)> {
+    variable_usages(rule)
+        .into_iter()
+        .find(|(_, (_, span))| span.contains(&col))
+}
+
+/// Find the definition of a variable inside the text of a settings file
+///
+/// Returns the line and the span of the quoted name inside the section of its
+/// kind (`addressGroups` or `portGroups`).
+pub fn find_definition(text: &str, kind: VariableKind, name: &str) -> Option<(u32, Span)> {
+    let section = match kind {
+        VariableKind::Address => "\"addressGroups\"",
+        VariableKind::Port => "\"portGroups\"",
+    };
+    let quoted = format!("\"{}\"", name);
+    let mut in_section = false;
+    for (line_nr, line) in text.lines().enumerate() {
+        let mut search_from = 0;
+        if let Some(start) = line.find(section) {
+            in_section = true;
+            search_from = start + section.len();
+        }
+        if !in_section {
+            continue;
+        }
+        if let Some(start) = line[search_from..].find(&quoted) {
+            let start = search_from + start;
+            return Some((line_nr as u32, start..start + quoted.len()));
+        }
+    }
+    None
+}
+
+/// A variable defined inside the `vars` section of a suricata.yaml
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigVariable {
+    pub kind: VariableKind,
+    /// The name of the variable, the span points to the key
+    pub name: Spanned<String>,
+    /// The value, without the quotes (e.g. "[80,443]")
+    pub value: String,
+    /// The line of the key
+    pub line: u32,
+}
+
+/// Find the address and port groups defined inside a suricata.yaml
+///
+/// The file is not parsed as a whole, only the `vars` section is scanned by
+/// its indentation, so the keys can be located precisely:
+///
+/// ```yaml
+/// vars:
+///   address-groups:
+///     HOME_NET: "[192.168.0.0/16,10.0.0.0/8,172.16.0.0/12]"
+///   port-groups:
+///     HTTP_PORTS: "80"
+/// ```
+pub fn parse_config_variables(text: &str) -> Vec<ConfigVariable> {
+    let mut variables = vec![];
+    // The indentation of the vars section and the current group
+    let mut vars_indent = None;
+    let mut group: Option<(usize, VariableKind)> = None;
+    for (line_nr, line) in text.lines().enumerate() {
+        let content = line.trim();
+        if content.is_empty() || content.starts_with('#') || content == "---" {
+            continue;
+        }
+        let indent = line.len() - line.trim_start().len();
+        let Some((key, value)) = content.split_once(':') else {
+            continue;
+        };
+        // Leaving a section, once a key is not indented deeper than the section
+        if group.is_some_and(|(group_indent, _)| indent <= group_indent) {
+            group = None;
+        }
+        if vars_indent.is_some_and(|vars_indent| indent <= vars_indent) {
+            vars_indent = None;
+        }
+        match (vars_indent, group) {
+            (None, _) if key == "vars" => vars_indent = Some(indent),
+            (Some(_), None) => {
+                group = match key {
+                    "address-groups" => Some((indent, VariableKind::Address)),
+                    "port-groups" => Some((indent, VariableKind::Port)),
+                    _ => None,
+                }
+            }
+            (Some(_), Some((_, kind))) => {
+                let key = key.trim();
+                let start = line[..indent].chars().count();
+                let value = value.split(" #").next().unwrap_or_default().trim();
+                variables.push(ConfigVariable {
+                    kind,
+                    name: (key.to_string(), start..start + key.chars().count()),
+                    value: value.trim_matches(['"', '\'']).to_string(),
+                    line: line_nr as u32,
+                });
+            }
+            _ => (),
+        }
+    }
+    variables
+}
+
+#[cfg(test)]
+mod tests {
+    use chumsky::Parser;
+
+    use super::*;
+
+    fn settings() -> VariablesSettings {
+        let mut settings = VariablesSettings::default();
+        settings
+            .address_groups
+            .insert("HOME_NET".to_string(), "[192.168.1.0/24]".to_string());
+        settings
+            .port_groups
+            .insert("SSH_PORTS".to_string(), "22022".to_string());
+        settings
+    }
+
+    fn definition(
+        variables: &Variables,
+        kind: VariableKind,
+        name: &str,
+    ) -> (String, VariableSource) {
+        let definition = variables.get(kind, name).unwrap();
+        (definition.value.clone(), definition.source)
+    }
+
+    #[test]
+    fn settings_override_the_defaults() {
+        let variables = Variables::new(&settings());
+        assert_eq!(
+            definition(&variables, VariableKind::Address, "HOME_NET"),
+            ("[192.168.1.0/24]".to_string(), VariableSource::Settings)
+        );
+        assert_eq!(
+            definition(&variables, VariableKind::Port, "SSH_PORTS"),
+            ("22022".to_string(), VariableSource::Settings)
+        );
+        // The variables missing from the settings keep their defaults
+        assert_eq!(
+            definition(&variables, VariableKind::Address, "EXTERNAL_NET"),
+            ("!$HOME_NET".to_string(), VariableSource::Default)
+        );
+        assert_eq!(
+            definition(&variables, VariableKind::Port, "HTTP_PORTS"),
+            ("80".to_string(), VariableSource::Default)
+        );
+        // Address and port groups do not share their names
+        assert!(variables.get(VariableKind::Port, "HOME_NET").is_none());
+    }
+
+    #[test]
+    fn settings_override_the_yaml_which_overrides_the_defaults() {
+        let yaml = "\
+vars:
+  address-groups:
+    HOME_NET: \"[10.0.0.0/8]\"
+    EXTERNAL_NET: \"any\"
+    DMZ_NET: \"[172.16.0.0/12]\"
+  port-groups:
+    SSH_PORTS: \"2222\"
+";
+        let variables =
+            Variables::new(&settings()).with_config_variables(parse_config_variables(yaml));
+        // The settings take precedence over the suricata.yaml
+        assert_eq!(
+            definition(&variables, VariableKind::Address, "HOME_NET"),
+            ("[192.168.1.0/24]".to_string(), VariableSource::Settings)
+        );
+        assert_eq!(
+            definition(&variables, VariableKind::Port, "SSH_PORTS"),
+            ("22022".to_string(), VariableSource::Settings)
+        );
+        // The suricata.yaml takes precedence over the defaults
+        assert_eq!(
+            definition(&variables, VariableKind::Address, "EXTERNAL_NET"),
+            ("any".to_string(), VariableSource::Config)
+        );
+        // The suricata.yaml can define new variables
+        assert_eq!(
+            definition(&variables, VariableKind::Address, "DMZ_NET"),
+            ("[172.16.0.0/12]".to_string(), VariableSource::Config)
+        );
+        // The variables defined nowhere else keep their defaults
+        assert_eq!(
+            definition(&variables, VariableKind::Port, "HTTP_PORTS"),
+            ("80".to_string(), VariableSource::Default)
+        );
+    }
+
+    #[test]
+    fn variables_are_found_inside_the_header() {
+        let (rule, _) = Rule::parser()
+            .parse("alert tcp $HOME_NET any -> $EXTERNAL_NET $HTTP_PORTS (sid:1;)")
+            .unwrap();
+        let usages = variable_usages(&rule);
+        assert_eq!(
+            usages,
+            vec![
+                (VariableKind::Address, ("HOME_NET".to_string(), 10..19)),
+                (VariableKind::Address, ("EXTERNAL_NET".to_string(), 27..40)),
+                (VariableKind::Port, ("HTTP_PORTS".to_string(), 41..52)),
+            ]
+        );
+        assert_eq!(variable_at(&rule, 45), Some(usages[2].clone()));
+        assert_eq!(variable_at(&rule, 24), None);
+    }
+
+    #[test]
+    fn definitions_are_found_inside_their_section() {
+        let text = "\
+{
+  \"variables\": {
+    \"portGroups\": {\"HOME_NET\": \"80\"},
+    \"addressGroups\": {
+      \"HOME_NET\": \"[10.0.0.0/8]\"
+    }
+  }
+}";
+        assert_eq!(
+            find_definition(text, VariableKind::Address, "HOME_NET"),
+            Some((4, 6..16))
+        );
+        assert_eq!(
+            find_definition(text, VariableKind::Port, "HOME_NET"),
+            Some((2, 19..29))
+        );
+        assert_eq!(find_definition(text, VariableKind::Port, "SSH_PORTS"), None);
+    }
+
+    fn usages(kind: VariableKind, name: &str) -> VariableUsages {
+        let (a, _) = AST::parse_str(
+            "\
+alert tcp $HOME_NET any -> any $HTTP_PORTS (msg:\"a\"; sid:1;)
+alert tcp any any -> any 80 (msg:\"b\"; sid:2;)
+alert tcp any any -> $HOME_NET any (msg:\"c\"; sid:3;)
+",
+        );
+        let (b, _) = AST::parse_str(
+            "alert tcp [$HOME_NET,!$DNS_SERVERS] any -> any any (msg:\"d\"; sid:4;)",
+        );
+        let files = [
+            ("/rules/local/b.rules".to_string(), &b),
+            ("/rules/a.rules".to_string(), &a),
+        ];
+        VariableUsages::find(&files, kind, name)
+    }
+
+    #[test]
+    fn usages_are_grouped_by_file() {
+        let home_net = usages(VariableKind::Address, "HOME_NET");
+        assert_eq!(home_net.rules, 3);
+        assert_eq!(
+            home_net.files,
+            [
+                ("/rules/a.rules".to_string(), vec![0, 2]),
+                ("/rules/local/b.rules".to_string(), vec![0]),
+            ]
+        );
+        // Ports and addresses are separate variables
+        assert_eq!(
+            usages(VariableKind::Port, "HOME_NET"),
+            VariableUsages::default()
+        );
+    }
+
+    #[test]
+    fn usages_are_summarized() {
+        let home_net = usages(VariableKind::Address, "HOME_NET");
+        assert_eq!(
+            home_net.summary(3),
+            "used in 3 rules across 2 files, e.g. a.rules:1, 3; b.rules:1"
+        );
+        assert_eq!(
+            home_net.summary(1),
+            "used in 3 rules across 2 files, e.g. a.rules:1"
+        );
+        assert_eq!(
+            usages(VariableKind::Port, "HTTP_PORTS").summary(3),
+            "used in 1 rule across 1 file, e.g. a.rules:1"
+        );
+        assert_eq!(
+            usages(VariableKind::Port, "SSH_PORTS").summary(3),
+            "not used in the workspace"
+        );
+    }
+
+    #[test]
+    fn config_variables_point_to_their_key() {
+        let text = "\
+%YAML 1.1
+---
+vars:
+  # more specific is better for alert accuracy and performance
+  address-groups:
+    HOME_NET: \"[192.168.0.0/16,10.0.0.0/8]\"
+    EXTERNAL_NET: \"!$HOME_NET\" # everything else
+
+  port-groups:
+    HTTP_PORTS: '80'
+    SSH_PORTS: 22
+default-rule-path: /etc/suricata/rules
+  HOME_NET: \"[1.1.1.1]\"
+";
+        let variables = parse_config_variables(text)
+            .into_iter()
+            .map(|variable| (variable.kind, variable.name, variable.value, variable.line))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            variables,
+            [
+                (
+                    VariableKind::Address,
+                    ("HOME_NET".to_string(), 4..12),
+                    "[192.168.0.0/16,10.0.0.0/8]".to_string(),
+                    5
+                ),
+                (
+                    VariableKind::Address,
+                    ("EXTERNAL_NET".to_string(), 4..16),
+                    "!$HOME_NET".to_string(),
+                    6
+                ),
+                (
+                    VariableKind::Port,
+                    ("HTTP_PORTS".to_string(), 4..14),
+                    "80".to_string(),
+                    9
+                ),
+                (
+                    VariableKind::Port,
+                    ("SSH_PORTS".to_string(), 4..13),
+                    "22".to_string(),
+                    10
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn config_variables_outside_of_vars_are_ignored() {
+        let text = "\
+address-groups:
+  HOME_NET: \"[10.0.0.0/8]\"
+outputs:
+  vars:
+    port-groups:
+      HTTP_PORTS: \"8080\"
+";
+        let variables = parse_config_variables(text);
+        assert_eq!(variables.len(), 1);
+        assert_eq!(variables[0].name, ("HTTP_PORTS".to_string(), 6..16));
+        assert_eq!(variables[0].line, 5);
+    }
+
+    #[test]
+    fn variables_are_known_while_a_file_uses_them() {
+        let ast = |text: &str| AST::parse_str(text).0;
+        let names = |index: &VariableIndex| {
+            let definitions = Variables::default();
+            let mut names = index
+                .names(VariableKind::Address, &definitions)
+                .into_iter()
+                .collect::<Vec<_>>();
+            names.sort();
+            names
+        };
+        let mut index = VariableIndex::default();
+        index.update("a", &ast("alert tcp $FOO any -> $BAR $WEB_PORTS (sid:1;)"));
+        index.update("b", &ast("alert tcp $FOO any -> any any (sid:2;)"));
+        assert_eq!(names(&index), vec!["BAR", "FOO"]);
+        assert!(index
+            .names(VariableKind::Port, &Variables::default())
+            .contains("WEB_PORTS"));
+
+        // $FOO is still used by the second file
+        index.update("a", &ast("alert tcp any any -> any any (sid:1;)"));
+        assert_eq!(names(&index), vec!["FOO"]);
+        index.remove("b");
+        assert!(names(&index).is_empty());
+        assert!(index.contains("a"));
+        assert!(!index.contains("b"));
+    }
+}
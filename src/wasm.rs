@@ -0,0 +1,141 @@
+//! Provides the bindings for JavaScript
+//!
+//! Exposes the parser, the lints and the formatter to web based tooling
+//! (e.g. rule editors running inside the browser). The library is only built
+//! as a `cdylib` for WebAssembly:
+//!
+//! ```bash
+//! cargo rustc --lib --release --target wasm32-unknown-unknown --crate-type cdylib \
+//!     --no-default-features --features wasm
+//! wasm-bindgen --target web --out-dir pkg target/wasm32-unknown-unknown/release/meerkat_ls.wasm
+//! ```
+//!
+//! The bindings are tested on node with
+//! `wasm-pack test --node -- --no-default-features --features wasm`.
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::format::{format_text, FormatStyle};
+use crate::lint::{validate_document, validate_workspace, ValidationContext, Violation};
+use crate::rule::AST;
+
+/// The result of parsing a rule file
+#[derive(Serialize)]
+struct ParsedRules {
+    ast: AST,
+    /// The violations, along with the line of the signature
+    violations: Vec<(u32, Violation)>,
+}
+
+/// Parse a rule file and run the lints over it
+///
+/// Returns the AST of the file along with the violations of the lints.
+#[wasm_bindgen]
+pub fn parse_rules(text: &str) -> Result<JsValue, JsValue> {
+    serde_wasm_bindgen::to_value(&parse(text)).map_err(JsValue::from)
+}
+
+fn parse(text: &str) -> ParsedRules {
+    let (ast, _) = AST::parse_str(text);
+    let context = ValidationContext::default();
+    let mut violations = validate_document(&ast, &context);
+    violations.extend(
+        validate_workspace(&[(String::new(), &ast)], &context)
+            .into_iter()
+            .map(|(_, line, violation)| (line, violation)),
+    );
+    violations.sort_by_key(|(line, violation)| (*line, violation.span.start));
+    ParsedRules { ast, violations }
+}
+
+/// Format a rule file
+#[wasm_bindgen]
+pub fn format_rules(text: &str) -> String {
+    format_text(text, &FormatStyle::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lint::LintCode;
+
+    #[test]
+    fn rules_are_parsed_and_linted() {
+        let text = [
+            "alert tcp any any -> any 80 (msg:\"a\"; sid:1; rev:1;)",
+            "# a comment",
+            "alert tcp any any -> any 80 (msg:\"b\"; flowbits:isset,seen; sid:1; rev:1;)",
+        ]
+        .join("\n");
+        let parsed = parse(&text);
+        assert_eq!(parsed.ast.rules.len(), 2);
+        let violations = parsed
+            .violations
+            .iter()
+            .map(|(line, violation)| (*line, violation.code))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            violations,
+            vec![
+                (0, LintCode::DuplicateSid),
+                (2, LintCode::UnsetFlowbit),
+                (2, LintCode::DuplicateSid),
+            ]
+        );
+    }
+
+    #[test]
+    fn parsed_rules_serialize_with_the_lines_of_the_violations() {
+        let parsed = parse("alert tcp any any -> any 80 (msg:\"a\"; sid:1; rev:1;)\n\n");
+        let value = serde_json::to_value(&parsed).unwrap();
+        assert!(value["ast"]["rules"]["0"].is_array());
+        assert_eq!(value["violations"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn rules_are_formatted() {
+        let text = "alert  tcp any any ->  any 80 (msg:\"a\";sid:1;)\n# comment\n";
+        assert_eq!(
+            format_rules(text),
+            format_text(text, &FormatStyle::default())
+        );
+        assert_ne!(format_rules(text), text);
+        assert!(format_rules(text).ends_with("\n# comment\n"));
+    }
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod wasm_tests {
+    use super::*;
+    use serde::de::IgnoredAny;
+    use serde::Deserialize;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    /// The lines of the violations, as read back from JavaScript
+    #[derive(Deserialize)]
+    struct Lines {
+        violations: Vec<(u32, IgnoredAny)>,
+    }
+
+    fn violation_lines(text: &str) -> Vec<u32> {
+        let value = parse_rules(text).unwrap();
+        let lines: Lines = serde_wasm_bindgen::from_value(value).unwrap();
+        lines.violations.into_iter().map(|(line, _)| line).collect()
+    }
+
+    #[wasm_bindgen_test]
+    fn formatted_rules_are_parsed_the_same() {
+        let text = [
+            "alert  tcp any any ->  any 80 (msg:\"a\";sid:1;rev:1;)",
+            "# a comment",
+            "alert tcp any any -> any 80 (msg:\"b\"; flowbits:isset,seen; sid:1; rev:1;)",
+            "",
+        ]
+        .join("\n");
+        let formatted = format_rules(&text);
+        assert_ne!(formatted, text);
+        assert_eq!(format_rules(&formatted), formatted);
+        assert_eq!(violation_lines(&text), vec![0, 2, 2]);
+        assert_eq!(violation_lines(&formatted), violation_lines(&text));
+    }
+}
@@ -0,0 +1,330 @@
+//! Provides the workspace index for the language server
+//!
+//! The index holds the parsed signatures of every rule file found inside the
+//! workspace folders. Parsing a whole ruleset is CPU-heavy, so the files are
+//! parsed in chunks on the blocking thread pool and the results are fed back
+//! through a bounded channel. This keeps the async runtime free to answer
+//! requests (hover, completion, etc.) while the index is being built. The open
+//! documents are parsed the same way, in chunks of lines.
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use dashmap::DashMap;
+use ropey::Rope;
+use tokio::sync::mpsc;
+use tower_lsp::lsp_types::Url;
+
+use crate::rule::AST;
+use crate::semantic_token::ImCompleteSemanticToken;
+
+/// Number of files parsed by a single blocking task
+const CHUNK_SIZE: usize = 16;
+/// Number of lines of a document parsed before the result is fed back
+const DOCUMENT_CHUNK_SIZE: usize = 2000;
+/// Number of parsed chunks which can wait inside the channel
+const CHANNEL_CAPACITY: usize = 4;
+/// How deep the directories of the datasets are searched
+const MAX_DATASET_DEPTH: usize = 4;
+/// The most files listed for a single directory of the datasets
+const MAX_DATASET_FILES: usize = 2000;
+/// How many bytes of a file are searched for a NUL byte, to detect binary content
+const BINARY_PROBE_SIZE: usize = 8000;
+
+/// Index of all rule files inside the workspace
+///
+/// Every file is inserted only once it is completely parsed, so readers
+/// always see the last completed snapshot of a file and never a half-built one.
+#[derive(Debug, Default)]
+pub struct WorkspaceIndex {
+    /// The AST of every indexed file, keyed by the URI of the file
+    pub files: DashMap<String, AST>,
+    cancelled: AtomicBool,
+}
+
+impl WorkspaceIndex {
+    /// Stop any indexing which is currently in progress
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Check if the indexing was cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Index all rule files inside the given folders
+    ///
+    /// Returns the number of files, which were indexed. A cancelled index can
+    /// be indexed again.
+    pub async fn index_folders(self: Arc<Self>, folders: Vec<PathBuf>) -> usize {
+        self.cancelled.store(false, Ordering::Relaxed);
+        // Look for the rule files without blocking the runtime
+        let files = tokio::task::spawn_blocking(move || {
+            let mut files = vec![];
+            folders
+                .iter()
+                .for_each(|folder| find_rule_files(folder, &mut files));
+            files
+        })
+        .await
+        .unwrap_or_default();
+
+        // Parse the files in chunks, the bounded channel makes sure the parsing
+        // does not run too far ahead of the index
+        let (sender, mut receiver) = mpsc::channel(CHANNEL_CAPACITY);
+        let index = self.clone();
+        tokio::spawn(async move {
+            for chunk in files.chunks(CHUNK_SIZE) {
+                if index.is_cancelled() {
+                    break;
+                }
+                let chunk = chunk.to_vec();
+                let parsed = match tokio::task::spawn_blocking(move || parse_files(chunk)).await {
+                    Ok(parsed) => parsed,
+                    Err(_) => break,
+                };
+                if sender.send(parsed).await.is_err() {
+                    break;
+                }
+                // Give the runtime a chance to handle other requests
+                tokio::task::yield_now().await;
+            }
+        });
+
+        // Store the parsed files inside the index
+        let mut indexed = 0;
+        while let Some(parsed) = receiver.recv().await {
+            if self.is_cancelled() {
+                break;
+            }
+            parsed.into_iter().for_each(|(uri, ast)| {
+                self.files.insert(uri, ast);
+                indexed += 1;
+            });
+        }
+        indexed
+    }
+}
+
+/// Parse a document on the blocking thread pool
+///
+/// The lines are parsed in chunks, which are fed back through a bounded
+/// channel and merged in the order of the lines. Once `is_outdated` returns
+/// true (e.g. a newer version of the document arrived), the parsing stops
+/// after the current chunk and nothing is returned.
+pub async fn parse_document(
+    rope: Rope,
+    is_outdated: impl Fn() -> bool,
+) -> Option<(AST, Vec<ImCompleteSemanticToken>)> {
+    let lines = rope.len_lines();
+    let (sender, mut receiver) = mpsc::channel(CHANNEL_CAPACITY);
+    tokio::task::spawn_blocking(move || {
+        for start in (0..lines).step_by(DOCUMENT_CHUNK_SIZE) {
+            let end = lines.min(start + DOCUMENT_CHUNK_SIZE);
+            // The receiver is gone, once the document is outdated
+            if sender
+                .blocking_send(AST::parse_lines(&rope, start..end))
+                .is_err()
+            {
+                break;
+            }
+        }
+    });
+    let mut ast = AST::default();
+    let mut semantic_tokens = vec![];
+    let mut parsed_lines = 0;
+    while let Some((chunk, chunk_tokens)) = receiver.recv().await {
+        if is_outdated() {
+            return None;
+        }
+        ast.merge(chunk);
+        semantic_tokens.extend(chunk_tokens);
+        parsed_lines += DOCUMENT_CHUNK_SIZE;
+    }
+    // The channel is also closed, when the parsing failed half way (panicked)
+    (parsed_lines >= lines).then_some((ast, semantic_tokens))
+}
+
+/// Recursively find all rule files (*.rules) inside a folder
+pub fn find_rule_files(path: &Path, files: &mut Vec<PathBuf>) {
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    entries.flatten().for_each(|entry| {
+        let path = entry.path();
+        let Ok(file_type) = entry.file_type() else {
+            return;
+        };
+        // Linked directories are skipped, since they could link to a parent
+        if file_type.is_symlink() && path.is_dir() {
+            return;
+        }
+        if file_type.is_dir() {
+            find_rule_files(&path, files);
+        } else if path
+            .extension()
+            .is_some_and(|extension| extension == "rules")
+        {
+            files.push(path);
+        }
+    });
+}
+
+/// Check if the content of a file is obviously binary (contains a NUL byte)
+///
+/// Rule files are plain text, so binary files are not parsed, even when the
+/// client asks about them.
+pub fn is_binary(text: &str) -> bool {
+    text.bytes().take(BINARY_PROBE_SIZE).any(|byte| byte == 0)
+}
+
+/// Find the files, which can be loaded by a dataset, inside a directory
+///
+/// Returns the paths relative to the directory (separated by /), sorted.
+/// Hidden entries and rule files are skipped.
+pub fn find_dataset_files(root: &Path) -> Vec<String> {
+    let mut files = vec![];
+    let mut directories = vec![(root.to_path_buf(), 0)];
+    while let Some((directory, depth)) = directories.pop() {
+        if files.len() >= MAX_DATASET_FILES {
+            break;
+        }
+        let Ok(entries) = fs::read_dir(&directory) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            // Linked directories are skipped, like the ones of the rule files
+            if file_type.is_symlink() && path.is_dir() {
+                continue;
+            }
+            if file_type.is_dir() {
+                if depth + 1 < MAX_DATASET_DEPTH {
+                    directories.push((path, depth + 1));
+                }
+                continue;
+            }
+            if path
+                .extension()
+                .is_some_and(|extension| extension == "rules")
+            {
+                continue;
+            }
+            let Ok(relative) = path.strip_prefix(root) else {
+                continue;
+            };
+            let components = relative
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>();
+            files.push(components.join("/"));
+            if files.len() >= MAX_DATASET_FILES {
+                break;
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+/// Parse a list of files, files which cannot be read are skipped
+fn parse_files(files: Vec<PathBuf>) -> Vec<(String, AST)> {
+    files
+        .into_iter()
+        .filter_map(|path| {
+            let text = fs::read_to_string(&path).ok()?;
+            if is_binary(&text) {
+                return None;
+            }
+            let uri = Url::from_file_path(&path).ok()?;
+            let (ast, _) = AST::parse_rope(&Rope::from_str(&text));
+            Some((uri.to_string(), ast))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn linked_directories_are_not_followed() {
+        use std::os::unix::fs::symlink;
+
+        let folder = tempfile::tempdir().unwrap();
+        let root = folder.path();
+        fs::create_dir(root.join("emerging")).unwrap();
+        fs::write(root.join("local.rules"), "").unwrap();
+        fs::write(root.join("emerging/dns.rules"), "").unwrap();
+        fs::write(root.join("emerging/hosts.txt"), "").unwrap();
+        // Both links would repeat the folder forever
+        symlink(".", root.join("loop")).unwrap();
+        symlink("..", root.join("emerging/parent")).unwrap();
+
+        let mut files = vec![];
+        find_rule_files(root, &mut files);
+        files.sort();
+        assert_eq!(
+            files,
+            vec![root.join("emerging/dns.rules"), root.join("local.rules")]
+        );
+        assert_eq!(find_dataset_files(root), vec!["emerging/hosts.txt"]);
+    }
+
+    #[tokio::test]
+    async fn indexing_starts_again_after_a_cancel() {
+        let folder = tempfile::tempdir().unwrap();
+        let rule = "alert tcp any any -> any any (msg:\"x\"; sid:1; rev:1;)\n";
+        fs::write(folder.path().join("local.rules"), rule).unwrap();
+        let index = Arc::new(WorkspaceIndex::default());
+        index.cancel();
+        let folders = vec![folder.path().to_path_buf()];
+        assert_eq!(index.clone().index_folders(folders).await, 1);
+        assert!(!index.is_cancelled());
+        assert_eq!(index.files.len(), 1);
+    }
+
+    #[test]
+    fn dataset_files_are_found_in_nested_directories() {
+        let folder = tempfile::tempdir().unwrap();
+        let root = folder.path();
+        fs::create_dir_all(root.join("lists/ips/v6")).unwrap();
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::write(root.join("top.lst"), "").unwrap();
+        fs::write(root.join("lists/domains.lst"), "").unwrap();
+        fs::write(root.join("lists/ips/v6/bad.lst"), "").unwrap();
+        fs::write(root.join("lists/local.rules"), "").unwrap();
+        fs::write(root.join(".git/config"), "").unwrap();
+
+        assert_eq!(
+            find_dataset_files(root),
+            vec!["lists/domains.lst", "lists/ips/v6/bad.lst", "top.lst"]
+        );
+        assert_eq!(
+            find_dataset_files(&root.join("lists/ips")),
+            vec!["v6/bad.lst"]
+        );
+    }
+
+    #[test]
+    fn missing_dataset_directories_have_no_files() {
+        let folder = tempfile::tempdir().unwrap();
+        assert!(find_dataset_files(&folder.path().join("missing")).is_empty());
+        fs::write(folder.path().join("file.lst"), "").unwrap();
+        assert!(find_dataset_files(&folder.path().join("file.lst")).is_empty());
+    }
+}
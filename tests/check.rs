@@ -0,0 +1,119 @@
+//! Tests of the `check` subcommand, which run the binary against a stand-in for Suricata
+#![cfg(unix)]
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use serde_json::{json, Value};
+
+/// The directory with the fixtures and the script standing in for Suricata
+fn fixtures() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/check")
+}
+
+fn fixture(file: &str) -> String {
+    fixtures().join(file).to_string_lossy().to_string()
+}
+
+/// Run the check subcommand with the stand-in for Suricata
+fn check(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_meerkat-ls"))
+        .arg("check")
+        .args(["--suricata", &fixture("suricata")])
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn valid_files_pass() {
+    let output = check(&[&fixture("valid.rules")]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn findings_are_mapped_back_to_their_file() {
+    let output = check(&["--json", &fixture("valid.rules"), &fixture("invalid.rules")]);
+    assert_eq!(output.status.code(), Some(1));
+    let findings: Value = serde_json::from_slice(&output.stdout).unwrap();
+    // The files are merged before Suricata sees them, it reports the fifth line
+    assert_eq!(
+        findings,
+        json!([{
+            "file": fixture("invalid.rules"),
+            "line": 2,
+            "column": 44,
+            "endColumn": 51,
+            "severity": "error",
+            "code": "39",
+            "message": "unknown rule keyword 'contnet'.",
+        }])
+    );
+}
+
+#[test]
+fn findings_excerpt_the_offending_line() {
+    let output = check(&[&fixture("invalid.rules")]);
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let expected = [
+        "error[39]: unknown rule keyword 'contnet'.".to_string(),
+        format!(" --> {}:2:44", fixture("invalid.rules")),
+        "  |".to_string(),
+        "2 | alert tcp any any -> any 80 (msg:\"fourth\"; contnet:\"HEAD\"; sid:4; rev:1;)"
+            .to_string(),
+        format!("  | {}^^^^^^^", " ".repeat(43)),
+    ];
+    assert_eq!(stdout.lines().take(5).collect::<Vec<_>>(), expected);
+}
+
+#[test]
+fn the_verification_mode_and_the_config_are_passed_to_suricata() {
+    let folder = tempfile::tempdir().unwrap();
+    let arguments = folder.path().join("arguments");
+    let output = Command::new(env!("CARGO_BIN_EXE_meerkat-ls"))
+        .env("SURICATA_STUB_ARGS", &arguments)
+        .args(["check", "--suricata", &fixture("suricata")])
+        .args(["--mode", "test", "-c", "/etc/suricata/suricata.yaml"])
+        .arg(fixture("valid.rules"))
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    let arguments = fs::read_to_string(arguments).unwrap();
+    assert!(arguments.contains(" -T -c /etc/suricata/suricata.yaml"));
+}
+
+#[test]
+fn a_missing_suricata_exits_with_3() {
+    let output = Command::new(env!("CARGO_BIN_EXE_meerkat-ls"))
+        .args(["check", "--suricata", "/nonexistent/suricata"])
+        .arg(fixture("valid.rules"))
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(3));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Suricata was not found (/nonexistent/suricata)"));
+}
+
+#[test]
+fn a_suricata_which_can_not_be_run_exits_with_2() {
+    // The rule file is not executable
+    let output = Command::new(env!("CARGO_BIN_EXE_meerkat-ls"))
+        .args(["check", "--suricata", &fixture("valid.rules")])
+        .arg(fixture("valid.rules"))
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with("error: could not run Suricata: "));
+}
+
+#[test]
+fn missing_files_exit_with_2() {
+    let missing = fixture("missing.rules");
+    let output = check(&[&missing]);
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(stderr, format!("error: {} does not exist\n", missing));
+}
@@ -0,0 +1,52 @@
+//! Golden-file tests of the `export` subcommand, which lock the schema of the inventory
+use std::process::{Command, Output};
+
+/// Export the fixture pack, the path is relative so the output does not depend on the checkout
+fn export(format: &str) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_meerkat-ls"))
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .args([
+            "export",
+            "--format",
+            format,
+            "tests/fixtures/export/pack.rules",
+        ])
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn json_inventory_matches_the_golden_file() {
+    let output = export("json");
+    assert_eq!(output.status.code(), Some(0));
+    let expected = include_str!("fixtures/export/expected.json");
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), expected);
+}
+
+#[test]
+fn csv_inventory_matches_the_golden_file() {
+    let output = export("csv");
+    assert_eq!(output.status.code(), Some(0));
+    let expected = include_str!("fixtures/export/expected.csv");
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), expected);
+}
+
+#[test]
+fn json_inventory_is_valid_json() {
+    let output = export("json");
+    let records: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    // Comments, empty lines and lines which are not signatures are skipped
+    assert_eq!(records.as_array().unwrap().len(), 3);
+}
+
+#[test]
+fn missing_files_exit_with_2() {
+    let output = Command::new(env!("CARGO_BIN_EXE_meerkat-ls"))
+        .args(["export", "missing.rules"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(stderr, "error: missing.rules does not exist\n");
+}
@@ -0,0 +1,145 @@
+//! Tests of the `fmt` subcommand, which run the binary on the fixtures
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
+
+const UNFORMATTED: &str = include_str!("fixtures/fmt/unformatted.rules");
+const FORMATTED: &str = include_str!("fixtures/fmt/formatted.rules");
+const TABS: &str = include_str!("fixtures/fmt/tabs.rules");
+
+/// Run the fmt subcommand with the arguments
+fn fmt(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_meerkat-ls"))
+        .arg("fmt")
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+/// Copy the fixtures into a temporary directory, which can be modified
+fn fixtures() -> tempfile::TempDir {
+    let folder = tempfile::tempdir().unwrap();
+    fs::write(folder.path().join("unformatted.rules"), UNFORMATTED).unwrap();
+    fs::write(folder.path().join("formatted.rules"), FORMATTED).unwrap();
+    folder
+}
+
+fn path(folder: &Path, file: &str) -> String {
+    folder.join(file).to_string_lossy().to_string()
+}
+
+#[test]
+fn comments_and_unparsable_lines_are_kept() {
+    assert!(FORMATTED.contains("# Local rules, reviewed every month\n"));
+    assert!(FORMATTED.contains("\nthis line is not a signature\n"));
+    assert_eq!(FORMATTED.lines().count(), UNFORMATTED.lines().count());
+}
+
+#[test]
+fn files_are_rewritten_in_place() {
+    let folder = fixtures();
+    let output = fmt(&[&path(folder.path(), "unformatted.rules")]);
+    assert_eq!(output.status.code(), Some(0));
+    let rewritten = fs::read_to_string(folder.path().join("unformatted.rules")).unwrap();
+    assert_eq!(rewritten, FORMATTED);
+}
+
+#[test]
+fn check_fails_for_unformatted_files_without_writing_them() {
+    let folder = fixtures();
+    let unformatted = path(folder.path(), "unformatted.rules");
+    let output = fmt(&["--check", &unformatted]);
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout, format!("{} is not formatted\n", unformatted));
+    let kept = fs::read_to_string(folder.path().join("unformatted.rules")).unwrap();
+    assert_eq!(kept, UNFORMATTED);
+}
+
+#[test]
+fn check_passes_for_formatted_files() {
+    let folder = fixtures();
+    let output = fmt(&["--check", &path(folder.path(), "formatted.rules")]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn directories_and_globs_are_searched_for_rule_files() {
+    let folder = fixtures();
+    fs::write(folder.path().join("notes.txt"), UNFORMATTED).unwrap();
+    let output = fmt(&[&folder.path().to_string_lossy()]);
+    assert_eq!(output.status.code(), Some(0));
+    for file in ["unformatted.rules", "formatted.rules"] {
+        let text = fs::read_to_string(folder.path().join(file)).unwrap();
+        assert_eq!(text, FORMATTED);
+    }
+    let notes = fs::read_to_string(folder.path().join("notes.txt")).unwrap();
+    assert_eq!(notes, UNFORMATTED);
+
+    let folder = fixtures();
+    let output = fmt(&["--check", &path(folder.path(), "*.rules")]);
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout.lines().count(), 1);
+}
+
+#[test]
+fn stdout_leaves_the_files_untouched() {
+    let folder = fixtures();
+    let output = fmt(&["--stdout", &path(folder.path(), "unformatted.rules")]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), FORMATTED);
+    let kept = fs::read_to_string(folder.path().join("unformatted.rules")).unwrap();
+    assert_eq!(kept, UNFORMATTED);
+}
+
+#[test]
+fn stdin_is_formatted_to_stdout() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_meerkat-ls"))
+        .args(["fmt", "--stdin"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    stdin.write_all(UNFORMATTED.as_bytes()).unwrap();
+    drop(stdin);
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), FORMATTED);
+}
+
+#[test]
+fn missing_files_are_an_error() {
+    let folder = tempfile::tempdir().unwrap();
+    let output = fmt(&[&path(folder.path(), "missing.rules")]);
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("missing.rules does not exist"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn tab_separated_headers_are_formatted_in_one_pass() {
+    let folder = tempfile::tempdir().unwrap();
+    let tabs = path(folder.path(), "tabs.rules");
+    fs::write(&tabs, TABS).unwrap();
+    let output = fmt(&[&tabs]);
+    assert_eq!(output.status.code(), Some(0));
+    let formatted = fs::read_to_string(&tabs).unwrap();
+    let expected = FORMATTED
+        .lines()
+        .filter(|line| line.contains("$HOME_NET"))
+        .map(|line| format!("{}\n", line))
+        .collect::<String>();
+    assert_eq!(formatted, expected);
+    // A second pass has nothing left to change
+    let output = fmt(&["--check", &tabs]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(output.stdout.is_empty());
+}
@@ -0,0 +1,28 @@
+//! Tests of the `lint` subcommand, which run the binary on the fixtures
+use std::process::{Command, Output};
+
+/// Run the lint subcommand with the arguments, relative to the checkout
+fn lint(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_meerkat-ls"))
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .arg("lint")
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn every_missing_file_is_an_error() {
+    let output = lint(&[
+        "missing.rules",
+        "tests/fixtures/export/pack.rules",
+        "rules/*.missing",
+    ]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(
+        stderr,
+        "error: missing.rules does not exist\nerror: rules/*.missing does not exist\n"
+    );
+}